@@ -0,0 +1,120 @@
+//! Benchmarks for the types every game leans on. Run with `cargo bench`,
+//! or `cargo bench -- turn_tracker` for one group, and compare against a
+//! saved baseline with `--save-baseline` and `--baseline`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use code_challenge_game_types::color::Color;
+use code_challenge_game_types::gametraits::{
+    from_player_move, to_game_state, to_player_move, PlayerMove, User,
+};
+use code_challenge_game_types::grid::{
+    decode, encode, flood_fill, Adjacency, Boundary, Connectivity, Grid, Transform,
+};
+use code_challenge_game_types::messages::FromClient;
+use code_challenge_game_types::pos::Pos;
+use code_challenge_game_types::TurnTracker;
+
+const PLAYERS: [usize; 4] = [2, 10, 100, 1000];
+
+fn users(n: usize) -> Vec<User> {
+    (0..n)
+        .map(|i| User::new(format!("p{i}"), Color::BLUE))
+        .collect()
+}
+
+fn turn_tracker(c: &mut Criterion) {
+    let mut group = c.benchmark_group("turn_tracker");
+    for n in PLAYERS {
+        let users = users(n);
+        group.bench_with_input(BenchmarkId::new("add", n), &users, |b, users| {
+            b.iter(|| {
+                let mut tracker = TurnTracker::new(vec![]);
+                for user in users {
+                    tracker.add_player(user.clone()).unwrap();
+                }
+                tracker
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("remove", n), &users, |b, users| {
+            b.iter_batched(
+                || TurnTracker::new(users.clone()),
+                |mut tracker| {
+                    for user in users {
+                        tracker.remove_player(&user.name).unwrap();
+                    }
+                    tracker
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("advance", n), &users, |b, users| {
+            let mut tracker = TurnTracker::new(users.clone());
+            b.iter(|| tracker.advance_player().is_some())
+        });
+    }
+    group.finish();
+}
+
+fn board(size: usize) -> Grid<Option<char>> {
+    Grid::from_fn(size, size, |x, y| match (x * 7 + y * 3) % 5 {
+        0 => Some('x'),
+        1 => Some('o'),
+        _ => None,
+    })
+}
+
+fn grid(c: &mut Criterion) {
+    let mut group = c.benchmark_group("grid");
+    for size in [8, 19, 64] {
+        let grid = board(size);
+        group.bench_with_input(BenchmarkId::new("neighbors", size), &grid, |b, grid| {
+            b.iter(|| {
+                grid.positions()
+                    .flat_map(|pos| grid.neighbors(pos, Adjacency::Diagonal, Boundary::Skip))
+                    .count()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("flood_fill", size), &grid, |b, grid| {
+            b.iter(|| flood_fill(grid, Pos::new(0, 0), Connectivity::Four, |_| true).len())
+        });
+        group.bench_with_input(BenchmarkId::new("transform", size), &grid, |b, grid| {
+            b.iter(|| grid.transformed(Transform::Rotate90))
+        });
+        let code = encode(&grid);
+        group.bench_with_input(BenchmarkId::new("encode", size), &grid, |b, grid| {
+            b.iter(|| encode(grid))
+        });
+        group.bench_with_input(BenchmarkId::new("decode", size), &code, |b, code| {
+            b.iter(|| decode::<Option<char>>(code).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn protocol(c: &mut Criterion) {
+    let mut group = c.benchmark_group("protocol");
+    for size in [3, 19] {
+        let rows: Vec<Vec<Option<char>>> = board(size).into_rows();
+        group.bench_with_input(BenchmarkId::new("encode_state", size), &rows, |b, rows| {
+            b.iter(|| to_game_state(black_box(rows)))
+        });
+    }
+    group.bench_function("encode_move", |b| {
+        b.iter(|| from_player_move(black_box(Pos::new(3, 4))))
+    });
+    let player_move = PlayerMove {
+        serialized: r#"{"move":{"x":3,"y":4}}"#.to_string(),
+    };
+    group.bench_function("decode_move", |b| {
+        b.iter(|| to_player_move::<Pos>(black_box(&player_move)).unwrap())
+    });
+    let auth = r#"{"auth":{"username":"bot","password":"secret"}}"#;
+    group.bench_function("decode_auth", |b| {
+        b.iter(|| serde_json::from_str::<FromClient>(black_box(auth)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, turn_tracker, grid, protocol);
+criterion_main!(benches);
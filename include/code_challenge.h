@@ -0,0 +1,61 @@
+/*
+ * C ABI of code-challenge-game-types, built with the `cabi` feature. See
+ * src/cabi.rs for the rules: strings are NUL-terminated UTF-8, strings
+ * returned are freed with cc_string_free, and calls that fail return NULL
+ * with the reason in cc_last_error.
+ */
+
+#ifndef CODE_CHALLENGE_H
+#define CODE_CHALLENGE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CC_ABI_VERSION 1
+
+typedef enum CcCommand {
+    CC_OFFER_DRAW,
+    CC_ACCEPT_DRAW,
+    CC_DECLINE_DRAW,
+    CC_CATCH_UP,
+} CcCommand;
+
+typedef struct CcMessage {
+    char *kind;
+    /* JSON, or NULL for messages without a body. */
+    char *body;
+} CcMessage;
+
+typedef struct CcReplay CcReplay;
+
+uint32_t cc_abi_version(void);
+const char *cc_last_error(void);
+void cc_string_free(char *s);
+
+char *cc_encode_auth(const char *username, const char *password);
+char *cc_encode_move(const char *move_json);
+char *cc_encode_command(CcCommand command);
+char *cc_encode_rewind(size_t move_index);
+
+CcMessage cc_decode(const char *json);
+void cc_message_free(CcMessage message);
+
+CcReplay *cc_replay_read(const uint8_t *bytes, size_t len);
+void cc_replay_free(CcReplay *replay);
+char *cc_replay_to_json(const CcReplay *replay);
+size_t cc_replay_num_players(const CcReplay *replay);
+char *cc_replay_player(const CcReplay *replay, size_t index);
+size_t cc_replay_num_moves(const CcReplay *replay);
+char *cc_replay_move_player(const CcReplay *replay, size_t index);
+char *cc_replay_move(const CcReplay *replay, size_t index);
+char *cc_replay_outcome(const CcReplay *replay);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
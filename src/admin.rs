@@ -0,0 +1,227 @@
+//! Commands for organizers stepping into live games from their tooling:
+//! kicking a player, pausing, forcing a forfeit, fixing a clock and
+//! restarting. Each request carries the admin token, and `Admin` checks it
+//! before passing the command on to the game's `GameHost` mailbox.
+//!
+//! ```text
+//! {"token":"...","command":{"adjust-clock":{"game":3,"player":"bob","by-ms":-5000}}}
+//! ```
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::host::{Command, GameHost, GameId, HostError};
+
+/// The secret admin requests have to present, as set in the server's
+/// configuration. Not printed by `Debug`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdminToken(String);
+
+impl AdminToken {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    /// Compares every byte whatever the first difference, so how long a
+    /// refusal takes says nothing about the token.
+    pub fn verify(&self, presented: &str) -> bool {
+        let (expected, presented) = (self.0.as_bytes(), presented.as_bytes());
+        expected.len() == presented.len()
+            && expected
+                .iter()
+                .zip(presented)
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+impl fmt::Debug for AdminToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AdminToken(..)")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminRequest {
+    pub token: String,
+    pub command: AdminCommand,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AdminCommand {
+    /// Disconnects the player, as if they'd dropped.
+    KickPlayer {
+        game: GameId,
+        player: String,
+    },
+    PauseGame {
+        game: GameId,
+    },
+    ResumeGame {
+        game: GameId,
+    },
+    /// Throws the player out of the game, which may hand it to whoever is
+    /// left.
+    ForceForfeit {
+        game: GameId,
+        player: String,
+        reason: String,
+    },
+    /// Gives the player more time, or takes some off if `by_ms` is
+    /// negative.
+    #[serde(rename_all = "kebab-case")]
+    AdjustClock {
+        game: GameId,
+        player: String,
+        by_ms: i64,
+    },
+    /// Starts the game over with the players it has.
+    RestartGame {
+        game: GameId,
+    },
+}
+
+impl AdminCommand {
+    pub fn game(&self) -> GameId {
+        match self {
+            AdminCommand::KickPlayer { game, .. }
+            | AdminCommand::PauseGame { game }
+            | AdminCommand::ResumeGame { game }
+            | AdminCommand::ForceForfeit { game, .. }
+            | AdminCommand::AdjustClock { game, .. }
+            | AdminCommand::RestartGame { game } => *game,
+        }
+    }
+
+    /// The host command doing this.
+    pub fn into_command(self) -> Command {
+        match self {
+            AdminCommand::KickPlayer { player, .. } => Command::Disconnect(player),
+            AdminCommand::PauseGame { .. } => Command::Pause,
+            AdminCommand::ResumeGame { .. } => Command::Resume,
+            AdminCommand::ForceForfeit { player, reason, .. } => {
+                Command::Disqualify(player, reason)
+            }
+            AdminCommand::AdjustClock { player, by_ms, .. } => Command::AdjustClock(player, by_ms),
+            AdminCommand::RestartGame { .. } => Command::Restart,
+        }
+    }
+}
+
+/// What the tooling is told. The command's own results come out of the
+/// host as events, like any other.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AdminReply {
+    Accepted,
+    Refused { reason: String },
+}
+
+impl From<Result<(), AdminError>> for AdminReply {
+    fn from(result: Result<(), AdminError>) -> Self {
+        match result {
+            Ok(()) => AdminReply::Accepted,
+            Err(e) => AdminReply::Refused {
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum AdminError {
+    #[error("wrong admin token")]
+    WrongToken,
+    #[error(transparent)]
+    Host(#[from] HostError),
+}
+
+/// Checks admin requests and hands them to the games.
+#[derive(Debug)]
+pub struct Admin {
+    token: AdminToken,
+}
+
+impl Admin {
+    pub fn new(token: AdminToken) -> Self {
+        Self { token }
+    }
+
+    /// Queues the command for its game, if the token is right. Every
+    /// request is logged, refused ones as warnings.
+    pub fn handle(&self, host: &GameHost, request: AdminRequest) -> Result<(), AdminError> {
+        if !self.token.verify(&request.token) {
+            warn!(command = ?request.command, "admin request with the wrong token");
+            return Err(AdminError::WrongToken);
+        }
+        info!(command = ?request.command, "admin command");
+        let game = request.command.game();
+        host.send(game, request.command.into_command())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::host::Event;
+    use crate::lifecycle::{GameLifecycle, LifecycleEvent};
+    use crate::replay::test_game::TakeAway;
+    use crate::test_support::users;
+
+    fn request(token: &str, command: AdminCommand) -> AdminRequest {
+        AdminRequest {
+            token: token.to_string(),
+            command,
+        }
+    }
+
+    #[test]
+    fn checks_the_token() {
+        let token = AdminToken::new("secret");
+        assert!(token.verify("secret"));
+        assert!(!token.verify("secreT"));
+        assert!(!token.verify("secret!"));
+        assert_eq!(format!("{token:?}"), "AdminToken(..)");
+    }
+
+    #[test]
+    fn passes_commands_to_the_host() {
+        let admin = Admin::new(AdminToken::new("secret"));
+        let host = GameHost::new(1);
+        let game = host.add(Box::new(TakeAway::new(4)));
+        host.send(game, Command::Reset(users(2))).unwrap();
+        host.send(game, Command::Start).unwrap();
+
+        let pause = AdminCommand::PauseGame { game };
+        assert_eq!(
+            admin.handle(&host, request("guess", pause.clone())),
+            Err(AdminError::WrongToken)
+        );
+        admin.handle(&host, request("secret", pause)).unwrap();
+        let paused = loop {
+            match host.events().recv_timeout(Duration::from_secs(5)).unwrap() {
+                (_, Event::Lifecycle(event)) if event.to == GameLifecycle::Paused => break event,
+                _ => {}
+            }
+        };
+        assert_eq!(
+            paused,
+            LifecycleEvent {
+                from: GameLifecycle::InProgress,
+                to: GameLifecycle::Paused
+            }
+        );
+
+        host.remove(game);
+        assert_eq!(
+            admin.handle(&host, request("secret", AdminCommand::RestartGame { game })),
+            Err(AdminError::Host(HostError::UnknownGame(game)))
+        );
+    }
+}
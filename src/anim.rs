@@ -0,0 +1,215 @@
+//! Tweening for game UIs, so pieces slide to where a move put them instead of
+//! jumping there. Feed the positions after each turn to `Animations::update`
+//! and paint `Animations::get` until `is_animating` turns false.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+use crate::color::Color;
+use crate::draw::{Point, Rect};
+use crate::time::Instant;
+
+/// Linear interpolation, `t` going from 0 at `self` to 1 at `to`.
+pub trait Lerp {
+    fn lerp(&self, to: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for Point {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        Point::new(self.x.lerp(&to.x, t), self.y.lerp(&to.y, t))
+    }
+}
+
+impl Lerp for Rect {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        Rect::new(
+            self.x0.lerp(&to.x0, t),
+            self.y0.lerp(&to.y0, t),
+            self.x1.lerp(&to.x1, t),
+            self.y1.lerp(&to.y1, t),
+        )
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        let (r1, g1, b1, a1) = self.as_rgba8();
+        let (r2, g2, b2, a2) = to.as_rgba8();
+        let channel = |from: u8, to: u8| (from as f64).lerp(&(to as f64), t).round() as u8;
+        Color::rgba8(
+            channel(r1, r2),
+            channel(g1, g2),
+            channel(b1, b2),
+            channel(a1, a2),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps the linear progress `t` in `0..=1` to eased progress.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tween<T> {
+    pub from: T,
+    pub to: T,
+    pub start: Instant,
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+impl<T: Lerp + Clone> Tween<T> {
+    pub fn progress(&self, now: Instant) -> f64 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        let elapsed = now.saturating_duration_since(self.start);
+        (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+    }
+
+    pub fn value(&self, now: Instant) -> T {
+        match self.progress(now) {
+            p if p >= 1.0 => self.to.clone(),
+            p => self.from.lerp(&self.to, self.easing.apply(p)),
+        }
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        self.progress(now) >= 1.0
+    }
+}
+
+/// Animation state of every entity on the board, keyed by something stable
+/// across turns like a piece id.
+#[derive(Clone, Debug)]
+pub struct Animations<K, T> {
+    duration: Duration,
+    easing: Easing,
+    entities: HashMap<K, Tween<T>>,
+}
+
+impl<K: Eq + Hash, T: Lerp + Clone> Animations<K, T> {
+    pub fn new(duration: Duration, easing: Easing) -> Self {
+        Self {
+            duration,
+            easing,
+            entities: HashMap::new(),
+        }
+    }
+
+    /// Starts moving `key` to `target` from wherever it is at `now`. Entities
+    /// not seen before appear at `target` right away.
+    pub fn set(&mut self, key: K, target: T, now: Instant) {
+        let tween = self.tween(self.entities.get(&key), target, now);
+        self.entities.insert(key, tween);
+    }
+
+    /// Animates towards the state after a turn. Entities missing from
+    /// `targets` are dropped.
+    pub fn update(&mut self, targets: impl IntoIterator<Item = (K, T)>, now: Instant) {
+        let previous = std::mem::take(&mut self.entities);
+        for (key, target) in targets {
+            let tween = self.tween(previous.get(&key), target, now);
+            self.entities.insert(key, tween);
+        }
+    }
+
+    fn tween(&self, current: Option<&Tween<T>>, target: T, now: Instant) -> Tween<T> {
+        let (from, duration) = match current {
+            Some(tween) => (tween.value(now), self.duration),
+            None => (target.clone(), Duration::ZERO),
+        };
+        Tween {
+            from,
+            to: target,
+            start: now,
+            duration,
+            easing: self.easing,
+        }
+    }
+
+    pub fn get(&self, key: &K, now: Instant) -> Option<T> {
+        self.entities.get(key).map(|tween| tween.value(now))
+    }
+
+    pub fn is_animating(&self, now: Instant) -> bool {
+        self.entities.values().any(|tween| !tween.is_done(now))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lerp_and_easing() {
+        assert_eq!(
+            Point::new(0.0, 10.0).lerp(&Point::new(10.0, 0.0), 0.25),
+            Point::new(2.5, 7.5)
+        );
+        assert_eq!(
+            Color::rgba8(0, 0, 0, 0).lerp(&Color::rgba8(200, 100, 0, 255), 0.5),
+            Color::rgba8(100, 50, 0, 128)
+        );
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert_eq!(easing.apply(2.0), 1.0);
+        }
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+        assert!(Easing::EaseIn.apply(0.5) < 0.5 && Easing::EaseOut.apply(0.5) > 0.5);
+    }
+
+    #[test]
+    fn animates_between_turns() {
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+        let mut anim = Animations::new(second, Easing::Linear);
+
+        anim.update([("a", 0.0), ("b", 5.0)], start);
+        assert_eq!(anim.get(&"a", start), Some(0.0));
+        assert!(!anim.is_animating(start));
+
+        anim.update([("a", 10.0)], start);
+        assert_eq!(anim.get(&"b", start), None);
+        assert_eq!(anim.get(&"a", start + second / 2), Some(5.0));
+        assert!(anim.is_animating(start + second / 2));
+
+        // Retargeting mid-move continues from the current position.
+        anim.set("a", 0.0, start + second / 2);
+        assert_eq!(anim.get(&"a", start + second), Some(2.5));
+        assert_eq!(anim.get(&"a", start + second * 2), Some(0.0));
+        assert!(!anim.is_animating(start + second * 2));
+    }
+}
@@ -0,0 +1,165 @@
+//! `proptest` strategies for the crate's types, so games can property-test
+//! their rules without writing generators by hand: `any::<Pos>()`,
+//! `any::<Grid<bool>>()` and so on, and `grid` for grids of cells from a
+//! strategy of your own.
+
+use std::fmt::Debug;
+
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+use crate::color::Color;
+use crate::gametraits::User;
+use crate::grid::Grid;
+use crate::messages::{
+    Auth, ForfeitReason, Forfeited, FromClient, GameOver, Move, Rewind, Rewound, TimeWarning,
+    ToClient, INVALID_MESSAGE_FORMAT, INVALID_MOVE, WRONG_PASSWORD,
+};
+use crate::pos::{Direction, Pos};
+
+/// The largest side `any::<Grid<T>>()` generates.
+pub const MAX_GRID_SIDE: usize = 12;
+
+/// Grids from 1x1 to `max_side` a side with cells drawn from `cell`.
+pub fn grid<S>(cell: S, max_side: usize) -> impl Strategy<Value = Grid<S::Value>>
+where
+    S: Strategy + Clone,
+{
+    (1..=max_side, 1..=max_side).prop_flat_map(move |(width, height)| {
+        vec(cell.clone(), width * height).prop_map(move |cells| {
+            let mut cells = cells.into_iter();
+            Grid::from_fn(width, height, |_, _| cells.next().unwrap())
+        })
+    })
+}
+
+/// Positions within 64 of the origin, enough to fall both on and off any
+/// board a test is likely to use.
+impl Arbitrary for Pos {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Pos>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (-64..64i32, -64..64i32)
+            .prop_map(|(x, y)| Pos::new(x, y))
+            .boxed()
+    }
+}
+
+impl Arbitrary for Direction {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Direction>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        select(Direction::ALL.to_vec()).boxed()
+    }
+}
+
+impl Arbitrary for User {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<User>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        ("[a-z][a-z0-9_]{0,11}", any::<u32>())
+            .prop_map(|(name, rgba)| User::new(name, Color::from_rgba32_u32(rgba)))
+            .boxed()
+    }
+}
+
+impl<T: Arbitrary + Clone + Debug + 'static> Arbitrary for Grid<T> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Grid<T>>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        grid(any::<T>(), MAX_GRID_SIDE).boxed()
+    }
+}
+
+impl<T: Arbitrary + 'static> Arbitrary for Move<T> {
+    type Parameters = T::Parameters;
+    type Strategy = BoxedStrategy<Move<T>>;
+
+    fn arbitrary_with(parameters: T::Parameters) -> Self::Strategy {
+        any_with::<T>(parameters).prop_map(Move::Move).boxed()
+    }
+}
+
+impl Arbitrary for Auth<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Auth<'static>>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        ("[a-z][a-z0-9_]{0,11}", "\\PC{0,16}")
+            .prop_map(|(username, password)| Auth {
+                username: username.into(),
+                password: password.into(),
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for FromClient<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<FromClient<'static>>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            any::<Auth<'static>>().prop_map(FromClient::Auth),
+            select(vec![
+                FromClient::OfferDraw,
+                FromClient::AcceptDraw,
+                FromClient::DeclineDraw,
+                FromClient::CatchUp
+            ]),
+            any::<usize>().prop_map(|move_index| FromClient::Rewind(Rewind { move_index })),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for ToClient {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<ToClient>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            select(vec![INVALID_MESSAGE_FORMAT, WRONG_PASSWORD, INVALID_MOVE]),
+            "\\PC{0,32}".prop_map(|reason| ToClient::GameOver(GameOver { reason })),
+            any::<u64>()
+                .prop_map(|remaining_ms| ToClient::TimeWarning(TimeWarning { remaining_ms })),
+            ("[a-z][a-z0-9_]{0,11}", option::of("\\PC{0,32}")).prop_map(|(player, reason)| {
+                ToClient::Forfeited(Forfeited {
+                    player,
+                    reason: reason.map_or(ForfeitReason::Resigned, |reason| {
+                        ForfeitReason::Disqualified { reason }
+                    }),
+                })
+            }),
+            (any::<usize>(), any::<usize>()).prop_map(|(move_index, moves)| {
+                ToClient::Rewound(Rewound { move_index, moves })
+            }),
+        ]
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn grids_survive_rows(grid in any::<Grid<u8>>()) {
+            prop_assert!((1..=MAX_GRID_SIDE).contains(&grid.width()));
+            let rows = grid.clone().into_rows();
+            prop_assert_eq!(Grid::from_rows(rows).unwrap(), grid);
+        }
+
+        #[test]
+        fn steps_undo(pos in any::<Pos>(), direction in any::<Direction>()) {
+            prop_assert_eq!(pos.step(direction).step(direction.opposite()), pos);
+        }
+    }
+}
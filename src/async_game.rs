@@ -0,0 +1,131 @@
+//! Games for async servers, which may await something outside the game on
+//! a move, such as a puzzle generator or a judge behind an HTTP API. The
+//! server drives every game through `AsyncGame`, and games with nothing to
+//! await are wrapped in `FromSync` rather than written twice.
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+
+use crate::games::Viewer;
+use crate::gametraits::{
+    FilteredState, GameTrait, PlayerMove, PlayerMoveResult, PlayerTurn, TurnToken, User,
+};
+
+/// `GameTrait`'s moves and state queries, awaited. The futures are `Send`,
+/// so a game can be driven from any task of a multi-threaded runtime.
+#[async_trait]
+pub trait AsyncGame: Send + Debug {
+    async fn player_moves(
+        &mut self,
+        turn_token: TurnToken,
+        player_move: PlayerMove,
+    ) -> PlayerMoveResult;
+    async fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn>;
+
+    async fn try_start_game(&mut self) -> Option<PlayerTurn>;
+
+    async fn player_connected(&mut self, user: User);
+    async fn player_disconnected(&mut self, user: &str);
+
+    async fn reset(&mut self, users: Vec<User>);
+
+    /// See `GameTrait::visible_to`.
+    async fn visible_to(&self, _viewer: Viewer<'_>) -> Option<FilteredState> {
+        None
+    }
+}
+
+/// A `GameTrait` game as an `AsyncGame`, every future ready as soon as
+/// it's polled. Games are quick enough not to need a blocking thread.
+#[derive(Clone, Debug)]
+pub struct FromSync(pub Box<dyn GameTrait>);
+
+impl FromSync {
+    pub fn new(game: Box<dyn GameTrait>) -> Self {
+        Self(game)
+    }
+
+    pub fn game(&self) -> &dyn GameTrait {
+        &*self.0
+    }
+
+    pub fn into_inner(self) -> Box<dyn GameTrait> {
+        self.0
+    }
+}
+
+#[async_trait]
+impl AsyncGame for FromSync {
+    async fn player_moves(
+        &mut self,
+        turn_token: TurnToken,
+        player_move: PlayerMove,
+    ) -> PlayerMoveResult {
+        self.0.player_moves(turn_token, player_move)
+    }
+
+    async fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        self.0.current_player_disconnected(turn_token)
+    }
+
+    async fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        self.0.try_start_game()
+    }
+
+    async fn player_connected(&mut self, user: User) {
+        self.0.player_connected(user)
+    }
+
+    async fn player_disconnected(&mut self, user: &str) {
+        self.0.player_disconnected(user)
+    }
+
+    async fn reset(&mut self, users: Vec<User>) {
+        self.0.reset(users)
+    }
+
+    async fn visible_to(&self, viewer: Viewer<'_>) -> Option<FilteredState> {
+        self.0.visible_to(viewer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::games::tictactoe::TicTacToe;
+    use crate::test_support::user;
+
+    /// Runs a future that never has to wait, as `FromSync`'s don't.
+    fn ready<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the future had to wait"),
+        }
+    }
+
+    #[test]
+    fn plays_a_sync_game() {
+        let mut game = FromSync::new(Box::new(TicTacToe::new()));
+        ready(game.player_connected(user("p1")));
+        ready(game.player_connected(user("p2")));
+        let turn = ready(game.try_start_game()).unwrap();
+        assert_eq!(turn.token.user.name, "p1");
+        let move_at = |x, y| PlayerMove {
+            serialized: format!("{{\"move\":{{\"x\":{x},\"y\":{y}}}}}"),
+        };
+        let PlayerMoveResult::Ok(turn) = ready(game.player_moves(turn.token, move_at(1, 1))) else {
+            panic!("expected the move to be taken");
+        };
+        assert_eq!(turn.token.user.name, "p2");
+        assert_eq!(
+            ready(game.visible_to(Viewer::Spectator)),
+            game.game().visible_to(Viewer::Spectator)
+        );
+        assert!(ready(game.visible_to(Viewer::Spectator)).is_some());
+    }
+}
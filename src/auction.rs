@@ -0,0 +1,320 @@
+//! Bidding for games that sell things to the highest bidder, so each one
+//! doesn't have to collect and settle bids on its own. A [`SealedAuction`]
+//! takes one hidden bid from every bidder and reveals them all at once, an
+//! [`OpenAuction`] goes up in the open until everyone else has passed.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bid {
+    pub bidder: String,
+    pub amount: u32,
+}
+
+/// Who bought and what they pay.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sale {
+    pub winner: String,
+    pub price: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum BidError {
+    #[error("not a bidder")]
+    NotABidder,
+    /// Sealed bids can't be changed, and a bidder that passed is out.
+    #[error("already bid")]
+    AlreadyBid,
+    /// The bid was below the reserve or the last bid plus the increment.
+    #[error("bid too low, the minimum is {minimum}")]
+    TooLow { minimum: u32 },
+    /// The highest bidder can't pass on their own bid.
+    #[error("the highest bidder can't pass")]
+    Leading,
+    #[error("the auction is closed")]
+    Closed,
+}
+
+/// What the winner of a sealed auction pays: their own bid, or the second
+/// highest (a Vickrey auction), but never less than the reserve.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Pricing {
+    #[default]
+    FirstPrice,
+    SecondPrice,
+}
+
+/// Every bidder bids once without seeing the others. The bids are only
+/// revealed once all are in, and ties go to whoever bid first.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedAuction {
+    bidders: Vec<String>,
+    bids: Vec<Bid>,
+    reserve: u32,
+}
+
+impl SealedAuction {
+    pub fn new(bidders: Vec<String>) -> Self {
+        Self {
+            bidders,
+            bids: vec![],
+            reserve: 0,
+        }
+    }
+
+    pub fn with_reserve(mut self, reserve: u32) -> Self {
+        self.reserve = reserve;
+        self
+    }
+
+    pub fn bid(&mut self, bidder: &str, amount: u32) -> Result<(), BidError> {
+        if self.is_complete() {
+            return Err(BidError::Closed);
+        }
+        if !self.bidders.iter().any(|b| b == bidder) {
+            return Err(BidError::NotABidder);
+        }
+        if self.has_bid(bidder) {
+            return Err(BidError::AlreadyBid);
+        }
+        if amount < self.reserve {
+            return Err(BidError::TooLow {
+                minimum: self.reserve,
+            });
+        }
+        self.bids.push(Bid {
+            bidder: bidder.to_string(),
+            amount,
+        });
+        Ok(())
+    }
+
+    pub fn has_bid(&self, bidder: &str) -> bool {
+        self.bids.iter().any(|bid| bid.bidder == bidder)
+    }
+
+    /// The bidders yet to bid, in the order they were given.
+    pub fn waiting_on(&self) -> Vec<&str> {
+        self.bidders
+            .iter()
+            .filter(|b| !self.has_bid(b))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Takes a bidder that left out of the auction, along with their bid.
+    pub fn remove_bidder(&mut self, bidder: &str) {
+        self.bidders.retain(|b| b != bidder);
+        self.bids.retain(|bid| bid.bidder != bidder);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.bids.len() == self.bidders.len()
+    }
+
+    /// The bids in the order they came, once every bidder has bid.
+    pub fn reveal(&self) -> Option<&[Bid]> {
+        self.is_complete().then_some(&self.bids[..])
+    }
+
+    /// The sale once every bidder has bid, `None` before that or when
+    /// nobody bid at all.
+    pub fn resolve(&self, pricing: Pricing) -> Option<Sale> {
+        let bids = self.reveal()?;
+        // The sort is stable, so of equal bids the earlier one stays first.
+        let mut ranked: Vec<&Bid> = bids.iter().collect();
+        ranked.sort_by_key(|bid| std::cmp::Reverse(bid.amount));
+        let winner = ranked.first()?;
+        let price = match pricing {
+            Pricing::FirstPrice => winner.amount,
+            Pricing::SecondPrice => ranked.get(1).map_or(self.reserve, |bid| bid.amount),
+        };
+        Some(Sale {
+            winner: winner.bidder.clone(),
+            price: price.max(self.reserve),
+        })
+    }
+}
+
+/// An ascending auction. Bidders raise in the open by at least the
+/// increment or pass and drop out, and it's over once only the highest
+/// bidder is left, or nobody is. Whose turn it is to speak is up to the game.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenAuction {
+    /// The bidders still in.
+    active: Vec<String>,
+    high_bid: Option<Bid>,
+    reserve: u32,
+    increment: u32,
+}
+
+impl OpenAuction {
+    pub fn new(bidders: Vec<String>, increment: u32) -> Self {
+        Self {
+            active: bidders,
+            high_bid: None,
+            reserve: 0,
+            increment: increment.max(1),
+        }
+    }
+
+    pub fn with_reserve(mut self, reserve: u32) -> Self {
+        self.reserve = reserve;
+        self
+    }
+
+    pub fn active(&self) -> &[String] {
+        &self.active
+    }
+
+    pub fn high_bid(&self) -> Option<&Bid> {
+        self.high_bid.as_ref()
+    }
+
+    /// The least the next bid may be.
+    pub fn minimum(&self) -> u32 {
+        self.high_bid
+            .as_ref()
+            .map_or(self.reserve, |bid| bid.amount + self.increment)
+    }
+
+    pub fn raise(&mut self, bidder: &str, amount: u32) -> Result<(), BidError> {
+        self.check(bidder)?;
+        if amount < self.minimum() {
+            return Err(BidError::TooLow {
+                minimum: self.minimum(),
+            });
+        }
+        self.high_bid = Some(Bid {
+            bidder: bidder.to_string(),
+            amount,
+        });
+        Ok(())
+    }
+
+    pub fn pass(&mut self, bidder: &str) -> Result<(), BidError> {
+        self.check(bidder)?;
+        if self.is_leading(bidder) {
+            return Err(BidError::Leading);
+        }
+        self.active.retain(|b| b != bidder);
+        Ok(())
+    }
+
+    /// Takes a bidder that left out of the auction. Unlike passing this
+    /// also withdraws their bid when they were leading, so the auction
+    /// starts over from the reserve.
+    pub fn remove_bidder(&mut self, bidder: &str) {
+        self.active.retain(|b| b != bidder);
+        if self.is_leading(bidder) {
+            self.high_bid = None;
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        match &self.high_bid {
+            Some(bid) => self.active == [bid.bidder.clone()],
+            None => self.active.is_empty(),
+        }
+    }
+
+    /// The sale once the auction is over, `None` before that or when
+    /// everybody passed.
+    pub fn result(&self) -> Option<Sale> {
+        let bid = self.high_bid.as_ref().filter(|_| self.is_over())?;
+        Some(Sale {
+            winner: bid.bidder.clone(),
+            price: bid.amount,
+        })
+    }
+
+    fn is_leading(&self, bidder: &str) -> bool {
+        self.high_bid
+            .as_ref()
+            .is_some_and(|bid| bid.bidder == bidder)
+    }
+
+    fn check(&self, bidder: &str) -> Result<(), BidError> {
+        if self.is_over() {
+            return Err(BidError::Closed);
+        }
+        if !self.active.iter().any(|b| b == bidder) {
+            return Err(BidError::NotABidder);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bidders(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn sealed_bids() {
+        let mut auction = SealedAuction::new(bidders(&["a", "b", "c"])).with_reserve(5);
+        assert_eq!(auction.bid("a", 10), Ok(()));
+        assert_eq!(auction.bid("a", 12), Err(BidError::AlreadyBid));
+        assert_eq!(auction.bid("b", 4), Err(BidError::TooLow { minimum: 5 }));
+        assert_eq!(auction.bid("d", 20), Err(BidError::NotABidder));
+        assert_eq!(auction.bid("b", 7), Ok(()));
+        assert_eq!(auction.waiting_on(), ["c"]);
+        assert_eq!(auction.reveal(), None);
+        assert_eq!(auction.resolve(Pricing::FirstPrice), None);
+
+        assert_eq!(auction.bid("c", 10), Ok(()));
+        assert_eq!(auction.reveal().map(<[Bid]>::len), Some(3));
+        assert_eq!(auction.bid("c", 11), Err(BidError::Closed));
+        let sale = |winner: &str, price| {
+            Some(Sale {
+                winner: winner.to_string(),
+                price,
+            })
+        };
+        // a and c tie, and a bid first.
+        assert_eq!(auction.resolve(Pricing::FirstPrice), sale("a", 10));
+        assert_eq!(auction.resolve(Pricing::SecondPrice), sale("a", 10));
+
+        auction.remove_bidder("c");
+        assert_eq!(auction.resolve(Pricing::SecondPrice), sale("a", 7));
+        auction.remove_bidder("b");
+        assert_eq!(auction.resolve(Pricing::SecondPrice), sale("a", 5));
+    }
+
+    #[test]
+    fn open_auction() {
+        let mut auction = OpenAuction::new(bidders(&["a", "b", "c"]), 2).with_reserve(3);
+        assert_eq!(auction.raise("a", 2), Err(BidError::TooLow { minimum: 3 }));
+        assert_eq!(auction.raise("a", 3), Ok(()));
+        assert_eq!(auction.raise("b", 4), Err(BidError::TooLow { minimum: 5 }));
+        assert_eq!(auction.pass("a"), Err(BidError::Leading));
+        assert_eq!(auction.raise("b", 6), Ok(()));
+        assert_eq!(auction.pass("c"), Ok(()));
+        assert_eq!(auction.raise("c", 9), Err(BidError::NotABidder));
+        assert!(!auction.is_over());
+        assert_eq!(auction.result(), None);
+        assert_eq!(auction.pass("a"), Ok(()));
+        assert!(auction.is_over());
+        assert_eq!(auction.raise("b", 9), Err(BidError::Closed));
+        assert_eq!(
+            auction.result(),
+            Some(Sale {
+                winner: "b".to_string(),
+                price: 6
+            })
+        );
+
+        let mut auction = OpenAuction::new(bidders(&["a", "b"]), 1);
+        auction.raise("a", 1).unwrap();
+        auction.remove_bidder("a");
+        assert_eq!(auction.high_bid(), None);
+        assert!(!auction.is_over());
+        auction.pass("b").unwrap();
+        assert!(auction.is_over());
+        assert_eq!(auction.result(), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! Bots that play from inside the process, for smoke tests, for filling
+//! empty seats in a tournament and as a baseline to measure real bots
+//! against.
+//!
+//! A bot plays like any client: it sends `auth` once connected, and on each
+//! `your-turn` answers with `{"move":...}`. Choosing the move needs the game
+//! itself rather than the state sent over the wire, so bots only play games
+//! that can list their `LegalMoves`; hooking one up to a socket is left to
+//! whoever hosts it.
+
+use std::borrow::Cow;
+
+use serde::Serialize;
+
+use crate::games::LegalMoves;
+use crate::gametraits::{from_player_move, PlayerMove};
+use crate::messages::{Auth, FromClient};
+use crate::rng::{GameRng, SplitMix64};
+
+/// How a bot picks its move in `game`, `None` when there is none to make.
+pub trait Strategy<G: LegalMoves> {
+    fn choose(&mut self, game: &G) -> Option<G::Move>;
+}
+
+/// What reaches the server when a bot is given its turn.
+#[derive(Debug)]
+pub enum Answer {
+    Move(PlayerMove),
+    /// The bot had no move to make.
+    NoMove,
+    /// Nothing arrives, as if the message was lost.
+    Dropped,
+}
+
+/// A bot as the server sees it, answering each turn over the protocol.
+pub trait Client<G> {
+    fn answer(&mut self, game: &G) -> Answer;
+}
+
+/// The answer of a bot that sends whatever `strategy` chooses, for
+/// implementing `Client` on a `Strategy`.
+pub fn answer<G, S>(strategy: &mut S, game: &G) -> Answer
+where
+    G: LegalMoves,
+    G::Move: Serialize,
+    S: Strategy<G>,
+{
+    match strategy.choose(game) {
+        Some(chosen) => Answer::Move(from_player_move(chosen)),
+        None => Answer::NoMove,
+    }
+}
+
+/// Plays a legal move picked uniformly at random, from a seed so a game
+/// against it can be played again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RandomBot<R: GameRng = SplitMix64> {
+    name: String,
+    rng: R,
+}
+
+impl RandomBot {
+    pub fn new(name: &str, seed: u64) -> Self {
+        Self::with_rng(name, SplitMix64::new(seed))
+    }
+}
+
+impl<R: GameRng> RandomBot<R> {
+    pub fn with_rng(name: &str, rng: R) -> Self {
+        Self {
+            name: name.to_string(),
+            rng,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What the bot sends on connecting.
+    pub fn auth<'a>(&'a self, password: &'a str) -> FromClient<'a> {
+        FromClient::Auth(Auth {
+            username: Cow::Borrowed(&self.name),
+            password: Cow::Borrowed(password),
+        })
+    }
+}
+
+impl<G: LegalMoves, R: GameRng> Strategy<G> for RandomBot<R> {
+    fn choose(&mut self, game: &G) -> Option<G::Move> {
+        let mut moves = game.legal_moves();
+        if moves.is_empty() {
+            return None;
+        }
+        Some(moves.swap_remove(self.rng.index(moves.len())))
+    }
+}
+
+impl<G, R> Client<G> for RandomBot<R>
+where
+    G: LegalMoves,
+    G::Move: Serialize,
+    R: GameRng,
+{
+    fn answer(&mut self, game: &G) -> Answer {
+        answer(self, game)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::games::tictactoe::TicTacToe;
+
+    #[test]
+    fn plays_legal_moves_to_the_end() {
+        let mut bot = RandomBot::new("bot", 1);
+        let mut game = TicTacToe::new();
+        let mut moves = 0;
+        while let Some(pos) = bot.choose(&game) {
+            assert!(game.legal_moves().contains(&pos));
+            assert!(game.play(pos));
+            moves += 1;
+        }
+        assert!(game.outcome().is_some());
+        assert!((5..=9).contains(&moves));
+
+        // The same seed plays the same game.
+        let mut again = RandomBot::new("bot", 1);
+        let mut replayed = TicTacToe::new();
+        while let Some(pos) = again.choose(&replayed) {
+            replayed.play(pos);
+        }
+        assert_eq!(replayed, game);
+    }
+}
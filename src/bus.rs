@@ -0,0 +1,113 @@
+//! One place for the server to hear what happens in every game, for logs,
+//! metrics and UIs alike. Each listener subscribes for its own copy of
+//! every event from then on.
+//!
+//! Only `GameHost` publishes, for the games it hosts, and only what goes
+//! through its commands: joins, turns, moves, scores and results. Games
+//! run without a host, and what spectators, clocks and recorders do on
+//! the side, stay off the bus unless whatever runs them publishes too.
+//!
+//! The bus is a tokio broadcast channel, so listeners can await events in
+//! a task or poll for them with `try_recv` from a thread. A listener that
+//! falls more than the bus's capacity behind loses the oldest events and
+//! is told how many it missed.
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::host::GameId;
+use crate::replay::GameOutcome;
+
+/// Events kept for a listener that hasn't caught up, unless said otherwise.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GameEvent {
+    PlayerJoined {
+        player: String,
+    },
+    /// `player` was handed the turn.
+    TurnStarted {
+        player: String,
+    },
+    /// The game took `player_move`, as the player sent it.
+    MoveApplied {
+        player: String,
+        player_move: String,
+    },
+    /// See `GameTrait::player_scores`.
+    ScoreChanged {
+        player: String,
+        score: i64,
+    },
+    /// `None` for games that ended without a result.
+    GameOver {
+        outcome: Option<GameOutcome>,
+    },
+}
+
+/// Clones publish into the same bus.
+#[derive(Clone, Debug)]
+pub struct EventBus {
+    sender: broadcast::Sender<(GameId, GameEvent)>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// Sends `event` to every listener. With nobody listening it's dropped.
+    pub fn publish(&self, game: GameId, event: GameEvent) {
+        let _ = self.sender.send((game, event));
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<(GameId, GameEvent)> {
+        self.sender.subscribe()
+    }
+
+    pub fn listeners(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    use super::*;
+
+    fn joined(player: &str) -> GameEvent {
+        GameEvent::PlayerJoined {
+            player: player.to_string(),
+        }
+    }
+
+    #[test]
+    fn every_listener_hears_every_event() {
+        let bus = EventBus::new(2);
+        let game = GameId::from_u64(7);
+        bus.publish(game, joined("nobody"));
+        let mut first = bus.subscribe();
+        let mut second = bus.clone().subscribe();
+        assert_eq!(bus.listeners(), 2);
+        bus.publish(game, joined("p1"));
+        assert_eq!(first.try_recv(), Ok((game, joined("p1"))));
+        assert_eq!(second.try_recv(), Ok((game, joined("p1"))));
+        assert_eq!(first.try_recv(), Err(TryRecvError::Empty));
+
+        for player in ["p2", "p3", "p4"] {
+            bus.publish(game, joined(player));
+        }
+        assert_eq!(first.try_recv(), Err(TryRecvError::Lagged(1)));
+        assert_eq!(first.try_recv(), Ok((game, joined("p3"))));
+    }
+}
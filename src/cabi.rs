@@ -0,0 +1,346 @@
+//! A C ABI over the protocol and replays, for bot SDKs in C, C++ and C# to
+//! build on instead of each implementing the protocol again. Declarations
+//! are in `include/code_challenge.h`, and the library is built with
+//! `cargo rustc --release --features cabi --crate-type cdylib` (or
+//! `staticlib`).
+//!
+//! Strings go in and come out as NUL-terminated UTF-8. Strings coming out
+//! belong to the caller, who frees them with `cc_string_free`, and replays
+//! are handles freed with `cc_replay_free`. A call that fails returns null
+//! and leaves the reason in `cc_last_error` for the thread that made it.
+//!
+//! Released functions keep their signatures. New ones may be added, and
+//! `cc_abi_version` goes up when they are.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+use std::slice;
+
+use serde_json::Value;
+
+use crate::messages::{self, line, AnyMessage, Auth, FromClient, Move, Rewind};
+use crate::replay::{codec, RecordedMove, Replay};
+
+pub const ABI_VERSION: u32 = 1;
+
+type CResult<T> = Result<T, Box<dyn Error>>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Messages without a body, for `cc_encode_command`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CcCommand {
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    CatchUp,
+}
+
+/// A decoded message: its kind, and its body as JSON or null for messages
+/// without one. Both are null if decoding failed.
+#[repr(C)]
+#[derive(Debug)]
+pub struct CcMessage {
+    pub kind: *mut c_char,
+    pub body: *mut c_char,
+}
+
+#[no_mangle]
+pub extern "C" fn cc_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// Why the last failed call on this thread failed, or null if none has.
+/// Valid until the next call on the thread fails.
+#[no_mangle]
+pub extern "C" fn cc_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// # Safety
+///
+/// `s` is null or a string from this library, not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn cc_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// `{"auth":...}`, refusing usernames the server would.
+///
+/// # Safety
+///
+/// `username` and `password` are null or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn cc_encode_auth(
+    username: *const c_char,
+    password: *const c_char,
+) -> *mut c_char {
+    string_out((|| {
+        let auth = Auth {
+            username: from_c(username)?.into(),
+            password: from_c(password)?.into(),
+        };
+        auth.validate()?;
+        Ok(line(&FromClient::Auth(auth)))
+    })())
+}
+
+/// `{"move":...}` around `move_json`, which has to be valid JSON.
+///
+/// # Safety
+///
+/// `move_json` is null or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn cc_encode_move(move_json: *const c_char) -> *mut c_char {
+    string_out((|| {
+        let player_move: Value = messages::decode(from_c(move_json)?)?;
+        Ok(line(&Move::Move(player_move)))
+    })())
+}
+
+#[no_mangle]
+pub extern "C" fn cc_encode_command(command: CcCommand) -> *mut c_char {
+    string_out(Ok(line(&match command {
+        CcCommand::OfferDraw => FromClient::OfferDraw,
+        CcCommand::AcceptDraw => FromClient::AcceptDraw,
+        CcCommand::DeclineDraw => FromClient::DeclineDraw,
+        CcCommand::CatchUp => FromClient::CatchUp,
+    })))
+}
+
+#[no_mangle]
+pub extern "C" fn cc_encode_rewind(move_index: usize) -> *mut c_char {
+    string_out(Ok(line(&FromClient::Rewind(Rewind { move_index }))))
+}
+
+/// Splits a message from the server into its kind and body, freed with
+/// `cc_message_free`.
+///
+/// # Safety
+///
+/// `json` is null or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn cc_decode(json: *const c_char) -> CcMessage {
+    let decoded = (|| -> CResult<_> {
+        let message: AnyMessage = messages::decode(from_c(json)?.trim_end())?;
+        let body = message.body.map(|body| CString::new(body.to_string()));
+        Ok((CString::new(message.kind)?, body.transpose()?))
+    })();
+    match decoded {
+        Ok((kind, body)) => CcMessage {
+            kind: kind.into_raw(),
+            body: body.map_or(ptr::null_mut(), CString::into_raw),
+        },
+        Err(e) => CcMessage {
+            kind: fail(e),
+            body: ptr::null_mut(),
+        },
+    }
+}
+
+/// # Safety
+///
+/// `message` came from `cc_decode` and wasn't freed before.
+#[no_mangle]
+pub unsafe extern "C" fn cc_message_free(message: CcMessage) {
+    cc_string_free(message.kind);
+    cc_string_free(message.body);
+}
+
+/// Reads a replay as stored, compressed or not, see `replay::codec`.
+///
+/// # Safety
+///
+/// `bytes` points to `len` readable bytes, or is null with `len` 0.
+#[no_mangle]
+pub unsafe extern "C" fn cc_replay_read(bytes: *const u8, len: usize) -> *mut Replay {
+    let bytes = match bytes.is_null() {
+        true => &[][..],
+        false => slice::from_raw_parts(bytes, len),
+    };
+    match codec::decode(bytes) {
+        Ok(replay) => Box::into_raw(Box::new(replay)),
+        Err(e) => fail(e.into()),
+    }
+}
+
+/// # Safety
+///
+/// `replay` is null or came from `cc_replay_read` and wasn't freed before.
+#[no_mangle]
+pub unsafe extern "C" fn cc_replay_free(replay: *mut Replay) {
+    if !replay.is_null() {
+        drop(Box::from_raw(replay));
+    }
+}
+
+/// The replay as JSON, as `Replay::to_json` writes it.
+///
+/// # Safety
+///
+/// `replay` is a live handle from `cc_replay_read`.
+#[no_mangle]
+pub unsafe extern "C" fn cc_replay_to_json(replay: *const Replay) -> *mut c_char {
+    string_out(Ok((*replay).to_json()))
+}
+
+/// # Safety
+///
+/// `replay` is a live handle from `cc_replay_read`.
+#[no_mangle]
+pub unsafe extern "C" fn cc_replay_num_players(replay: *const Replay) -> usize {
+    (*replay).players.len()
+}
+
+/// # Safety
+///
+/// `replay` is a live handle from `cc_replay_read`.
+#[no_mangle]
+pub unsafe extern "C" fn cc_replay_player(replay: *const Replay, index: usize) -> *mut c_char {
+    let players = &(*replay).players;
+    string_out(match players.get(index) {
+        Some(player) => Ok(player.name.clone()),
+        None => Err(format!("no player {index}, there are {}", players.len()).into()),
+    })
+}
+
+/// # Safety
+///
+/// `replay` is a live handle from `cc_replay_read`.
+#[no_mangle]
+pub unsafe extern "C" fn cc_replay_num_moves(replay: *const Replay) -> usize {
+    (*replay).moves.len()
+}
+
+/// Who made move `index`.
+///
+/// # Safety
+///
+/// `replay` is a live handle from `cc_replay_read`.
+#[no_mangle]
+pub unsafe extern "C" fn cc_replay_move_player(replay: *const Replay, index: usize) -> *mut c_char {
+    string_out(recorded_move(&*replay, index).map(|m| m.player.clone()))
+}
+
+/// Move `index` as its player sent it.
+///
+/// # Safety
+///
+/// `replay` is a live handle from `cc_replay_read`.
+#[no_mangle]
+pub unsafe extern "C" fn cc_replay_move(replay: *const Replay, index: usize) -> *mut c_char {
+    string_out(recorded_move(&*replay, index).map(|m| m.player_move.clone()))
+}
+
+/// The outcome as JSON, `null` for games that didn't finish.
+///
+/// # Safety
+///
+/// `replay` is a live handle from `cc_replay_read`.
+#[no_mangle]
+pub unsafe extern "C" fn cc_replay_outcome(replay: *const Replay) -> *mut c_char {
+    string_out(serde_json::to_string(&(*replay).outcome).map_err(Into::into))
+}
+
+fn recorded_move(replay: &Replay, index: usize) -> CResult<&RecordedMove> {
+    replay
+        .moves
+        .get(index)
+        .ok_or_else(|| format!("no move {index}, there are {}", replay.moves.len()).into())
+}
+
+/// # Safety
+///
+/// `s` is null or NUL-terminated.
+unsafe fn from_c<'a>(s: *const c_char) -> CResult<&'a str> {
+    if s.is_null() {
+        return Err("a string was null".into());
+    }
+    Ok(CStr::from_ptr(s).to_str()?)
+}
+
+fn string_out(s: CResult<String>) -> *mut c_char {
+    match s.and_then(|s| Ok(CString::new(s)?)) {
+        Ok(s) => s.into_raw(),
+        Err(e) => fail(e),
+    }
+}
+
+fn fail<T>(error: Box<dyn Error>) -> *mut T {
+    let message = CString::new(error.to_string().replace('\0', "")).unwrap();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    ptr::null_mut()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::user;
+
+    unsafe fn take(s: *mut c_char) -> String {
+        let taken = CStr::from_ptr(s).to_str().unwrap().to_string();
+        cc_string_free(s);
+        taken
+    }
+
+    #[test]
+    fn encodes_and_decodes() {
+        unsafe {
+            assert_eq!(
+                take(cc_encode_move(c"{\"x\":1,\"y\":2}".as_ptr())),
+                "{\"move\":{\"x\":1,\"y\":2}}\n"
+            );
+            assert_eq!(
+                take(cc_encode_command(CcCommand::OfferDraw)),
+                "\"offer-draw\"\n"
+            );
+
+            let message = cc_decode(c"{\"game-over\":{\"reason\":\"won\"}}\n".as_ptr());
+            assert_eq!(CStr::from_ptr(message.kind).to_str(), Ok("game-over"));
+            assert_eq!(
+                CStr::from_ptr(message.body).to_str(),
+                Ok("{\"reason\":\"won\"}")
+            );
+            cc_message_free(message);
+
+            assert!(cc_encode_auth(c"".as_ptr(), c"secret".as_ptr()).is_null());
+            assert!(cc_encode_move(c"{".as_ptr()).is_null());
+            assert!(CStr::from_ptr(cc_last_error())
+                .to_str()
+                .unwrap()
+                .contains("EOF"));
+        }
+    }
+
+    #[test]
+    fn reads_replays() {
+        let mut replay = Replay::new(vec![user("p1"), user("p2")]);
+        replay.moves.push(RecordedMove {
+            player: "p1".to_string(),
+            player_move: "{\"move\":3}".to_string(),
+            think_ms: None,
+            annotations: vec![],
+        });
+        let bytes = codec::encode(&replay).unwrap();
+        unsafe {
+            let handle = cc_replay_read(bytes.as_ptr(), bytes.len());
+            assert_eq!(cc_replay_num_players(handle), 2);
+            assert_eq!(take(cc_replay_player(handle, 1)), "p2");
+            assert_eq!(cc_replay_num_moves(handle), 1);
+            assert_eq!(take(cc_replay_move_player(handle, 0)), "p1");
+            assert_eq!(take(cc_replay_move(handle, 0)), "{\"move\":3}");
+            assert!(cc_replay_move(handle, 1).is_null());
+            assert_eq!(take(cc_replay_outcome(handle)), "null");
+            cc_replay_free(handle);
+
+            assert!(cc_replay_read(b"nope".as_ptr(), 4).is_null());
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! A standard 52 card deck for card games, shuffled from a seed so a deal
+//! can be reproduced from the seed alone.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rng::{GameRng, SplitMix64};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl Suit {
+    pub const ALL: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+    fn symbol(self) -> char {
+        match self {
+            Suit::Clubs => 'C',
+            Suit::Diamonds => 'D',
+            Suit::Hearts => 'H',
+            Suit::Spades => 'S',
+        }
+    }
+}
+
+/// Ranks go from 2 to 14, jack, queen, king and ace being 11 to 14. Cards
+/// sort by suit and then rank, the way hands are held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Card {
+    pub rank: u8,
+    pub suit: Suit,
+}
+
+impl Card {
+    pub const JACK: u8 = 11;
+    pub const QUEEN: u8 = 12;
+    pub const KING: u8 = 13;
+    pub const ACE: u8 = 14;
+
+    pub const fn new(rank: u8, suit: Suit) -> Self {
+        Self { rank, suit }
+    }
+}
+
+impl Ord for Card {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.suit, self.rank).cmp(&(other.suit, other.rank))
+    }
+}
+
+impl PartialOrd for Card {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Short names such as `10H` and `QS`.
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.rank {
+            Card::JACK => write!(f, "J")?,
+            Card::QUEEN => write!(f, "Q")?,
+            Card::KING => write!(f, "K")?,
+            Card::ACE => write!(f, "A")?,
+            rank => write!(f, "{rank}")?,
+        }
+        write!(f, "{}", self.suit.symbol())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deck {
+    /// The top of the deck is the end.
+    cards: Vec<Card>,
+}
+
+impl Deck {
+    /// All 52 cards in order, by suit and then rank.
+    pub fn standard() -> Self {
+        let cards = Suit::ALL
+            .into_iter()
+            .flat_map(|suit| (2..=Card::ACE).map(move |rank| Card::new(rank, suit)))
+            .collect();
+        Self { cards }
+    }
+
+    /// The deck shuffled from `seed`, the same seed always giving the same
+    /// order.
+    pub fn shuffled(seed: u64) -> Self {
+        let mut deck = Self::standard();
+        deck.shuffle(&mut SplitMix64::new(seed));
+        deck
+    }
+
+    pub fn shuffle(&mut self, rng: &mut impl GameRng) {
+        rng.shuffle(&mut self.cards);
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    /// Takes the top card.
+    pub fn draw(&mut self) -> Option<Card> {
+        self.cards.pop()
+    }
+
+    /// Deals `per_hand` cards to each of `hands` hands, one at a time around
+    /// the table, or as many as there are. Each hand comes back sorted.
+    pub fn deal(&mut self, hands: usize, per_hand: usize) -> Vec<Vec<Card>> {
+        let mut dealt = vec![Vec::with_capacity(per_hand); hands];
+        'dealing: for _ in 0..per_hand {
+            for hand in &mut dealt {
+                let Some(card) = self.draw() else {
+                    break 'dealing;
+                };
+                hand.push(card);
+            }
+        }
+        for hand in &mut dealt {
+            hand.sort();
+        }
+        dealt
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shuffles_and_deals() {
+        let deck = Deck::standard();
+        assert_eq!(deck.len(), 52);
+        assert_eq!(deck.cards()[12].to_string(), "AC");
+        assert_eq!(Card::new(10, Suit::Hearts).to_string(), "10H");
+
+        assert_eq!(Deck::shuffled(5), Deck::shuffled(5));
+        assert_ne!(Deck::shuffled(5), Deck::shuffled(6));
+        let mut deck = Deck::shuffled(5);
+        let mut sorted = deck.cards().to_vec();
+        sorted.sort();
+        assert_eq!(sorted, Deck::standard().cards());
+
+        let hands = deck.deal(3, 17);
+        assert!(hands.iter().all(|hand| hand.len() == 17));
+        assert!(hands.iter().all(|hand| hand.is_sorted()));
+        assert_eq!(deck.len(), 1);
+        let hands = deck.deal(2, 5);
+        assert_eq!(hands.iter().map(Vec::len).collect::<Vec<_>>(), [1, 0]);
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::messages;
+
+pub const DEFAULT_WARNING_THRESHOLDS: [Duration; 3] = [
+    Duration::from_secs(30),
+    Duration::from_secs(10),
+    Duration::from_secs(5),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeWarning {
+    pub player: String,
+    pub remaining: Duration,
+}
+
+impl TimeWarning {
+    pub fn to_message(&self) -> messages::ToClient {
+        messages::ToClient::TimeWarning(messages::TimeWarning {
+            remaining_ms: self.remaining.as_millis() as u64,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct PlayerClock {
+    remaining: Duration,
+    warnings_sent: usize,
+}
+
+/// Per-player remaining time, emitting a `TimeWarning` each time a player
+/// drops below one of the configured thresholds.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameClock {
+    initial: Duration,
+    warning_thresholds: Vec<Duration>,
+    players: HashMap<String, PlayerClock>,
+}
+
+impl GameClock {
+    pub fn new(initial: Duration) -> Self {
+        Self {
+            initial,
+            warning_thresholds: DEFAULT_WARNING_THRESHOLDS.to_vec(),
+            players: HashMap::new(),
+        }
+    }
+
+    pub fn with_warnings(mut self, mut thresholds: Vec<Duration>) -> Self {
+        thresholds.sort_by(|a, b| b.cmp(a));
+        thresholds.dedup();
+        self.warning_thresholds = thresholds;
+        for clock in self.players.values_mut() {
+            clock.warnings_sent = passed_thresholds(&self.warning_thresholds, clock.remaining);
+        }
+        self
+    }
+
+    pub fn add_player(&mut self, username: &str) {
+        let warnings_sent = passed_thresholds(&self.warning_thresholds, self.initial);
+        self.players.insert(
+            username.to_string(),
+            PlayerClock {
+                remaining: self.initial,
+                warnings_sent,
+            },
+        );
+    }
+
+    pub fn remove_player(&mut self, username: &str) {
+        self.players.remove(username);
+    }
+
+    pub fn remaining(&self, username: &str) -> Option<Duration> {
+        self.players.get(username).map(|c| c.remaining)
+    }
+
+    pub fn is_out_of_time(&self, username: &str) -> bool {
+        self.remaining(username) == Some(Duration::ZERO)
+    }
+
+    /// Deducts `elapsed` from the player's clock, returning a warning if that
+    /// took the player below one or more thresholds.
+    pub fn spend(&mut self, username: &str, elapsed: Duration) -> Option<TimeWarning> {
+        let clock = self.players.get_mut(username)?;
+        clock.remaining = clock.remaining.saturating_sub(elapsed);
+
+        let passed = passed_thresholds(&self.warning_thresholds, clock.remaining);
+        if passed <= clock.warnings_sent {
+            return None;
+        }
+        clock.warnings_sent = passed;
+        debug!(player = username, remaining = ?clock.remaining, "time warning");
+        Some(TimeWarning {
+            player: username.to_string(),
+            remaining: clock.remaining,
+        })
+    }
+
+    pub fn add_time(&mut self, username: &str, extra: Duration) {
+        if let Some(clock) = self.players.get_mut(username) {
+            clock.remaining += extra;
+            clock.warnings_sent = passed_thresholds(&self.warning_thresholds, clock.remaining);
+        }
+    }
+
+    /// Takes time off without warning the player, as when an organizer
+    /// corrects a clock. Only thresholds passed after this are warned of.
+    pub fn take_time(&mut self, username: &str, time: Duration) {
+        if let Some(clock) = self.players.get_mut(username) {
+            clock.remaining = clock.remaining.saturating_sub(time);
+            clock.warnings_sent = passed_thresholds(&self.warning_thresholds, clock.remaining);
+        }
+    }
+}
+
+fn passed_thresholds(thresholds: &[Duration], remaining: Duration) -> usize {
+    thresholds.iter().filter(|t| remaining <= **t).count()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn warns_once_per_threshold() {
+        let mut clock = GameClock::new(secs(60));
+        clock.add_player("p1");
+
+        assert_eq!(clock.spend("p1", secs(20)), None);
+        assert_eq!(
+            clock.spend("p1", secs(15)),
+            Some(TimeWarning {
+                player: "p1".to_string(),
+                remaining: secs(25)
+            })
+        );
+        assert_eq!(clock.spend("p1", secs(1)), None);
+        assert!(clock.spend("p1", secs(16)).is_some());
+        assert!(clock.spend("p1", secs(20)).is_some());
+        assert!(clock.is_out_of_time("p1"));
+    }
+
+    #[test]
+    fn skipping_thresholds_warns_once() {
+        let mut clock = GameClock::new(secs(60));
+        clock.add_player("p1");
+
+        assert_eq!(
+            clock.spend("p1", secs(57)),
+            Some(TimeWarning {
+                player: "p1".to_string(),
+                remaining: secs(3)
+            })
+        );
+        assert_eq!(clock.spend("p1", secs(1)), None);
+    }
+
+    #[test]
+    fn custom_thresholds() {
+        let mut clock = GameClock::new(secs(10)).with_warnings(vec![secs(2), secs(8)]);
+        clock.add_player("p1");
+
+        assert!(clock.spend("p1", secs(3)).is_some());
+        assert!(clock.spend("p1", secs(3)).is_none());
+        assert!(clock.spend("p1", secs(3)).is_some());
+    }
+
+    #[test]
+    fn added_time_rearms_warnings() {
+        let mut clock = GameClock::new(secs(60));
+        clock.add_player("p1");
+
+        assert!(clock.spend("p1", secs(35)).is_some());
+        clock.add_time("p1", secs(10));
+        assert_eq!(clock.remaining("p1"), Some(secs(35)));
+        assert!(clock.spend("p1", secs(10)).is_some());
+    }
+
+    #[test]
+    fn taken_time_is_not_warned_of() {
+        let mut clock = GameClock::new(secs(60));
+        clock.add_player("p1");
+
+        clock.take_time("p1", secs(50));
+        assert_eq!(clock.remaining("p1"), Some(secs(10)));
+        assert_eq!(clock.spend("p1", secs(1)), None);
+        clock.take_time("p1", secs(60));
+        assert!(clock.is_out_of_time("p1"));
+    }
+
+    #[test]
+    fn unknown_player() {
+        let mut clock = GameClock::new(secs(60));
+        assert_eq!(clock.spend("p1", secs(10)), None);
+        assert_eq!(clock.remaining("p1"), None);
+    }
+}
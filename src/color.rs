@@ -0,0 +1,215 @@
+//! Player colors: `Color` from `core::color`, palettes, and
+//! `ColorAllocator`, which gives players colors that are easy to tell apart.
+
+use serde::{Deserialize, Serialize};
+
+use crate::draw::Marker;
+
+pub use crate::core::color::Color;
+
+/// Distinct, saturated colors that stay apart on both light and dark
+/// backgrounds, from Sasha Trubetskoy's list of 20 simple distinct colors.
+pub const DISTINCT: [Color; 12] = [
+    Color::rgb8(0xe6, 0x19, 0x4b),
+    Color::rgb8(0x3c, 0xb4, 0x4b),
+    Color::rgb8(0xff, 0xe1, 0x19),
+    Color::rgb8(0x43, 0x63, 0xd8),
+    Color::rgb8(0xf5, 0x82, 0x31),
+    Color::rgb8(0x91, 0x1e, 0xb4),
+    Color::rgb8(0x42, 0xd4, 0xf4),
+    Color::rgb8(0xf0, 0x32, 0xe6),
+    Color::rgb8(0xbf, 0xef, 0x45),
+    Color::rgb8(0xfa, 0xbe, 0xd4),
+    Color::rgb8(0x46, 0x99, 0x90),
+    Color::rgb8(0x9a, 0x63, 0x24),
+];
+
+/// The Okabe-Ito palette, which stays distinguishable with the common forms
+/// of color blindness.
+pub const OKABE_ITO: [Color; 8] = [
+    Color::rgb8(0xe6, 0x9f, 0x00),
+    Color::rgb8(0x56, 0xb4, 0xe9),
+    Color::rgb8(0x00, 0x9e, 0x73),
+    Color::rgb8(0xf0, 0xe4, 0x42),
+    Color::rgb8(0x00, 0x72, 0xb2),
+    Color::rgb8(0xd5, 0x5e, 0x00),
+    Color::rgb8(0xcc, 0x79, 0xa7),
+    Color::rgb8(0x00, 0x00, 0x00),
+];
+
+/// The built-in palettes, by name for configs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    #[default]
+    Distinct,
+    ColorblindSafe,
+}
+
+impl Palette {
+    pub fn colors(self) -> &'static [Color] {
+        match self {
+            Palette::Distinct => &DISTINCT,
+            Palette::ColorblindSafe => &OKABE_ITO,
+        }
+    }
+}
+
+/// Hands out player colors from a palette, each new player getting the free
+/// color that differs most from the ones in use. Colors of players that leave
+/// are given out again. Once the palette runs out the least used color is
+/// shared.
+///
+/// Each player also gets a `Marker`, so games that draw players only by color
+/// can draw their marker instead and stay readable without color vision.
+#[derive(Clone, Debug)]
+pub struct ColorAllocator {
+    palette: Vec<Color>,
+    assigned: Vec<(String, Color)>,
+}
+
+impl Default for ColorAllocator {
+    fn default() -> Self {
+        Self::from_palette(Palette::default())
+    }
+}
+
+impl ColorAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_palette(palette: Palette) -> Self {
+        Self::with_palette(palette.colors().to_vec())
+    }
+
+    pub fn with_palette(palette: Vec<Color>) -> Self {
+        assert!(!palette.is_empty(), "palette must have a color");
+        Self {
+            palette,
+            assigned: vec![],
+        }
+    }
+
+    pub fn color(&self, player: &str) -> Option<Color> {
+        self.assigned
+            .iter()
+            .find(|(name, _)| name == player)
+            .map(|(_, color)| *color)
+    }
+
+    /// The marker of `player`: the same for everyone with a given palette
+    /// color, but different between players who had to share a color.
+    pub fn marker(&self, player: &str) -> Option<Marker> {
+        let color = self.color(player)?;
+        let index = self.palette.iter().position(|c| *c == color)?;
+        let shared_with = self
+            .assigned
+            .iter()
+            .filter(|(_, c)| *c == color)
+            .position(|(name, _)| name == player)?;
+        Some(Marker::for_index(index + shared_with))
+    }
+
+    /// The color of `player`, picking one if they don't have one yet.
+    pub fn assign(&mut self, player: &str) -> Color {
+        if let Some(color) = self.color(player) {
+            return color;
+        }
+        let uses = |color: &Color| self.assigned.iter().filter(|(_, c)| c == color).count();
+        let distance_to_used = |color: &Color| {
+            self.assigned
+                .iter()
+                .map(|(_, used)| distance(*color, *used))
+                .fold(f64::INFINITY, f64::min)
+        };
+        let color = self
+            .palette
+            .iter()
+            .map(|c| (*c, uses(c), distance_to_used(c)))
+            // Of the least used, the first of the most distant, so ties go
+            // by palette order.
+            .reduce(|best, next| {
+                let better = next.1 < best.1 || (next.1 == best.1 && next.2 > best.2);
+                if better {
+                    next
+                } else {
+                    best
+                }
+            })
+            .map_or(Color::BLACK, |(color, _, _)| color);
+        self.assigned.push((player.to_string(), color));
+        color
+    }
+
+    pub fn release(&mut self, player: &str) {
+        self.assigned.retain(|(name, _)| name != player);
+    }
+}
+
+/// Perceptual distance between two colors, the "redmean" approximation.
+fn distance(a: Color, b: Color) -> f64 {
+    let (r1, g1, b1, _) = a.as_rgba8();
+    let (r2, g2, b2, _) = b.as_rgba8();
+    let mean_r = (r1 as f64 + r2 as f64) / 2.0;
+    let (dr, dg, db) = (
+        r1 as f64 - r2 as f64,
+        g1 as f64 - g2 as f64,
+        b1 as f64 - b2 as f64,
+    );
+    ((2.0 + mean_r / 256.0) * dr * dr + 4.0 * dg * dg + (2.0 + (255.0 - mean_r) / 256.0) * db * db)
+        .sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn picks_distant_colors() {
+        let mut colors = ColorAllocator::with_palette(vec![
+            Color::rgb8(0xff, 0, 0),
+            Color::rgb8(0xf0, 0x10, 0),
+            Color::rgb8(0, 0, 0xff),
+            Color::rgb8(0x10, 0x10, 0xf0),
+        ]);
+        assert_eq!(colors.assign("p1"), Color::rgb8(0xff, 0, 0));
+        assert_eq!(colors.assign("p2"), Color::rgb8(0, 0, 0xff));
+        assert_eq!(colors.assign("p1"), Color::rgb8(0xff, 0, 0));
+        assert_eq!(colors.color("p3"), None);
+    }
+
+    #[test]
+    fn reclaims_and_shares() {
+        let mut colors = ColorAllocator::with_palette(vec![Color::RED, Color::BLUE]);
+        assert_eq!(colors.assign("p1"), Color::RED);
+        assert_eq!(colors.assign("p2"), Color::BLUE);
+        colors.release("p1");
+        assert_eq!(colors.assign("p3"), Color::RED);
+        assert_eq!(colors.assign("p4"), Color::RED);
+        assert_eq!(colors.assign("p5"), Color::BLUE);
+    }
+
+    #[test]
+    fn markers() {
+        let mut colors = ColorAllocator::with_palette(vec![Color::RED, Color::BLUE]);
+        for player in ["p1", "p2", "p3"] {
+            colors.assign(player);
+        }
+        assert_eq!(colors.marker("p1"), Some(Marker::Circle));
+        assert_eq!(colors.marker("p2"), Some(Marker::Square));
+        assert_eq!(colors.marker("p3"), Some(Marker::Square));
+        assert_eq!(colors.marker("p4"), None);
+    }
+
+    #[test]
+    fn default_palette_is_distinct() {
+        let mut colors = ColorAllocator::new();
+        let assigned: Vec<_> = (0..DISTINCT.len())
+            .map(|i| colors.assign(&format!("p{i}")))
+            .collect();
+        for (i, a) in assigned.iter().enumerate() {
+            assert!(!assigned[i + 1..].contains(a));
+        }
+    }
+}
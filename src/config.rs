@@ -0,0 +1,547 @@
+//! A server's settings in one file: the games it runs and how each is set
+//! up, time controls, the player color palette and the tournament, in TOML
+//! or JSON. Anything left out takes the value the games use by default.
+//!
+//! ```toml
+//! palette = "colorblind-safe"
+//! time-control = { initial-ms = 60000 }
+//!
+//! [tournament]
+//! name = "Spring cup"
+//! rounds = 5
+//!
+//! [[games]]
+//! game = "gomoku"
+//! size = 9
+//! win-length = 4
+//!
+//! [[games]]
+//! game = "snake"
+//! width = 30
+//! tick-deadline-ms = 200
+//! ```
+//!
+//! Files that don't parse say where, as `games[1].win-length` and a line
+//! and column, and so do settings that parse but make no sense, such as a
+//! ship longer than the ocean. TOML needs the `toml` feature.
+
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::Palette;
+use crate::games::battleship::{Battleship, STANDARD_FLEET};
+use crate::games::connect_four::ConnectFour;
+use crate::games::gomoku::{Gomoku, GomokuConfig};
+use crate::games::othello::Othello;
+use crate::games::rps::RockPaperScissors;
+use crate::games::snake::SnakeGame;
+use crate::games::tictactoe::TicTacToe;
+use crate::games::whist::Whist;
+use crate::gametraits::GameTrait;
+use crate::handicap::HandicapScale;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ServerConfig {
+    #[serde(default)]
+    pub palette: Palette,
+    /// For every timed game that doesn't set its own.
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+    #[serde(default)]
+    pub tournament: Option<TournamentConfig>,
+    /// Head starts for players rated below their opponents.
+    #[serde(default)]
+    pub handicap: Option<HandicapScale>,
+    #[serde(default)]
+    pub games: Vec<GameSettings>,
+}
+
+/// Thinking time for each player, for the whole game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TimeControl {
+    pub initial_ms: u64,
+}
+
+impl TimeControl {
+    pub fn initial(&self) -> Duration {
+        Duration::from_millis(self.initial_ms)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TournamentConfig {
+    pub name: String,
+    pub rounds: u32,
+    /// Games each pair of players plays a round, taking turns to go first.
+    #[serde(default = "two")]
+    pub games_per_pairing: u32,
+    /// For pairings and for games that shuffle.
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn two() -> u32 {
+    2
+}
+
+/// A game and its settings, named by `game`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "game", rename_all = "kebab-case")]
+pub enum GameSettings {
+    TicTacToe,
+    ConnectFour(ConnectFourSettings),
+    Gomoku(GomokuConfig),
+    Othello,
+    Snake(SnakeSettings),
+    Battleship(BattleshipSettings),
+    RockPaperScissors(RockPaperScissorsSettings),
+    Whist(WhistSettings),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct ConnectFourSettings {
+    pub width: usize,
+    pub height: usize,
+    pub time_control: Option<TimeControl>,
+}
+
+impl Default for ConnectFourSettings {
+    fn default() -> Self {
+        Self {
+            width: 7,
+            height: 6,
+            time_control: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct SnakeSettings {
+    pub width: usize,
+    pub height: usize,
+    pub wrap: bool,
+    /// Trails that never shrink, as in Tron, if left out.
+    pub length: Option<usize>,
+    pub tick_deadline_ms: Option<u64>,
+}
+
+impl Default for SnakeSettings {
+    fn default() -> Self {
+        Self {
+            width: 20,
+            height: 20,
+            wrap: false,
+            length: None,
+            tick_deadline_ms: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct BattleshipSettings {
+    pub size: usize,
+    /// Ship lengths.
+    pub fleet: Vec<usize>,
+}
+
+impl Default for BattleshipSettings {
+    fn default() -> Self {
+        Self {
+            size: 10,
+            fleet: STANDARD_FLEET.to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct RockPaperScissorsSettings {
+    pub best_of: u32,
+}
+
+impl Default for RockPaperScissorsSettings {
+    fn default() -> Self {
+        Self { best_of: 3 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WhistSettings {
+    /// Every game deals the same hands from the same seed.
+    pub seed: u64,
+}
+
+impl GameSettings {
+    /// A new game as set up here, timed by `time_control` if it's timed
+    /// at all and doesn't have its own. Settings are expected to have been
+    /// validated, and a Gomoku board that isn't panics.
+    pub fn build(&self, time_control: Option<TimeControl>) -> Box<dyn GameTrait> {
+        match self {
+            GameSettings::TicTacToe => Box::new(TicTacToe::new()),
+            GameSettings::ConnectFour(settings) => {
+                let game = ConnectFour::new(settings.width, settings.height);
+                match settings.time_control.or(time_control) {
+                    Some(time_control) => Box::new(game.with_clock(time_control.initial())),
+                    None => Box::new(game),
+                }
+            }
+            GameSettings::Gomoku(config) => {
+                Box::new(Gomoku::new(config.clone()).expect("validated"))
+            }
+            GameSettings::Othello => Box::new(Othello::new()),
+            GameSettings::Snake(settings) => {
+                let mut game = SnakeGame::new(settings.width, settings.height);
+                if settings.wrap {
+                    game = game.wrapping();
+                }
+                if let Some(length) = settings.length {
+                    game = game.with_length(length);
+                }
+                if let Some(ms) = settings.tick_deadline_ms {
+                    game = game.with_tick_deadline(Duration::from_millis(ms));
+                }
+                Box::new(game)
+            }
+            GameSettings::Battleship(settings) => {
+                Box::new(Battleship::new(settings.size, settings.fleet.clone()))
+            }
+            GameSettings::RockPaperScissors(settings) => {
+                Box::new(RockPaperScissors::new(settings.best_of))
+            }
+            GameSettings::Whist(settings) => Box::new(Whist::new(settings.seed)),
+        }
+    }
+
+    fn validate(&self) -> Result<(), (&'static str, String)> {
+        let at_least = |field, value: usize, min: usize| match value < min {
+            true => Err((field, format!("must be at least {min}"))),
+            false => Ok(()),
+        };
+        match self {
+            GameSettings::ConnectFour(settings) => {
+                at_least("width", settings.width, 4)?;
+                at_least("height", settings.height, 4)?;
+                if let Some(time_control) = &settings.time_control {
+                    time_control.validate().map_err(|e| ("time-control", e))?;
+                }
+            }
+            GameSettings::Gomoku(config) => {
+                config.validate().map_err(|e| ("", e.to_string()))?;
+            }
+            GameSettings::Snake(settings) => {
+                at_least("width", settings.width, 3)?;
+                at_least("height", settings.height, 3)?;
+                at_least("length", settings.length.unwrap_or(1), 1)?;
+            }
+            GameSettings::Battleship(settings) => {
+                at_least("size", settings.size, 2)?;
+                at_least("fleet", settings.fleet.len(), 1)?;
+                if let Some(ship) = settings
+                    .fleet
+                    .iter()
+                    .find(|ship| !(2..=settings.size).contains(*ship))
+                {
+                    return Err((
+                        "fleet",
+                        format!("a ship of {ship} doesn't fit an ocean of {}", settings.size),
+                    ));
+                }
+            }
+            GameSettings::RockPaperScissors(settings) => {
+                at_least("best-of", settings.best_of as usize, 1)?;
+            }
+            GameSettings::TicTacToe | GameSettings::Othello | GameSettings::Whist(_) => {}
+        }
+        Ok(())
+    }
+}
+
+impl TimeControl {
+    fn validate(&self) -> Result<(), String> {
+        match self.initial_ms {
+            0 => Err("initial-ms must be more than 0".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn from_json(json: &str) -> Result<Self, ConfigFileError> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let config: Self = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+            let path = e.path().to_string();
+            let error = e.into_inner();
+            ConfigFileError::syntax(path, error.to_string(), error.line(), error.column())
+        })?;
+        deserializer.end().map_err(|e| {
+            ConfigFileError::syntax(".".to_string(), e.to_string(), e.line(), e.column())
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(source: &str) -> Result<Self, ConfigFileError> {
+        let config: Self = serde_path_to_error::deserialize(toml::Deserializer::new(source))
+            .map_err(|e| {
+                let path = e.path().to_string();
+                let error = e.into_inner();
+                let (line, column) = error
+                    .span()
+                    .map_or((0, 0), |span| line_and_column(source, span.start));
+                ConfigFileError::syntax(path, error.message().to_string(), line, column)
+            })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads a `.json` file, or a `.toml` file with the `toml` feature.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ConfigFileError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&source),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&source),
+            _ => Err(ConfigFileError::UnknownFormat(path.display().to_string())),
+        }
+    }
+
+    /// Settings that parse but can't be played, the first found.
+    pub fn validate(&self) -> Result<(), InvalidSetting> {
+        if let Some(time_control) = &self.time_control {
+            time_control
+                .validate()
+                .map_err(|reason| InvalidSetting::new("time-control", reason))?;
+        }
+        if let Some(tournament) = &self.tournament {
+            if tournament.rounds == 0 {
+                return Err(InvalidSetting::new(
+                    "tournament.rounds",
+                    "must be at least 1",
+                ));
+            }
+            if tournament.games_per_pairing == 0 {
+                return Err(InvalidSetting::new(
+                    "tournament.games-per-pairing",
+                    "must be at least 1",
+                ));
+            }
+        }
+        if let Some(handicap) = &self.handicap {
+            if handicap.rating_per_step.is_nan() || handicap.rating_per_step <= 0.0 {
+                return Err(InvalidSetting::new(
+                    "handicap.rating-per-step",
+                    "must be more than 0",
+                ));
+            }
+        }
+        for (i, game) in self.games.iter().enumerate() {
+            game.validate().map_err(|(field, reason)| {
+                let path = match field {
+                    "" => format!("games[{i}]"),
+                    field => format!("games[{i}].{field}"),
+                };
+                InvalidSetting::new(path, reason)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// One of each game configured, ready to host.
+    pub fn build_games(&self) -> Vec<Box<dyn GameTrait>> {
+        self.games
+            .iter()
+            .map(|game| game.build(self.time_control))
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigFileError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The file isn't valid TOML or JSON, or doesn't fit `ServerConfig`.
+    /// Lines and columns are from 1, or 0 where the parser couldn't tell.
+    #[error("{reason} at {path} (line {line}, column {column})")]
+    Syntax {
+        path: String,
+        reason: String,
+        line: usize,
+        column: usize,
+    },
+    #[error(transparent)]
+    Invalid(#[from] InvalidSetting),
+    #[error("{0} isn't a config format known, .json or .toml")]
+    UnknownFormat(String),
+}
+
+impl ConfigFileError {
+    fn syntax(path: String, reason: String, line: usize, column: usize) -> Self {
+        let suffix = format!(" at line {line} column {column}");
+        let reason = reason.strip_suffix(&suffix).unwrap_or(&reason).to_string();
+        ConfigFileError::Syntax {
+            path,
+            reason,
+            line,
+            column,
+        }
+    }
+}
+
+/// A setting that parsed but can't be played with.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{path}: {reason}")]
+pub struct InvalidSetting {
+    /// As in `games[2].fleet`.
+    pub path: String,
+    pub reason: String,
+}
+
+impl InvalidSetting {
+    fn new(path: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Both from 1.
+#[cfg(feature = "toml")]
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(games: Vec<GameSettings>) -> ServerConfig {
+        ServerConfig {
+            games,
+            ..ServerConfig::default()
+        }
+    }
+
+    #[test]
+    fn builds_games_with_the_server_time_control() {
+        let mut config = config(vec![
+            GameSettings::ConnectFour(ConnectFourSettings::default()),
+            GameSettings::Snake(SnakeSettings::default()),
+        ]);
+        config.time_control = Some(TimeControl { initial_ms: 60_000 });
+        config.validate().unwrap();
+
+        let mut games = config.build_games();
+        let four = games[0].as_any().downcast_ref::<ConnectFour>().unwrap();
+        assert_eq!(four.board().width(), 7);
+        assert!(four.clock().is_none());
+        games[0].reset(crate::test_support::users(2));
+        games[0].try_start_game();
+        assert!(games[0].clock_mut().is_some());
+    }
+
+    #[test]
+    fn says_which_setting_is_invalid() {
+        let fleet = config(vec![
+            GameSettings::TicTacToe,
+            GameSettings::Battleship(BattleshipSettings {
+                size: 4,
+                fleet: vec![3, 5],
+            }),
+        ]);
+        assert_eq!(
+            fleet.validate(),
+            Err(InvalidSetting::new(
+                "games[1].fleet",
+                "a ship of 5 doesn't fit an ocean of 4"
+            ))
+        );
+
+        let gomoku = config(vec![GameSettings::Gomoku(GomokuConfig {
+            size: 2,
+            ..GomokuConfig::default()
+        })]);
+        assert_eq!(gomoku.validate().unwrap_err().path, "games[0]");
+
+        let tournament = ServerConfig {
+            tournament: Some(TournamentConfig {
+                name: "cup".to_string(),
+                rounds: 0,
+                games_per_pairing: 2,
+                seed: 0,
+            }),
+            ..ServerConfig::default()
+        };
+        assert_eq!(tournament.validate().unwrap_err().path, "tournament.rounds");
+    }
+
+    #[test]
+    fn reads_json() {
+        let config = ServerConfig::from_json(
+            r#"{"palette":"colorblind-safe","games":[{"game":"gomoku","size":9,"win-length":4}]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.palette, Palette::ColorblindSafe);
+        assert_eq!(
+            config.games,
+            [GameSettings::Gomoku(GomokuConfig {
+                size: 9,
+                win_length: 4,
+                exact: false
+            })]
+        );
+
+        let Err(ConfigFileError::Syntax { path, line, .. }) =
+            ServerConfig::from_json("{\"games\":[\n{\"game\":\"gomoku\",\"size\":\"big\"}]}")
+        else {
+            panic!()
+        };
+        assert_eq!((path.as_str(), line), ("games[0].size", 2));
+
+        assert!(matches!(
+            ServerConfig::from_json(r#"{"games":[{"game":"gomoku","size":99}]}"#),
+            Err(ConfigFileError::Invalid(_))
+        ));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn reads_toml() {
+        let source = "palette = \"distinct\"\n\n[[games]]\ngame = \"snake\"\nwidth = \"wide\"\n";
+        let Err(ConfigFileError::Syntax { path, line, .. }) = ServerConfig::from_toml(source)
+        else {
+            panic!()
+        };
+        assert_eq!((path.as_str(), line), ("games[0].width", 5));
+
+        let config = ServerConfig::from_toml("[[games]]\ngame = \"whist\"\nseed = 7\n").unwrap();
+        assert_eq!(
+            config.games,
+            [GameSettings::Whist(WhistSettings { seed: 7 })]
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn counts_lines_and_columns() {
+        assert_eq!(line_and_column("a = 1\nbé = x", 11), (2, 5));
+        assert_eq!(line_and_column("x", 0), (1, 1));
+    }
+}
@@ -0,0 +1,11 @@
+//! The types everything else is built on: positions, boards, colors and
+//! game outcomes. They build without the standard library, needing only
+//! `alloc`, so bot SDKs for embedded and wasm targets can share them with
+//! the server by turning off default features. With `std` the rest of the
+//! crate re-exports them from where they've always been, `crate::pos`,
+//! `crate::grid` and so on.
+
+pub mod color;
+pub mod grid;
+pub mod outcome;
+pub mod pos;
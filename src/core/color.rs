@@ -0,0 +1,46 @@
+//! The color type used in `User`. With the `gui` feature it is piet's
+//! `Color`, without it a plain RGBA value with the same constructors, so code
+//! that only builds users doesn't have to care which one it gets.
+
+#[cfg(feature = "gui")]
+pub use druid::piet::Color;
+
+#[cfg(not(feature = "gui"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Color(u32);
+
+#[cfg(not(feature = "gui"))]
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba8(0, 0, 0, 0);
+    pub const BLACK: Color = Color::rgb8(0, 0, 0);
+    pub const WHITE: Color = Color::rgb8(0xff, 0xff, 0xff);
+    pub const GRAY: Color = Color::rgb8(0x80, 0x80, 0x80);
+    pub const RED: Color = Color::rgb8(0xff, 0, 0);
+    pub const GREEN: Color = Color::rgb8(0, 0x80, 0);
+    pub const LIME: Color = Color::rgb8(0, 0xff, 0);
+    pub const BLUE: Color = Color::rgb8(0, 0, 0xff);
+    pub const YELLOW: Color = Color::rgb8(0xff, 0xff, 0);
+    pub const AQUA: Color = Color::rgb8(0, 0xff, 0xff);
+    pub const FUCHSIA: Color = Color::rgb8(0xff, 0, 0xff);
+
+    pub const fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color(u32::from_be_bytes([r, g, b, a]))
+    }
+
+    pub const fn rgb8(r: u8, g: u8, b: u8) -> Color {
+        Color::rgba8(r, g, b, 0xff)
+    }
+
+    pub const fn from_rgba32_u32(rgba: u32) -> Color {
+        Color(rgba)
+    }
+
+    pub const fn as_rgba_u32(self) -> u32 {
+        self.0
+    }
+
+    pub fn as_rgba8(self) -> (u8, u8, u8, u8) {
+        let [r, g, b, a] = self.0.to_be_bytes();
+        (r, g, b, a)
+    }
+}
@@ -0,0 +1,348 @@
+//! A rectangular board stored row by row, indexed by `(x, y)` with `x` the
+//! column and `y` the row counted from the top.
+
+use ::core::fmt;
+use ::core::mem;
+use ::core::ops::{Index, IndexMut};
+
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+use super::pos::{Direction, Pos};
+
+/// Serialized as a list of rows, the way clients usually want boards.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(
+    into = "Vec<Vec<T>>",
+    try_from = "Vec<Vec<T>>",
+    bound(
+        serialize = "T: Clone + Serialize",
+        deserialize = "T: Deserialize<'de>"
+    )
+)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+/// Which cells are neighbors in `Grid::neighbors`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Adjacency {
+    /// The four cells sharing a side.
+    Orthogonal,
+    /// The eight cells sharing a side or a corner.
+    Diagonal,
+    /// The six neighbors of a hex board stored with `x` as the axial `q`
+    /// and `y` as `r`.
+    Hex,
+}
+
+impl Adjacency {
+    pub(crate) fn offsets(self) -> &'static [Pos] {
+        const ORTHOGONAL: [Pos; 4] = [
+            Pos::new(0, -1),
+            Pos::new(1, 0),
+            Pos::new(0, 1),
+            Pos::new(-1, 0),
+        ];
+        const DIAGONAL: [Pos; 8] = [
+            Pos::new(0, -1),
+            Pos::new(1, -1),
+            Pos::new(1, 0),
+            Pos::new(1, 1),
+            Pos::new(0, 1),
+            Pos::new(-1, 1),
+            Pos::new(-1, 0),
+            Pos::new(-1, -1),
+        ];
+        const HEX: [Pos; 6] = [
+            Pos::new(1, 0),
+            Pos::new(1, -1),
+            Pos::new(0, -1),
+            Pos::new(-1, 0),
+            Pos::new(-1, 1),
+            Pos::new(0, 1),
+        ];
+        match self {
+            Adjacency::Orthogonal => &ORTHOGONAL,
+            Adjacency::Diagonal => &DIAGONAL,
+            Adjacency::Hex => &HEX,
+        }
+    }
+}
+
+/// What `Grid::neighbors` does with neighbors off the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// Moves them to the nearest cell on the board, which can be the cell
+    /// itself or repeat another neighbor.
+    Clamp,
+    /// Leaves them out.
+    Skip,
+    /// Wraps them around to the opposite edge.
+    Wrap,
+}
+
+impl Boundary {
+    /// Applies the policy to one coordinate on an axis of length `len`.
+    pub(crate) fn resolve(self, value: i32, len: usize) -> Option<i32> {
+        let len = len as i32;
+        match self {
+            _ if (0..len).contains(&value) => Some(value),
+            Boundary::Skip => None,
+            _ if len == 0 => None,
+            Boundary::Clamp => Some(value.clamp(0, len - 1)),
+            Boundary::Wrap => Some(value.rem_euclid(len)),
+        }
+    }
+}
+
+/// Rows of different lengths given to `Grid::from_rows`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaggedRows;
+
+impl fmt::Display for RaggedRows {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("grid rows have different lengths")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RaggedRows {}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(usize, usize) -> T) -> Self {
+        Self {
+            width,
+            height,
+            cells: (0..width * height)
+                .map(|i| f(i % width, i / width))
+                .collect(),
+        }
+    }
+
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Self, RaggedRows> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, Vec::len);
+        if rows.iter().any(|row| row.len() != width) {
+            return Err(RaggedRows);
+        }
+        Ok(Self {
+            width,
+            height,
+            cells: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn into_rows(self) -> Vec<Vec<T>> {
+        if self.width == 0 {
+            return (0..self.height).map(|_| vec![]).collect();
+        }
+        let mut cells = self.cells.into_iter();
+        (0..self.height)
+            .map(|_| cells.by_ref().take(self.width).collect())
+            .collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether `(x, y)` is on the board. Takes signed coordinates so
+    /// neighbors can be checked without underflowing first.
+    pub fn in_bounds(&self, x: i64, y: i64) -> bool {
+        x >= 0 && y >= 0 && (x as u64) < self.width as u64 && (y as u64) < self.height as u64
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.in_bounds(pos.x as i64, pos.y as i64)
+    }
+
+    pub fn at(&self, pos: Pos) -> Option<&T> {
+        self.contains(pos)
+            .then(|| &self.cells[pos.y as usize * self.width + pos.x as usize])
+    }
+
+    pub fn at_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        self.contains(pos)
+            .then(|| &mut self.cells[pos.y as usize * self.width + pos.x as usize])
+    }
+
+    /// `pos` brought onto the board as if its edges wrapped around, for
+    /// toroidal boards. Panics on an empty grid.
+    pub fn wrap(&self, pos: Pos) -> Pos {
+        assert!(!self.cells.is_empty(), "can't wrap onto an empty grid");
+        pos.wrap(self.width, self.height)
+    }
+
+    /// One step from `pos`, wrapping around the edges.
+    pub fn step_wrapped(&self, pos: Pos, direction: Direction) -> Pos {
+        self.wrap(pos.step(direction))
+    }
+
+    pub fn at_wrapped(&self, pos: Pos) -> &T {
+        &self[self.wrap(pos)]
+    }
+
+    pub fn at_wrapped_mut(&mut self, pos: Pos) -> &mut T {
+        let pos = self.wrap(pos);
+        &mut self[pos]
+    }
+
+    /// Every position on the board, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = Pos> + '_ {
+        (0..self.height).flat_map(|y| (0..self.width).map(move |x| Pos::new(x as i32, y as i32)))
+    }
+
+    /// The neighbors of `pos` in the order of `Direction::ALL`, or of
+    /// `hex::DIRECTIONS` for hexes.
+    pub fn neighbors(
+        &self,
+        pos: Pos,
+        adjacency: Adjacency,
+        boundary: Boundary,
+    ) -> impl Iterator<Item = Pos> + '_ {
+        adjacency.offsets().iter().filter_map(move |offset| {
+            let next = pos + *offset;
+            Some(Pos::new(
+                boundary.resolve(next.x, self.width)?,
+                boundary.resolve(next.y, self.height)?,
+            ))
+        })
+    }
+
+    fn index_of(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then_some(y * self.width + x)
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        self.index_of(x, y).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        self.index_of(x, y).map(|i| &mut self.cells[i])
+    }
+
+    /// Replaces the cell at `(x, y)`, returning what was there, or `None`
+    /// when off the board.
+    pub fn set(&mut self, x: usize, y: usize, value: T) -> Option<T> {
+        self.get_mut(x, y).map(|cell| mem::replace(cell, value))
+    }
+
+    pub fn row(&self, y: usize) -> Option<&[T]> {
+        (y < self.height).then(|| &self.cells[y * self.width..(y + 1) * self.width])
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> + '_ {
+        (0..self.height).map(|y| &self.cells[y * self.width..(y + 1) * self.width])
+    }
+
+    /// The cells of column `x` from top to bottom, empty if `x` is off the
+    /// board.
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> + '_ {
+        let height = if x < self.width { self.height } else { 0 };
+        (0..height).map(move |y| &self.cells[y * self.width + x])
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T> + '_> + '_ {
+        (0..self.width).map(|x| self.column(x))
+    }
+
+    /// Every cell with its coordinates, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &T)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| (i % self.width, i / self.width, cell))
+    }
+
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn map<U>(&self, mut f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(&mut f).collect(),
+        }
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        match self.index_of(x, y) {
+            Some(i) => &self.cells[i],
+            None => panic!(
+                "({x}, {y}) is outside the {}x{} grid",
+                self.width, self.height
+            ),
+        }
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        match self.index_of(x, y) {
+            Some(i) => &mut self.cells[i],
+            None => panic!(
+                "({x}, {y}) is outside the {}x{} grid",
+                self.width, self.height
+            ),
+        }
+    }
+}
+
+impl<T> Index<Pos> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Pos) -> &T {
+        match self.at(pos) {
+            Some(cell) => cell,
+            None => panic!("{pos:?} is outside the {}x{} grid", self.width, self.height),
+        }
+    }
+}
+
+impl<T> IndexMut<Pos> for Grid<T> {
+    fn index_mut(&mut self, pos: Pos) -> &mut T {
+        let (width, height) = (self.width, self.height);
+        match self.at_mut(pos) {
+            Some(cell) => cell,
+            None => panic!("{pos:?} is outside the {width}x{height} grid"),
+        }
+    }
+}
+
+impl<T> From<Grid<T>> for Vec<Vec<T>> {
+    fn from(grid: Grid<T>) -> Self {
+        grid.into_rows()
+    }
+}
+
+impl<T> TryFrom<Vec<Vec<T>>> for Grid<T> {
+    type Error = RaggedRows;
+
+    fn try_from(rows: Vec<Vec<T>>) -> Result<Self, RaggedRows> {
+        Self::from_rows(rows)
+    }
+}
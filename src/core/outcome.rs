@@ -0,0 +1,15 @@
+//! How a game ended, as recorded in replays and game history.
+
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GameOutcome {
+    Win {
+        winner: String,
+    },
+    Draw,
+    /// The players agreed to a draw before the game was over.
+    AgreedDraw,
+}
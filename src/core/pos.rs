@@ -0,0 +1,320 @@
+//! Positions and directions on square boards. `y` grows downwards, matching
+//! `Grid` rows and screen coordinates, so `North` is `y - 1`. `Pos3` adds a
+//! layer `z` for `Grid3`.
+
+use ::core::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Pos {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Pos {
+    pub const ORIGIN: Pos = Pos::new(0, 0);
+
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn step(self, direction: Direction) -> Self {
+        self + direction.offset()
+    }
+
+    /// The same position on a board of the given size whose edges wrap
+    /// around, so one step off the right edge comes back in on the left.
+    /// The board must not be empty.
+    pub fn wrap(self, width: usize, height: usize) -> Self {
+        Self::new(
+            self.x.rem_euclid(width as i32),
+            self.y.rem_euclid(height as i32),
+        )
+    }
+
+    /// Distance moving only orthogonally.
+    pub fn manhattan(self, other: Pos) -> u32 {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+
+    /// Distance moving like a chess king.
+    pub fn chebyshev(self, other: Pos) -> u32 {
+        self.x.abs_diff(other.x).max(self.y.abs_diff(other.y))
+    }
+
+    /// The four orthogonal neighbors, north first and going clockwise.
+    pub fn neighbors4(self) -> [Pos; 4] {
+        Direction::CARDINAL.map(|d| self.step(d))
+    }
+
+    /// All eight neighbors, north first and going clockwise.
+    pub fn neighbors8(self) -> [Pos; 8] {
+        Direction::ALL.map(|d| self.step(d))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// Clockwise from north.
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+    pub const CARDINAL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+    pub const DIAGONAL: [Direction; 4] = [
+        Direction::NorthEast,
+        Direction::SouthEast,
+        Direction::SouthWest,
+        Direction::NorthWest,
+    ];
+
+    pub fn offset(self) -> Pos {
+        match self {
+            Direction::North => Pos::new(0, -1),
+            Direction::NorthEast => Pos::new(1, -1),
+            Direction::East => Pos::new(1, 0),
+            Direction::SouthEast => Pos::new(1, 1),
+            Direction::South => Pos::new(0, 1),
+            Direction::SouthWest => Pos::new(-1, 1),
+            Direction::West => Pos::new(-1, 0),
+            Direction::NorthWest => Pos::new(-1, -1),
+        }
+    }
+
+    /// The direction with this offset, if it is a unit step.
+    pub fn from_offset(offset: Pos) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.offset() == offset)
+    }
+
+    pub fn is_diagonal(self) -> bool {
+        self as usize % 2 == 1
+    }
+
+    /// Turns clockwise in steps of 45 degrees; negative steps turn
+    /// counter-clockwise.
+    pub fn rotate(self, eighths: i32) -> Self {
+        Self::ALL[(self as i32 + eighths).rem_euclid(8) as usize]
+    }
+
+    pub fn rotate_cw(self) -> Self {
+        self.rotate(2)
+    }
+
+    pub fn rotate_ccw(self) -> Self {
+        self.rotate(-2)
+    }
+
+    pub fn opposite(self) -> Self {
+        self.rotate(4)
+    }
+}
+
+impl Add for Pos {
+    type Output = Pos;
+
+    fn add(self, other: Pos) -> Pos {
+        Pos::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Add<Direction> for Pos {
+    type Output = Pos;
+
+    fn add(self, direction: Direction) -> Pos {
+        self.step(direction)
+    }
+}
+
+impl AddAssign for Pos {
+    fn add_assign(&mut self, other: Pos) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Pos {
+    type Output = Pos;
+
+    fn sub(self, other: Pos) -> Pos {
+        Pos::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl SubAssign for Pos {
+    fn sub_assign(&mut self, other: Pos) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Pos {
+    type Output = Pos;
+
+    fn neg(self) -> Pos {
+        Pos::new(-self.x, -self.y)
+    }
+}
+
+impl Mul<i32> for Pos {
+    type Output = Pos;
+
+    fn mul(self, k: i32) -> Pos {
+        Pos::new(self.x * k, self.y * k)
+    }
+}
+
+impl From<(i32, i32)> for Pos {
+    fn from((x, y): (i32, i32)) -> Self {
+        Self::new(x, y)
+    }
+}
+
+/// A position on a `Grid3`, `z` being the layer.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Pos3 {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Pos3 {
+    pub const ORIGIN: Pos3 = Pos3::new(0, 0, 0);
+
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// The position within its layer.
+    pub fn xy(self) -> Pos {
+        Pos::new(self.x, self.y)
+    }
+
+    pub fn manhattan(self, other: Pos3) -> u32 {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y) + self.z.abs_diff(other.z)
+    }
+
+    pub fn chebyshev(self, other: Pos3) -> u32 {
+        self.x
+            .abs_diff(other.x)
+            .max(self.y.abs_diff(other.y))
+            .max(self.z.abs_diff(other.z))
+    }
+}
+
+impl Add for Pos3 {
+    type Output = Pos3;
+
+    fn add(self, other: Pos3) -> Pos3 {
+        Pos3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl AddAssign for Pos3 {
+    fn add_assign(&mut self, other: Pos3) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Pos3 {
+    type Output = Pos3;
+
+    fn sub(self, other: Pos3) -> Pos3 {
+        Pos3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl SubAssign for Pos3 {
+    fn sub_assign(&mut self, other: Pos3) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Pos3 {
+    type Output = Pos3;
+
+    fn neg(self) -> Pos3 {
+        Pos3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<i32> for Pos3 {
+    type Output = Pos3;
+
+    fn mul(self, k: i32) -> Pos3 {
+        Pos3::new(self.x * k, self.y * k, self.z * k)
+    }
+}
+
+impl From<(i32, i32, i32)> for Pos3 {
+    fn from((x, y, z): (i32, i32, i32)) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arithmetic_and_distances() {
+        let mut pos = Pos::new(2, 3);
+        assert_eq!(pos + Direction::North, Pos::new(2, 2));
+        assert_eq!(pos - Pos::new(3, 3), Pos::new(-1, 0));
+        assert_eq!(-pos * 2, Pos::new(-4, -6));
+        pos += Direction::SouthWest.offset();
+        assert_eq!(pos, Pos::new(1, 4));
+        assert_eq!(pos.manhattan(Pos::ORIGIN), 5);
+        assert_eq!(pos.chebyshev(Pos::ORIGIN), 4);
+        assert_eq!(Pos::ORIGIN.neighbors4()[1], Pos::new(1, 0));
+        assert_eq!(Pos::ORIGIN.neighbors8().len(), 8);
+    }
+
+    #[test]
+    fn rotation() {
+        assert_eq!(Direction::North.rotate_cw(), Direction::East);
+        assert_eq!(Direction::North.rotate_ccw(), Direction::West);
+        assert_eq!(Direction::NorthEast.rotate(-1), Direction::North);
+        assert_eq!(Direction::SouthEast.opposite(), Direction::NorthWest);
+        assert!(Direction::SouthWest.is_diagonal() && !Direction::South.is_diagonal());
+        for d in Direction::ALL {
+            assert_eq!(Direction::from_offset(d.offset()), Some(d));
+            assert_eq!(d.offset() + d.opposite().offset(), Pos::ORIGIN);
+        }
+        assert_eq!(Direction::from_offset(Pos::new(2, 0)), None);
+    }
+
+    #[test]
+    fn pos3() {
+        let pos = Pos3::new(1, 2, 3);
+        assert_eq!(pos - Pos3::new(1, 1, 1) * 2, Pos3::new(-1, 0, 1));
+        assert_eq!(pos.manhattan(Pos3::ORIGIN), 6);
+        assert_eq!((-pos).chebyshev(Pos3::ORIGIN), 3);
+        assert_eq!(pos.xy(), Pos::new(1, 2));
+    }
+}
@@ -0,0 +1,101 @@
+//! Dice for games of chance, rolled from a seed and keeping a log of every
+//! roll. A game hands its log to the replay through `GameTrait::dice_log`,
+//! and since the rolls follow from the seed alone anyone holding the replay
+//! can roll them again with `DiceLog::verify` and see they weren't picked
+//! by hand.
+
+use serde::{Deserialize, Serialize};
+
+use crate::rng::{GameRng, SplitMix64};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Roll {
+    pub sides: u32,
+    /// From 1 to `sides`.
+    pub value: u32,
+}
+
+/// The seed the dice started from and every roll made since.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiceLog {
+    pub seed: u64,
+    pub rolls: Vec<Roll>,
+}
+
+impl DiceLog {
+    /// Rolls fresh dice from the seed and checks they come up the same,
+    /// returning the index of the first roll that doesn't.
+    pub fn verify(&self) -> Result<(), usize> {
+        let mut dice = Dice::new(self.seed);
+        match self
+            .rolls
+            .iter()
+            .position(|roll| dice.roll(roll.sides) != roll.value)
+        {
+            Some(index) => Err(index),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dice {
+    rng: SplitMix64,
+    log: DiceLog,
+}
+
+impl Dice {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SplitMix64::new(seed),
+            log: DiceLog {
+                seed,
+                rolls: vec![],
+            },
+        }
+    }
+
+    /// One die of `sides` sides, which must not be zero.
+    pub fn roll(&mut self, sides: u32) -> u32 {
+        let value = self.rng.below(sides as u64) as u32 + 1;
+        self.log.rolls.push(Roll { sides, value });
+        value
+    }
+
+    /// `count` dice of `sides` sides, rolled one after the other.
+    pub fn roll_many(&mut self, count: usize, sides: u32) -> Vec<u32> {
+        (0..count).map(|_| self.roll(sides)).collect()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.log.seed
+    }
+
+    pub fn rolls(&self) -> &[Roll] {
+        &self.log.rolls
+    }
+
+    pub fn log(&self) -> &DiceLog {
+        &self.log
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn logs_and_verifies_rolls() {
+        let mut dice = Dice::new(7);
+        let values = dice.roll_many(20, 6);
+        assert!(values.iter().all(|v| (1..=6).contains(v)));
+        dice.roll(20);
+        assert_eq!(dice.rolls().len(), 21);
+        assert_eq!(Dice::new(7).roll_many(20, 6), values);
+
+        let mut log = dice.log().clone();
+        assert_eq!(log.verify(), Ok(()));
+        log.rolls[4].value = log.rolls[4].value % 6 + 1;
+        assert_eq!(log.verify(), Err(4));
+    }
+}
@@ -0,0 +1,418 @@
+//! Renderer independent drawing. Games describe a frame as a list of
+//! `DrawCommand`s through `Draw`, and a `Painter` backend turns the list into
+//! pixels, so the drawing code can be tested by looking at the commands.
+
+use std::sync::Arc;
+
+use crate::color::Color;
+
+pub mod ansi;
+#[cfg(feature = "egui")]
+pub mod egui;
+pub mod hud;
+pub mod layout;
+#[cfg(feature = "gui")]
+pub mod piet;
+#[cfg(feature = "png")]
+pub mod png;
+pub mod svg;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Size {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Size {
+    pub const fn new(width: f64, height: f64) -> Self {
+        Self { width, height }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+impl Rect {
+    pub const fn new(x0: f64, y0: f64, x1: f64, y1: f64) -> Self {
+        Self { x0, y0, x1, y1 }
+    }
+
+    pub fn from_origin_size(origin: Point, size: Size) -> Self {
+        Self::new(
+            origin.x,
+            origin.y,
+            origin.x + size.width,
+            origin.y + size.height,
+        )
+    }
+
+    pub fn width(&self) -> f64 {
+        self.x1 - self.x0
+    }
+
+    pub fn height(&self) -> f64 {
+        self.y1 - self.y0
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new((self.x0 + self.x1) / 2.0, (self.y0 + self.y1) / 2.0)
+    }
+}
+
+/// A 2D affine transform as the coefficients `[a, b, c, d, e, f]` of
+///
+/// ```text
+/// | a c e |
+/// | b d f |
+/// | 0 0 1 |
+/// ```
+///
+/// the same layout piet and SVG use.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Affine(pub [f64; 6]);
+
+impl Affine {
+    pub const IDENTITY: Affine = Affine([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    pub const fn translate(x: f64, y: f64) -> Self {
+        Affine([1.0, 0.0, 0.0, 1.0, x, y])
+    }
+
+    pub const fn scale(s: f64) -> Self {
+        Affine([s, 0.0, 0.0, s, 0.0, 0.0])
+    }
+
+    /// Rotation by `radians`, clockwise on screen since y points down.
+    pub fn rotate(radians: f64) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Affine([cos, sin, -sin, cos, 0.0, 0.0])
+    }
+
+    /// The transform applying `self` first and then `next`.
+    pub fn then(self, next: Affine) -> Affine {
+        let [a, b, c, d, e, f] = self.0;
+        let [na, nb, nc, nd, ne, nf] = next.0;
+        Affine([
+            na * a + nc * b,
+            nb * a + nd * b,
+            na * c + nc * d,
+            nb * c + nd * d,
+            na * e + nc * f + ne,
+            nb * e + nd * f + nf,
+        ])
+    }
+
+    pub fn apply(&self, p: Point) -> Point {
+        let [a, b, c, d, e, f] = self.0;
+        Point::new(a * p.x + c * p.y + e, b * p.x + d * p.y + f)
+    }
+
+    /// How much the transform scales lengths, on average over directions.
+    pub fn length_scale(&self) -> f64 {
+        let [a, b, c, d, _, _] = self.0;
+        (a * d - b * c).abs().sqrt()
+    }
+
+    /// Whether rectangles stay rectangles with horizontal and vertical edges.
+    pub fn is_axis_aligned(&self) -> bool {
+        let [_, b, c, _, _, _] = self.0;
+        b == 0.0 && c == 0.0
+    }
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stroke {
+    pub color: Color,
+    pub width: f64,
+}
+
+/// Non-premultiplied RGBA pixels, row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    Rect {
+        rect: Rect,
+        fill: Option<Color>,
+        stroke: Option<Stroke>,
+    },
+    Circle {
+        center: Point,
+        radius: f64,
+        fill: Option<Color>,
+        stroke: Option<Stroke>,
+    },
+    Line {
+        from: Point,
+        to: Point,
+        stroke: Stroke,
+    },
+    /// A closed shape through `points`.
+    Polygon {
+        points: Vec<Point>,
+        fill: Option<Color>,
+        stroke: Option<Stroke>,
+    },
+    /// `origin` is the top left corner of the text.
+    Text {
+        text: String,
+        origin: Point,
+        size: f64,
+        color: Color,
+    },
+    Image {
+        rect: Rect,
+        image: Arc<Image>,
+    },
+    /// Saves the current transform, to be brought back by `Restore`.
+    Save,
+    Restore,
+    /// Applies a transform to everything drawn after it, on top of the
+    /// current one.
+    Transform(Affine),
+}
+
+impl DrawCommand {
+    pub fn fill_rect(rect: Rect, color: Color) -> Self {
+        DrawCommand::Rect {
+            rect,
+            fill: Some(color),
+            stroke: None,
+        }
+    }
+
+    pub fn stroke_rect(rect: Rect, color: Color, width: f64) -> Self {
+        DrawCommand::Rect {
+            rect,
+            fill: None,
+            stroke: Some(Stroke { color, width }),
+        }
+    }
+
+    pub fn fill_circle(center: Point, radius: f64, color: Color) -> Self {
+        DrawCommand::Circle {
+            center,
+            radius,
+            fill: Some(color),
+            stroke: None,
+        }
+    }
+
+    pub fn line(from: Point, to: Point, color: Color, width: f64) -> Self {
+        DrawCommand::Line {
+            from,
+            to,
+            stroke: Stroke { color, width },
+        }
+    }
+
+    pub fn text(text: impl Into<String>, origin: Point, size: f64, color: Color) -> Self {
+        DrawCommand::Text {
+            text: text.into(),
+            origin,
+            size,
+            color,
+        }
+    }
+}
+
+/// Shapes for telling players apart without relying on color alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Marker {
+    Circle,
+    Square,
+    Triangle,
+    Diamond,
+    InvertedTriangle,
+    Star,
+}
+
+impl Marker {
+    pub const ALL: [Marker; 6] = [
+        Marker::Circle,
+        Marker::Square,
+        Marker::Triangle,
+        Marker::Diamond,
+        Marker::InvertedTriangle,
+        Marker::Star,
+    ];
+
+    /// The marker for the `index`th player or palette color, repeating once
+    /// all have been used.
+    pub fn for_index(index: usize) -> Marker {
+        Self::ALL[index % Self::ALL.len()]
+    }
+
+    /// The marker filled with `color`, fitting in a circle of `radius`
+    /// around `center`.
+    pub fn draw(self, center: Point, radius: f64, color: Color) -> DrawCommand {
+        let polygon = |corners: &[(f64, f64)]| DrawCommand::Polygon {
+            points: corners
+                .iter()
+                .map(|(x, y)| Point::new(center.x + x * radius, center.y + y * radius))
+                .collect(),
+            fill: Some(color),
+            stroke: None,
+        };
+        let side = std::f64::consts::FRAC_1_SQRT_2;
+        let (tri_x, tri_y) = (0.75f64.sqrt(), 0.5);
+        match self {
+            Marker::Circle => DrawCommand::fill_circle(center, radius, color),
+            Marker::Square => {
+                polygon(&[(-side, -side), (side, -side), (side, side), (-side, side)])
+            }
+            Marker::Triangle => polygon(&[(0.0, -1.0), (tri_x, tri_y), (-tri_x, tri_y)]),
+            Marker::Diamond => polygon(&[(0.0, -1.0), (1.0, 0.0), (0.0, 1.0), (-1.0, 0.0)]),
+            Marker::InvertedTriangle => polygon(&[(0.0, 1.0), (-tri_x, -tri_y), (tri_x, -tri_y)]),
+            Marker::Star => {
+                let points: Vec<_> = (0..10)
+                    .map(|i| {
+                        let r = if i % 2 == 0 { 1.0 } else { 0.45 };
+                        let angle = std::f64::consts::PI * i as f64 / 5.0;
+                        (r * angle.sin(), -r * angle.cos())
+                    })
+                    .collect();
+                polygon(&points)
+            }
+        }
+    }
+}
+
+/// Something that can describe how it looks, like a game's current state.
+pub trait Draw {
+    fn draw(&self, size: Size) -> Vec<DrawCommand>;
+}
+
+/// Rounds the edges of `rect` to whole physical pixels at `scale`, so fills
+/// don't get blurry anti-aliased edges.
+pub fn snap_rect(rect: Rect, scale: f64) -> Rect {
+    let snap = |v: f64| (v * scale).round() / scale;
+    Rect::new(snap(rect.x0), snap(rect.y0), snap(rect.x1), snap(rect.y1))
+}
+
+/// Moves a horizontal or vertical line so a stroke of `width` covers whole
+/// physical pixels at `scale`, rather than half covering two rows of them.
+/// Other lines are returned as they are.
+pub fn snap_line(from: Point, to: Point, width: f64, scale: f64) -> (Point, Point) {
+    let pixels = (width * scale).round().max(1.0);
+    let offset = if pixels % 2.0 == 1.0 { 0.5 } else { 0.0 };
+    let snap = |v: f64| (((v * scale - offset).round()) + offset) / scale;
+    if from.y == to.y {
+        (
+            Point::new(from.x, snap(from.y)),
+            Point::new(to.x, snap(to.y)),
+        )
+    } else if from.x == to.x {
+        (
+            Point::new(snap(from.x), from.y),
+            Point::new(snap(to.x), to.y),
+        )
+    } else {
+        (from, to)
+    }
+}
+
+/// A rendering backend. Games draw in logical units, and backends that render
+/// to pixels multiply by `scale_factor` to get physical pixels, so a frame is
+/// as sharp on a hi-DPI screen as the screen allows.
+pub trait Painter {
+    fn draw(&mut self, command: &DrawCommand);
+
+    /// Physical pixels per logical unit, 2.0 on a typical hi-DPI screen.
+    fn scale_factor(&self) -> f64 {
+        1.0
+    }
+
+    fn draw_all(&mut self, commands: &[DrawCommand]) {
+        for command in commands {
+            self.draw(command);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::replay::test_game::TakeAway;
+
+    #[test]
+    fn affine() {
+        let p = Point::new(1.0, 2.0);
+        let t = Affine::scale(2.0).then(Affine::translate(10.0, 0.0));
+        assert_eq!(t.apply(p), Point::new(12.0, 4.0));
+        assert_eq!(Affine::IDENTITY.then(t), t);
+
+        let r = Affine::rotate(std::f64::consts::FRAC_PI_2).apply(Point::new(1.0, 0.0));
+        assert!((r.x - 0.0).abs() < 1e-9 && (r.y - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn snapping() {
+        assert_eq!(
+            snap_rect(Rect::new(0.2, 0.7, 10.3, 5.26), 2.0),
+            Rect::new(0.0, 0.5, 10.5, 5.5)
+        );
+        let (from, to) = snap_line(Point::new(0.0, 3.0), Point::new(9.0, 3.0), 1.0, 1.0);
+        assert_eq!((from.y, to.y), (3.5, 3.5));
+        let (from, _) = snap_line(Point::new(3.0, 0.0), Point::new(3.0, 9.0), 1.0, 2.0);
+        assert_eq!(from.x, 3.0);
+        let diagonal = (Point::new(0.0, 0.0), Point::new(1.0, 1.0));
+        assert_eq!(snap_line(diagonal.0, diagonal.1, 1.0, 1.0), diagonal);
+    }
+
+    #[test]
+    fn markers() {
+        let center = Point::new(10.0, 10.0);
+        assert_eq!(
+            Marker::Circle.draw(center, 2.0, Color::RED),
+            DrawCommand::fill_circle(center, 2.0, Color::RED)
+        );
+        let DrawCommand::Polygon { points, .. } = Marker::Diamond.draw(center, 2.0, Color::RED)
+        else {
+            panic!("expected a polygon");
+        };
+        assert_eq!(points[0], Point::new(10.0, 8.0));
+        assert_eq!(points[1], Point::new(12.0, 10.0));
+        assert_eq!(Marker::for_index(7), Marker::Square);
+    }
+
+    #[test]
+    fn games_emit_commands() {
+        let commands = TakeAway::new(2).draw(Size::new(100.0, 50.0));
+        assert_eq!(
+            commands,
+            vec![
+                DrawCommand::fill_circle(Point::new(25.0, 25.0), 20.0, Color::BLACK),
+                DrawCommand::fill_circle(Point::new(75.0, 25.0), 20.0, Color::BLACK),
+            ]
+        );
+    }
+}
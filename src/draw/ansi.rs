@@ -0,0 +1,348 @@
+//! Renders draw commands as text for terminals, so games can be watched over
+//! SSH or in CI logs. The frame is sampled onto a grid of character cells:
+//! fills become cell backgrounds, lines and outlines become line characters
+//! and text is written into the cells it starts in.
+
+use super::{Affine, Draw, DrawCommand, Painter, Point, Rect, Size};
+use crate::color::Color;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cell {
+    ch: char,
+    fg: Option<Color>,
+    bg: Option<Color>,
+}
+
+const EMPTY: Cell = Cell {
+    ch: ' ',
+    fg: None,
+    bg: None,
+};
+
+#[derive(Clone, Debug)]
+pub struct AnsiPainter {
+    cols: usize,
+    rows: usize,
+    /// Size of one cell in drawing coordinates.
+    cell: Size,
+    cells: Vec<Cell>,
+    transform: Affine,
+    saved: Vec<Affine>,
+}
+
+impl AnsiPainter {
+    /// A painter showing a frame of `size` in `cols` by `rows` characters.
+    /// Terminal cells are about twice as tall as wide, so `rows` is usually
+    /// half of `cols` for a square frame.
+    pub fn new(size: Size, cols: usize, rows: usize) -> Self {
+        Self {
+            cols,
+            rows,
+            cell: Size::new(size.width / cols as f64, size.height / rows as f64),
+            cells: vec![EMPTY; cols * rows],
+            transform: Affine::IDENTITY,
+            saved: vec![],
+        }
+    }
+
+    /// The frame with 24-bit color escape codes.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        for row in self.cells.chunks(self.cols) {
+            let mut current = (None, None);
+            for cell in row {
+                if (cell.fg, cell.bg) != current {
+                    out.push_str("\x1b[0m");
+                    if let Some(fg) = cell.fg {
+                        let (r, g, b, _) = fg.as_rgba8();
+                        out.push_str(&format!("\x1b[38;2;{r};{g};{b}m"));
+                    }
+                    if let Some(bg) = cell.bg {
+                        let (r, g, b, _) = bg.as_rgba8();
+                        out.push_str(&format!("\x1b[48;2;{r};{g};{b}m"));
+                    }
+                    current = (cell.fg, cell.bg);
+                }
+                out.push(cell.ch);
+            }
+            if current != (None, None) {
+                out.push_str("\x1b[0m");
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// The frame without colors, filled cells showing as `#`.
+    pub fn to_plain(&self) -> String {
+        let mut out = String::new();
+        for row in self.cells.chunks(self.cols) {
+            let line: String = row
+                .iter()
+                .map(|cell| match cell {
+                    Cell {
+                        ch: ' ',
+                        bg: Some(_),
+                        ..
+                    } => '#',
+                    cell => cell.ch,
+                })
+                .collect();
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    fn cell_center(&self, col: usize, row: usize) -> Point {
+        Point::new(
+            (col as f64 + 0.5) * self.cell.width,
+            (row as f64 + 0.5) * self.cell.height,
+        )
+    }
+
+    fn cell_at(&mut self, p: Point) -> Option<&mut Cell> {
+        let col = (p.x / self.cell.width).floor();
+        let row = (p.y / self.cell.height).floor();
+        if col < 0.0 || row < 0.0 || col >= self.cols as f64 || row >= self.rows as f64 {
+            return None;
+        }
+        self.cells.get_mut(row as usize * self.cols + col as usize)
+    }
+
+    fn fill_where(&mut self, color: Color, inside: impl Fn(Point) -> bool) {
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if inside(self.cell_center(col, row)) {
+                    let cell = &mut self.cells[row * self.cols + col];
+                    *cell = Cell {
+                        ch: ' ',
+                        fg: None,
+                        bg: Some(color),
+                    };
+                }
+            }
+        }
+    }
+
+    fn line(&mut self, from: Point, to: Point, color: Color) {
+        let (dx, dy) = (
+            (to.x - from.x) / self.cell.width,
+            (to.y - from.y) / self.cell.height,
+        );
+        let ch = if dy.abs() < dx.abs() / 2.0 {
+            '-'
+        } else if dx.abs() < dy.abs() / 2.0 {
+            '|'
+        } else if (dx > 0.0) == (dy > 0.0) {
+            '\\'
+        } else {
+            '/'
+        };
+        let steps = (dx.abs().max(dy.abs()) * 2.0).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            let p = Point::new(from.x + (to.x - from.x) * t, from.y + (to.y - from.y) * t);
+            if let Some(cell) = self.cell_at(p) {
+                cell.ch = ch;
+                cell.fg = Some(color);
+            }
+        }
+    }
+
+    /// The bounding box of `rect` after the current transform.
+    fn transform_rect(&self, rect: Rect) -> Rect {
+        let corners = [
+            Point::new(rect.x0, rect.y0),
+            Point::new(rect.x1, rect.y0),
+            Point::new(rect.x0, rect.y1),
+            Point::new(rect.x1, rect.y1),
+        ]
+        .map(|p| self.transform.apply(p));
+        let xs = corners.map(|p| p.x);
+        let ys = corners.map(|p| p.y);
+        Rect::new(
+            xs.into_iter().fold(f64::INFINITY, f64::min),
+            ys.into_iter().fold(f64::INFINITY, f64::min),
+            xs.into_iter().fold(f64::NEG_INFINITY, f64::max),
+            ys.into_iter().fold(f64::NEG_INFINITY, f64::max),
+        )
+    }
+}
+
+/// A frame of `drawable` at `size` as colored text.
+pub fn to_ansi(drawable: &impl Draw, size: Size, cols: usize, rows: usize) -> String {
+    let mut painter = AnsiPainter::new(size, cols, rows);
+    painter.draw_all(&drawable.draw(size));
+    painter.to_ansi()
+}
+
+impl Painter for AnsiPainter {
+    fn draw(&mut self, command: &DrawCommand) {
+        match command {
+            DrawCommand::Rect { rect, fill, stroke } => {
+                let r = self.transform_rect(*rect);
+                if let Some(color) = fill {
+                    self.fill_where(*color, |p| {
+                        p.x >= r.x0 && p.x < r.x1 && p.y >= r.y0 && p.y < r.y1
+                    });
+                }
+                if let Some(stroke) = stroke {
+                    let (right, bottom) = (r.x1 - 1e-9, r.y1 - 1e-9);
+                    // Sides first, so the corners show as part of the top and
+                    // bottom edges.
+                    for x in [r.x0, right] {
+                        self.line(Point::new(x, r.y0), Point::new(x, bottom), stroke.color);
+                    }
+                    for y in [r.y0, bottom] {
+                        self.line(Point::new(r.x0, y), Point::new(right, y), stroke.color);
+                    }
+                }
+            }
+            DrawCommand::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => {
+                let center = self.transform.apply(*center);
+                let radius = radius * self.transform.length_scale();
+                if let Some(color) = fill {
+                    self.fill_where(*color, |p| (p.x - center.x).hypot(p.y - center.y) <= radius);
+                }
+                if let Some(stroke) = stroke {
+                    let steps = (radius / self.cell.width.min(self.cell.height) * 8.0)
+                        .ceil()
+                        .max(8.0) as usize;
+                    for i in 0..steps {
+                        let angle = std::f64::consts::TAU * i as f64 / steps as f64;
+                        let p = Point::new(
+                            center.x + radius * angle.cos(),
+                            center.y + radius * angle.sin(),
+                        );
+                        if let Some(cell) = self.cell_at(p) {
+                            cell.ch = 'o';
+                            cell.fg = Some(stroke.color);
+                        }
+                    }
+                }
+            }
+            DrawCommand::Line { from, to, stroke } => {
+                let (from, to) = (self.transform.apply(*from), self.transform.apply(*to));
+                self.line(from, to, stroke.color);
+            }
+            DrawCommand::Polygon {
+                points,
+                fill,
+                stroke,
+            } => {
+                let points: Vec<_> = points.iter().map(|p| self.transform.apply(*p)).collect();
+                if let Some(color) = fill {
+                    self.fill_where(*color, |p| inside_polygon(&points, p));
+                }
+                if let Some(stroke) = stroke {
+                    for (i, from) in points.iter().enumerate() {
+                        let to = points[(i + 1) % points.len()];
+                        self.line(*from, to, stroke.color);
+                    }
+                }
+            }
+            DrawCommand::Text {
+                text,
+                origin,
+                color,
+                ..
+            } => {
+                let mut p = self.transform.apply(*origin);
+                for ch in text.chars() {
+                    if let Some(cell) = self.cell_at(p) {
+                        cell.ch = ch;
+                        cell.fg = Some(*color);
+                    }
+                    p.x += self.cell.width;
+                }
+            }
+            DrawCommand::Image { rect, image } => {
+                let r = self.transform_rect(*rect);
+                if image.width == 0 || image.height == 0 {
+                    return;
+                }
+                for row in 0..self.rows {
+                    for col in 0..self.cols {
+                        let p = self.cell_center(col, row);
+                        if p.x < r.x0 || p.x >= r.x1 || p.y < r.y0 || p.y >= r.y1 {
+                            continue;
+                        }
+                        let x = ((p.x - r.x0) / r.width() * image.width as f64) as usize;
+                        let y = ((p.y - r.y0) / r.height() * image.height as f64) as usize;
+                        let i = (y * image.width + x) * 4;
+                        if let Some(&[red, green, blue, alpha]) = image.pixels.get(i..i + 4) {
+                            if alpha > 0 {
+                                self.cells[row * self.cols + col].bg =
+                                    Some(Color::rgba8(red, green, blue, 0xff));
+                            }
+                        }
+                    }
+                }
+            }
+            DrawCommand::Save => self.saved.push(self.transform),
+            DrawCommand::Restore => {
+                if let Some(transform) = self.saved.pop() {
+                    self.transform = transform;
+                }
+            }
+            DrawCommand::Transform(t) => self.transform = t.then(self.transform),
+        }
+    }
+}
+
+/// Even-odd rule point in polygon test.
+fn inside_polygon(points: &[Point], p: Point) -> bool {
+    let mut inside = false;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::draw::Marker;
+    use crate::replay::test_game::TakeAway;
+
+    #[test]
+    fn game_frame() {
+        let size = Size::new(100.0, 50.0);
+        let mut painter = AnsiPainter::new(size, 10, 5);
+        painter.draw_all(&TakeAway::new(2).draw(size));
+        assert_eq!(
+            painter.to_plain(),
+            "  #    #\n ###  ###\n##########\n ###  ###\n  #    #\n"
+        );
+    }
+
+    #[test]
+    fn lines_shapes_text_and_transforms() {
+        let mut painter = AnsiPainter::new(Size::new(8.0, 4.0), 8, 4);
+        painter.draw_all(&[
+            DrawCommand::line(Point::new(0.0, 0.5), Point::new(8.0, 0.5), Color::RED, 1.0),
+            DrawCommand::Save,
+            DrawCommand::Transform(Affine::translate(2.0, 2.0)),
+            DrawCommand::text("hi", Point::new(0.0, 0.0), 1.0, Color::WHITE),
+            DrawCommand::Restore,
+            DrawCommand::stroke_rect(Rect::new(0.0, 3.0, 3.0, 4.0), Color::WHITE, 1.0),
+        ]);
+        assert_eq!(painter.to_plain(), "--------\n\n  hi\n---\n");
+
+        let ansi = painter.to_ansi();
+        assert!(ansi.starts_with("\x1b[0m\x1b[38;2;255;0;0m--------\x1b[0m\n"));
+
+        let mut painter = AnsiPainter::new(Size::new(5.0, 5.0), 5, 5);
+        painter.draw(&Marker::Diamond.draw(Point::new(2.5, 2.5), 2.5, Color::RED));
+        assert_eq!(painter.to_plain(), "  #\n ###\n#####\n ###\n  #\n");
+    }
+}
@@ -0,0 +1,269 @@
+//! Executes draw commands on an egui `Painter`, for frontends built on egui
+//! rather than druid. egui has no transform stack, so transforms are applied
+//! to the coordinates here before shapes are handed over.
+
+use egui::epaint::{Mesh, TextureHandle};
+use egui::{Align2, Color32, ColorImage, FontId, Pos2, Sense, Shape, TextureOptions};
+
+use super::{Affine, Draw, DrawCommand, Painter, Point, Size, Stroke};
+use crate::color::Color;
+
+pub struct EguiPainter<'a> {
+    painter: &'a egui::Painter,
+    transform: Affine,
+    saved: Vec<Affine>,
+    /// Textures for the images drawn this frame, kept alive until the
+    /// painter is dropped.
+    textures: Vec<TextureHandle>,
+}
+
+impl<'a> EguiPainter<'a> {
+    /// Draws with `origin`, in egui points, as the top-left of the frame.
+    pub fn new(painter: &'a egui::Painter, origin: Pos2) -> Self {
+        Self {
+            painter,
+            transform: Affine::translate(origin.x as f64, origin.y as f64),
+            saved: vec![],
+            textures: vec![],
+        }
+    }
+
+    fn pos(&self, p: Point) -> Pos2 {
+        let p = self.transform.apply(p);
+        Pos2::new(p.x as f32, p.y as f32)
+    }
+
+    fn stroke(&self, stroke: Stroke) -> egui::Stroke {
+        egui::Stroke::new(
+            (stroke.width * self.transform.length_scale()) as f32,
+            to_color32(stroke.color),
+        )
+    }
+
+    fn polygon(&self, points: &[Point], fill: Option<Color>, stroke: Option<Stroke>) {
+        let positions: Vec<_> = points.iter().map(|p| self.pos(*p)).collect();
+        let stroke = stroke.map(|s| self.stroke(s));
+        if let Some(color) = fill {
+            if is_convex(points) {
+                self.painter.add(Shape::convex_polygon(
+                    positions.clone(),
+                    to_color32(color),
+                    egui::Stroke::NONE,
+                ));
+            } else {
+                // egui only fills convex shapes itself.
+                let mut mesh = Mesh::default();
+                for p in &positions {
+                    mesh.colored_vertex(*p, to_color32(color));
+                }
+                for [a, b, c] in triangulate(points) {
+                    mesh.add_triangle(a as u32, b as u32, c as u32);
+                }
+                self.painter.add(Shape::mesh(mesh));
+            }
+        }
+        if let Some(stroke) = stroke {
+            self.painter.add(Shape::closed_line(positions, stroke));
+        }
+    }
+}
+
+/// Allocates the rest of the `ui` and paints a frame of `drawable` in it. The
+/// response tells where clicks landed, relative to its `rect.min`.
+pub fn show(ui: &mut egui::Ui, drawable: &impl Draw) -> egui::Response {
+    let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click());
+    let rect = response.rect;
+    let commands = drawable.draw(Size::new(rect.width() as f64, rect.height() as f64));
+    EguiPainter::new(&painter, rect.min).draw_all(&commands);
+    response
+}
+
+impl Painter for EguiPainter<'_> {
+    fn scale_factor(&self) -> f64 {
+        self.painter.ctx().pixels_per_point() as f64
+    }
+
+    fn draw(&mut self, command: &DrawCommand) {
+        match command {
+            DrawCommand::Rect { rect, fill, stroke } => {
+                let corners = [
+                    Point::new(rect.x0, rect.y0),
+                    Point::new(rect.x1, rect.y0),
+                    Point::new(rect.x1, rect.y1),
+                    Point::new(rect.x0, rect.y1),
+                ];
+                self.polygon(&corners, *fill, *stroke);
+            }
+            DrawCommand::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => {
+                let center = self.pos(*center);
+                let radius = (radius * self.transform.length_scale()) as f32;
+                if let Some(color) = fill {
+                    self.painter
+                        .add(Shape::circle_filled(center, radius, to_color32(*color)));
+                }
+                if let Some(stroke) = stroke {
+                    self.painter
+                        .add(Shape::circle_stroke(center, radius, self.stroke(*stroke)));
+                }
+            }
+            DrawCommand::Line { from, to, stroke } => {
+                self.painter.add(Shape::line_segment(
+                    [self.pos(*from), self.pos(*to)],
+                    self.stroke(*stroke),
+                ));
+            }
+            DrawCommand::Polygon {
+                points,
+                fill,
+                stroke,
+            } => self.polygon(points, *fill, *stroke),
+            DrawCommand::Text {
+                text,
+                origin,
+                size,
+                color,
+            } => {
+                self.painter.text(
+                    self.pos(*origin),
+                    Align2::LEFT_TOP,
+                    text,
+                    FontId::proportional((size * self.transform.length_scale()) as f32),
+                    to_color32(*color),
+                );
+            }
+            DrawCommand::Image { rect, image } => {
+                let texture = self.painter.ctx().load_texture(
+                    "draw-command-image",
+                    ColorImage::from_rgba_unmultiplied([image.width, image.height], &image.pixels),
+                    TextureOptions::LINEAR,
+                );
+                // Rotated images are drawn in their bounding box.
+                let (a, b) = (
+                    self.pos(Point::new(rect.x0, rect.y0)),
+                    self.pos(Point::new(rect.x1, rect.y1)),
+                );
+                self.painter.image(
+                    texture.id(),
+                    egui::Rect::from_two_pos(a, b),
+                    egui::Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(1.0, 1.0)),
+                    Color32::WHITE,
+                );
+                self.textures.push(texture);
+            }
+            DrawCommand::Save => self.saved.push(self.transform),
+            DrawCommand::Restore => {
+                if let Some(transform) = self.saved.pop() {
+                    self.transform = transform;
+                }
+            }
+            DrawCommand::Transform(t) => self.transform = t.then(self.transform),
+        }
+    }
+}
+
+fn to_color32(color: Color) -> Color32 {
+    let (r, g, b, a) = color.as_rgba8();
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+fn is_convex(points: &[Point]) -> bool {
+    let n = points.len();
+    let turns = (0..n).map(|i| cross(points[i], points[(i + 1) % n], points[(i + 2) % n]));
+    let (mut left, mut right) = (false, false);
+    for turn in turns {
+        left |= turn > 0.0;
+        right |= turn < 0.0;
+    }
+    !(left && right)
+}
+
+/// Splits a simple polygon into triangles by ear clipping, returning indices
+/// into `points`.
+fn triangulate(points: &[Point]) -> Vec<[usize; 3]> {
+    let area: f64 = (0..points.len())
+        .map(|i| cross(Point::default(), points[i], points[(i + 1) % points.len()]))
+        .sum();
+    let orientation = area.signum();
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = vec![];
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let [a, b, c] = [
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            ];
+            let [pa, pb, pc] = [points[a], points[b], points[c]];
+            cross(pa, pb, pc) * orientation > 0.0
+                && remaining.iter().all(|&j| {
+                    [a, b, c].contains(&j)
+                        || !(cross(pa, pb, points[j]) * orientation >= 0.0
+                            && cross(pb, pc, points[j]) * orientation >= 0.0
+                            && cross(pc, pa, points[j]) * orientation >= 0.0)
+                })
+        });
+        // Self-intersecting polygons have no ears left at some point; fan out
+        // what remains rather than drop it.
+        let Some(i) = ear else {
+            break;
+        };
+        triangles.push([
+            remaining[(i + n - 1) % n],
+            remaining[i],
+            remaining[(i + 1) % n],
+        ]);
+        remaining.remove(i);
+    }
+    for i in 1..remaining.len().saturating_sub(1) {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::draw::Marker;
+
+    #[test]
+    fn triangulates_concave_polygons() {
+        let DrawCommand::Polygon { points, .. } =
+            Marker::Star.draw(Point::new(0.0, 0.0), 1.0, Color::RED)
+        else {
+            panic!("star is not a polygon");
+        };
+        assert!(!is_convex(&points));
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), points.len() - 2);
+        let area: f64 = triangles
+            .iter()
+            .map(|&[a, b, c]| cross(points[a], points[b], points[c]).abs() / 2.0)
+            .sum();
+        let expected: f64 = (0..points.len())
+            .map(|i| cross(Point::default(), points[i], points[(i + 1) % points.len()]))
+            .sum::<f64>()
+            .abs()
+            / 2.0;
+        assert!((area - expected).abs() < 1e-9);
+
+        let square = [
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 0.0),
+            Point::new(1.0, 1.0),
+            Point::new(0.0, 1.0),
+        ];
+        assert!(is_convex(&square));
+        assert_eq!(triangulate(&square).len(), 2);
+    }
+}
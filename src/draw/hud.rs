@@ -0,0 +1,205 @@
+//! The player list most games show next to the board: a color swatch and name
+//! per player, their score and time left, and a marker on whoever is to move.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::{Draw, DrawCommand, Point, Rect, Size};
+use crate::clock::GameClock;
+use crate::color::Color;
+use crate::gametraits::User;
+use crate::turn_tracker::TurnTracker;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HudRow {
+    pub user: User,
+    pub score: Option<i64>,
+    pub remaining: Option<Duration>,
+    pub to_move: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hud {
+    pub rows: Vec<HudRow>,
+    pub font_size: f64,
+    pub text_color: Color,
+    /// Background of the row of the player to move.
+    pub highlight: Color,
+}
+
+impl Hud {
+    pub fn new<'a>(players: impl IntoIterator<Item = &'a User>) -> Self {
+        Self {
+            rows: players
+                .into_iter()
+                .map(|user| HudRow {
+                    user: user.clone(),
+                    score: None,
+                    remaining: None,
+                    to_move: false,
+                })
+                .collect(),
+            font_size: 14.0,
+            text_color: Color::BLACK,
+            highlight: Color::rgba8(0xff, 0xff, 0x00, 0x40),
+        }
+    }
+
+    /// The players in turn order, marking the one whose turn it is.
+    pub fn from_turn_tracker(tracker: &TurnTracker) -> Self {
+        let mut hud = Self::new(tracker.players());
+        if let Some(current) = tracker.current_player() {
+            hud = hud.with_turn(&current.name);
+        }
+        hud
+    }
+
+    pub fn with_turn(mut self, player: &str) -> Self {
+        for row in &mut self.rows {
+            row.to_move = row.user.name == player;
+        }
+        self
+    }
+
+    pub fn with_scores(mut self, scores: &HashMap<String, i64>) -> Self {
+        for row in &mut self.rows {
+            row.score = scores.get(&row.user.name).copied();
+        }
+        self
+    }
+
+    pub fn with_clock(mut self, clock: &GameClock) -> Self {
+        for row in &mut self.rows {
+            row.remaining = clock.remaining(&row.user.name);
+        }
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f64) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn row_height(&self) -> f64 {
+        self.font_size * 1.5
+    }
+
+    /// The HUD laid out top to bottom inside `area`. Scores and clocks are
+    /// right-aligned columns, assuming about 0.6 of the font size per
+    /// character since text can't be measured here.
+    pub fn draw_in(&self, area: Rect) -> Vec<DrawCommand> {
+        let row_height = self.row_height();
+        let char_width = self.font_size * 0.6;
+        let clock_x = area.x1 - char_width * 6.0;
+        let score_x = clock_x - char_width * 7.0;
+
+        let mut commands = vec![];
+        for (i, row) in self.rows.iter().enumerate() {
+            let top = area.y0 + i as f64 * row_height;
+            if top + row_height > area.y1 {
+                break;
+            }
+            if row.to_move {
+                commands.push(DrawCommand::fill_rect(
+                    Rect::new(area.x0, top, area.x1, top + row_height),
+                    self.highlight,
+                ));
+            }
+            let text_top = top + (row_height - self.font_size) / 2.0;
+            commands.push(DrawCommand::fill_circle(
+                Point::new(area.x0 + row_height / 2.0, top + row_height / 2.0),
+                self.font_size * 0.4,
+                row.user.color,
+            ));
+            commands.push(DrawCommand::text(
+                row.user.name.clone(),
+                Point::new(area.x0 + row_height, text_top),
+                self.font_size,
+                self.text_color,
+            ));
+            let right_aligned = [
+                (row.score.map(|s| s.to_string()), clock_x - char_width),
+                (row.remaining.map(format_clock), area.x1),
+            ];
+            for (text, right) in right_aligned {
+                if let Some(text) = text {
+                    let x = right - text.chars().count() as f64 * char_width;
+                    commands.push(DrawCommand::text(
+                        text,
+                        Point::new(x.max(score_x), text_top),
+                        self.font_size,
+                        self.text_color,
+                    ));
+                }
+            }
+        }
+        commands
+    }
+}
+
+impl Draw for Hud {
+    fn draw(&self, size: Size) -> Vec<DrawCommand> {
+        self.draw_in(Rect::from_origin_size(Point::default(), size))
+    }
+}
+
+/// Time left as `m:ss`, or `s.t` under ten seconds when every tenth counts.
+pub fn format_clock(remaining: Duration) -> String {
+    if remaining < Duration::from_secs(10) {
+        let tenths = remaining.as_millis() / 100;
+        format!("{}.{}", tenths / 10, tenths % 10)
+    } else {
+        let secs = remaining.as_secs();
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn user(name: &str, color: Color) -> User {
+        User::new(name, color)
+    }
+
+    #[test]
+    fn clock_format() {
+        assert_eq!(format_clock(Duration::from_secs(125)), "2:05");
+        assert_eq!(format_clock(Duration::from_secs(10)), "0:10");
+        assert_eq!(format_clock(Duration::from_millis(9_876)), "9.8");
+    }
+
+    #[test]
+    fn rows_from_tracker_scores_and_clock() {
+        let mut tracker =
+            TurnTracker::new(vec![user("alice", Color::RED), user("bob", Color::BLUE)]);
+        tracker.advance_player();
+        tracker.advance_player();
+        let mut clock = GameClock::new(Duration::from_secs(90));
+        clock.add_player("alice");
+        let scores = HashMap::from([("bob".to_string(), 12)]);
+
+        let hud = Hud::from_turn_tracker(&tracker)
+            .with_scores(&scores)
+            .with_clock(&clock)
+            .with_font_size(10.0);
+        assert!(!hud.rows[0].to_move && hud.rows[1].to_move);
+
+        let commands = hud.draw(Size::new(200.0, 100.0));
+        assert_eq!(
+            commands,
+            vec![
+                DrawCommand::fill_circle(Point::new(7.5, 7.5), 4.0, Color::RED),
+                DrawCommand::text("alice", Point::new(15.0, 2.5), 10.0, Color::BLACK),
+                DrawCommand::text("1:30", Point::new(176.0, 2.5), 10.0, Color::BLACK),
+                DrawCommand::fill_rect(Rect::new(0.0, 15.0, 200.0, 30.0), hud.highlight),
+                DrawCommand::fill_circle(Point::new(7.5, 22.5), 4.0, Color::BLUE),
+                DrawCommand::text("bob", Point::new(15.0, 17.5), 10.0, Color::BLACK),
+                DrawCommand::text("12", Point::new(146.0, 17.5), 10.0, Color::BLACK),
+            ]
+        );
+
+        // Rows that don't fit are left out.
+        assert_eq!(hud.draw(Size::new(200.0, 20.0)).len(), 3);
+    }
+}
@@ -0,0 +1,214 @@
+//! Pixel math for boards: where each cell of a square or hex grid goes in a
+//! viewport, and which cell a point (a click, say) falls in. Boards are
+//! scaled to fit inside the margins and centered, keeping cells square or
+//! hexes regular whatever the viewport's aspect ratio.
+
+use super::{Point, Rect, Size};
+use crate::hex::Hex;
+
+const SQRT_3: f64 = 1.732_050_807_568_877_2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridLayout {
+    pub origin: Point,
+    pub cell: f64,
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl GridLayout {
+    pub fn fit(viewport: Size, cols: usize, rows: usize, margin: f64) -> Self {
+        let cell = ((viewport.width - 2.0 * margin) / cols as f64)
+            .min((viewport.height - 2.0 * margin) / rows as f64)
+            .max(0.0);
+        let origin = Point::new(
+            (viewport.width - cell * cols as f64) / 2.0,
+            (viewport.height - cell * rows as f64) / 2.0,
+        );
+        Self {
+            origin,
+            cell,
+            cols,
+            rows,
+        }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        Rect::from_origin_size(
+            self.origin,
+            Size::new(self.cell * self.cols as f64, self.cell * self.rows as f64),
+        )
+    }
+
+    pub fn cell_rect(&self, col: usize, row: usize) -> Rect {
+        Rect::from_origin_size(
+            Point::new(
+                self.origin.x + col as f64 * self.cell,
+                self.origin.y + row as f64 * self.cell,
+            ),
+            Size::new(self.cell, self.cell),
+        )
+    }
+
+    pub fn cell_center(&self, col: usize, row: usize) -> Point {
+        self.cell_rect(col, row).center()
+    }
+
+    /// The `(col, row)` of the cell containing `p`, if any.
+    pub fn cell_at(&self, p: Point) -> Option<(usize, usize)> {
+        let col = ((p.x - self.origin.x) / self.cell).floor();
+        let row = ((p.y - self.origin.y) / self.cell).floor();
+        if col < 0.0 || row < 0.0 || col >= self.cols as f64 || row >= self.rows as f64 {
+            return None;
+        }
+        Some((col as usize, row as usize))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HexOrientation {
+    PointyTop,
+    FlatTop,
+}
+
+/// Places hexes given in axial `(q, r)` coordinates. `size` is the distance
+/// from a hex's center to its corners.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HexLayout {
+    pub orientation: HexOrientation,
+    pub origin: Point,
+    pub size: f64,
+}
+
+impl HexLayout {
+    /// The layout fitting all of `hexes` in the viewport.
+    pub fn fit(
+        viewport: Size,
+        orientation: HexOrientation,
+        hexes: impl IntoIterator<Item = (i32, i32)>,
+        margin: f64,
+    ) -> Self {
+        let unit = HexLayout {
+            orientation,
+            origin: Point::default(),
+            size: 1.0,
+        };
+        let (half_width, half_height) = match orientation {
+            HexOrientation::PointyTop => (SQRT_3 / 2.0, 1.0),
+            HexOrientation::FlatTop => (1.0, SQRT_3 / 2.0),
+        };
+        let mut bounds: Option<Rect> = None;
+        for (q, r) in hexes {
+            let c = unit.center(q, r);
+            let hex = Rect::new(
+                c.x - half_width,
+                c.y - half_height,
+                c.x + half_width,
+                c.y + half_height,
+            );
+            bounds = Some(match bounds {
+                None => hex,
+                Some(b) => Rect::new(
+                    b.x0.min(hex.x0),
+                    b.y0.min(hex.y0),
+                    b.x1.max(hex.x1),
+                    b.y1.max(hex.y1),
+                ),
+            });
+        }
+        let Some(bounds) = bounds else {
+            return unit;
+        };
+
+        let size = ((viewport.width - 2.0 * margin) / bounds.width())
+            .min((viewport.height - 2.0 * margin) / bounds.height())
+            .max(0.0);
+        let center = bounds.center();
+        HexLayout {
+            orientation,
+            origin: Point::new(
+                viewport.width / 2.0 - center.x * size,
+                viewport.height / 2.0 - center.y * size,
+            ),
+            size,
+        }
+    }
+
+    pub fn center(&self, q: i32, r: i32) -> Point {
+        let (q, r) = (q as f64, r as f64);
+        let (x, y) = match self.orientation {
+            HexOrientation::PointyTop => (SQRT_3 * q + SQRT_3 / 2.0 * r, 1.5 * r),
+            HexOrientation::FlatTop => (1.5 * q, SQRT_3 / 2.0 * q + SQRT_3 * r),
+        };
+        Point::new(self.origin.x + x * self.size, self.origin.y + y * self.size)
+    }
+
+    /// The corners of a hex, clockwise, for drawing it as a polygon.
+    pub fn corners(&self, q: i32, r: i32) -> Vec<Point> {
+        let center = self.center(q, r);
+        let start = match self.orientation {
+            HexOrientation::PointyTop => -30.0f64,
+            HexOrientation::FlatTop => 0.0,
+        };
+        (0..6)
+            .map(|i| {
+                let (sin, cos) = (start + 60.0 * i as f64).to_radians().sin_cos();
+                Point::new(center.x + self.size * cos, center.y + self.size * sin)
+            })
+            .collect()
+    }
+
+    /// The hex containing `p`.
+    pub fn hex_at(&self, p: Point) -> (i32, i32) {
+        let (x, y) = (
+            (p.x - self.origin.x) / self.size,
+            (p.y - self.origin.y) / self.size,
+        );
+        let (q, r) = match self.orientation {
+            HexOrientation::PointyTop => (SQRT_3 / 3.0 * x - y / 3.0, 2.0 / 3.0 * y),
+            HexOrientation::FlatTop => (2.0 / 3.0 * x, -x / 3.0 + SQRT_3 / 3.0 * y),
+        };
+        Hex::round(q, r).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn grid() {
+        let grid = GridLayout::fit(Size::new(100.0, 50.0), 4, 2, 5.0);
+        assert_eq!(grid.cell, 20.0);
+        assert_eq!(grid.bounds(), Rect::new(10.0, 5.0, 90.0, 45.0));
+        assert_eq!(grid.cell_rect(1, 1), Rect::new(30.0, 25.0, 50.0, 45.0));
+        assert_eq!(grid.cell_center(0, 0), Point::new(20.0, 15.0));
+        assert_eq!(grid.cell_at(Point::new(31.0, 44.0)), Some((1, 1)));
+        assert_eq!(grid.cell_at(Point::new(9.0, 20.0)), None);
+        assert_eq!(grid.cell_at(Point::new(90.0, 20.0)), None);
+    }
+
+    #[test]
+    fn hex() {
+        let layout = HexLayout::fit(
+            Size::new(100.0, 100.0),
+            HexOrientation::PointyTop,
+            [(0, 0)],
+            0.0,
+        );
+        assert_eq!(layout.size, 50.0);
+        assert_eq!(layout.center(0, 0), Point::new(50.0, 50.0));
+
+        for orientation in [HexOrientation::PointyTop, HexOrientation::FlatTop] {
+            let hexes = [(0, 0), (1, 0), (0, 1), (-1, 2), (2, -1)];
+            let layout = HexLayout::fit(Size::new(300.0, 200.0), orientation, hexes, 10.0);
+            for (q, r) in hexes {
+                assert_eq!(layout.hex_at(layout.center(q, r)), (q, r));
+                for corner in layout.corners(q, r) {
+                    assert!(corner.x >= 9.999 && corner.x <= 290.001);
+                    assert!(corner.y >= 9.999 && corner.y <= 190.001);
+                }
+            }
+        }
+    }
+}
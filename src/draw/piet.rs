@@ -0,0 +1,152 @@
+//! Executes draw commands on a piet `RenderContext`, which is what druid
+//! paints with.
+
+use druid::kurbo;
+use druid::piet::{
+    FontFamily, ImageFormat, InterpolationMode, RenderContext, Text, TextLayoutBuilder,
+};
+
+use super::{Affine, Draw, DrawCommand, Painter, Point, Rect, Size};
+
+/// Draws in logical units, leaving the scaling to physical pixels to piet.
+pub struct PietPainter<'a, R> {
+    ctx: &'a mut R,
+    scale: f64,
+}
+
+impl<'a, R: RenderContext> PietPainter<'a, R> {
+    pub fn new(ctx: &'a mut R) -> Self {
+        Self { ctx, scale: 1.0 }
+    }
+
+    /// Reports `scale` as the painter's scale factor, for contexts that
+    /// render to a hi-DPI surface.
+    pub fn with_scale_factor(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+impl<R: RenderContext> Painter for PietPainter<'_, R> {
+    fn scale_factor(&self) -> f64 {
+        self.scale
+    }
+
+    fn draw(&mut self, command: &DrawCommand) {
+        match command {
+            DrawCommand::Rect { rect, fill, stroke } => {
+                let rect = to_rect(*rect);
+                if let Some(color) = fill {
+                    self.ctx.fill(rect, color);
+                }
+                if let Some(stroke) = stroke {
+                    self.ctx.stroke(rect, &stroke.color, stroke.width);
+                }
+            }
+            DrawCommand::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => {
+                let circle = kurbo::Circle::new(to_point(*center), *radius);
+                if let Some(color) = fill {
+                    self.ctx.fill(circle, color);
+                }
+                if let Some(stroke) = stroke {
+                    self.ctx.stroke(circle, &stroke.color, stroke.width);
+                }
+            }
+            DrawCommand::Line { from, to, stroke } => {
+                let line = kurbo::Line::new(to_point(*from), to_point(*to));
+                self.ctx.stroke(line, &stroke.color, stroke.width);
+            }
+            DrawCommand::Polygon {
+                points,
+                fill,
+                stroke,
+            } => {
+                let mut path = kurbo::BezPath::new();
+                for (i, p) in points.iter().enumerate() {
+                    if i == 0 {
+                        path.move_to(to_point(*p));
+                    } else {
+                        path.line_to(to_point(*p));
+                    }
+                }
+                path.close_path();
+                if let Some(color) = fill {
+                    self.ctx.fill(&path, color);
+                }
+                if let Some(stroke) = stroke {
+                    self.ctx.stroke(&path, &stroke.color, stroke.width);
+                }
+            }
+            DrawCommand::Text {
+                text,
+                origin,
+                size,
+                color,
+            } => {
+                let layout = self
+                    .ctx
+                    .text()
+                    .new_text_layout(text.clone())
+                    .font(FontFamily::SYSTEM_UI, *size)
+                    .text_color(*color)
+                    .build();
+                match layout {
+                    Ok(layout) => self.ctx.draw_text(&layout, to_point(*origin)),
+                    Err(e) => tracing::warn!(?text, error = ?e, "failed to lay out text"),
+                }
+            }
+            DrawCommand::Image { rect, image } => {
+                let made = self.ctx.make_image(
+                    image.width,
+                    image.height,
+                    &image.pixels,
+                    ImageFormat::RgbaSeparate,
+                );
+                match made {
+                    Ok(made) => {
+                        self.ctx
+                            .draw_image(&made, to_rect(*rect), InterpolationMode::Bilinear)
+                    }
+                    Err(e) => tracing::warn!(error = ?e, "failed to create image"),
+                }
+            }
+            DrawCommand::Save => {
+                if let Err(e) = self.ctx.save() {
+                    tracing::warn!(error = ?e, "failed to save render state");
+                }
+            }
+            DrawCommand::Restore => {
+                if let Err(e) = self.ctx.restore() {
+                    tracing::warn!(error = ?e, "failed to restore render state");
+                }
+            }
+            DrawCommand::Transform(Affine(coefficients)) => {
+                self.ctx.transform(kurbo::Affine::new(*coefficients))
+            }
+        }
+    }
+}
+
+/// Paints `drawable` in a druid widget, for implementing `Paint::paint` on top
+/// of `Draw`.
+pub fn paint(ctx: &mut druid::PaintCtx, drawable: &impl Draw) {
+    let size = ctx.size();
+    let scale = ctx.scale().x();
+    let commands = drawable.draw(Size::new(size.width, size.height));
+    PietPainter::new(ctx.render_ctx)
+        .with_scale_factor(scale)
+        .draw_all(&commands);
+}
+
+fn to_point(p: Point) -> kurbo::Point {
+    kurbo::Point::new(p.x, p.y)
+}
+
+fn to_rect(r: Rect) -> kurbo::Rect {
+    kurbo::Rect::new(r.x0, r.y0, r.x1, r.y1)
+}
@@ -0,0 +1,291 @@
+//! Renders draw commands to PNG without a window, for thumbnails in the match
+//! history browser. Text is not rendered, as tiny-skia has no text support.
+
+use tiny_skia::{
+    FillRule, IntSize, Paint, Path, PathBuilder, Pixmap, PixmapPaint, Stroke, Transform,
+};
+
+use super::{snap_line, snap_rect, Affine, Draw, DrawCommand, Painter, Point, Rect, Size};
+use crate::color::Color;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PngError {
+    /// Width or height was zero.
+    #[error("the image has no pixels")]
+    EmptyImage,
+    #[error("PNG encoding failed: {0}")]
+    Encoding(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct PngPainter {
+    pixmap: Pixmap,
+    scale: f64,
+    transform: Affine,
+    saved: Vec<Affine>,
+}
+
+impl PngPainter {
+    /// A transparent image of `width` by `height` pixels.
+    pub fn new(width: u32, height: u32) -> Result<Self, PngError> {
+        Self::with_scale(Size::new(width as f64, height as f64), 1.0)
+    }
+
+    /// A transparent image of the logical `size`, `scale` physical pixels per
+    /// logical unit.
+    pub fn with_scale(size: Size, scale: f64) -> Result<Self, PngError> {
+        let width = (size.width * scale).ceil() as u32;
+        let height = (size.height * scale).ceil() as u32;
+        Ok(Self {
+            pixmap: Pixmap::new(width, height).ok_or(PngError::EmptyImage)?,
+            scale,
+            transform: Affine::scale(scale),
+            saved: vec![],
+        })
+    }
+
+    pub fn with_background(mut self, color: Color) -> Self {
+        let (r, g, b, a) = color.as_rgba8();
+        self.pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a));
+        self
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        let pixel = self.pixmap.pixel(x, y)?.demultiply();
+        Some(Color::rgba8(
+            pixel.red(),
+            pixel.green(),
+            pixel.blue(),
+            pixel.alpha(),
+        ))
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, PngError> {
+        self.pixmap
+            .encode_png()
+            .map_err(|e| PngError::Encoding(e.to_string()))
+    }
+
+    fn fill_and_stroke(
+        &mut self,
+        path: Option<Path>,
+        fill: Option<Color>,
+        stroke: Option<super::Stroke>,
+        transform: Affine,
+    ) {
+        let Some(path) = path else {
+            return;
+        };
+        let transform = to_transform(transform);
+        if let Some(color) = fill {
+            self.pixmap
+                .fill_path(&path, &paint(color), FillRule::Winding, transform, None);
+        }
+        if let Some(stroke) = stroke {
+            let width = Stroke {
+                width: stroke.width as f32,
+                ..Default::default()
+            };
+            self.pixmap
+                .stroke_path(&path, &paint(stroke.color), &width, transform, None);
+        }
+    }
+
+    /// Rects and lines are snapped to physical pixels when the transform
+    /// allows it, which means drawing them in physical coordinates with
+    /// strokes scaled to match.
+    fn physical_stroke(&self, stroke: super::Stroke) -> super::Stroke {
+        super::Stroke {
+            width: stroke.width * self.transform.length_scale(),
+            ..stroke
+        }
+    }
+}
+
+/// A frame of `drawable` as a PNG of `width` by `height` pixels.
+pub fn to_png(drawable: &impl Draw, width: u32, height: u32) -> Result<Vec<u8>, PngError> {
+    to_png_scaled(drawable, Size::new(width as f64, height as f64), 1.0)
+}
+
+/// A frame of `drawable` at the logical `size`, rendered with `scale`
+/// physical pixels per logical unit.
+pub fn to_png_scaled(drawable: &impl Draw, size: Size, scale: f64) -> Result<Vec<u8>, PngError> {
+    let mut painter = PngPainter::with_scale(size, scale)?;
+    painter.draw_all(&drawable.draw(size));
+    painter.encode()
+}
+
+impl Painter for PngPainter {
+    fn scale_factor(&self) -> f64 {
+        self.scale
+    }
+
+    fn draw(&mut self, command: &DrawCommand) {
+        match command {
+            DrawCommand::Rect { rect, fill, stroke } => {
+                let (rect, stroke, transform) = if self.transform.is_axis_aligned() {
+                    let a = self.transform.apply(Point::new(rect.x0, rect.y0));
+                    let b = self.transform.apply(Point::new(rect.x1, rect.y1));
+                    let physical =
+                        Rect::new(a.x.min(b.x), a.y.min(b.y), a.x.max(b.x), a.y.max(b.y));
+                    (
+                        snap_rect(physical, 1.0),
+                        stroke.map(|s| self.physical_stroke(s)),
+                        Affine::IDENTITY,
+                    )
+                } else {
+                    (*rect, *stroke, self.transform)
+                };
+                let rect = tiny_skia::Rect::from_ltrb(
+                    rect.x0 as f32,
+                    rect.y0 as f32,
+                    rect.x1 as f32,
+                    rect.y1 as f32,
+                );
+                self.fill_and_stroke(rect.map(PathBuilder::from_rect), *fill, stroke, transform);
+            }
+            DrawCommand::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => {
+                let circle =
+                    PathBuilder::from_circle(center.x as f32, center.y as f32, *radius as f32);
+                self.fill_and_stroke(circle, *fill, *stroke, self.transform);
+            }
+            DrawCommand::Line { from, to, stroke } => {
+                let (from, to, stroke, transform) = if self.transform.is_axis_aligned() {
+                    let stroke = self.physical_stroke(*stroke);
+                    let (from, to) = snap_line(
+                        self.transform.apply(*from),
+                        self.transform.apply(*to),
+                        stroke.width,
+                        1.0,
+                    );
+                    (from, to, stroke, Affine::IDENTITY)
+                } else {
+                    (*from, *to, *stroke, self.transform)
+                };
+                let mut builder = PathBuilder::new();
+                builder.move_to(from.x as f32, from.y as f32);
+                builder.line_to(to.x as f32, to.y as f32);
+                self.fill_and_stroke(builder.finish(), None, Some(stroke), transform);
+            }
+            DrawCommand::Polygon {
+                points,
+                fill,
+                stroke,
+            } => {
+                let mut builder = PathBuilder::new();
+                for (i, p) in points.iter().enumerate() {
+                    if i == 0 {
+                        builder.move_to(p.x as f32, p.y as f32);
+                    } else {
+                        builder.line_to(p.x as f32, p.y as f32);
+                    }
+                }
+                builder.close();
+                self.fill_and_stroke(builder.finish(), *fill, *stroke, self.transform);
+            }
+            DrawCommand::Text { .. } => {}
+            DrawCommand::Image { rect, image } => {
+                let Some(size) = IntSize::from_wh(image.width as u32, image.height as u32) else {
+                    return;
+                };
+                let Some(pixmap) = Pixmap::from_vec(premultiply(&image.pixels), size) else {
+                    tracing::warn!(
+                        image.width,
+                        image.height,
+                        "image pixels don't match its size"
+                    );
+                    return;
+                };
+                let placement = Affine([
+                    rect.width() / image.width as f64,
+                    0.0,
+                    0.0,
+                    rect.height() / image.height as f64,
+                    rect.x0,
+                    rect.y0,
+                ]);
+                let transform = to_transform(placement.then(self.transform));
+                self.pixmap.draw_pixmap(
+                    0,
+                    0,
+                    pixmap.as_ref(),
+                    &PixmapPaint::default(),
+                    transform,
+                    None,
+                );
+            }
+            DrawCommand::Save => self.saved.push(self.transform),
+            DrawCommand::Restore => {
+                if let Some(transform) = self.saved.pop() {
+                    self.transform = transform;
+                }
+            }
+            DrawCommand::Transform(t) => self.transform = t.then(self.transform),
+        }
+    }
+}
+
+fn paint(color: Color) -> Paint<'static> {
+    let (r, g, b, a) = color.as_rgba8();
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, a);
+    paint.anti_alias = true;
+    paint
+}
+
+fn to_transform(Affine([a, b, c, d, e, f]): Affine) -> Transform {
+    Transform::from_row(a as f32, b as f32, c as f32, d as f32, e as f32, f as f32)
+}
+
+fn premultiply(pixels: &[u8]) -> Vec<u8> {
+    pixels
+        .chunks_exact(4)
+        .flat_map(|p| {
+            let mul = |c: u8| ((c as u16 * p[3] as u16 + 127) / 255) as u8;
+            [mul(p[0]), mul(p[1]), mul(p[2]), p[3]]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::replay::test_game::TakeAway;
+
+    #[test]
+    fn renders_game_frame() {
+        let mut painter = PngPainter::new(100, 50).unwrap();
+        painter.draw_all(&TakeAway::new(2).draw(Size::new(100.0, 50.0)));
+        assert_eq!(painter.pixel(25, 25), Some(Color::BLACK));
+        assert_eq!(painter.pixel(50, 2), Some(Color::rgba8(0, 0, 0, 0)));
+        assert!(painter.encode().unwrap().starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert!(matches!(
+            to_png(&TakeAway::new(2), 0, 10),
+            Err(PngError::EmptyImage)
+        ));
+    }
+
+    #[test]
+    fn renders_hi_dpi() {
+        let mut painter = PngPainter::with_scale(Size::new(10.0, 10.0), 2.0).unwrap();
+        assert_eq!(painter.scale_factor(), 2.0);
+        painter.draw(&DrawCommand::fill_rect(
+            Rect::new(0.0, 0.0, 5.25, 10.0),
+            Color::RED,
+        ));
+        assert_eq!(painter.pixel(19, 19), Some(Color::rgba8(0, 0, 0, 0)));
+        assert_eq!(painter.pixel(9, 19), Some(Color::RED));
+        assert_eq!(painter.pixel(10, 0), Some(Color::RED));
+        assert_eq!(painter.pixel(11, 0), Some(Color::rgba8(0, 0, 0, 0)));
+    }
+
+    #[test]
+    fn premultiplies_images() {
+        assert_eq!(premultiply(&[255, 128, 0, 128]), vec![128, 64, 0, 128]);
+    }
+}
@@ -0,0 +1,349 @@
+//! Renders draw commands to an SVG document, for embedding positions in
+//! emails and web pages.
+
+use std::fmt::Write;
+
+use super::{Affine, Draw, DrawCommand, Image, Painter, Size, Stroke};
+use crate::color::Color;
+
+#[derive(Clone, Debug)]
+pub struct SvgPainter {
+    body: String,
+    /// Number of `<g>` elements opened by transforms since each `Save`, the
+    /// last entry being the innermost.
+    groups: Vec<usize>,
+}
+
+impl SvgPainter {
+    pub fn new() -> Self {
+        Self {
+            body: String::new(),
+            groups: vec![0],
+        }
+    }
+
+    /// Closes any open groups and wraps what was drawn in an `<svg>` element
+    /// of the given size.
+    pub fn finish(mut self, size: Size) -> String {
+        for open in std::mem::take(&mut self.groups) {
+            self.close_groups(open);
+        }
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n{}</svg>\n",
+            self.body,
+            w = size.width,
+            h = size.height,
+        )
+    }
+
+    fn close_groups(&mut self, count: usize) {
+        for _ in 0..count {
+            self.body.push_str("</g>\n");
+        }
+    }
+}
+
+impl Default for SvgPainter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The SVG for a frame of `drawable` at `size`.
+pub fn to_svg(drawable: &impl Draw, size: Size) -> String {
+    let mut painter = SvgPainter::new();
+    painter.draw_all(&drawable.draw(size));
+    painter.finish(size)
+}
+
+impl Painter for SvgPainter {
+    fn draw(&mut self, command: &DrawCommand) {
+        let out = &mut self.body;
+        match command {
+            DrawCommand::Rect { rect, fill, stroke } => writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"{}/>",
+                rect.x0,
+                rect.y0,
+                rect.width(),
+                rect.height(),
+                paint_attributes(*fill, *stroke)
+            ),
+            DrawCommand::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => writeln!(
+                out,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{radius}\"{}/>",
+                center.x,
+                center.y,
+                paint_attributes(*fill, *stroke)
+            ),
+            DrawCommand::Line { from, to, stroke } => writeln!(
+                out,
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"{}/>",
+                from.x,
+                from.y,
+                to.x,
+                to.y,
+                paint_attributes(None, Some(*stroke))
+            ),
+            DrawCommand::Polygon {
+                points,
+                fill,
+                stroke,
+            } => {
+                let points: Vec<_> = points.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+                writeln!(
+                    out,
+                    "<polygon points=\"{}\"{}/>",
+                    points.join(" "),
+                    paint_attributes(*fill, *stroke)
+                )
+            }
+            DrawCommand::Text {
+                text,
+                origin,
+                size,
+                color,
+            } => writeln!(
+                out,
+                "<text x=\"{}\" y=\"{}\" font-size=\"{size}\" font-family=\"sans-serif\" dominant-baseline=\"hanging\"{}>{}</text>",
+                origin.x,
+                origin.y,
+                color_attributes("fill", *color),
+                escape(text)
+            ),
+            DrawCommand::Image { rect, image } => writeln!(
+                out,
+                "<image x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" preserveAspectRatio=\"none\" href=\"data:image/png;base64,{}\"/>",
+                rect.x0,
+                rect.y0,
+                rect.width(),
+                rect.height(),
+                base64(&uncompressed_png(image))
+            ),
+            DrawCommand::Save => {
+                self.groups.push(0);
+                Ok(())
+            }
+            DrawCommand::Restore => {
+                if self.groups.len() > 1 {
+                    let open = self.groups.pop().unwrap_or(0);
+                    self.close_groups(open);
+                }
+                Ok(())
+            }
+            DrawCommand::Transform(Affine([a, b, c, d, e, f])) => {
+                match self.groups.last_mut() {
+                    Some(open) => *open += 1,
+                    None => self.groups.push(1),
+                }
+                writeln!(out, "<g transform=\"matrix({a} {b} {c} {d} {e} {f})\">")
+            }
+        }
+        .unwrap()
+    }
+}
+
+fn paint_attributes(fill: Option<Color>, stroke: Option<Stroke>) -> String {
+    let mut out = match fill {
+        Some(color) => color_attributes("fill", color),
+        None => " fill=\"none\"".to_string(),
+    };
+    if let Some(Stroke { color, width }) = stroke {
+        out += &color_attributes("stroke", color);
+        write!(out, " stroke-width=\"{width}\"").unwrap();
+    }
+    out
+}
+
+fn color_attributes(attribute: &str, color: Color) -> String {
+    let (r, g, b, a) = color.as_rgba8();
+    let mut out = format!(" {attribute}=\"#{r:02x}{g:02x}{b:02x}\"");
+    if a != 0xff {
+        write!(out, " {attribute}-opacity=\"{}\"", a as f64 / 255.0).unwrap();
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A PNG with the pixels in stored (uncompressed) deflate blocks, which is
+/// plenty for the small images games draw and needs no extra dependency.
+fn uncompressed_png(image: &Image) -> Vec<u8> {
+    let row_len = image.width * 4;
+    let mut raw = Vec::with_capacity((row_len + 1) * image.height);
+    for row in image.pixels.chunks(row_len.max(1)).take(image.height) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(blocks.peek().is_none() as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = vec![];
+    header.extend_from_slice(&(image.width as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    for (kind, data) in [(b"IHDR", header), (b"IDAT", zlib), (b"IEND", vec![])] {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend_from_slice(kind);
+        png.extend_from_slice(&data);
+        let crc = crc32(&png[start..]);
+        png.extend_from_slice(&crc.to_be_bytes());
+    }
+    png
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::draw::{Point, Rect};
+    use crate::replay::test_game::TakeAway;
+
+    #[test]
+    fn game_frame() {
+        assert_eq!(
+            to_svg(&TakeAway::new(2), Size::new(100.0, 50.0)),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50" viewBox="0 0 100 50">
+<circle cx="25" cy="25" r="20" fill="#000000"/>
+<circle cx="75" cy="25" r="20" fill="#000000"/>
+</svg>
+"##
+        );
+    }
+
+    #[test]
+    fn shapes_text_and_transforms() {
+        let mut painter = SvgPainter::new();
+        painter.draw_all(&[
+            DrawCommand::Save,
+            DrawCommand::Transform(Affine::translate(5.0, 0.0)),
+            DrawCommand::stroke_rect(
+                Rect::new(0.0, 0.0, 10.0, 5.0),
+                Color::rgba8(0xff, 0, 0, 0x80),
+                1.5,
+            ),
+            DrawCommand::Restore,
+            DrawCommand::line(
+                Point::new(0.0, 0.0),
+                Point::new(1.0, 1.0),
+                Color::WHITE,
+                2.0,
+            ),
+            DrawCommand::Transform(Affine::scale(2.0)),
+            DrawCommand::text("a < b", Point::new(1.0, 2.0), 12.0, Color::BLACK),
+        ]);
+        assert_eq!(
+            painter.finish(Size::new(10.0, 10.0)),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10" viewBox="0 0 10 10">
+<g transform="matrix(1 0 0 1 5 0)">
+<rect x="0" y="0" width="10" height="5" fill="none" stroke="#ff0000" stroke-opacity="0.5019607843137255" stroke-width="1.5"/>
+</g>
+<line x1="0" y1="0" x2="1" y2="1" fill="none" stroke="#ffffff" stroke-width="2"/>
+<g transform="matrix(2 0 0 2 0 0)">
+<text x="1" y="2" font-size="12" font-family="sans-serif" dominant-baseline="hanging" fill="#000000">a &lt; b</text>
+</g>
+</svg>
+"##
+        );
+    }
+
+    #[test]
+    fn embeds_images_as_png() {
+        let image = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![0xff, 0, 0, 0xff],
+        };
+        let png = uncompressed_png(&image);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(&png[png.len() - 4..], &[0xae, 0x42, 0x60, 0x82]);
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+
+        let mut painter = SvgPainter::new();
+        painter.draw(&DrawCommand::Image {
+            rect: Rect::new(0.0, 0.0, 4.0, 4.0),
+            image: Arc::new(image),
+        });
+        assert!(painter
+            .finish(Size::new(4.0, 4.0))
+            .contains("href=\"data:image/png;base64,iVBORw0KGgo"));
+    }
+}
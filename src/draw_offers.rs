@@ -0,0 +1,203 @@
+//! Players agreeing to end a game in a draw. One offers, the others accept
+//! or decline, and the game ends once everyone still playing has accepted.
+//! Only games that opt in with `GameTrait::draw_offers_allowed` take offers.
+
+use crate::gametraits::GameTrait;
+use crate::messages::{DrawOffer, GameOver, ToClient};
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum DrawOfferError {
+    #[error("this game doesn't take draw offers")]
+    NotAllowed,
+    #[error("{0} isn't playing")]
+    NotPlaying(String),
+    #[error("there's no draw offer to answer")]
+    NoOffer,
+    #[error("{0} already agreed to a draw")]
+    AlreadyAgreed(String),
+}
+
+/// The open offer in a game, if any, kept by the server next to the game.
+/// Each method returns what to tell everyone in the game.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DrawOffers {
+    /// Who offered first, then who accepted. Empty with no offer open.
+    agreed: Vec<String>,
+}
+
+impl DrawOffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.agreed.is_empty()
+    }
+
+    pub fn offered_by(&self) -> Option<&str> {
+        self.agreed.first().map(String::as_str)
+    }
+
+    /// Offers a draw. Offering when another player already has is
+    /// accepting theirs.
+    pub fn offer(
+        &mut self,
+        game: &mut dyn GameTrait,
+        player: &str,
+    ) -> Result<ToClient, DrawOfferError> {
+        if self.is_open() {
+            return self.accept(game, player);
+        }
+        check_player(game, player)?;
+        self.agreed.push(player.to_string());
+        Ok(ToClient::DrawOffered(DrawOffer {
+            player: player.to_string(),
+        }))
+    }
+
+    /// Accepts the open offer. The last player to accept ends the game with
+    /// `GameTrait::draw_agreed`, and the offer is closed.
+    pub fn accept(
+        &mut self,
+        game: &mut dyn GameTrait,
+        player: &str,
+    ) -> Result<ToClient, DrawOfferError> {
+        check_player(game, player)?;
+        if !self.is_open() {
+            return Err(DrawOfferError::NoOffer);
+        }
+        if self.agreed.iter().any(|p| p == player) {
+            return Err(DrawOfferError::AlreadyAgreed(player.to_string()));
+        }
+        self.agreed.push(player.to_string());
+        let everyone = game.turn_tracker().is_some_and(|tracker| {
+            tracker
+                .players()
+                .all(|user| self.agreed.contains(&user.name))
+        });
+        if !everyone {
+            return Ok(ToClient::DrawOffered(DrawOffer {
+                player: player.to_string(),
+            }));
+        }
+        self.agreed.clear();
+        game.draw_agreed();
+        Ok(ToClient::GameOver(GameOver {
+            reason: "draw agreed".to_string(),
+        }))
+    }
+
+    /// Declines the open offer, which closes it.
+    pub fn decline(
+        &mut self,
+        game: &dyn GameTrait,
+        player: &str,
+    ) -> Result<ToClient, DrawOfferError> {
+        check_player(game, player)?;
+        if !self.is_open() {
+            return Err(DrawOfferError::NoOffer);
+        }
+        self.agreed.clear();
+        Ok(ToClient::DrawDeclined(DrawOffer {
+            player: player.to_string(),
+        }))
+    }
+
+    /// Drops the open offer without telling anyone, as when a player
+    /// leaves.
+    pub fn withdraw(&mut self) {
+        self.agreed.clear();
+    }
+}
+
+fn check_player(game: &dyn GameTrait, player: &str) -> Result<(), DrawOfferError> {
+    if !game.draw_offers_allowed() {
+        return Err(DrawOfferError::NotAllowed);
+    }
+    match game.turn_tracker() {
+        Some(tracker) if tracker.is_playing(player) => Ok(()),
+        _ => Err(DrawOfferError::NotPlaying(player.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::games::connect_four::{ConnectFour, Outcome};
+    use crate::games::rps::RockPaperScissors;
+    use crate::replay::{GameOutcome, GameRecorder};
+    use crate::test_support::users;
+
+    fn started(players: usize) -> GameRecorder {
+        let mut game = GameRecorder::new(Box::new(ConnectFour::default()));
+        game.reset(users(players));
+        game.try_start_game().unwrap();
+        game
+    }
+
+    #[test]
+    fn agreed_draws_end_the_game() {
+        let mut game = started(2);
+        let mut offers = DrawOffers::new();
+        assert_eq!(
+            offers.offer(&mut game, "p1"),
+            Ok(ToClient::DrawOffered(DrawOffer {
+                player: "p1".to_string()
+            }))
+        );
+        assert_eq!(offers.offered_by(), Some("p1"));
+        assert!(matches!(
+            offers.accept(&mut game, "p2"),
+            Ok(ToClient::GameOver(_))
+        ));
+        assert!(!offers.is_open());
+        assert_eq!(game.replay().outcome, Some(GameOutcome::AgreedDraw));
+        let board = game.game().as_any().downcast_ref::<ConnectFour>().unwrap();
+        assert_eq!(board.outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn offers_cross_into_agreement() {
+        let mut game = started(2);
+        let mut offers = DrawOffers::new();
+        offers.offer(&mut game, "p2").unwrap();
+        assert!(matches!(
+            offers.offer(&mut game, "p1"),
+            Ok(ToClient::GameOver(_))
+        ));
+    }
+
+    #[test]
+    fn declined_offers_close() {
+        let mut game = started(2);
+        let mut offers = DrawOffers::new();
+        assert_eq!(offers.accept(&mut game, "p2"), Err(DrawOfferError::NoOffer));
+        offers.offer(&mut game, "p1").unwrap();
+        assert_eq!(
+            offers.accept(&mut game, "p1"),
+            Err(DrawOfferError::AlreadyAgreed("p1".to_string()))
+        );
+        assert!(matches!(
+            offers.decline(&game, "p2"),
+            Ok(ToClient::DrawDeclined(_))
+        ));
+        assert!(!offers.is_open());
+        assert_eq!(game.replay().outcome, None);
+    }
+
+    #[test]
+    fn only_players_of_games_that_allow_it() {
+        let mut offers = DrawOffers::new();
+        let mut game = started(2);
+        assert_eq!(
+            offers.offer(&mut game, "p3"),
+            Err(DrawOfferError::NotPlaying("p3".to_string()))
+        );
+        let mut rps = RockPaperScissors::default();
+        rps.reset(users(2));
+        assert_eq!(
+            offers.offer(&mut rps, "p1"),
+            Err(DrawOfferError::NotAllowed)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Every way the crate can fail, in one type for servers that would rather
+//! pass errors up with `?` than handle each module's own. The module errors
+//! convert into it and stay reachable by matching on the variant.
+
+use crate::admin::AdminError;
+use crate::auction::BidError;
+use crate::config::{ConfigFileError, InvalidSetting};
+#[cfg(feature = "png")]
+use crate::draw::png::PngError;
+use crate::draw_offers::DrawOfferError;
+use crate::games::gomoku::ConfigError;
+use crate::grid::{DecodeError, RaggedRows};
+use crate::host::HostError;
+use crate::i18n::CatalogError;
+use crate::lifecycle::LifecycleError;
+use crate::messages::MalformedMessage;
+use crate::names::NameError;
+use crate::private::NoChannel;
+use crate::replay::codec::CodecError;
+use crate::replay::{Divergence, NotationError, PlaybackError, ReplayJsonError};
+use crate::rules::ParseRulesVersionError;
+use crate::simulate::SimulationError;
+use crate::snapshot::DirStoreError;
+use crate::turn_tracker::TrackerError;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(transparent)]
+    Tracker(#[from] TrackerError),
+    #[error(transparent)]
+    Name(#[from] NameError),
+    #[error(transparent)]
+    Message(#[from] MalformedMessage),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    #[error(transparent)]
+    RaggedRows(#[from] RaggedRows),
+    #[error(transparent)]
+    NoChannel(#[from] NoChannel),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Bid(#[from] BidError),
+    #[error(transparent)]
+    DrawOffer(#[from] DrawOfferError),
+    #[error(transparent)]
+    Notation(#[from] NotationError),
+    #[error(transparent)]
+    Playback(#[from] PlaybackError),
+    #[error(transparent)]
+    Divergence(#[from] Divergence),
+    #[error(transparent)]
+    Codec(#[from] CodecError),
+    #[error(transparent)]
+    ReplayJson(#[from] ReplayJsonError),
+    #[error(transparent)]
+    Store(#[from] DirStoreError),
+    #[error(transparent)]
+    Host(#[from] HostError),
+    #[error(transparent)]
+    Lifecycle(#[from] LifecycleError),
+    #[error(transparent)]
+    Admin(#[from] AdminError),
+    #[error(transparent)]
+    Catalog(#[from] CatalogError),
+    #[error(transparent)]
+    ConfigFile(#[from] ConfigFileError),
+    #[error(transparent)]
+    InvalidSetting(#[from] InvalidSetting),
+    #[error(transparent)]
+    RulesVersion(#[from] ParseRulesVersionError),
+    #[error(transparent)]
+    Simulation(#[from] SimulationError),
+    #[cfg(feature = "png")]
+    #[error(transparent)]
+    Png(#[from] PngError),
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    History(#[from] crate::history::sqlite::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{tracker, user};
+
+    fn join_twice() -> Result<()> {
+        let mut t = tracker(1);
+        t.add_player(user("p1"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn module_errors_convert() {
+        let error = join_twice().unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Tracker(TrackerError::AlreadyPlaying(ref name)) if name == "p1"
+        ));
+        assert_eq!(error.to_string(), "p1 is already playing");
+    }
+}
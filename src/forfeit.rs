@@ -0,0 +1,137 @@
+//! Players leaving a game in progress for good: resigning, or thrown out by
+//! the server. Either way they're out of the turn order, whoever is left
+//! alone wins, and everyone is told.
+
+use crate::gametraits::{GameTrait, PlayerTurn, TurnToken};
+use crate::messages::{ForfeitReason, Forfeited, GameOver, ToClient};
+use crate::replay::GameOutcome;
+
+/// What became of a game after a player forfeited.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Forfeit {
+    pub player: String,
+    pub reason: ForfeitReason,
+    /// A win for the only player left, if there's one.
+    pub outcome: Option<GameOutcome>,
+    /// The turn to hand out, if it was the player's and the game goes on.
+    pub next: Option<PlayerTurn>,
+}
+
+impl Forfeit {
+    /// What to tell everyone in the game, the player who left included.
+    pub fn notices(&self) -> Vec<ToClient> {
+        let mut notices = vec![ToClient::Forfeited(Forfeited {
+            player: self.player.clone(),
+            reason: self.reason.clone(),
+        })];
+        if let Some(GameOutcome::Win { winner }) = &self.outcome {
+            notices.push(ToClient::GameOver(GameOver {
+                reason: format!("{winner} wins by forfeit"),
+            }));
+        }
+        notices
+    }
+}
+
+/// What `GameTrait::resign` and `disqualify` do unless a game knows better.
+/// The player is disconnected, as the current player if it's their turn,
+/// and the game's turn tracker says who's left. Games without one can only
+/// be left, not won by forfeit.
+pub fn forfeit<G: GameTrait + ?Sized>(
+    game: &mut G,
+    player: &str,
+    reason: ForfeitReason,
+) -> Forfeit {
+    let current = game
+        .turn_tracker()
+        .and_then(|tracker| tracker.current_player())
+        .filter(|user| user.name == player)
+        .cloned();
+    let next = match current {
+        Some(user) => game.current_player_disconnected(TurnToken { user }),
+        None => {
+            game.player_disconnected(player);
+            None
+        }
+    };
+    let outcome = game.turn_tracker().and_then(|tracker| {
+        let mut left = tracker.players();
+        match (left.next(), left.next()) {
+            (Some(winner), None) => Some(GameOutcome::Win {
+                winner: winner.name.clone(),
+            }),
+            _ => None,
+        }
+    });
+    Forfeit {
+        player: player.to_string(),
+        reason,
+        next: next.filter(|_| outcome.is_none()),
+        outcome,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gametraits::{PlayerMove, PlayerMoveResult};
+    use crate::replay::test_game::TakeAway;
+    use crate::test_support::users;
+
+    fn started(players: usize) -> (TakeAway, PlayerTurn) {
+        let mut game = TakeAway::new(10);
+        game.reset(users(players));
+        let turn = game.try_start_game().unwrap();
+        (game, turn)
+    }
+
+    #[test]
+    fn last_player_left_wins() {
+        let (mut game, _) = started(2);
+        let forfeit = game.resign("p2");
+        assert_eq!(
+            forfeit.outcome,
+            Some(GameOutcome::Win {
+                winner: "p1".to_string()
+            })
+        );
+        assert_eq!(forfeit.next, None);
+        assert!(!game.turn_tracker().unwrap().is_playing("p2"));
+        assert_eq!(forfeit.notices().len(), 2);
+    }
+
+    #[test]
+    fn game_goes_on_without_the_current_player() {
+        let (mut game, turn) = started(3);
+        assert_eq!(turn.token.user.name, "p1");
+        let forfeit = game.disqualify("p1", "sent a move for p2");
+        assert_eq!(forfeit.outcome, None);
+        assert_eq!(
+            forfeit.reason,
+            ForfeitReason::Disqualified {
+                reason: "sent a move for p2".to_string()
+            }
+        );
+        assert_eq!(forfeit.notices().len(), 1);
+        let next = forfeit.next.unwrap();
+        assert_eq!(next.token.user.name, "p2");
+        let result = game.player_moves(
+            next.token,
+            PlayerMove {
+                serialized: "1".to_string(),
+            },
+        );
+        let PlayerMoveResult::Ok(turn) = result else {
+            panic!("{result:?}")
+        };
+        assert_eq!(turn.token.user.name, "p3");
+    }
+
+    #[test]
+    fn others_leaving_keeps_the_turn() {
+        let (mut game, _) = started(3);
+        let forfeit = game.resign("p3");
+        assert_eq!(forfeit.outcome, None);
+        assert_eq!(forfeit.next, None);
+    }
+}
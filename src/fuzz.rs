@@ -0,0 +1,72 @@
+//! What the fuzz targets in `fuzz/` run, kept in the crate so the same
+//! checks can be run over a fixed corpus in the tests. Each takes arbitrary
+//! bytes, as read from disk or written by an old version, and must never
+//! panic on them; whatever does decode has to survive a round trip.
+//!
+//! Run a target with `cargo fuzz run replay_decoding` from the crate root.
+
+use crate::games::tictactoe::TicTacToe;
+use crate::grid::{decode_with_metadata, encode_with_metadata, Grid};
+use crate::replay::{codec, Replay};
+use crate::snapshot::GameSnapshot;
+
+/// Stored replays, gzipped or plain JSON, and replays in notation.
+pub fn replay_decoding(bytes: &[u8]) {
+    if let Ok(replay) = codec::decode(bytes) {
+        let encoded = codec::encode(&replay).expect("decoded replays encode");
+        assert_eq!(codec::decode(&encoded).ok(), Some(replay));
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        if let Ok(replay) = Replay::from_notation(text) {
+            replay.to_notation();
+        }
+    }
+}
+
+/// Snapshots restored onto a real game, which has to reject moves it
+/// doesn't accept rather than fall over.
+pub fn snapshot_restoring(bytes: &[u8]) {
+    if let Ok(snapshot) = GameSnapshot::load(bytes) {
+        let _ = snapshot.restore(Box::new(TicTacToe::new()));
+    }
+}
+
+/// Boards in the FEN-like string encoding.
+pub fn board_parsing(bytes: &[u8]) {
+    let Ok(code) = std::str::from_utf8(bytes) else {
+        return;
+    };
+    if let Ok((grid, metadata)) = decode_with_metadata::<char>(code) {
+        let fields: Vec<&str> = metadata.iter().map(String::as_str).collect();
+        let encoded = encode_with_metadata(&grid, &fields);
+        let decoded: Option<(Grid<char>, Vec<String>)> = decode_with_metadata(&encoded).ok();
+        assert_eq!(decoded, Some((grid, metadata)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CORPUS: &[&[u8]] = &[
+        b"",
+        b"{}",
+        b"{\"tags\":{},\"players\":[],\"initial_state\":null,\"moves\":[],\"outcome\":null}",
+        b"\x1f\x8b\x08\x00",
+        b"[Event \"x\"]\n\n1. p1 {\"move\":1}",
+        b"..x/2o. o 12",
+        b"3/",
+        b"/ /",
+        b"99999999999999999999x",
+        b"\xff\xfe",
+    ];
+
+    #[test]
+    fn corpus_does_not_panic() {
+        for input in CORPUS {
+            replay_decoding(input);
+            snapshot_restoring(input);
+            board_parsing(input);
+        }
+    }
+}
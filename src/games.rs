@@ -0,0 +1,45 @@
+//! Complete games built from the rest of the crate, ready to host and
+//! meant to be read as examples of how the pieces fit together.
+
+use serde::Serialize;
+
+pub mod battleship;
+pub mod connect_four;
+pub mod gomoku;
+pub mod othello;
+pub mod rps;
+pub mod snake;
+pub mod tictactoe;
+pub mod whist;
+
+/// Games that can list the moves open to the player to move, for bots and
+/// for tests walking every line of play.
+pub trait LegalMoves {
+    type Move;
+
+    /// Empty once the game is over.
+    fn legal_moves(&self) -> Vec<Self::Move>;
+
+    /// A hash of the position, player to move included, for games where
+    /// positions can repeat to be stopped when they do.
+    fn position_hash(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Who a view of a game is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Viewer<'a> {
+    Player(&'a str),
+    /// Sees everything, like the audience watching the server's screen.
+    Spectator,
+}
+
+/// Games where players mustn't see everything, such as the other hands in a
+/// card game. The state a player is sent on their turn should be their view,
+/// so nothing hidden ever goes out over the protocol.
+pub trait HiddenInformation {
+    type View: Serialize;
+
+    fn view(&self, viewer: Viewer<'_>) -> Self::View;
+}
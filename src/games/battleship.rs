@@ -0,0 +1,476 @@
+//! Battleship for two players, in two phases. First each player in turn
+//! places their whole fleet in secret, then they take turns firing at each
+//! other's ocean, and whoever sinks the other's fleet first wins.
+//!
+//! On their turn a player is sent their view, where the other player's
+//! ships only show once sunk,
+//! `{"your-turn":{"phase":"placement","fleet":[5,4,3,3,2],"you":"p1","oceans":[...]}}`.
+//! In the placement phase they answer with where each ship of the fleet
+//! starts and which way it runs, in fleet order,
+//! `{"move":{"place":[{"start":{"x":0,"y":0},"direction":"east"},...]}}`,
+//! and in the battle phase with a cell to fire at, `{"move":{"fire":{"x":3,"y":4}}}`.
+
+use std::any::Any;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::{HiddenInformation, Viewer};
+use crate::color::Color;
+use crate::draw::layout::GridLayout;
+use crate::draw::{Draw, DrawCommand, Rect, Size};
+use crate::gametraits::{
+    to_filtered_state, to_game_state, to_player_move, FilteredState, GameTrait, Paint, PlayerMove,
+    PlayerMoveResult, PlayerTurn, TurnToken, User,
+};
+use crate::grid::Grid;
+use crate::pos::{Direction, Pos};
+use crate::rules::RulesVersion;
+use crate::TurnTracker;
+
+/// See `rules` for when it goes up.
+pub const RULES_VERSION: RulesVersion = RulesVersion::INITIAL;
+
+pub const STANDARD_FLEET: [usize; 5] = [5, 4, 3, 3, 2];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    Placement,
+    Battle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Shot {
+    Miss,
+    Hit,
+}
+
+/// Where a ship goes: `start` and the cells after it towards `direction`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Placement {
+    pub start: Pos,
+    pub direction: Direction,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Place(Vec<Placement>),
+    Fire(Pos),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Ocean {
+    /// The cells of each ship, empty until placed.
+    ships: Vec<Vec<Pos>>,
+    /// The shots fired at this ocean.
+    shots: Grid<Option<Shot>>,
+}
+
+impl Ocean {
+    fn is_sunk(&self, ship: &[Pos]) -> bool {
+        ship.iter().all(|pos| self.shots[*pos] == Some(Shot::Hit))
+    }
+}
+
+/// One player's ocean as someone sees it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OceanView {
+    pub player: String,
+    pub shots: Grid<Option<Shot>>,
+    /// Every ship for its owner and spectators, only the sunk ones for the
+    /// opponent.
+    pub ships: Vec<Vec<Pos>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct View {
+    pub phase: Phase,
+    pub fleet: Vec<usize>,
+    /// Missing for spectators.
+    pub you: Option<String>,
+    pub oceans: Vec<OceanView>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Battleship {
+    size: usize,
+    fleet: Vec<usize>,
+    oceans: Vec<Ocean>,
+    /// The seat that moves next.
+    to_move: usize,
+    winner: Option<usize>,
+    tracker: TurnTracker,
+    /// The players by seat, fixed when the game starts.
+    players: Vec<User>,
+}
+
+impl Default for Battleship {
+    fn default() -> Self {
+        Self::new(10, STANDARD_FLEET.to_vec())
+    }
+}
+
+impl Battleship {
+    /// A square ocean of `size` cells a side for each player, each with a
+    /// ship of every length in `fleet`.
+    pub fn new(size: usize, fleet: Vec<usize>) -> Self {
+        let ocean = Ocean {
+            ships: vec![vec![]; fleet.len()],
+            shots: Grid::new(size, size, None),
+        };
+        Self {
+            size,
+            fleet,
+            oceans: vec![ocean.clone(), ocean],
+            to_move: 0,
+            winner: None,
+            tracker: TurnTracker::new(vec![]),
+            players: vec![],
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        let placed = |ocean: &Ocean| ocean.ships.iter().all(|ship| !ship.is_empty());
+        match self.oceans.iter().all(placed) {
+            true => Phase::Battle,
+            false => Phase::Placement,
+        }
+    }
+
+    pub fn to_move(&self) -> usize {
+        self.to_move
+    }
+
+    pub fn winner(&self) -> Option<usize> {
+        self.winner
+    }
+
+    /// Places the fleet of the seat to move. Returns false, changing
+    /// nothing, unless there is one placement per ship, every ship is on the
+    /// board running along a row or column and no two overlap.
+    pub fn place(&mut self, placements: &[Placement]) -> bool {
+        if self.phase() != Phase::Placement || placements.len() != self.fleet.len() {
+            return false;
+        }
+        let mut taken = Grid::new(self.size, self.size, false);
+        let mut ships = vec![];
+        for (placement, length) in placements.iter().zip(&self.fleet) {
+            if placement.direction.is_diagonal() {
+                return false;
+            }
+            let offset = placement.direction.offset();
+            let ship: Vec<Pos> = (0..*length as i32)
+                .map(|i| placement.start + offset * i)
+                .collect();
+            for pos in &ship {
+                if taken.at(*pos) != Some(&false) {
+                    return false;
+                }
+                taken[*pos] = true;
+            }
+            ships.push(ship);
+        }
+        self.oceans[self.to_move].ships = ships;
+        self.to_move = 1 - self.to_move;
+        true
+    }
+
+    /// Fires at the opponent's ocean for the seat to move, returning what
+    /// it hit. `None` when it's not the battle phase, the cell is off the
+    /// board or already shot at, or the game is over.
+    pub fn fire(&mut self, pos: Pos) -> Option<Shot> {
+        if self.phase() != Phase::Battle || self.winner.is_some() {
+            return None;
+        }
+        let target = &mut self.oceans[1 - self.to_move];
+        if target.shots.at(pos) != Some(&None) {
+            return None;
+        }
+        let hit = target.ships.iter().flatten().any(|cell| *cell == pos);
+        let shot = match hit {
+            true => Shot::Hit,
+            false => Shot::Miss,
+        };
+        target.shots[pos] = Some(shot);
+        if target.ships.iter().all(|ship| target.is_sunk(ship)) {
+            self.winner = Some(self.to_move);
+        } else {
+            self.to_move = 1 - self.to_move;
+        }
+        Some(shot)
+    }
+
+    fn name(&self, seat: usize) -> String {
+        self.players
+            .get(seat)
+            .map_or_else(|| format!("seat {seat}"), |user| user.name.clone())
+    }
+
+    fn turn(&self, token: TurnToken) -> PlayerTurn {
+        let state = to_game_state(self.view(Viewer::Player(&token.user.name)));
+        PlayerTurn { token, state }
+    }
+
+    fn next_turn(&mut self) -> Option<PlayerTurn> {
+        let user = self.tracker.advance_player().cloned()?;
+        Some(self.turn(TurnToken { user }))
+    }
+}
+
+impl HiddenInformation for Battleship {
+    type View = View;
+
+    fn view(&self, viewer: Viewer<'_>) -> View {
+        let you = match viewer {
+            Viewer::Player(name) => self.players.iter().position(|u| u.name == name),
+            Viewer::Spectator => None,
+        };
+        let oceans = self
+            .oceans
+            .iter()
+            .enumerate()
+            .map(|(seat, ocean)| {
+                let sees_all = viewer == Viewer::Spectator || you == Some(seat);
+                OceanView {
+                    player: self.name(seat),
+                    shots: ocean.shots.clone(),
+                    ships: ocean
+                        .ships
+                        .iter()
+                        .filter(|ship| sees_all || (!ship.is_empty() && ocean.is_sunk(ship)))
+                        .cloned()
+                        .collect(),
+                }
+            })
+            .collect();
+        View {
+            phase: self.phase(),
+            fleet: self.fleet.clone(),
+            you: you.map(|seat| self.name(seat)),
+            oceans,
+        }
+    }
+}
+
+impl GameTrait for Battleship {
+    /// Moves out of turn are refused without a turn to retry with.
+    fn player_moves(&mut self, turn_token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+        if self.tracker.current_player().map(|u| &u.name) != Some(&turn_token.user.name) {
+            return PlayerMoveResult::InvalidMove(None);
+        }
+        let Some(action) = to_player_move::<Action>(&player_move) else {
+            return PlayerMoveResult::InvalidFormat(Some(self.turn(turn_token)));
+        };
+        let valid = match action {
+            Action::Place(placements) => self.place(&placements),
+            Action::Fire(pos) => self.fire(pos).is_some(),
+        };
+        if !valid {
+            return PlayerMoveResult::InvalidMove(Some(self.turn(turn_token)));
+        }
+        match self.winner {
+            Some(_) => PlayerMoveResult::Win,
+            None => PlayerMoveResult::next(self.next_turn()),
+        }
+    }
+
+    /// A game of two can't go on with one, so it stops there.
+    fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        self.tracker.remove_player(&turn_token.user.name).ok();
+        None
+    }
+
+    fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        if self.tracker.num_players() != 2 {
+            return None;
+        }
+        self.players = self.tracker.players().cloned().collect();
+        self.next_turn()
+    }
+
+    fn player_connected(&mut self, user: User) {
+        if let Err(e) = self.tracker.add_player(user) {
+            warn!(error = %e, "player couldn't join");
+        }
+    }
+
+    fn player_disconnected(&mut self, user: &str) {
+        self.tracker.remove_player(user).ok();
+    }
+
+    fn reset(&mut self, users: Vec<User>) {
+        *self = Self {
+            tracker: TurnTracker::new(users),
+            ..Self::new(self.size, self.fleet.clone())
+        };
+    }
+
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        Some(&self.tracker)
+    }
+
+    fn rules_version(&self) -> RulesVersion {
+        RULES_VERSION
+    }
+
+    fn visible_to(&self, viewer: Viewer<'_>) -> Option<FilteredState> {
+        Some(to_filtered_state(self.view(viewer)))
+    }
+}
+
+/// What spectators see: both oceans side by side with every ship, hits in
+/// red and misses in white.
+impl Draw for Battleship {
+    fn draw(&self, size: Size) -> Vec<DrawCommand> {
+        let half = Size::new(size.width / 2.0, size.height);
+        let mut commands = vec![];
+        for (seat, ocean) in self.oceans.iter().enumerate() {
+            let mut layout = GridLayout::fit(half, self.size, self.size, half.width * 0.05);
+            layout.origin.x += seat as f64 * half.width;
+            commands.push(DrawCommand::fill_rect(
+                layout.bounds(),
+                Color::rgb8(0x1d, 0x4e, 0x89),
+            ));
+            for pos in ocean.ships.iter().flatten() {
+                let cell = layout.cell_rect(pos.x as usize, pos.y as usize);
+                let inset = layout.cell * 0.1;
+                commands.push(DrawCommand::fill_rect(
+                    Rect::new(
+                        cell.x0 + inset,
+                        cell.y0 + inset,
+                        cell.x1 - inset,
+                        cell.y1 - inset,
+                    ),
+                    Color::GRAY,
+                ));
+            }
+            for (x, y, shot) in ocean.shots.iter() {
+                let color = match shot {
+                    Some(Shot::Hit) => Color::RED,
+                    Some(Shot::Miss) => Color::WHITE,
+                    None => continue,
+                };
+                commands.push(DrawCommand::fill_circle(
+                    layout.cell_center(x, y),
+                    layout.cell * 0.25,
+                    color,
+                ));
+            }
+        }
+        commands
+    }
+}
+
+impl Paint for Battleship {
+    #[cfg(feature = "gui")]
+    fn paint(&self, ctx: &mut druid::PaintCtx) {
+        crate::draw::piet::paint(ctx, self);
+    }
+
+    fn eq(&self, other: &dyn Paint) -> bool {
+        other.as_any().downcast_ref::<Self>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::user;
+
+    fn at(x: i32, y: i32, direction: Direction) -> Placement {
+        Placement {
+            start: Pos::new(x, y),
+            direction,
+        }
+    }
+
+    fn small_game() -> Battleship {
+        let mut game = Battleship::new(4, vec![2, 1]);
+        game.reset(vec![user("p1"), user("p2")]);
+        game.try_start_game().unwrap();
+        game
+    }
+
+    #[test]
+    fn placement() {
+        let mut game = small_game();
+        assert!(!game.place(&[at(3, 0, Direction::East), at(0, 0, Direction::East)]));
+        assert!(!game.place(&[at(0, 0, Direction::South), at(0, 1, Direction::East)]));
+        assert!(!game.place(&[at(0, 0, Direction::SouthEast), at(3, 3, Direction::East)]));
+        assert!(!game.place(&[at(0, 0, Direction::South)]));
+        assert_eq!(game.fire(Pos::new(0, 0)), None);
+        assert!(game.place(&[at(0, 0, Direction::South), at(3, 3, Direction::East)]));
+        assert_eq!(game.phase(), Phase::Placement);
+        assert!(game.place(&[at(1, 1, Direction::East), at(0, 3, Direction::North)]));
+        assert_eq!(game.phase(), Phase::Battle);
+    }
+
+    #[test]
+    fn refuses_moves_out_of_turn() {
+        let mut game = small_game();
+        let result = game.player_moves(
+            TurnToken { user: user("p2") },
+            PlayerMove {
+                serialized: concat!(
+                    r#"{"move":{"place":[{"start":{"x":0,"y":0},"direction":"south"},"#,
+                    r#"{"start":{"x":3,"y":3},"direction":"east"}]}}"#
+                )
+                .to_string(),
+            },
+        );
+        assert_eq!(result, PlayerMoveResult::InvalidMove(None));
+        assert_eq!(game.to_move(), 0);
+        assert!(game
+            .oceans
+            .iter()
+            .all(|ocean| ocean.ships.iter().all(Vec::is_empty)));
+    }
+
+    #[test]
+    fn battle_and_views() {
+        let mut game = small_game();
+        game.place(&[at(0, 0, Direction::South), at(3, 3, Direction::East)]);
+        game.place(&[at(1, 1, Direction::East), at(0, 3, Direction::East)]);
+
+        assert_eq!(game.fire(Pos::new(0, 3)), Some(Shot::Hit));
+        assert_eq!(game.fire(Pos::new(0, 0)), Some(Shot::Hit));
+        // The same cell can't be fired at twice.
+        assert_eq!(game.fire(Pos::new(0, 3)), None);
+        assert_eq!(game.fire(Pos::new(2, 2)), Some(Shot::Miss));
+
+        // p1 sees their own ships and p2's sunk one, p2 only their own.
+        let p1 = game.view(Viewer::Player("p1"));
+        assert_eq!(p1.you.as_deref(), Some("p1"));
+        assert_eq!(p1.oceans[0].ships.len(), 2);
+        assert_eq!(p1.oceans[1].ships, [vec![Pos::new(0, 3)]]);
+        let p2 = game.view(Viewer::Player("p2"));
+        assert!(p2.oceans[0].ships.is_empty());
+        assert_eq!(p2.oceans[0].shots[Pos::new(0, 0)], Some(Shot::Hit));
+        let spectator = game.view(Viewer::Spectator);
+        assert!(spectator.oceans.iter().all(|ocean| ocean.ships.len() == 2));
+        assert_eq!(
+            game.visible_to(Viewer::Player("p2")),
+            Some(to_filtered_state(&p2))
+        );
+        assert_ne!(
+            game.visible_to(Viewer::Player("p2")),
+            game.visible_to(Viewer::Spectator)
+        );
+
+        assert_eq!(game.to_move(), 1);
+        assert_eq!(game.fire(Pos::new(0, 1)), Some(Shot::Hit));
+        assert_eq!(game.winner(), None);
+        assert_eq!(game.fire(Pos::new(1, 1)), Some(Shot::Hit));
+        assert_eq!(game.winner(), None);
+        assert_eq!(game.fire(Pos::new(3, 3)), Some(Shot::Hit));
+        assert_eq!(game.winner(), Some(1));
+        assert_eq!(game.fire(Pos::new(2, 1)), None);
+    }
+}
@@ -0,0 +1,492 @@
+//! Connect Four for two players, red moving first. Discs drop to the lowest
+//! free cell of the chosen column and four in a row in any direction wins.
+//!
+//! On their turn a player is sent the board, top row first, and their color,
+//! `{"your-turn":{"board":[[null,...],...,[null,"red",...]],"you":"yellow"}}`,
+//! and answers with a column counted from the left, `{"move":3}`.
+//!
+//! With a clock, each player's thinking time is taken off their clock as
+//! they move, and a player who runs out loses.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::{LegalMoves, Viewer};
+use crate::clock::{GameClock, TimeWarning};
+use crate::color::Color;
+use crate::draw::layout::GridLayout;
+use crate::draw::{Draw, DrawCommand, Size};
+use crate::gametraits::{
+    to_filtered_state, to_game_state, to_player_move, FilteredState, GameTrait, Paint, PlayerMove,
+    PlayerMoveResult, PlayerTurn, TurnToken, User,
+};
+use crate::grid::{find_line, Grid};
+use crate::handicap::Handicap;
+use crate::pos::Pos;
+use crate::replay::GameOutcome;
+use crate::rules::RulesVersion;
+use crate::time::Instant;
+use crate::TurnTracker;
+
+/// See `rules` for when it goes up.
+pub const RULES_VERSION: RulesVersion = RulesVersion::INITIAL;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Disc {
+    Red,
+    Yellow,
+}
+
+impl Disc {
+    pub fn other(self) -> Disc {
+        match self {
+            Disc::Red => Disc::Yellow,
+            Disc::Yellow => Disc::Red,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Disc),
+    Draw,
+}
+
+/// What the player to move is sent.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct State {
+    pub board: Grid<Option<Disc>>,
+    pub you: Disc,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectFour {
+    board: Grid<Option<Disc>>,
+    outcome: Option<Outcome>,
+    tracker: TurnTracker,
+    /// Red's player and then yellow's, fixed when the game starts.
+    players: Vec<User>,
+    /// Each player's thinking time for the whole game, if timed.
+    time_control: Option<Duration>,
+    clock: Option<GameClock>,
+    /// Extra thinking time for players with a handicap.
+    time_bonus: HashMap<String, Duration>,
+    /// When the player to move got their turn, for charging their clock.
+    turn_started: Option<Instant>,
+    warning: Option<TimeWarning>,
+}
+
+impl Default for ConnectFour {
+    fn default() -> Self {
+        Self::new(7, 6)
+    }
+}
+
+impl ConnectFour {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            board: Grid::new(width, height, None),
+            outcome: None,
+            tracker: TurnTracker::new(vec![]),
+            players: vec![],
+            time_control: None,
+            clock: None,
+            time_bonus: HashMap::new(),
+            turn_started: None,
+            warning: None,
+        }
+    }
+
+    /// Gives each player `initial` thinking time for the whole game.
+    pub fn with_clock(mut self, initial: Duration) -> Self {
+        self.time_control = Some(initial);
+        self
+    }
+
+    pub fn board(&self) -> &Grid<Option<Disc>> {
+        &self.board
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// The players' clocks once a timed game has started.
+    pub fn clock(&self) -> Option<&GameClock> {
+        self.clock.as_ref()
+    }
+
+    /// The warning from the last move that took a player's clock below a
+    /// threshold, for the server to pass on.
+    pub fn take_time_warning(&mut self) -> Option<TimeWarning> {
+        self.warning.take()
+    }
+
+    pub fn to_move(&self) -> Disc {
+        let discs = self.board.cells().iter().flatten().count();
+        match discs % 2 {
+            0 => Disc::Red,
+            _ => Disc::Yellow,
+        }
+    }
+
+    /// Where a disc dropped in `column` would land.
+    pub fn landing(&self, column: usize) -> Option<Pos> {
+        let y = (0..self.board.height())
+            .rev()
+            .find(|y| self.board.get(column, *y) == Some(&None))?;
+        Some(Pos::new(column as i32, y as i32))
+    }
+
+    /// Drops the next disc in `column`, returning where it landed, or `None`
+    /// when the column is full or missing or the game is over.
+    pub fn drop_disc(&mut self, column: usize) -> Option<Pos> {
+        if self.outcome.is_some() {
+            return None;
+        }
+        let pos = self.landing(column)?;
+        let disc = self.to_move();
+        self.board[pos] = Some(disc);
+        if find_line(&self.board, pos, 4, |cell| *cell == Some(disc)).is_some() {
+            self.outcome = Some(Outcome::Win(disc));
+        } else if self.board.cells().iter().all(Option::is_some) {
+            self.outcome = Some(Outcome::Draw);
+        }
+        Some(pos)
+    }
+
+    /// Takes `elapsed` off the player's clock, returning false once they are
+    /// out of time.
+    fn charge(&mut self, player: &str, elapsed: Duration) -> bool {
+        let Some(clock) = &mut self.clock else {
+            return true;
+        };
+        if let Some(warning) = clock.spend(player, elapsed) {
+            self.warning = Some(warning);
+        }
+        !clock.is_out_of_time(player)
+    }
+
+    fn state(&self) -> State {
+        State {
+            board: self.board.clone(),
+            you: self.to_move(),
+        }
+    }
+
+    fn turn(&self, token: TurnToken) -> PlayerTurn {
+        PlayerTurn {
+            token,
+            state: to_game_state(self.state()),
+        }
+    }
+
+    fn next_turn(&mut self) -> Option<PlayerTurn> {
+        let user = self.tracker.advance_player().cloned()?;
+        self.turn_started = Some(Instant::now());
+        Some(self.turn(TurnToken { user }))
+    }
+
+    fn color(&self, disc: Disc) -> Color {
+        self.players
+            .get(disc as usize)
+            .map_or(Color::BLACK, |user| user.color)
+    }
+}
+
+impl LegalMoves for ConnectFour {
+    type Move = usize;
+
+    fn legal_moves(&self) -> Vec<usize> {
+        if self.outcome.is_some() {
+            return vec![];
+        }
+        (0..self.board.width())
+            .filter(|column| self.landing(*column).is_some())
+            .collect()
+    }
+}
+
+impl GameTrait for ConnectFour {
+    /// A player out of time loses, their move uncounted, to their opponent.
+    /// Moves out of turn are refused without a turn to retry with.
+    fn player_moves(&mut self, turn_token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+        if self.tracker.current_player().map(|u| &u.name) != Some(&turn_token.user.name) {
+            return PlayerMoveResult::InvalidMove(None);
+        }
+        let elapsed = self.turn_started.map_or(Duration::ZERO, |t| t.elapsed());
+        if !self.charge(&turn_token.user.name, elapsed) {
+            let winner = self.to_move().other();
+            self.outcome = Some(Outcome::Win(winner));
+            return match self.players.get(winner as usize) {
+                Some(user) => PlayerMoveResult::OutOfTime(GameOutcome::Win {
+                    winner: user.name.clone(),
+                }),
+                None => PlayerMoveResult::InvalidMove(None),
+            };
+        }
+        self.turn_started = Some(Instant::now());
+
+        let Some(column) = to_player_move::<usize>(&player_move) else {
+            return PlayerMoveResult::InvalidFormat(Some(self.turn(turn_token)));
+        };
+        if self.drop_disc(column).is_none() {
+            return PlayerMoveResult::InvalidMove(Some(self.turn(turn_token)));
+        }
+        match self.outcome {
+            Some(Outcome::Win(_)) => PlayerMoveResult::Win,
+            Some(Outcome::Draw) => PlayerMoveResult::Draw,
+            None => PlayerMoveResult::next(self.next_turn()),
+        }
+    }
+
+    /// A game of two can't go on with one, so it stops there.
+    fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        self.tracker.remove_player(&turn_token.user.name).ok();
+        None
+    }
+
+    fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        if self.tracker.num_players() != 2 {
+            return None;
+        }
+        self.players = self.tracker.players().cloned().collect();
+        self.clock = self.time_control.map(|initial| {
+            let mut clock = GameClock::new(initial);
+            for user in &self.players {
+                clock.add_player(&user.name);
+                if let Some(bonus) = self.time_bonus.get(&user.name) {
+                    clock.add_time(&user.name, *bonus);
+                }
+            }
+            clock
+        });
+        self.next_turn()
+    }
+
+    fn player_connected(&mut self, user: User) {
+        if let Err(e) = self.tracker.add_player(user) {
+            warn!(error = %e, "player couldn't join");
+        }
+    }
+
+    fn player_disconnected(&mut self, user: &str) {
+        self.tracker.remove_player(user).ok();
+    }
+
+    fn reset(&mut self, users: Vec<User>) {
+        *self = Self {
+            tracker: TurnTracker::new(users),
+            time_control: self.time_control,
+            ..Self::new(self.board.width(), self.board.height())
+        };
+    }
+
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        Some(&self.tracker)
+    }
+
+    fn rules_version(&self) -> RulesVersion {
+        RULES_VERSION
+    }
+
+    /// Timed games add the time bonus to the player's clock.
+    fn apply_handicap(&mut self, player: &str, mut handicap: Handicap) -> Handicap {
+        if self.time_control.is_some() {
+            *self.time_bonus.entry(player.to_string()).or_default() += handicap.time_bonus();
+            handicap.time_bonus_ms = 0;
+        }
+        handicap
+    }
+
+    fn clock_mut(&mut self) -> Option<&mut GameClock> {
+        self.clock.as_mut()
+    }
+
+    fn visible_to(&self, _viewer: Viewer<'_>) -> Option<FilteredState> {
+        Some(to_filtered_state(self.state()))
+    }
+
+    fn draw_offers_allowed(&self) -> bool {
+        true
+    }
+
+    fn draw_agreed(&mut self) {
+        self.outcome = Some(Outcome::Draw);
+    }
+}
+
+/// A blue board with a hole per cell, discs in their player's color.
+impl Draw for ConnectFour {
+    fn draw(&self, size: Size) -> Vec<DrawCommand> {
+        let (width, height) = (self.board.width(), self.board.height());
+        let layout = GridLayout::fit(size, width, height, size.width.min(size.height) * 0.05);
+        let mut commands = vec![DrawCommand::fill_rect(
+            layout.bounds(),
+            Color::rgb8(0x1f, 0x4e, 0xb4),
+        )];
+        for (x, y, disc) in self.board.iter() {
+            let color = disc.map_or(Color::WHITE, |disc| self.color(disc));
+            commands.push(DrawCommand::fill_circle(
+                layout.cell_center(x, y),
+                layout.cell * 0.4,
+                color,
+            ));
+        }
+        commands
+    }
+}
+
+impl Paint for ConnectFour {
+    #[cfg(feature = "gui")]
+    fn paint(&self, ctx: &mut druid::PaintCtx) {
+        crate::draw::piet::paint(ctx, self);
+    }
+
+    fn eq(&self, other: &dyn Paint) -> bool {
+        other.as_any().downcast_ref::<Self>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::user;
+
+    fn drop_all(game: &mut ConnectFour, columns: &[usize]) {
+        for column in columns {
+            assert!(game.drop_disc(*column).is_some(), "column {column}");
+        }
+    }
+
+    #[test]
+    fn gravity_and_lines() {
+        let mut game = ConnectFour::default();
+        assert_eq!(game.drop_disc(3), Some(Pos::new(3, 5)));
+        assert_eq!(game.drop_disc(3), Some(Pos::new(3, 4)));
+        assert_eq!(game.drop_disc(7), None);
+
+        // Red builds a diagonal up to the right from the bottom left corner.
+        let mut game = ConnectFour::default();
+        drop_all(&mut game, &[0, 1, 1, 2, 2, 3, 2, 3, 6, 3]);
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.to_move(), Disc::Red);
+        drop_all(&mut game, &[3]);
+        assert_eq!(game.board()[Pos::new(3, 2)], Some(Disc::Red));
+        assert_eq!(game.outcome(), Some(Outcome::Win(Disc::Red)));
+        assert!(game.legal_moves().is_empty());
+
+        let mut game = ConnectFour::new(2, 2);
+        drop_all(&mut game, &[0, 0]);
+        assert_eq!(game.legal_moves(), [1]);
+        drop_all(&mut game, &[1, 1]);
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn clock_runs_out() {
+        let mut game = ConnectFour::default().with_clock(Duration::from_secs(15));
+        game.reset(vec![user("p1"), user("p2")]);
+        let turn = game.try_start_game().unwrap();
+        assert!(game.charge("p1", Duration::from_secs(6)));
+        assert_eq!(
+            game.take_time_warning().map(|w| w.player),
+            Some("p1".into())
+        );
+        assert!(game.charge("p2", Duration::from_secs(1)));
+        assert_eq!(game.take_time_warning(), None);
+        assert!(!game.charge("p1", Duration::from_secs(10)));
+
+        let result = game.player_moves(
+            turn.token,
+            PlayerMove {
+                serialized: "{\"move\":3}".into(),
+            },
+        );
+        assert_eq!(
+            result,
+            PlayerMoveResult::OutOfTime(GameOutcome::Win {
+                winner: "p2".to_string()
+            })
+        );
+        assert_eq!(game.outcome(), Some(Outcome::Win(Disc::Yellow)));
+    }
+
+    #[test]
+    fn refuses_moves_out_of_turn() {
+        let mut game = ConnectFour::default().with_clock(Duration::from_secs(15));
+        game.reset(vec![user("p1"), user("p2")]);
+        game.try_start_game().unwrap();
+        game.clock_mut()
+            .unwrap()
+            .take_time("p2", Duration::from_secs(15));
+        let result = game.player_moves(
+            TurnToken { user: user("p2") },
+            PlayerMove {
+                serialized: "{\"move\":3}".into(),
+            },
+        );
+        assert_eq!(result, PlayerMoveResult::InvalidMove(None));
+        assert_eq!(game.outcome(), None);
+        assert!(game.board().cells().iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn handicaps_add_clock_time() {
+        let handicap = Handicap {
+            time_bonus_ms: 5_000,
+            ..Handicap::NONE
+        };
+        let mut untimed = ConnectFour::default();
+        untimed.reset(vec![user("p1"), user("p2")]);
+        assert_eq!(untimed.apply_handicap("p2", handicap), handicap);
+
+        let mut game = ConnectFour::default().with_clock(Duration::from_secs(15));
+        game.reset(vec![user("p1"), user("p2")]);
+        assert!(game.apply_handicap("p2", handicap).is_none());
+        game.try_start_game().unwrap();
+        let clock = game.clock().unwrap();
+        assert_eq!(clock.remaining("p1"), Some(Duration::from_secs(15)));
+        assert_eq!(clock.remaining("p2"), Some(Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn plays_through_the_protocol() {
+        let mut game = ConnectFour::default();
+        game.reset(vec![user("p1"), user("p2")]);
+        let mut turn = game.try_start_game().unwrap();
+        for column in [0, 1, 0, 1, 0, 1] {
+            let serialized = format!("{{\"move\":{column}}}");
+            match game.player_moves(turn.token, PlayerMove { serialized }) {
+                PlayerMoveResult::Ok(next) => turn = next,
+                other => panic!("{other:?}"),
+            }
+        }
+        let PlayerMoveResult::InvalidFormat(Some(turn)) = game.player_moves(
+            turn.token,
+            PlayerMove {
+                serialized: "{\"move\":-1}".into(),
+            },
+        ) else {
+            panic!("expected an invalid format");
+        };
+        assert_eq!(
+            game.player_moves(
+                turn.token,
+                PlayerMove {
+                    serialized: "{\"move\":0}".into(),
+                }
+            ),
+            PlayerMoveResult::Win
+        );
+    }
+}
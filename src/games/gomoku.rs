@@ -0,0 +1,418 @@
+//! Gomoku: two players, black first, take turns placing stones on the
+//! intersections of a square board, and the first to get a line of five
+//! wins. The board size and line length come from a `GomokuConfig`, so the
+//! same game covers 15x15 tournaments and quick 9x9 rounds of four in a row.
+//!
+//! On their turn a player is sent the board and their color,
+//! `{"your-turn":{"board":[[null,...],...],"you":"black","win-length":5}}`,
+//! and answers with an intersection, `{"move":{"x":7,"y":7}}`.
+
+use std::any::Any;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::{LegalMoves, Viewer};
+use crate::color::Color;
+use crate::draw::layout::GridLayout;
+use crate::draw::{Draw, DrawCommand, Size, Stroke};
+use crate::gametraits::{
+    to_filtered_state, to_game_state, to_player_move, FilteredState, GameTrait, Paint, PlayerMove,
+    PlayerMoveResult, PlayerTurn, TurnToken, User,
+};
+use crate::grid::{lines_through, Grid};
+use crate::pos::Pos;
+use crate::rules::RulesVersion;
+use crate::TurnTracker;
+
+/// See `rules` for when it goes up.
+pub const RULES_VERSION: RulesVersion = RulesVersion::INITIAL;
+
+/// How a game of Gomoku is set up, read from the match config, with any
+/// field left out taking its standard value.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct GomokuConfig {
+    /// Intersections along each side.
+    pub size: usize,
+    pub win_length: usize,
+    /// Only lines of exactly `win_length` win, as in standard Gomoku.
+    /// Otherwise longer lines count too.
+    pub exact: bool,
+}
+
+impl Default for GomokuConfig {
+    fn default() -> Self {
+        Self {
+            size: 15,
+            win_length: 5,
+            exact: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ConfigError {
+    /// Boards go from 3 to 26 intersections a side.
+    #[error("board size {0} isn't between 3 and 26")]
+    BoardSize(usize),
+    /// Lines must be at least 3 long and fit on the board.
+    #[error("win length {0} doesn't fit the board")]
+    WinLength(usize),
+}
+
+impl GomokuConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(3..=26).contains(&self.size) {
+            return Err(ConfigError::BoardSize(self.size));
+        }
+        if !(3..=self.size).contains(&self.win_length) {
+            return Err(ConfigError::WinLength(self.win_length));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stone {
+    Black,
+    White,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Stone),
+    Draw,
+}
+
+/// What the player to move is sent.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct State {
+    pub board: Grid<Option<Stone>>,
+    pub you: Stone,
+    pub win_length: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gomoku {
+    config: GomokuConfig,
+    board: Grid<Option<Stone>>,
+    outcome: Option<Outcome>,
+    /// The line that won the game, for highlighting.
+    winning_line: Vec<Pos>,
+    tracker: TurnTracker,
+    /// Black's player and then white's, fixed when the game starts.
+    players: Vec<User>,
+}
+
+impl Gomoku {
+    pub fn new(config: GomokuConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(Self {
+            board: Grid::new(config.size, config.size, None),
+            config,
+            outcome: None,
+            winning_line: vec![],
+            tracker: TurnTracker::new(vec![]),
+            players: vec![],
+        })
+    }
+
+    pub fn config(&self) -> &GomokuConfig {
+        &self.config
+    }
+
+    pub fn board(&self) -> &Grid<Option<Stone>> {
+        &self.board
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub fn winning_line(&self) -> &[Pos] {
+        &self.winning_line
+    }
+
+    pub fn to_move(&self) -> Stone {
+        let stones = self.board.cells().iter().flatten().count();
+        match stones % 2 {
+            0 => Stone::Black,
+            _ => Stone::White,
+        }
+    }
+
+    /// Places the next stone at `pos`. Returns false, changing nothing, when
+    /// the intersection is taken or off the board or the game is over.
+    pub fn play(&mut self, pos: Pos) -> bool {
+        if self.outcome.is_some() || self.board.at(pos) != Some(&None) {
+            return false;
+        }
+        let stone = self.to_move();
+        self.board[pos] = Some(stone);
+        let (n, exact) = (self.config.win_length, self.config.exact);
+        let line = lines_through(&self.board, pos, |cell| *cell == Some(stone))
+            .into_iter()
+            .find(|line| line.len() == n || (!exact && line.len() > n));
+        match line {
+            Some(line) => {
+                self.winning_line = line;
+                self.outcome = Some(Outcome::Win(stone));
+            }
+            _ if self.board.cells().iter().all(Option::is_some) => {
+                self.outcome = Some(Outcome::Draw);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn state(&self) -> State {
+        State {
+            board: self.board.clone(),
+            you: self.to_move(),
+            win_length: self.config.win_length,
+        }
+    }
+
+    fn turn(&self, token: TurnToken) -> PlayerTurn {
+        PlayerTurn {
+            token,
+            state: to_game_state(self.state()),
+        }
+    }
+
+    fn next_turn(&mut self) -> Option<PlayerTurn> {
+        let user = self.tracker.advance_player().cloned()?;
+        Some(self.turn(TurnToken { user }))
+    }
+}
+
+impl LegalMoves for Gomoku {
+    type Move = Pos;
+
+    fn legal_moves(&self) -> Vec<Pos> {
+        if self.outcome.is_some() {
+            return vec![];
+        }
+        self.board
+            .positions()
+            .filter(|pos| self.board[*pos].is_none())
+            .collect()
+    }
+}
+
+impl GameTrait for Gomoku {
+    /// Moves out of turn are refused without a turn to retry with.
+    fn player_moves(&mut self, turn_token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+        if self.tracker.current_player().map(|u| &u.name) != Some(&turn_token.user.name) {
+            return PlayerMoveResult::InvalidMove(None);
+        }
+        let Some(pos) = to_player_move::<Pos>(&player_move) else {
+            return PlayerMoveResult::InvalidFormat(Some(self.turn(turn_token)));
+        };
+        if !self.play(pos) {
+            return PlayerMoveResult::InvalidMove(Some(self.turn(turn_token)));
+        }
+        match self.outcome {
+            Some(Outcome::Win(_)) => PlayerMoveResult::Win,
+            Some(Outcome::Draw) => PlayerMoveResult::Draw,
+            None => PlayerMoveResult::next(self.next_turn()),
+        }
+    }
+
+    /// A game of two can't go on with one, so it stops there.
+    fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        self.tracker.remove_player(&turn_token.user.name).ok();
+        None
+    }
+
+    fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        if self.tracker.num_players() != 2 {
+            return None;
+        }
+        self.players = self.tracker.players().cloned().collect();
+        self.next_turn()
+    }
+
+    fn player_connected(&mut self, user: User) {
+        if let Err(e) = self.tracker.add_player(user) {
+            warn!(error = %e, "player couldn't join");
+        }
+    }
+
+    fn player_disconnected(&mut self, user: &str) {
+        self.tracker.remove_player(user).ok();
+    }
+
+    fn reset(&mut self, users: Vec<User>) {
+        *self = Self {
+            tracker: TurnTracker::new(users),
+            ..Self::new(self.config.clone()).expect("the config was checked before")
+        };
+    }
+
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        Some(&self.tracker)
+    }
+
+    fn rules_version(&self) -> RulesVersion {
+        RULES_VERSION
+    }
+
+    fn visible_to(&self, _viewer: Viewer<'_>) -> Option<FilteredState> {
+        Some(to_filtered_state(self.state()))
+    }
+
+    fn draw_offers_allowed(&self) -> bool {
+        true
+    }
+
+    fn draw_agreed(&mut self) {
+        self.outcome = Some(Outcome::Draw);
+    }
+}
+
+/// A wooden board with lines through the intersections, the winning line's
+/// stones ringed in red.
+impl Draw for Gomoku {
+    fn draw(&self, size: Size) -> Vec<DrawCommand> {
+        let n = self.config.size;
+        let layout = GridLayout::fit(size, n, n, size.width.min(size.height) * 0.02);
+        let mut commands = vec![DrawCommand::fill_rect(
+            layout.bounds(),
+            Color::rgb8(0xdc, 0xb3, 0x5c),
+        )];
+        for i in 0..n {
+            commands.push(DrawCommand::line(
+                layout.cell_center(i, 0),
+                layout.cell_center(i, n - 1),
+                Color::BLACK,
+                1.0,
+            ));
+            commands.push(DrawCommand::line(
+                layout.cell_center(0, i),
+                layout.cell_center(n - 1, i),
+                Color::BLACK,
+                1.0,
+            ));
+        }
+        for (x, y, stone) in self.board.iter() {
+            let Some(stone) = stone else {
+                continue;
+            };
+            let won = self.winning_line.contains(&Pos::new(x as i32, y as i32));
+            commands.push(DrawCommand::Circle {
+                center: layout.cell_center(x, y),
+                radius: layout.cell * 0.45,
+                fill: Some(match stone {
+                    Stone::Black => Color::BLACK,
+                    Stone::White => Color::WHITE,
+                }),
+                stroke: won.then_some(Stroke {
+                    color: Color::RED,
+                    width: layout.cell * 0.08,
+                }),
+            });
+        }
+        commands
+    }
+}
+
+impl Paint for Gomoku {
+    #[cfg(feature = "gui")]
+    fn paint(&self, ctx: &mut druid::PaintCtx) {
+        crate::draw::piet::paint(ctx, self);
+    }
+
+    fn eq(&self, other: &dyn Paint) -> bool {
+        other.as_any().downcast_ref::<Self>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::user;
+
+    fn play_all(game: &mut Gomoku, moves: &[(i32, i32)]) {
+        for (x, y) in moves {
+            assert!(game.play(Pos::new(*x, *y)), "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn config() {
+        assert_eq!(
+            Gomoku::new(GomokuConfig::default()).map(|g| g.board().width()),
+            Ok(15)
+        );
+        let small = GomokuConfig {
+            size: 2,
+            ..GomokuConfig::default()
+        };
+        assert_eq!(Gomoku::new(small), Err(ConfigError::BoardSize(2)));
+        let long = GomokuConfig {
+            size: 9,
+            win_length: 10,
+            ..GomokuConfig::default()
+        };
+        assert_eq!(Gomoku::new(long), Err(ConfigError::WinLength(10)));
+    }
+
+    #[test]
+    fn lines_win() {
+        let mut game = Gomoku::new(GomokuConfig::default()).unwrap();
+        // Black plays down a diagonal while white plays along the top.
+        play_all(&mut game, &[(3, 3), (0, 0), (4, 4), (1, 0), (5, 5), (2, 0)]);
+        play_all(&mut game, &[(6, 6), (3, 0)]);
+        assert_eq!(game.outcome(), None);
+        play_all(&mut game, &[(7, 7)]);
+        assert_eq!(game.outcome(), Some(Outcome::Win(Stone::Black)));
+        assert_eq!(game.winning_line().len(), 5);
+
+        // With exact lines an overline doesn't count.
+        let mut game = Gomoku::new(GomokuConfig {
+            size: 9,
+            win_length: 4,
+            exact: true,
+        })
+        .unwrap();
+        let moves = [
+            (0, 4),
+            (0, 0),
+            (1, 4),
+            (0, 1),
+            (3, 4),
+            (0, 2),
+            (4, 4),
+            (8, 8),
+        ];
+        play_all(&mut game, &moves);
+        play_all(&mut game, &[(2, 4)]);
+        assert_eq!(game.outcome(), None);
+        play_all(&mut game, &[(0, 3)]);
+        assert_eq!(game.outcome(), Some(Outcome::Win(Stone::White)));
+    }
+
+    #[test]
+    fn refuses_moves_out_of_turn() {
+        let mut game = Gomoku::new(GomokuConfig::default()).unwrap();
+        game.reset(vec![user("p1"), user("p2")]);
+        game.try_start_game().unwrap();
+        let result = game.player_moves(
+            TurnToken { user: user("p2") },
+            PlayerMove {
+                serialized: "{\"move\":{\"x\":7,\"y\":7}}".to_string(),
+            },
+        );
+        assert_eq!(result, PlayerMoveResult::InvalidMove(None));
+        assert!(game.board().cells().iter().all(Option::is_none));
+    }
+}
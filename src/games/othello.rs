@@ -0,0 +1,584 @@
+//! Othello on the usual 8x8 board, black moving first. The board is a pair
+//! of `BitBoard`s, so finding every legal move takes a few shifts per
+//! direction, quick enough for built-in bots to search with.
+//!
+//! On their turn a player is sent the board and their color,
+//! `{"your-turn":{"board":[[null,...],...],"you":"black"}}`, and answers
+//! with the cell to play, `{"move":{"x":2,"y":3}}`. A player without a
+//! legal move is skipped. The game ends when neither can move and is scored
+//! by area: each player's discs plus the empty regions only they border.
+//! When the last move loses its player the game, the winner is sent one
+//! more turn and wins by answering it.
+
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::{LegalMoves, Viewer};
+use crate::anim::Easing;
+use crate::color::Color;
+use crate::draw::layout::GridLayout;
+use crate::draw::{Affine, Draw, DrawCommand, Point, Size};
+use crate::gametraits::{
+    to_filtered_state, to_game_state, to_player_move, FilteredState, GameTrait, Paint, PlayerMove,
+    PlayerMoveResult, PlayerTurn, TurnToken, User,
+};
+use crate::grid::{area_score, BitBoard, Connectivity, Grid, ZobristCell, ZobristKeys};
+use crate::handicap::Handicap;
+use crate::pos::{Direction, Pos};
+use crate::rules::RulesVersion;
+use crate::TurnTracker;
+
+/// See `rules` for when it goes up.
+pub const RULES_VERSION: RulesVersion = RulesVersion::INITIAL;
+
+const SIZE: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Disc {
+    Black,
+    White,
+}
+
+impl Disc {
+    pub fn other(self) -> Disc {
+        match self {
+            Disc::Black => Disc::White,
+            Disc::White => Disc::Black,
+        }
+    }
+}
+
+impl ZobristCell for Disc {
+    fn zobrist_index(&self) -> Option<usize> {
+        Some(*self as usize)
+    }
+}
+
+fn zobrist_keys() -> &'static ZobristKeys {
+    static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+    KEYS.get_or_init(|| ZobristKeys::new(SIZE, SIZE, 2, 0x07e110))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Disc),
+    Draw,
+}
+
+/// What the player to move is sent.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct State {
+    pub board: Grid<Option<Disc>>,
+    pub you: Disc,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Othello {
+    black: BitBoard,
+    white: BitBoard,
+    to_move: Disc,
+    outcome: Option<Outcome>,
+    /// The discs the last move turned over, for animating them.
+    flipped: Vec<Pos>,
+    tracker: TurnTracker,
+    /// Black's player and then white's, fixed when the game starts.
+    players: Vec<User>,
+}
+
+impl Default for Othello {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Othello {
+    pub fn new() -> Self {
+        let mut black = BitBoard::new(SIZE, SIZE);
+        let mut white = BitBoard::new(SIZE, SIZE);
+        white.set(Pos::new(3, 3), true);
+        white.set(Pos::new(4, 4), true);
+        black.set(Pos::new(4, 3), true);
+        black.set(Pos::new(3, 4), true);
+        Self {
+            black,
+            white,
+            to_move: Disc::Black,
+            outcome: None,
+            flipped: vec![],
+            tracker: TurnTracker::new(vec![]),
+            players: vec![],
+        }
+    }
+
+    pub fn to_move(&self) -> Disc {
+        self.to_move
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub fn discs(&self, disc: Disc) -> BitBoard {
+        match disc {
+            Disc::Black => self.black,
+            Disc::White => self.white,
+        }
+    }
+
+    pub fn board(&self) -> Grid<Option<Disc>> {
+        Grid::from_fn(SIZE, SIZE, |x, y| {
+            let pos = Pos::new(x as i32, y as i32);
+            [Disc::Black, Disc::White]
+                .into_iter()
+                .find(|disc| self.discs(*disc).test(pos))
+        })
+    }
+
+    /// The discs the last move turned over.
+    pub fn flipped(&self) -> &[Pos] {
+        &self.flipped
+    }
+
+    /// The cells where `disc` could play.
+    pub fn moves(&self, disc: Disc) -> BitBoard {
+        let (own, opponent) = (self.discs(disc), self.discs(disc.other()));
+        let empty = !(own | opponent);
+        let mut moves = BitBoard::new(SIZE, SIZE);
+        for direction in Direction::ALL {
+            // Runs of opponent discs starting next to one of ours.
+            let mut run = own.shift(direction) & opponent;
+            for _ in 1..SIZE - 2 {
+                run |= run.shift(direction) & opponent;
+            }
+            moves |= run.shift(direction) & empty;
+        }
+        moves
+    }
+
+    /// Each player's discs plus the territory they alone border.
+    pub fn scores(&self) -> (usize, usize) {
+        let scores = area_score(&self.board(), Connectivity::Four, |cell| *cell);
+        let score = |disc| scores.get(&disc).copied().unwrap_or(0);
+        (score(Disc::Black), score(Disc::White))
+    }
+
+    /// Plays `pos` for the player to move. Returns false, changing nothing,
+    /// when it isn't a legal move.
+    pub fn play(&mut self, pos: Pos) -> bool {
+        let disc = self.to_move;
+        if self.outcome.is_some() || !self.moves(disc).test(pos) {
+            return false;
+        }
+        let (own, opponent) = (self.discs(disc), self.discs(disc.other()));
+        self.flipped.clear();
+        for direction in Direction::ALL {
+            let mut run = vec![];
+            let mut next = pos.step(direction);
+            while opponent.test(next) {
+                run.push(next);
+                next = next.step(direction);
+            }
+            if own.test(next) {
+                self.flipped.extend(run);
+            }
+        }
+
+        let mut changed = BitBoard::new(SIZE, SIZE);
+        for flipped in &self.flipped {
+            changed.set(*flipped, true);
+        }
+        let (own, opponent) = match disc {
+            Disc::Black => (&mut self.black, &mut self.white),
+            Disc::White => (&mut self.white, &mut self.black),
+        };
+        own.set(pos, true);
+        *own |= changed;
+        *opponent = *opponent ^ changed;
+
+        if !self.moves(disc.other()).is_empty() {
+            self.to_move = disc.other();
+        } else if self.moves(disc).is_empty() {
+            let (black, white) = self.scores();
+            self.outcome = Some(match black.cmp(&white) {
+                Ordering::Greater => Outcome::Win(Disc::Black),
+                Ordering::Less => Outcome::Win(Disc::White),
+                Ordering::Equal => Outcome::Draw,
+            });
+            if let Some(Outcome::Win(winner)) = self.outcome {
+                self.to_move = winner;
+            }
+        }
+        true
+    }
+
+    fn state(&self) -> State {
+        State {
+            board: self.board(),
+            you: self.to_move,
+        }
+    }
+
+    fn turn(&self, token: TurnToken) -> PlayerTurn {
+        PlayerTurn {
+            token,
+            state: to_game_state(self.state()),
+        }
+    }
+
+    fn next_turn(&mut self) -> Option<PlayerTurn> {
+        let user = self.tracker.advance_player().cloned()?;
+        Some(self.turn(TurnToken { user }))
+    }
+
+    fn color(&self, disc: Disc) -> Color {
+        match disc {
+            Disc::Black => Color::BLACK,
+            Disc::White => Color::WHITE,
+        }
+    }
+
+    /// The board partway through turning over the discs the last move
+    /// flipped, `progress` going from 0 as the move is played to 1 once
+    /// they show their new color. Each disc narrows to an edge and widens
+    /// again, changing color halfway.
+    pub fn draw_flipping(&self, size: Size, progress: f64) -> Vec<DrawCommand> {
+        let layout = GridLayout::fit(size, SIZE, SIZE, size.width.min(size.height) * 0.05);
+        let bounds = layout.bounds();
+        let mut commands = vec![DrawCommand::fill_rect(
+            bounds,
+            Color::rgb8(0x1b, 0x7a, 0x3a),
+        )];
+        for i in 1..SIZE {
+            let offset = i as f64 * layout.cell;
+            commands.push(DrawCommand::line(
+                Point::new(bounds.x0 + offset, bounds.y0),
+                Point::new(bounds.x0 + offset, bounds.y1),
+                Color::BLACK,
+                1.0,
+            ));
+            commands.push(DrawCommand::line(
+                Point::new(bounds.x0, bounds.y0 + offset),
+                Point::new(bounds.x1, bounds.y0 + offset),
+                Color::BLACK,
+                1.0,
+            ));
+        }
+
+        let turn = Easing::EaseInOut.apply(progress);
+        let radius = layout.cell * 0.4;
+        for (x, y, disc) in self.board().iter() {
+            let Some(disc) = *disc else {
+                continue;
+            };
+            let center = layout.cell_center(x, y);
+            let pos = Pos::new(x as i32, y as i32);
+            if turn >= 1.0 || !self.flipped.contains(&pos) {
+                commands.push(DrawCommand::fill_circle(center, radius, self.color(disc)));
+                continue;
+            }
+            let shown = match turn < 0.5 {
+                true => disc.other(),
+                false => disc,
+            };
+            let squeeze = Affine::translate(-center.x, -center.y)
+                .then(Affine([(1.0 - 2.0 * turn).abs(), 0.0, 0.0, 1.0, 0.0, 0.0]))
+                .then(Affine::translate(center.x, center.y));
+            commands.extend([
+                DrawCommand::Save,
+                DrawCommand::Transform(squeeze),
+                DrawCommand::fill_circle(center, radius, self.color(shown)),
+                DrawCommand::Restore,
+            ]);
+        }
+        commands
+    }
+}
+
+impl LegalMoves for Othello {
+    type Move = Pos;
+
+    fn legal_moves(&self) -> Vec<Pos> {
+        if self.outcome.is_some() {
+            return vec![];
+        }
+        self.moves(self.to_move).positions().collect()
+    }
+
+    fn position_hash(&self) -> Option<u64> {
+        let keys = zobrist_keys();
+        let turn = match self.to_move {
+            Disc::Black => 0,
+            Disc::White => keys.turn_key(),
+        };
+        Some(keys.hash(&self.board()) ^ turn)
+    }
+}
+
+impl GameTrait for Othello {
+    /// Moves out of turn are refused without a turn to retry with.
+    fn player_moves(&mut self, turn_token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+        if self.tracker.current_player().map(|u| &u.name) != Some(&turn_token.user.name) {
+            return PlayerMoveResult::InvalidMove(None);
+        }
+        let Some(pos) = to_player_move::<Pos>(&player_move) else {
+            return PlayerMoveResult::InvalidFormat(Some(self.turn(turn_token)));
+        };
+        if let Some(Outcome::Win(winner)) = self.outcome {
+            return match self.players.get(winner as usize) {
+                Some(user) if user.name == turn_token.user.name => PlayerMoveResult::Win,
+                _ => PlayerMoveResult::InvalidMove(None),
+            };
+        }
+        let mover = self.to_move;
+        if !self.play(pos) {
+            return PlayerMoveResult::InvalidMove(Some(self.turn(turn_token)));
+        }
+        match self.outcome {
+            Some(Outcome::Win(winner)) if winner == mover => PlayerMoveResult::Win,
+            Some(Outcome::Win(_)) => PlayerMoveResult::next(self.next_turn()),
+            Some(Outcome::Draw) => PlayerMoveResult::Draw,
+            None => {
+                if self.to_move == mover {
+                    // The opponent has to pass.
+                    self.tracker.advance_player();
+                }
+                PlayerMoveResult::next(self.next_turn())
+            }
+        }
+    }
+
+    /// A game of two can't go on with one, so it stops there.
+    fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        self.tracker.remove_player(&turn_token.user.name).ok();
+        None
+    }
+
+    fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        if self.tracker.num_players() != 2 {
+            return None;
+        }
+        self.players = self.tracker.players().cloned().collect();
+        self.next_turn()
+    }
+
+    fn player_connected(&mut self, user: User) {
+        if let Err(e) = self.tracker.add_player(user) {
+            warn!(error = %e, "player couldn't join");
+        }
+    }
+
+    fn player_disconnected(&mut self, user: &str) {
+        self.tracker.remove_player(user).ok();
+    }
+
+    fn reset(&mut self, users: Vec<User>) {
+        *self = Self {
+            tracker: TurnTracker::new(users),
+            ..Self::new()
+        };
+    }
+
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        Some(&self.tracker)
+    }
+
+    fn rules_version(&self) -> RulesVersion {
+        RULES_VERSION
+    }
+
+    /// Extra material is discs on the corners, as many as are free, the
+    /// way Othello clubs give handicaps.
+    fn apply_handicap(&mut self, player: &str, mut handicap: Handicap) -> Handicap {
+        let disc = match self.tracker.players().position(|user| user.name == player) {
+            Some(0) => Disc::Black,
+            Some(1) => Disc::White,
+            _ => return handicap,
+        };
+        let last = SIZE as i32 - 1;
+        for (x, y) in [(0, 0), (last, last), (0, last), (last, 0)] {
+            let corner = Pos::new(x, y);
+            if handicap.extra_material == 0 {
+                break;
+            }
+            if (self.black | self.white).test(corner) {
+                continue;
+            }
+            match disc {
+                Disc::Black => self.black.set(corner, true),
+                Disc::White => self.white.set(corner, true),
+            }
+            handicap.extra_material -= 1;
+        }
+        handicap
+    }
+
+    /// Black is the first player, white the second, whether or not they
+    /// are still playing.
+    fn player_scores(&self) -> HashMap<String, i64> {
+        let (black, white) = self.scores();
+        [(Disc::Black, black), (Disc::White, white)]
+            .into_iter()
+            .filter_map(|(disc, score)| {
+                let user = self.players.get(disc as usize)?;
+                Some((user.name.clone(), score as i64))
+            })
+            .collect()
+    }
+
+    fn visible_to(&self, _viewer: Viewer<'_>) -> Option<FilteredState> {
+        Some(to_filtered_state(self.state()))
+    }
+
+    fn draw_offers_allowed(&self) -> bool {
+        true
+    }
+
+    fn draw_agreed(&mut self) {
+        self.outcome = Some(Outcome::Draw);
+    }
+}
+
+impl Draw for Othello {
+    fn draw(&self, size: Size) -> Vec<DrawCommand> {
+        self.draw_flipping(size, 1.0)
+    }
+}
+
+impl Paint for Othello {
+    #[cfg(feature = "gui")]
+    fn paint(&self, ctx: &mut druid::PaintCtx) {
+        crate::draw::piet::paint(ctx, self);
+    }
+
+    fn eq(&self, other: &dyn Paint) -> bool {
+        other.as_any().downcast_ref::<Self>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{render_text, user};
+
+    #[test]
+    fn refuses_moves_out_of_turn() {
+        let mut game = Othello::new();
+        game.reset(vec![user("p1"), user("p2")]);
+        game.try_start_game().unwrap();
+        let before = game.clone();
+        let result = game.player_moves(
+            TurnToken { user: user("p2") },
+            PlayerMove {
+                serialized: "{\"move\":{\"x\":2,\"y\":3}}".to_string(),
+            },
+        );
+        assert_eq!(result, PlayerMoveResult::InvalidMove(None));
+        assert_eq!(game, before);
+    }
+
+    #[test]
+    fn scores_stay_with_their_color() {
+        let mut game = Othello::new();
+        game.reset(vec![user("p1"), user("p2")]);
+        game.try_start_game().unwrap();
+        assert!(game.play(Pos::new(2, 3)));
+        let (black, white) = game.scores();
+        assert_ne!(black, white);
+        game.player_disconnected("p1");
+        assert_eq!(
+            game.player_scores(),
+            HashMap::from([
+                ("p1".to_string(), black as i64),
+                ("p2".to_string(), white as i64)
+            ])
+        );
+    }
+
+    #[test]
+    fn moves_and_flips() {
+        let mut game = Othello::new();
+        assert_eq!(
+            game.legal_moves(),
+            [
+                Pos::new(3, 2),
+                Pos::new(2, 3),
+                Pos::new(5, 4),
+                Pos::new(4, 5)
+            ]
+        );
+        assert!(!game.play(Pos::new(0, 0)));
+        assert!(game.play(Pos::new(3, 2)));
+        assert_eq!(game.flipped(), [Pos::new(3, 3)]);
+        assert_eq!(game.discs(Disc::Black).count(), 4);
+        assert_eq!(game.discs(Disc::White).count(), 1);
+        assert_eq!(game.to_move(), Disc::White);
+        assert_eq!(game.scores(), (4, 1));
+    }
+
+    #[test]
+    fn passes_and_ends() {
+        // White's only disc can be taken at once, leaving nobody a move.
+        let mut game = Othello::new();
+        game.white = BitBoard::new(SIZE, SIZE);
+        game.black = BitBoard::new(SIZE, SIZE);
+        game.white.set(Pos::new(1, 0), true);
+        game.black.set(Pos::new(2, 0), true);
+        assert_eq!(game.legal_moves(), [Pos::new(0, 0)]);
+        assert!(game.play(Pos::new(0, 0)));
+        // Black has every disc and, by area, the whole board.
+        assert_eq!(game.scores(), (64, 0));
+        assert_eq!(game.outcome(), Some(Outcome::Win(Disc::Black)));
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn hashes_positions_with_the_player_to_move() {
+        let mut game = Othello::new();
+        let start = game.position_hash();
+        assert_eq!(start, Othello::new().position_hash());
+        game.play(Pos::new(3, 2));
+        assert_ne!(game.position_hash(), start);
+        let mut black_again = game.clone();
+        black_again.to_move = Disc::Black;
+        assert_ne!(black_again.position_hash(), game.position_hash());
+    }
+
+    #[test]
+    fn handicaps_take_free_corners() {
+        let mut game = Othello::new();
+        game.reset(vec![user("p1"), user("p2")]);
+        let handicap = Handicap {
+            extra_material: 6,
+            ..Handicap::NONE
+        };
+        assert_eq!(game.apply_handicap("nobody", handicap), handicap);
+        let unused = game.apply_handicap("p2", handicap);
+        assert_eq!(unused.extra_material, 2);
+        assert_eq!(game.scores(), (2, 6));
+        assert!(game.discs(Disc::White).test(Pos::new(7, 0)));
+        assert_eq!(game.apply_handicap("p1", handicap).extra_material, 6);
+    }
+
+    #[test]
+    fn animates_flips() {
+        let mut game = Othello::new();
+        game.play(Pos::new(3, 2));
+        let size = Size::new(400.0, 400.0);
+        let halfway = render_text(&game.draw_flipping(size, 0.25));
+        assert!(halfway.contains("transform"));
+        // Before the halfway point the flipped disc still shows white.
+        assert!(halfway.contains("#ffffffff"));
+        assert_eq!(game.draw_flipping(size, 1.0), game.draw(size));
+        assert!(!render_text(&game.draw(size)).contains("transform"));
+    }
+}
@@ -0,0 +1,375 @@
+//! Rock-paper-scissors between two players over a best-of-N series, the
+//! smallest game with simultaneous moves.
+//!
+//! Each round both players get a turn in order with the same state,
+//! `{"your-turn":{"best-of":3,"you":"p1","players":["p1","p2"],"wins":[1,0],"rounds":[["rock","scissors"]]}}`,
+//! and answer with a throw, `{"move":"paper"}`. A throw is held back until
+//! the other player has thrown too, so whoever answers second can't see
+//! what the first threw, and then the round is settled. Drawn rounds don't
+//! count. The first to win a majority of the series wins; when the first
+//! to throw takes the deciding round they get one more turn and win by
+//! answering it.
+
+use std::any::Any;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::{LegalMoves, Viewer};
+use crate::color::Color;
+use crate::draw::{Draw, DrawCommand, Point, Rect, Size};
+use crate::gametraits::{
+    to_filtered_state, to_game_state, to_player_move, FilteredState, GameTrait, Paint, PlayerMove,
+    PlayerMoveResult, PlayerTurn, TurnToken, User,
+};
+use crate::handicap::Handicap;
+use crate::rules::RulesVersion;
+use crate::TurnTracker;
+
+/// See `rules` for when it goes up.
+pub const RULES_VERSION: RulesVersion = RulesVersion::INITIAL;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Throw {
+    Rock,
+    Paper,
+    Scissors,
+}
+
+impl Throw {
+    pub const ALL: [Throw; 3] = [Throw::Rock, Throw::Paper, Throw::Scissors];
+
+    pub fn beats(self, other: Throw) -> bool {
+        matches!(
+            (self, other),
+            (Throw::Rock, Throw::Scissors)
+                | (Throw::Paper, Throw::Rock)
+                | (Throw::Scissors, Throw::Paper)
+        )
+    }
+}
+
+/// What each player is sent: the series so far but never a throw of the
+/// round being played.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct State {
+    pub best_of: u32,
+    pub you: String,
+    pub players: Vec<String>,
+    pub wins: [u32; 2],
+    /// The throws of every round played, drawn ones included, by seat.
+    pub rounds: Vec<[Throw; 2]>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RockPaperScissors {
+    best_of: u32,
+    wins: [u32; 2],
+    rounds: Vec<[Throw; 2]>,
+    /// The throws made so far this round, by seat.
+    pending: [Option<Throw>; 2],
+    tracker: TurnTracker,
+    /// The players by seat, fixed when the game starts.
+    players: Vec<User>,
+}
+
+impl Default for RockPaperScissors {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl RockPaperScissors {
+    /// A series won by the first to take more than half of `best_of`
+    /// rounds.
+    pub fn new(best_of: u32) -> Self {
+        Self {
+            best_of: best_of.max(1),
+            wins: [0; 2],
+            rounds: vec![],
+            pending: [None; 2],
+            tracker: TurnTracker::new(vec![]),
+            players: vec![],
+        }
+    }
+
+    pub fn wins(&self) -> [u32; 2] {
+        self.wins
+    }
+
+    pub fn rounds(&self) -> &[[Throw; 2]] {
+        &self.rounds
+    }
+
+    /// The seat that has won the series, if any.
+    pub fn winner(&self) -> Option<usize> {
+        self.wins.iter().position(|w| *w > self.best_of / 2)
+    }
+
+    /// Records the throw of `seat` for this round, settling the round once
+    /// both have thrown. Returns false, changing nothing, when the seat has
+    /// already thrown this round or the series is over.
+    pub fn throw(&mut self, seat: usize, throw: Throw) -> bool {
+        if self.winner().is_some() || self.pending[seat].is_some() {
+            return false;
+        }
+        self.pending[seat] = Some(throw);
+        if let [Some(a), Some(b)] = self.pending {
+            if a.beats(b) {
+                self.wins[0] += 1;
+            } else if b.beats(a) {
+                self.wins[1] += 1;
+            }
+            self.rounds.push([a, b]);
+            self.pending = [None; 2];
+        }
+        true
+    }
+
+    fn state(&self, you: &str) -> State {
+        State {
+            best_of: self.best_of,
+            you: you.to_string(),
+            players: self.players.iter().map(|u| u.name.clone()).collect(),
+            wins: self.wins,
+            rounds: self.rounds.clone(),
+        }
+    }
+
+    fn turn(&self, token: TurnToken) -> PlayerTurn {
+        let state = to_game_state(self.state(&token.user.name));
+        PlayerTurn { token, state }
+    }
+
+    fn next_turn(&mut self) -> Option<PlayerTurn> {
+        let user = self.tracker.advance_player().cloned()?;
+        Some(self.turn(TurnToken { user }))
+    }
+}
+
+impl LegalMoves for RockPaperScissors {
+    type Move = Throw;
+
+    fn legal_moves(&self) -> Vec<Throw> {
+        match self.winner() {
+            Some(_) => vec![],
+            None => Throw::ALL.to_vec(),
+        }
+    }
+}
+
+impl GameTrait for RockPaperScissors {
+    /// Moves out of turn are refused without a turn to retry with.
+    fn player_moves(&mut self, turn_token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+        if self.tracker.current_player().map(|u| &u.name) != Some(&turn_token.user.name) {
+            return PlayerMoveResult::InvalidMove(None);
+        }
+        let Some(throw) = to_player_move::<Throw>(&player_move) else {
+            return PlayerMoveResult::InvalidFormat(Some(self.turn(turn_token)));
+        };
+        let Some(seat) = self
+            .players
+            .iter()
+            .position(|u| u.name == turn_token.user.name)
+        else {
+            return PlayerMoveResult::InvalidMove(None);
+        };
+        // The winner of a series that is over answers their last turn.
+        match self.winner() {
+            Some(winner) if winner == seat => return PlayerMoveResult::Win,
+            Some(_) => return PlayerMoveResult::InvalidMove(None),
+            None => {}
+        }
+        if !self.throw(seat, throw) {
+            return PlayerMoveResult::InvalidMove(Some(self.turn(turn_token)));
+        }
+        match self.winner() {
+            Some(winner) if winner == seat => PlayerMoveResult::Win,
+            _ => PlayerMoveResult::next(self.next_turn()),
+        }
+    }
+
+    /// A game of two can't go on with one, so it stops there.
+    fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        self.tracker.remove_player(&turn_token.user.name).ok();
+        None
+    }
+
+    fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        if self.tracker.num_players() != 2 {
+            return None;
+        }
+        self.players = self.tracker.players().cloned().collect();
+        self.next_turn()
+    }
+
+    fn player_connected(&mut self, user: User) {
+        if let Err(e) = self.tracker.add_player(user) {
+            warn!(error = %e, "player couldn't join");
+        }
+    }
+
+    fn player_disconnected(&mut self, user: &str) {
+        self.tracker.remove_player(user).ok();
+    }
+
+    fn reset(&mut self, users: Vec<User>) {
+        *self = Self {
+            tracker: TurnTracker::new(users),
+            ..Self::new(self.best_of)
+        };
+    }
+
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        Some(&self.tracker)
+    }
+
+    fn rules_version(&self) -> RulesVersion {
+        RULES_VERSION
+    }
+
+    /// A score offset is rounds won before the first throw, short of
+    /// winning the series outright.
+    fn apply_handicap(&mut self, player: &str, mut handicap: Handicap) -> Handicap {
+        let Some(seat) = self
+            .tracker
+            .players()
+            .position(|user| user.name == player)
+            .filter(|seat| *seat < 2)
+        else {
+            return handicap;
+        };
+        let room = i64::from(self.best_of / 2 - self.wins[seat]);
+        let wins = handicap.score_offset.clamp(0, room);
+        self.wins[seat] += wins as u32;
+        handicap.score_offset -= wins;
+        handicap
+    }
+
+    /// Spectators get the state of a player with no name.
+    fn visible_to(&self, viewer: Viewer<'_>) -> Option<FilteredState> {
+        let you = match viewer {
+            Viewer::Player(name) => name,
+            Viewer::Spectator => "",
+        };
+        Some(to_filtered_state(self.state(you)))
+    }
+}
+
+/// A column per player headed by their name and wins, with their throws
+/// listed below round by round.
+impl Draw for RockPaperScissors {
+    fn draw(&self, size: Size) -> Vec<DrawCommand> {
+        let rows = self.rounds.len() + 2;
+        let font = (size.height / rows as f64 * 0.6).min(size.width / 20.0);
+        let mut commands = vec![DrawCommand::fill_rect(
+            Rect::new(0.0, 0.0, size.width, size.height),
+            Color::rgb8(0x20, 0x20, 0x28),
+        )];
+        for seat in 0..2 {
+            let x = seat as f64 * size.width / 2.0 + font;
+            let (name, color) = match self.players.get(seat) {
+                Some(user) => (user.name.as_str(), user.color),
+                None => ("", Color::WHITE),
+            };
+            let label = format!("{name} ({})", self.wins[seat]);
+            commands.push(DrawCommand::text(label, Point::new(x, font), font, color));
+            for (i, round) in self.rounds.iter().enumerate() {
+                let won = round[seat].beats(round[1 - seat]);
+                let text = format!("{:?}", round[seat]).to_lowercase();
+                let y = (i + 2) as f64 * font * 1.5;
+                let color = if won { Color::WHITE } else { Color::GRAY };
+                commands.push(DrawCommand::text(text, Point::new(x, y), font, color));
+            }
+        }
+        commands
+    }
+}
+
+impl Paint for RockPaperScissors {
+    #[cfg(feature = "gui")]
+    fn paint(&self, ctx: &mut druid::PaintCtx) {
+        crate::draw::piet::paint(ctx, self);
+    }
+
+    fn eq(&self, other: &dyn Paint) -> bool {
+        other.as_any().downcast_ref::<Self>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::user;
+
+    #[test]
+    fn best_of_three() {
+        let mut game = RockPaperScissors::new(3);
+        assert!(game.throw(0, Throw::Rock));
+        assert!(!game.throw(0, Throw::Paper));
+        assert!(game.throw(1, Throw::Rock));
+        assert_eq!(game.wins(), [0, 0]);
+
+        game.throw(1, Throw::Scissors);
+        game.throw(0, Throw::Rock);
+        assert_eq!(game.wins(), [1, 0]);
+        game.throw(0, Throw::Scissors);
+        game.throw(1, Throw::Rock);
+        assert_eq!(game.wins(), [1, 1]);
+        assert_eq!(game.winner(), None);
+        game.throw(0, Throw::Paper);
+        game.throw(1, Throw::Rock);
+        assert_eq!(game.winner(), Some(0));
+        assert_eq!(game.rounds().len(), 4);
+        assert!(game.legal_moves().is_empty());
+        assert!(!game.throw(1, Throw::Paper));
+    }
+
+    #[test]
+    fn pending_throws_stay_hidden() {
+        let mut game = RockPaperScissors::new(1);
+        game.reset(vec![user("p1"), user("p2")]);
+        game.try_start_game().unwrap();
+        game.throw(0, Throw::Paper);
+        let state = game.state("p2");
+        assert!(state.rounds.is_empty());
+        assert_eq!(state.wins, [0, 0]);
+        game.throw(1, Throw::Rock);
+        assert_eq!(game.state("p2").rounds, [[Throw::Paper, Throw::Rock]]);
+        assert_eq!(game.winner(), Some(0));
+    }
+
+    #[test]
+    fn refuses_moves_out_of_turn() {
+        let mut game = RockPaperScissors::new(3);
+        game.reset(vec![user("p1"), user("p2")]);
+        game.try_start_game().unwrap();
+        let result = game.player_moves(
+            TurnToken { user: user("p2") },
+            PlayerMove {
+                serialized: "{\"move\":\"rock\"}".to_string(),
+            },
+        );
+        assert_eq!(result, PlayerMoveResult::InvalidMove(None));
+        assert_eq!(game.pending, [None; 2]);
+    }
+
+    #[test]
+    fn handicaps_are_rounds_won() {
+        let mut game = RockPaperScissors::new(5);
+        game.reset(vec![user("p1"), user("p2")]);
+        let handicap = Handicap {
+            score_offset: 3,
+            ..Handicap::NONE
+        };
+        assert_eq!(game.apply_handicap("p2", handicap).score_offset, 1);
+        assert_eq!(game.wins(), [0, 2]);
+        assert_eq!(game.winner(), None);
+    }
+}
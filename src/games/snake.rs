@@ -0,0 +1,483 @@
+//! Snake for any number of players, moving at the same time on a shared
+//! board. By default it plays as Tron: trails never shrink and the walls are
+//! solid. `with_length` turns trails into snakes of a fixed length and
+//! `wrapping` opens the walls onto the other side.
+//!
+//! Moves are simultaneous. Every tick each snake still alive gets a turn
+//! with the board as it was when the tick began,
+//! `{"your-turn":{"tick":3,"width":20,"height":20,"wrap":false,"snakes":[...],"you":"p1"}}`,
+//! and answers with a heading, `{"move":"north"}`. Once all have answered
+//! the snakes move together. A snake dies running into a wall, a trail or
+//! another snake's head, and its trail stays on the board. When one snake is
+//! left its player gets a last turn and wins by answering it; when none are,
+//! the game is a draw.
+//!
+//! With a tick deadline, an answer coming later than that after the turn was
+//! given out still counts as a move but is ignored, the snake going straight
+//! on.
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::warn;
+
+use super::Viewer;
+use crate::color::Color;
+use crate::draw::layout::GridLayout;
+use crate::draw::{Draw, DrawCommand, Rect, Size};
+use crate::gametraits::{
+    to_filtered_state, to_game_state, to_player_move, FilteredState, GameTrait, Paint, PlayerMove,
+    PlayerMoveResult, PlayerTurn, TurnToken, User,
+};
+use crate::grid::Grid;
+use crate::pos::{Direction, Pos};
+use crate::rules::RulesVersion;
+use crate::time::Instant;
+use crate::TurnTracker;
+
+/// See `rules` for when it goes up.
+pub const RULES_VERSION: RulesVersion = RulesVersion::INITIAL;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Snake {
+    /// Head first.
+    pub body: VecDeque<Pos>,
+    pub heading: Direction,
+    pub alive: bool,
+}
+
+/// What each player is sent, the same for everyone in a tick.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct State {
+    pub tick: u32,
+    pub width: usize,
+    pub height: usize,
+    pub wrap: bool,
+    pub snakes: Vec<SnakeView>,
+    pub you: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SnakeView {
+    pub name: String,
+    pub body: Vec<Pos>,
+    pub heading: Direction,
+    pub alive: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnakeGame {
+    /// The index of the snake whose body covers each cell.
+    board: Grid<Option<usize>>,
+    wrap: bool,
+    length: Option<usize>,
+    tick_deadline: Option<Duration>,
+    tick: u32,
+    /// One per player in `players`, set up when the game starts.
+    snakes: Vec<Snake>,
+    /// The headings chosen so far this tick, `None` for going straight on.
+    pending: Vec<Option<Option<Direction>>>,
+    players: Vec<User>,
+    tracker: TurnTracker,
+    turn_started: Option<Instant>,
+}
+
+impl SnakeGame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            board: Grid::new(width, height, None),
+            wrap: false,
+            length: None,
+            tick_deadline: None,
+            tick: 0,
+            snakes: vec![],
+            pending: vec![],
+            players: vec![],
+            tracker: TurnTracker::new(vec![]),
+            turn_started: None,
+        }
+    }
+
+    /// Snakes leaving the board come back in on the other side.
+    pub fn wrapping(mut self) -> Self {
+        self.wrap = true;
+        self
+    }
+
+    /// Snakes grow to `length` cells and then drag their tail along.
+    pub fn with_length(mut self, length: usize) -> Self {
+        self.length = Some(length.max(1));
+        self
+    }
+
+    pub fn with_tick_deadline(mut self, deadline: Duration) -> Self {
+        self.tick_deadline = Some(deadline);
+        self
+    }
+
+    pub fn board(&self) -> &Grid<Option<usize>> {
+        &self.board
+    }
+
+    pub fn snakes(&self) -> &[Snake] {
+        &self.snakes
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    pub fn alive(&self) -> usize {
+        self.snakes.iter().filter(|s| s.alive).count()
+    }
+
+    /// Puts `players` snakes on the board, spread evenly across the middle
+    /// row and heading alternately north and south.
+    pub fn place_snakes(&mut self, players: usize) {
+        self.board = Grid::new(self.board.width(), self.board.height(), None);
+        self.tick = 0;
+        self.snakes = (0..players)
+            .map(|i| {
+                let x = (i + 1) * self.board.width() / (players + 1);
+                let head = Pos::new(x as i32, self.board.height() as i32 / 2);
+                self.board[head] = Some(i);
+                Snake {
+                    body: VecDeque::from([head]),
+                    heading: match i % 2 {
+                        0 => Direction::North,
+                        _ => Direction::South,
+                    },
+                    alive: true,
+                }
+            })
+            .collect();
+        self.pending = vec![None; players];
+    }
+
+    /// Moves every snake alive one cell, turning those given a heading
+    /// first. Headings for dead snakes are ignored, as are diagonal ones.
+    pub fn step(&mut self, headings: &[Option<Direction>]) {
+        let mut heads: Vec<Option<Pos>> = vec![None; self.snakes.len()];
+        for (i, snake) in self.snakes.iter_mut().enumerate() {
+            if !snake.alive {
+                continue;
+            }
+            if let Some(heading) = headings.get(i).copied().flatten() {
+                if !heading.is_diagonal() {
+                    snake.heading = heading;
+                }
+            }
+            let next = snake.body[0].step(snake.heading);
+            heads[i] = match self.wrap {
+                true => Some(self.board.wrap(next)),
+                false => self.board.contains(next).then_some(next),
+            };
+            if self.length.is_some_and(|length| snake.body.len() >= length) {
+                if let Some(tail) = snake.body.pop_back() {
+                    self.board[tail] = None;
+                }
+            }
+        }
+
+        for (i, head) in heads.iter().enumerate() {
+            if !self.snakes[i].alive {
+                continue;
+            }
+            let crashed = match head {
+                None => true,
+                Some(head) => {
+                    self.board[*head].is_some()
+                        || heads.iter().filter(|h| **h == Some(*head)).count() > 1
+                }
+            };
+            if crashed {
+                self.snakes[i].alive = false;
+            }
+        }
+        for (i, head) in heads.into_iter().enumerate() {
+            if let (true, Some(head)) = (self.snakes[i].alive, head) {
+                self.snakes[i].body.push_front(head);
+                self.board[head] = Some(i);
+            }
+        }
+        self.tick += 1;
+    }
+
+    fn state(&self, you: &str) -> State {
+        State {
+            tick: self.tick,
+            width: self.board.width(),
+            height: self.board.height(),
+            wrap: self.wrap,
+            snakes: self
+                .snakes
+                .iter()
+                .zip(&self.players)
+                .map(|(snake, user)| SnakeView {
+                    name: user.name.clone(),
+                    body: snake.body.iter().copied().collect(),
+                    heading: snake.heading,
+                    alive: snake.alive,
+                })
+                .collect(),
+            you: you.to_string(),
+        }
+    }
+
+    fn turn(&self, token: TurnToken) -> PlayerTurn {
+        let state = to_game_state(self.state(&token.user.name));
+        PlayerTurn { token, state }
+    }
+
+    fn next_turn(&mut self) -> Option<PlayerTurn> {
+        let user = self.tracker.advance_player().cloned()?;
+        self.turn_started = Some(Instant::now());
+        Some(self.turn(TurnToken { user }))
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.players.iter().position(|user| user.name == name)
+    }
+
+    /// Moves the snakes once everyone alive has chosen, and takes the
+    /// crashed ones out of the rotation.
+    fn finish_tick_if_ready(&mut self) {
+        let ready = self
+            .snakes
+            .iter()
+            .zip(&self.pending)
+            .all(|(snake, pending)| !snake.alive || pending.is_some());
+        if !ready {
+            return;
+        }
+        let headings: Vec<_> = self.pending.iter().map(|p| p.flatten()).collect();
+        self.step(&headings);
+        self.pending = vec![None; self.snakes.len()];
+        for (snake, user) in self.snakes.iter().zip(&self.players) {
+            if !snake.alive {
+                self.tracker.remove_player(&user.name).ok();
+            }
+        }
+    }
+
+    fn color(&self, index: usize) -> Color {
+        match self.snakes[index].alive {
+            true => self.players.get(index).map_or(Color::WHITE, |u| u.color),
+            false => Color::GRAY,
+        }
+    }
+}
+
+impl GameTrait for SnakeGame {
+    /// Moves out of turn are refused without a turn to retry with.
+    fn player_moves(&mut self, turn_token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+        if self.tracker.current_player().map(|u| &u.name) != Some(&turn_token.user.name) {
+            return PlayerMoveResult::InvalidMove(None);
+        }
+        let Some(heading) = to_player_move::<Direction>(&player_move) else {
+            return PlayerMoveResult::InvalidFormat(Some(self.turn(turn_token)));
+        };
+        if heading.is_diagonal() {
+            return PlayerMoveResult::InvalidMove(Some(self.turn(turn_token)));
+        }
+        if self.alive() == 1 && self.snakes.len() > 1 {
+            return PlayerMoveResult::Win;
+        }
+        let late = match (self.tick_deadline, self.turn_started) {
+            (Some(deadline), Some(started)) => started.elapsed() > deadline,
+            _ => false,
+        };
+        if let Some(i) = self.index_of(&turn_token.user.name) {
+            self.pending[i] = Some((!late).then_some(heading));
+        }
+        self.finish_tick_if_ready();
+        match self.alive() {
+            0 => PlayerMoveResult::Draw,
+            _ => PlayerMoveResult::next(self.next_turn()),
+        }
+    }
+
+    /// The player's snake crashes where it stands.
+    fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        if let Some(i) = self.index_of(&turn_token.user.name) {
+            self.snakes[i].alive = false;
+        }
+        self.tracker.remove_player(&turn_token.user.name).ok();
+        self.finish_tick_if_ready();
+        match self.alive() {
+            0 => None,
+            _ => self.next_turn(),
+        }
+    }
+
+    fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        if self.tracker.num_players() < 2 {
+            return None;
+        }
+        self.players = self.tracker.players().cloned().collect();
+        self.place_snakes(self.players.len());
+        self.next_turn()
+    }
+
+    fn player_connected(&mut self, user: User) {
+        if let Err(e) = self.tracker.add_player(user) {
+            warn!(error = %e, "player couldn't join");
+        }
+    }
+
+    fn player_disconnected(&mut self, user: &str) {
+        if let Some(i) = self.index_of(user) {
+            self.snakes[i].alive = false;
+        }
+        self.tracker.remove_player(user).ok();
+    }
+
+    fn reset(&mut self, users: Vec<User>) {
+        *self = Self {
+            wrap: self.wrap,
+            length: self.length,
+            tick_deadline: self.tick_deadline,
+            tracker: TurnTracker::new(users),
+            ..Self::new(self.board.width(), self.board.height())
+        };
+    }
+
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        Some(&self.tracker)
+    }
+
+    fn rules_version(&self) -> RulesVersion {
+        RULES_VERSION
+    }
+
+    /// Spectators get the state of a player with no name.
+    fn visible_to(&self, viewer: Viewer<'_>) -> Option<FilteredState> {
+        let you = match viewer {
+            Viewer::Player(name) => name,
+            Viewer::Spectator => "",
+        };
+        Some(to_filtered_state(self.state(you)))
+    }
+}
+
+/// Every snake's cells in its player's color on a dark board, crashed ones
+/// in gray, heads drawn full size and bodies a little smaller.
+impl Draw for SnakeGame {
+    fn draw(&self, size: Size) -> Vec<DrawCommand> {
+        let layout = GridLayout::fit(size, self.board.width(), self.board.height(), 0.0);
+        let mut commands = vec![DrawCommand::fill_rect(
+            layout.bounds(),
+            Color::rgb8(0x10, 0x10, 0x18),
+        )];
+        let inset = layout.cell * 0.1;
+        for (i, snake) in self.snakes.iter().enumerate() {
+            let color = self.color(i);
+            for (n, pos) in snake.body.iter().enumerate() {
+                let cell = layout.cell_rect(pos.x as usize, pos.y as usize);
+                let rect = match n {
+                    0 => cell,
+                    _ => Rect::new(
+                        cell.x0 + inset,
+                        cell.y0 + inset,
+                        cell.x1 - inset,
+                        cell.y1 - inset,
+                    ),
+                };
+                commands.push(DrawCommand::fill_rect(rect, color));
+            }
+        }
+        commands
+    }
+}
+
+impl Paint for SnakeGame {
+    #[cfg(feature = "gui")]
+    fn paint(&self, ctx: &mut druid::PaintCtx) {
+        crate::draw::piet::paint(ctx, self);
+    }
+
+    fn eq(&self, other: &dyn Paint) -> bool {
+        other.as_any().downcast_ref::<Self>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::user;
+
+    const N: Option<Direction> = Some(Direction::North);
+    const E: Option<Direction> = Some(Direction::East);
+    const S: Option<Direction> = Some(Direction::South);
+    const W: Option<Direction> = Some(Direction::West);
+
+    #[test]
+    fn crashes() {
+        let mut game = SnakeGame::new(5, 5);
+        game.place_snakes(2);
+        assert_eq!(game.snakes()[0].body[0], Pos::new(1, 2));
+        assert_eq!(game.snakes()[1].body[0], Pos::new(3, 2));
+
+        // Both turn toward the middle cell and collide head on.
+        game.step(&[E, W]);
+        assert_eq!(game.alive(), 0);
+        assert_eq!(game.tick(), 1);
+
+        let mut game = SnakeGame::new(5, 5);
+        game.place_snakes(2);
+        game.step(&[None, None]);
+        game.step(&[None, E]);
+        assert_eq!(game.alive(), 2);
+        // The first snake hits the top wall, the second turns away from the
+        // right one.
+        game.step(&[None, N]);
+        assert_eq!(game.alive(), 1);
+        assert_eq!(game.snakes()[1].body[0], Pos::new(4, 2));
+        // Trails stay, so turning back into one's own trail is a crash.
+        game.step(&[None, S]);
+        assert_eq!(game.alive(), 0);
+    }
+
+    #[test]
+    fn wrapping_and_length() {
+        let mut game = SnakeGame::new(4, 4).wrapping().with_length(2);
+        game.place_snakes(1);
+        for _ in 0..4 {
+            game.step(&[None]);
+        }
+        // Once around the board, only two cells long.
+        assert!(game.snakes()[0].alive);
+        assert_eq!(
+            game.snakes()[0].body,
+            VecDeque::from([Pos::new(2, 2), Pos::new(2, 3)])
+        );
+        assert_eq!(game.board().cells().iter().flatten().count(), 2);
+    }
+
+    #[test]
+    fn simultaneous_turns() {
+        let mut game = SnakeGame::new(5, 5);
+        game.reset(vec![user("p1"), user("p2")]);
+        let turn = game.try_start_game().unwrap();
+        let send = |serialized: &str| PlayerMove {
+            serialized: serialized.to_string(),
+        };
+        let PlayerMoveResult::Ok(turn) = game.player_moves(turn.token, send("{\"move\":\"east\"}"))
+        else {
+            panic!("expected the second player's turn");
+        };
+        // The first player's choice doesn't show before the tick is over.
+        assert_eq!(game.tick(), 0);
+        assert_eq!(turn.token.user.name, "p2");
+        assert_eq!(
+            game.player_moves(turn.token, send("{\"move\":\"west\"}")),
+            PlayerMoveResult::Draw
+        );
+    }
+}
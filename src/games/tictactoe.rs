@@ -0,0 +1,416 @@
+//! Tic-Tac-Toe for two players, X moving first.
+//!
+//! On their turn a player is sent the board as rows of `null`, `"x"` or
+//! `"o"` along with their own mark,
+//! `{"your-turn":{"board":[[null,"x",null],...],"you":"o"}}`, and answers
+//! with the cell to mark, `{"move":{"x":1,"y":2}}`.
+
+use std::any::Any;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::{LegalMoves, Viewer};
+use crate::color::Color;
+use crate::draw::layout::GridLayout;
+use crate::draw::{Draw, DrawCommand, Point, Size, Stroke};
+use crate::gametraits::{
+    to_filtered_state, to_game_state, to_player_move, FilteredState, GameTrait, Paint, PlayerMove,
+    PlayerMoveResult, PlayerTurn, TurnToken, User,
+};
+use crate::grid::{find_line, Grid};
+use crate::pos::Pos;
+use crate::rules::RulesVersion;
+use crate::TurnTracker;
+
+/// See `rules` for when it goes up.
+pub const RULES_VERSION: RulesVersion = RulesVersion::INITIAL;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mark {
+    X,
+    O,
+}
+
+impl Mark {
+    pub fn other(self) -> Mark {
+        match self {
+            Mark::X => Mark::O,
+            Mark::O => Mark::X,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Mark),
+    Draw,
+}
+
+/// What the player to move is sent.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct State {
+    pub board: Grid<Option<Mark>>,
+    pub you: Mark,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TicTacToe {
+    board: Grid<Option<Mark>>,
+    outcome: Option<Outcome>,
+    tracker: TurnTracker,
+    /// X's player and then O's, fixed when the game starts.
+    players: Vec<User>,
+}
+
+impl Default for TicTacToe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TicTacToe {
+    pub fn new() -> Self {
+        Self {
+            board: Grid::new(3, 3, None),
+            outcome: None,
+            tracker: TurnTracker::new(vec![]),
+            players: vec![],
+        }
+    }
+
+    pub fn board(&self) -> &Grid<Option<Mark>> {
+        &self.board
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub fn to_move(&self) -> Mark {
+        let marks = self.board.cells().iter().flatten().count();
+        match marks % 2 {
+            0 => Mark::X,
+            _ => Mark::O,
+        }
+    }
+
+    /// Puts the next mark at `pos`. Returns false, changing nothing, when the
+    /// cell is taken or off the board or the game is over.
+    pub fn play(&mut self, pos: Pos) -> bool {
+        if self.outcome.is_some() || self.board.at(pos) != Some(&None) {
+            return false;
+        }
+        let mark = self.to_move();
+        self.board[pos] = Some(mark);
+        if find_line(&self.board, pos, 3, |cell| *cell == Some(mark)).is_some() {
+            self.outcome = Some(Outcome::Win(mark));
+        } else if self.board.cells().iter().all(Option::is_some) {
+            self.outcome = Some(Outcome::Draw);
+        }
+        true
+    }
+
+    fn state(&self) -> State {
+        State {
+            board: self.board.clone(),
+            you: self.to_move(),
+        }
+    }
+
+    fn turn(&self, token: TurnToken) -> PlayerTurn {
+        PlayerTurn {
+            token,
+            state: to_game_state(self.state()),
+        }
+    }
+
+    fn next_turn(&mut self) -> Option<PlayerTurn> {
+        let user = self.tracker.advance_player().cloned()?;
+        Some(self.turn(TurnToken { user }))
+    }
+
+    fn color(&self, mark: Mark) -> Color {
+        self.players
+            .get(mark as usize)
+            .map_or(Color::BLACK, |user| user.color)
+    }
+}
+
+impl LegalMoves for TicTacToe {
+    type Move = Pos;
+
+    fn legal_moves(&self) -> Vec<Pos> {
+        if self.outcome.is_some() {
+            return vec![];
+        }
+        self.board
+            .positions()
+            .filter(|pos| self.board[*pos].is_none())
+            .collect()
+    }
+}
+
+impl GameTrait for TicTacToe {
+    /// Moves out of turn are refused without a turn to retry with.
+    fn player_moves(&mut self, turn_token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+        if self.tracker.current_player().map(|u| &u.name) != Some(&turn_token.user.name) {
+            return PlayerMoveResult::InvalidMove(None);
+        }
+        let Some(pos) = to_player_move::<Pos>(&player_move) else {
+            return PlayerMoveResult::InvalidFormat(Some(self.turn(turn_token)));
+        };
+        if !self.play(pos) {
+            return PlayerMoveResult::InvalidMove(Some(self.turn(turn_token)));
+        }
+        match self.outcome {
+            Some(Outcome::Win(_)) => PlayerMoveResult::Win,
+            Some(Outcome::Draw) => PlayerMoveResult::Draw,
+            None => PlayerMoveResult::next(self.next_turn()),
+        }
+    }
+
+    /// A game of two can't go on with one, so it stops there.
+    fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        self.tracker.remove_player(&turn_token.user.name).ok();
+        None
+    }
+
+    fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        if self.tracker.num_players() != 2 {
+            return None;
+        }
+        self.players = self.tracker.players().cloned().collect();
+        self.next_turn()
+    }
+
+    fn player_connected(&mut self, user: User) {
+        if let Err(e) = self.tracker.add_player(user) {
+            warn!(error = %e, "player couldn't join");
+        }
+    }
+
+    fn player_disconnected(&mut self, user: &str) {
+        self.tracker.remove_player(user).ok();
+    }
+
+    fn reset(&mut self, users: Vec<User>) {
+        *self = Self {
+            tracker: TurnTracker::new(users),
+            ..Self::new()
+        };
+    }
+
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        Some(&self.tracker)
+    }
+
+    fn rules_version(&self) -> RulesVersion {
+        RULES_VERSION
+    }
+
+    fn visible_to(&self, _viewer: Viewer<'_>) -> Option<FilteredState> {
+        Some(to_filtered_state(self.state()))
+    }
+
+    fn draw_offers_allowed(&self) -> bool {
+        true
+    }
+
+    fn draw_agreed(&mut self) {
+        self.outcome = Some(Outcome::Draw);
+    }
+}
+
+/// The board in gray lines with each mark in its player's color.
+impl Draw for TicTacToe {
+    fn draw(&self, size: Size) -> Vec<DrawCommand> {
+        let layout = GridLayout::fit(size, 3, 3, size.width.min(size.height) * 0.05);
+        let bounds = layout.bounds();
+        let line_width = layout.cell * 0.04;
+        let mut commands = vec![];
+        for i in 1..3 {
+            let offset = i as f64 * layout.cell;
+            commands.push(DrawCommand::line(
+                Point::new(bounds.x0 + offset, bounds.y0),
+                Point::new(bounds.x0 + offset, bounds.y1),
+                Color::GRAY,
+                line_width,
+            ));
+            commands.push(DrawCommand::line(
+                Point::new(bounds.x0, bounds.y0 + offset),
+                Point::new(bounds.x1, bounds.y0 + offset),
+                Color::GRAY,
+                line_width,
+            ));
+        }
+
+        let arm = layout.cell * 0.3;
+        for (x, y, mark) in self.board.iter() {
+            let Some(mark) = mark else {
+                continue;
+            };
+            let center = layout.cell_center(x, y);
+            let color = self.color(*mark);
+            match mark {
+                Mark::X => {
+                    for dy in [-arm, arm] {
+                        commands.push(DrawCommand::line(
+                            Point::new(center.x - arm, center.y - dy),
+                            Point::new(center.x + arm, center.y + dy),
+                            color,
+                            line_width * 2.0,
+                        ));
+                    }
+                }
+                Mark::O => commands.push(DrawCommand::Circle {
+                    center,
+                    radius: arm,
+                    fill: None,
+                    stroke: Some(Stroke {
+                        color,
+                        width: line_width * 2.0,
+                    }),
+                }),
+            }
+        }
+        commands
+    }
+}
+
+impl Paint for TicTacToe {
+    #[cfg(feature = "gui")]
+    fn paint(&self, ctx: &mut druid::PaintCtx) {
+        crate::draw::piet::paint(ctx, self);
+    }
+
+    fn eq(&self, other: &dyn Paint) -> bool {
+        other.as_any().downcast_ref::<Self>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::replay::{verify_replay, GameRecorder};
+    use crate::test_support::{assert_render_golden, assert_replay_golden, user};
+
+    fn play_all(game: &mut TicTacToe, moves: &[(i32, i32)]) {
+        for (x, y) in moves {
+            assert!(game.play(Pos::new(*x, *y)), "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let mut game = TicTacToe::new();
+        assert_eq!(game.legal_moves().len(), 9);
+        play_all(&mut game, &[(0, 0), (1, 1), (1, 0)]);
+        assert_eq!(game.to_move(), Mark::O);
+        assert!(!game.play(Pos::new(1, 1)));
+        assert!(!game.play(Pos::new(3, 0)));
+        play_all(&mut game, &[(2, 2), (2, 0)]);
+        assert_eq!(game.outcome(), Some(Outcome::Win(Mark::X)));
+        assert!(game.legal_moves().is_empty());
+        assert!(!game.play(Pos::new(0, 2)));
+
+        let mut game = TicTacToe::new();
+        play_all(
+            &mut game,
+            &[
+                (1, 1),
+                (0, 0),
+                (2, 2),
+                (0, 2),
+                (0, 1),
+                (2, 1),
+                (1, 0),
+                (1, 2),
+                (2, 0),
+            ],
+        );
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn plays_through_the_protocol() {
+        let mut recorder = GameRecorder::new(Box::new(TicTacToe::new()));
+        recorder.player_connected(user("p1"));
+        recorder.player_connected(user("p2"));
+        let mut turn = recorder.try_start_game().unwrap();
+        assert_eq!(
+            turn.state.serialized,
+            "{\"your-turn\":{\"board\":[[null,null,null],[null,null,null],[null,null,null]],\"you\":\"x\"}}\n"
+        );
+
+        let send = |serialized: &str| PlayerMove {
+            serialized: serialized.to_string(),
+        };
+        let PlayerMoveResult::InvalidFormat(Some(again)) =
+            recorder.player_moves(turn.token, send("{\"move\":\"center\"}"))
+        else {
+            panic!("expected an invalid format");
+        };
+        turn = again;
+        for (x, y) in [(0, 0), (1, 1), (1, 0), (2, 2)] {
+            let serialized = format!("{{\"move\":{{\"x\":{x},\"y\":{y}}}}}");
+            match recorder.player_moves(turn.token, send(&serialized)) {
+                PlayerMoveResult::Ok(next) => turn = next,
+                other => panic!("{other:?}"),
+            }
+        }
+        assert_eq!(turn.token.user.name, "p1");
+        assert_eq!(
+            recorder.player_moves(turn.token, send("{\"move\":{\"x\":2,\"y\":0}}")),
+            PlayerMoveResult::Win
+        );
+        assert_eq!(
+            verify_replay(Box::new(TicTacToe::new()), recorder.replay()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn refuses_moves_out_of_turn() {
+        let mut game = TicTacToe::new();
+        game.reset(vec![user("p1"), user("p2")]);
+        game.try_start_game().unwrap();
+        let result = game.player_moves(
+            TurnToken { user: user("p2") },
+            PlayerMove {
+                serialized: "{\"move\":{\"x\":1,\"y\":1}}".to_string(),
+            },
+        );
+        assert_eq!(result, PlayerMoveResult::InvalidMove(None));
+        assert!(game.board().cells().iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn renders() {
+        let mut game = TicTacToe::new();
+        game.reset(vec![user("p1"), user("p2")]);
+        game.try_start_game();
+        play_all(&mut game, &[(0, 0), (1, 1)]);
+        assert_render_golden(
+            &game,
+            Size::new(300.0, 300.0),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/tictactoe.txt"),
+        );
+    }
+
+    #[test]
+    fn golden_replay() {
+        assert_replay_golden(
+            Box::new(TicTacToe::new()),
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/golden/replays/tictactoe.txt"
+            ),
+        );
+    }
+}
@@ -0,0 +1,463 @@
+//! Whist without trumps for two to four players, each dealt an equal share
+//! of a deck shuffled from the game's seed. The leader plays any card, the
+//! others must follow suit if they can, and the highest card of the suit
+//! led takes the trick and leads the next. Whoever takes the most tricks
+//! wins, and if the last trick goes to somebody else they are sent one more
+//! turn and win by answering it.
+//!
+//! Players only ever see their own hand. On their turn they are sent
+//! `{"your-turn":{"you":"p1","hand":[{"rank":12,"suit":"spades"},...],"players":[...],"trick":[...],...}}`
+//! where the other players only show how many cards they hold, and answer
+//! with a card from their hand, `{"move":{"rank":12,"suit":"spades"}}`.
+
+use std::any::Any;
+
+use serde::Serialize;
+use tracing::warn;
+
+use super::{HiddenInformation, LegalMoves, Viewer};
+use crate::cards::{Card, Deck, Suit};
+use crate::color::Color;
+use crate::draw::{Draw, DrawCommand, Point, Rect, Size};
+use crate::gametraits::{
+    to_filtered_state, to_game_state, to_player_move, FilteredState, GameTrait, Paint, PlayerMove,
+    PlayerMoveResult, PlayerTurn, TurnToken, User,
+};
+use crate::rules::RulesVersion;
+use crate::TurnTracker;
+
+/// See `rules` for when it goes up.
+pub const RULES_VERSION: RulesVersion = RulesVersion::INITIAL;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The index of the player who took the most tricks.
+    Win(usize),
+    Draw,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PlayedCard {
+    pub player: String,
+    pub card: Card,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SeatView {
+    pub name: String,
+    pub cards_left: usize,
+    pub tricks: usize,
+    /// Only there for the viewer's own seat, or for spectators.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hand: Option<Vec<Card>>,
+}
+
+/// A player's or a spectator's view, and the state sent on each turn.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct View {
+    /// Missing for spectators.
+    pub you: Option<String>,
+    /// The viewer's own hand.
+    pub hand: Vec<Card>,
+    pub players: Vec<SeatView>,
+    /// The cards played to the trick so far, the leader's first.
+    pub trick: Vec<PlayedCard>,
+    /// The trick taken last, so players can see how it went.
+    pub last_trick: Vec<PlayedCard>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Whist {
+    seed: u64,
+    /// One hand per seat, sorted.
+    hands: Vec<Vec<Card>>,
+    tricks: Vec<usize>,
+    trick: Vec<(usize, Card)>,
+    last_trick: Vec<(usize, Card)>,
+    leader: usize,
+    outcome: Option<Outcome>,
+    tracker: TurnTracker,
+    /// The players by seat, fixed when the game starts.
+    players: Vec<User>,
+}
+
+impl Whist {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            hands: vec![],
+            tricks: vec![],
+            trick: vec![],
+            last_trick: vec![],
+            leader: 0,
+            outcome: None,
+            tracker: TurnTracker::new(vec![]),
+            players: vec![],
+        }
+    }
+
+    /// Shuffles the deck from the seed and deals it out evenly to `seats`
+    /// players, the first of them leading.
+    pub fn deal(&mut self, seats: usize) {
+        let mut deck = Deck::shuffled(self.seed);
+        self.hands = deck.deal(seats, deck.len() / seats);
+        self.tricks = vec![0; seats];
+        self.trick.clear();
+        self.last_trick.clear();
+        self.leader = 0;
+        self.outcome = None;
+    }
+
+    pub fn hand(&self, seat: usize) -> &[Card] {
+        &self.hands[seat]
+    }
+
+    pub fn tricks(&self) -> &[usize] {
+        &self.tricks
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// The seat to play next.
+    pub fn to_play(&self) -> usize {
+        (self.leader + self.trick.len()) % self.hands.len()
+    }
+
+    fn led_suit(&self) -> Option<Suit> {
+        self.trick.first().map(|(_, card)| card.suit)
+    }
+
+    /// The seat with the highest card of the suit led so far.
+    fn trick_winner(&self) -> Option<usize> {
+        let suit = self.led_suit()?;
+        self.trick
+            .iter()
+            .filter(|(_, card)| card.suit == suit)
+            .max_by_key(|(_, card)| card.rank)
+            .map(|(seat, _)| *seat)
+    }
+
+    /// Plays `card` from the hand of the seat to play. Returns false,
+    /// changing nothing, when they don't hold it, it doesn't follow suit
+    /// when it could or the game is over.
+    pub fn play(&mut self, card: Card) -> bool {
+        if self.outcome.is_some() || !self.legal_moves().contains(&card) {
+            return false;
+        }
+        let seat = self.to_play();
+        self.hands[seat].retain(|c| *c != card);
+        self.trick.push((seat, card));
+        if self.trick.len() < self.hands.len() {
+            return true;
+        }
+
+        let Some(winner) = self.trick_winner() else {
+            return true;
+        };
+        self.tricks[winner] += 1;
+        self.leader = winner;
+        self.last_trick = std::mem::take(&mut self.trick);
+
+        if self.hands.iter().all(Vec::is_empty) {
+            let most = self.tricks.iter().max().copied().unwrap_or(0);
+            let leaders: Vec<usize> = (0..self.tricks.len())
+                .filter(|seat| self.tricks[*seat] == most)
+                .collect();
+            self.outcome = Some(match leaders[..] {
+                [seat] => Outcome::Win(seat),
+                _ => Outcome::Draw,
+            });
+        }
+        true
+    }
+
+    fn played(&self, trick: &[(usize, Card)]) -> Vec<PlayedCard> {
+        trick
+            .iter()
+            .map(|(seat, card)| PlayedCard {
+                player: self.name(*seat),
+                card: *card,
+            })
+            .collect()
+    }
+
+    fn name(&self, seat: usize) -> String {
+        self.players
+            .get(seat)
+            .map_or_else(|| format!("seat {seat}"), |user| user.name.clone())
+    }
+
+    fn seat_of(&self, name: &str) -> Option<usize> {
+        self.players.iter().position(|user| user.name == name)
+    }
+
+    fn turn(&self, token: TurnToken) -> PlayerTurn {
+        let state = to_game_state(self.view(Viewer::Player(&token.user.name)));
+        PlayerTurn { token, state }
+    }
+
+    /// Gives the turn to `seat`, who isn't necessarily next in the rotation
+    /// since the winner of a trick leads.
+    fn turn_for(&mut self, seat: usize) -> Option<PlayerTurn> {
+        for _ in 0..self.tracker.num_players() {
+            let user = self.tracker.advance_player()?;
+            if Some(&user.name) == self.players.get(seat).map(|u| &u.name) {
+                let user = user.clone();
+                return Some(self.turn(TurnToken { user }));
+            }
+        }
+        None
+    }
+}
+
+impl LegalMoves for Whist {
+    type Move = Card;
+
+    fn legal_moves(&self) -> Vec<Card> {
+        if self.outcome.is_some() || self.hands.is_empty() {
+            return vec![];
+        }
+        let hand = &self.hands[self.to_play()];
+        match self.led_suit() {
+            Some(suit) if hand.iter().any(|card| card.suit == suit) => hand
+                .iter()
+                .copied()
+                .filter(|card| card.suit == suit)
+                .collect(),
+            _ => hand.clone(),
+        }
+    }
+}
+
+impl HiddenInformation for Whist {
+    type View = View;
+
+    fn view(&self, viewer: Viewer<'_>) -> View {
+        let you = match viewer {
+            Viewer::Player(name) => self.seat_of(name),
+            Viewer::Spectator => None,
+        };
+        View {
+            you: you.map(|seat| self.name(seat)),
+            hand: you.map(|seat| self.hands[seat].clone()).unwrap_or_default(),
+            players: (0..self.hands.len())
+                .map(|seat| SeatView {
+                    name: self.name(seat),
+                    cards_left: self.hands[seat].len(),
+                    tricks: self.tricks[seat],
+                    hand: (viewer == Viewer::Spectator || you == Some(seat))
+                        .then(|| self.hands[seat].clone()),
+                })
+                .collect(),
+            trick: self.played(&self.trick),
+            last_trick: self.played(&self.last_trick),
+        }
+    }
+}
+
+impl GameTrait for Whist {
+    /// Moves out of turn are refused without a turn to retry with. Once the
+    /// game is decided only the winner has a turn left.
+    fn player_moves(&mut self, turn_token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+        let Some(seat) = self.seat_of(&turn_token.user.name) else {
+            return PlayerMoveResult::InvalidMove(None);
+        };
+        let to_play = match self.outcome {
+            Some(Outcome::Win(winner)) => winner,
+            _ => self.to_play(),
+        };
+        if seat != to_play {
+            return PlayerMoveResult::InvalidMove(None);
+        }
+        let Some(card) = to_player_move::<Card>(&player_move) else {
+            return PlayerMoveResult::InvalidFormat(Some(self.turn(turn_token)));
+        };
+        if self.outcome.is_some() {
+            return PlayerMoveResult::Win;
+        }
+        let mover = self.to_play();
+        if !self.play(card) {
+            return PlayerMoveResult::InvalidMove(Some(self.turn(turn_token)));
+        }
+        match self.outcome {
+            Some(Outcome::Win(winner)) if winner == mover => PlayerMoveResult::Win,
+            Some(Outcome::Win(winner)) => PlayerMoveResult::next(self.turn_for(winner)),
+            Some(Outcome::Draw) => PlayerMoveResult::Draw,
+            None => PlayerMoveResult::next(self.turn_for(self.to_play())),
+        }
+    }
+
+    /// The hand can't be played out without them, so the game stops.
+    fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        self.tracker.remove_player(&turn_token.user.name).ok();
+        None
+    }
+
+    fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        if !(2..=4).contains(&self.tracker.num_players()) {
+            return None;
+        }
+        self.players = self.tracker.players().cloned().collect();
+        self.deal(self.players.len());
+        self.turn_for(0)
+    }
+
+    fn player_connected(&mut self, user: User) {
+        if let Err(e) = self.tracker.add_player(user) {
+            warn!(error = %e, "player couldn't join");
+        }
+    }
+
+    fn player_disconnected(&mut self, user: &str) {
+        self.tracker.remove_player(user).ok();
+    }
+
+    fn reset(&mut self, users: Vec<User>) {
+        *self = Self {
+            tracker: TurnTracker::new(users),
+            ..Self::new(self.seed)
+        };
+    }
+
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        Some(&self.tracker)
+    }
+
+    fn rules_version(&self) -> RulesVersion {
+        RULES_VERSION
+    }
+
+    fn visible_to(&self, viewer: Viewer<'_>) -> Option<FilteredState> {
+        Some(to_filtered_state(self.view(viewer)))
+    }
+}
+
+/// The spectator's view: a row per player with their name, tricks and
+/// hand, and the trick being played below.
+impl Draw for Whist {
+    fn draw(&self, size: Size) -> Vec<DrawCommand> {
+        let rows = self.hands.len() + 1;
+        let row_height = size.height / rows as f64;
+        let card_width = (size.width / 16.0).min(row_height * 0.7);
+        let font = card_width * 0.4;
+        let mut commands = vec![DrawCommand::fill_rect(
+            Rect::new(0.0, 0.0, size.width, size.height),
+            Color::rgb8(0x0b, 0x5d, 0x1e),
+        )];
+        let card = |commands: &mut Vec<DrawCommand>, card: Card, x: f64, y: f64| {
+            let rect = Rect::new(x, y, x + card_width * 0.9, y + card_width * 1.3);
+            let red = matches!(card.suit, Suit::Diamonds | Suit::Hearts);
+            commands.push(DrawCommand::fill_rect(rect, Color::WHITE));
+            commands.push(DrawCommand::text(
+                card.to_string(),
+                Point::new(x + font * 0.2, y + font * 0.2),
+                font,
+                if red { Color::RED } else { Color::BLACK },
+            ));
+        };
+
+        for (seat, hand) in self.hands.iter().enumerate() {
+            let y = seat as f64 * row_height + row_height * 0.15;
+            let color = self.players.get(seat).map_or(Color::WHITE, |u| u.color);
+            let label = format!("{} ({})", self.name(seat), self.tricks[seat]);
+            commands.push(DrawCommand::text(label, Point::new(font, y), font, color));
+            for (i, c) in hand.iter().enumerate() {
+                card(&mut commands, *c, (i + 3) as f64 * card_width, y);
+            }
+        }
+        let y = self.hands.len() as f64 * row_height + row_height * 0.15;
+        for (i, (_, c)) in self.trick.iter().enumerate() {
+            card(&mut commands, *c, (i + 3) as f64 * card_width * 1.2, y);
+        }
+        commands
+    }
+}
+
+impl Paint for Whist {
+    #[cfg(feature = "gui")]
+    fn paint(&self, ctx: &mut druid::PaintCtx) {
+        crate::draw::piet::paint(ctx, self);
+    }
+
+    fn eq(&self, other: &dyn Paint) -> bool {
+        other.as_any().downcast_ref::<Self>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::user;
+
+    #[test]
+    fn refuses_moves_out_of_turn() {
+        let mut game = Whist::new(7);
+        game.reset(vec![user("p1"), user("p2")]);
+        game.try_start_game().unwrap();
+        let before = game.clone();
+        let result = game.player_moves(
+            TurnToken { user: user("p2") },
+            PlayerMove {
+                serialized: "{\"move\":{\"rank\":2,\"suit\":\"spades\"}}".to_string(),
+            },
+        );
+        assert_eq!(result, PlayerMoveResult::InvalidMove(None));
+        assert_eq!(game, before);
+    }
+
+    #[test]
+    fn follows_suit_and_takes_tricks() {
+        let mut game = Whist::new(1);
+        game.deal(2);
+        game.hands = vec![
+            vec![Card::new(3, Suit::Clubs), Card::new(9, Suit::Hearts)],
+            vec![
+                Card::new(5, Suit::Clubs),
+                Card::new(Card::ACE, Suit::Spades),
+            ],
+        ];
+        assert!(game.play(Card::new(3, Suit::Clubs)));
+        // The second player has a club, so must play it.
+        assert_eq!(game.legal_moves(), [Card::new(5, Suit::Clubs)]);
+        assert!(!game.play(Card::new(Card::ACE, Suit::Spades)));
+        assert!(game.play(Card::new(5, Suit::Clubs)));
+        assert_eq!(game.tricks(), [0, 1]);
+        assert_eq!(game.to_play(), 1);
+
+        // An ace of another suit doesn't beat the suit led.
+        assert!(game.play(Card::new(Card::ACE, Suit::Spades)));
+        assert!(game.play(Card::new(9, Suit::Hearts)));
+        assert_eq!(game.tricks(), [0, 2]);
+        assert_eq!(game.outcome(), Some(Outcome::Win(1)));
+    }
+
+    #[test]
+    fn views_hide_other_hands() {
+        let mut game = Whist::new(7);
+        game.reset(vec![user("p1"), user("p2"), user("p3")]);
+        game.try_start_game().unwrap();
+        assert_eq!(game.hand(0).len(), 17);
+        let mut again = Whist::new(7);
+        again.deal(3);
+        assert_eq!(game.hand(0), again.hand(0));
+
+        let view = game.view(Viewer::Player("p2"));
+        assert_eq!(view.you.as_deref(), Some("p2"));
+        assert_eq!(view.hand, game.hand(1));
+        assert!(view.players[0].hand.is_none() && view.players[2].hand.is_none());
+        assert_eq!(view.players[0].cards_left, 17);
+        assert_eq!(view.players[1].hand.as_deref(), Some(game.hand(1)));
+
+        let spectator = game.view(Viewer::Spectator);
+        assert!(spectator.hand.is_empty());
+        assert!(spectator.players.iter().all(|seat| seat.hand.is_some()));
+    }
+}
@@ -1,11 +1,25 @@
-use std::{any::Any, fmt::Debug};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::ops::Deref;
+use std::sync::Arc;
 
+#[cfg(feature = "gui")]
 use druid::Data;
 use serde::{Deserialize, Serialize};
 
-use crate::messages;
+use crate::clock::GameClock;
+use crate::color::Color;
+use crate::dice::DiceLog;
+use crate::forfeit::{forfeit, Forfeit};
+use crate::games::Viewer;
+use crate::handicap::Handicap;
+use crate::messages::{self, ForfeitReason};
+use crate::replay::GameOutcome;
+use crate::rules::RulesVersion;
+use crate::TurnTracker;
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct PlayerGameState {
     pub serialized: String,
 }
@@ -19,15 +33,44 @@ where
     }
 }
 
+/// A game as one viewer may see it, for spectators and for players between
+/// their turns.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct FilteredState {
+    pub serialized: String,
+}
+
+pub fn to_filtered_state<S>(state: S) -> FilteredState
+where
+    S: Serialize,
+{
+    FilteredState {
+        serialized: serde_json::to_string(&state).unwrap() + "\n",
+    }
+}
+
 #[derive(Debug)]
 pub struct PlayerMove {
     pub serialized: String,
 }
 
 pub fn to_player_move<'a, MoveType: Deserialize<'a>>(p_move: &'a PlayerMove) -> Option<MoveType> {
-    serde_json::from_str::<messages::Move<MoveType>>(&p_move.serialized)
-        .map(|messages::Move::Move(m)| Some(m))
-        .unwrap_or(None)
+    decode_player_move(p_move).ok()
+}
+
+/// Like `to_player_move`, saying what's wrong with moves that don't parse.
+pub fn decode_player_move<'a, MoveType: Deserialize<'a>>(
+    p_move: &'a PlayerMove,
+) -> Result<MoveType, messages::MalformedMessage> {
+    messages::decode::<messages::Move<MoveType>>(&p_move.serialized)
+        .map(|messages::Move::Move(m)| m)
+}
+
+/// A move as a client sends it, `{"move":...}`, for bots playing in-process.
+pub fn from_player_move<MoveType: Serialize>(p_move: MoveType) -> PlayerMove {
+    PlayerMove {
+        serialized: serde_json::to_string(&messages::Move::Move(p_move)).unwrap(),
+    }
 }
 
 pub trait GameTrait: dyn_clone::DynClone + Send + Debug + Paint {
@@ -40,21 +83,181 @@ pub trait GameTrait: dyn_clone::DynClone + Send + Debug + Paint {
     fn player_disconnected(&mut self, user: &str);
 
     fn reset(&mut self, users: Vec<User>);
+
+    /// The dice rolled so far, for games of chance to have their rolls
+    /// recorded in the replay.
+    fn dice_log(&self) -> Option<&DiceLog> {
+        None
+    }
+
+    /// Who's playing and whose turn it is, for `resign` and `disqualify` to
+    /// work out who's left.
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        None
+    }
+
+    /// The rules the game plays by, recorded in its replays so they're only
+    /// verified against the same rules.
+    fn rules_version(&self) -> RulesVersion {
+        RulesVersion::INITIAL
+    }
+
+    /// Gives `player` a head start, between `reset` and the game starting.
+    /// Returns the part the game has no use for, which unless it says
+    /// otherwise is all of it.
+    fn apply_handicap(&mut self, _player: &str, handicap: Handicap) -> Handicap {
+        handicap
+    }
+
+    /// Each player's score, for games that keep one as they go. Hosts
+    /// publish a `GameEvent::ScoreChanged` whenever one changes.
+    fn player_scores(&self) -> HashMap<String, i64> {
+        HashMap::new()
+    }
+
+    /// The players' clocks, for timed games, so organizers can correct
+    /// them.
+    fn clock_mut(&mut self) -> Option<&mut GameClock> {
+        None
+    }
+
+    /// The state as `viewer` may see it, the one way servers get a game's
+    /// state outside a turn. Games with something to hide send the view
+    /// from their `HiddenInformation`, so a spectator's or another player's
+    /// copy never carries what they mustn't see. `None` for games that
+    /// show nothing between turns.
+    fn visible_to(&self, _viewer: Viewer<'_>) -> Option<FilteredState> {
+        None
+    }
+
+    /// `player` gives up and leaves the game. If one player is left, they
+    /// win.
+    fn resign(&mut self, player: &str) -> Forfeit {
+        forfeit(self, player, ForfeitReason::Resigned)
+    }
+
+    /// Whether the players may agree to a draw, for games that don't play
+    /// themselves out. Off unless a game opts in.
+    fn draw_offers_allowed(&self) -> bool {
+        false
+    }
+
+    /// The players agreed to a draw and the game is over.
+    fn draw_agreed(&mut self) {}
+
+    /// Like `resign`, for a player the server throws out.
+    fn disqualify(&mut self, player: &str, reason: &str) -> Forfeit {
+        forfeit(
+            self,
+            player,
+            ForfeitReason::Disqualified {
+                reason: reason.to_string(),
+            },
+        )
+    }
 }
 dyn_clone::clone_trait_object!(GameTrait);
 
+/// Drawing a game and comparing it to another. Only `paint` needs the `gui`
+/// feature, `eq` and `as_any` are there either way. `paint` draws nothing
+/// unless overridden, so turning `gui` on doesn't break games written
+/// without it.
 pub trait Paint: dyn_clone::DynClone + Send + Debug {
-    fn paint(&self, ctx: &mut druid::PaintCtx);
+    #[cfg(feature = "gui")]
+    fn paint(&self, _ctx: &mut druid::PaintCtx) {}
     fn eq(&self, other: &dyn Paint) -> bool;
     fn as_any(&self) -> &dyn Any;
 }
 dyn_clone::clone_trait_object!(Paint);
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Data)]
-pub struct User {
+/// A player, shared rather than copied: clones point at the same name and
+/// color, so the tracker, scoreboards, clocks and renderers can each hold
+/// one for the price of a reference count. Reads go through to `UserData`.
+#[derive(Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "gui", derive(Data))]
+#[serde(transparent)]
+pub struct User(Arc<UserData>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "gui", derive(Data))]
+pub struct UserData {
     pub name: String,
     #[serde(skip_serializing)]
-    pub color: druid::piet::Color,
+    pub color: Color,
+}
+
+impl User {
+    pub fn new(name: impl Into<String>, color: Color) -> Self {
+        Self(Arc::new(UserData {
+            name: name.into(),
+            color,
+        }))
+    }
+
+    /// Whether `self` and `other` are clones of one user, not just equal.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for User {
+    type Target = UserData;
+
+    fn deref(&self) -> &UserData {
+        &self.0
+    }
+}
+
+impl Debug for User {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("User")
+            .field("name", &self.name)
+            .field("color", &self.color)
+            .finish()
+    }
+}
+
+pub(crate) fn color_to_hex(color: Color) -> String {
+    format!("#{:08x}", color.as_rgba_u32())
+}
+
+pub(crate) fn color_from_hex(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#').filter(|h| h.len() == 8)?;
+    u32::from_str_radix(hex, 16)
+        .ok()
+        .map(Color::from_rgba32_u32)
+}
+
+/// Serializes users including their color, for use with `#[serde(with)]`
+/// where a `Vec<User>` has to survive a round trip, unlike in the protocol.
+pub(crate) mod stored_users {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{color_from_hex, color_to_hex, User};
+
+    #[derive(Serialize, Deserialize)]
+    struct StoredUser {
+        name: String,
+        color: String,
+    }
+
+    pub fn serialize<S: Serializer>(users: &[User], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(users.iter().map(|u| StoredUser {
+            name: u.name.clone(),
+            color: color_to_hex(u.color),
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<User>, D::Error> {
+        Vec::<StoredUser>::deserialize(deserializer)?
+            .into_iter()
+            .map(|u| {
+                let color = color_from_hex(&u.color)
+                    .ok_or_else(|| de::Error::custom(format!("invalid color {}", u.color)))?;
+                Ok(User::new(u.name, color))
+            })
+            .collect()
+    }
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -75,4 +278,17 @@ pub enum PlayerMoveResult {
     Draw,
     InvalidMove(Option<PlayerTurn>),
     InvalidFormat(Option<PlayerTurn>),
+    /// The player ran out of time before their move counted, and the game
+    /// ended with `outcome` without it.
+    OutOfTime(GameOutcome),
+}
+
+impl PlayerMoveResult {
+    /// `Ok` with the next turn, or `InvalidMove(None)` when there's no one
+    /// to hand it to, as in a game that everyone left. It comes after the
+    /// move is made, so games refuse moves before changing anything when
+    /// they haven't started or the mover isn't the current player.
+    pub fn next(turn: Option<PlayerTurn>) -> Self {
+        turn.map_or(PlayerMoveResult::InvalidMove(None), PlayerMoveResult::Ok)
+    }
 }
@@ -0,0 +1,251 @@
+//! `Grid`, the board from `core::grid`, and what's built on it: bitboards,
+//! change tracking, text encoding, regions, symmetries, Zobrist hashing and
+//! finding lines of cells.
+
+use crate::pos::{Direction, Pos};
+
+mod bitboard;
+mod changes;
+mod encoding;
+mod grid3;
+mod regions;
+mod territory;
+mod transform;
+mod zobrist;
+
+pub use crate::core::grid::{Adjacency, Boundary, Grid, RaggedRows};
+pub use bitboard::{BitBoard, Bits};
+pub use changes::{GridDelta, TrackedGrid};
+pub use encoding::{
+    decode, decode_with_metadata, encode, encode_with_metadata, CellSymbol, DecodeError,
+    MAX_ROW_WIDTH,
+};
+pub use grid3::Grid3;
+pub use regions::{fill, flood_fill, is_connected, Connectivity, Regions};
+pub use territory::{area_score, territory, Territory};
+pub use transform::Transform;
+pub use zobrist::{HashedGrid, ZobristCell, ZobristKeys};
+
+/// The axes a line can run along, each covering its opposite direction too.
+const LINE_AXES: [Direction; 4] = [
+    Direction::East,
+    Direction::South,
+    Direction::SouthEast,
+    Direction::NorthEast,
+];
+
+/// The cells through `pos` along `axis` that satisfy `predicate` without a
+/// gap, in order along the axis. Empty if `pos` itself doesn't.
+fn run_through<T>(
+    grid: &Grid<T>,
+    pos: Pos,
+    axis: Direction,
+    predicate: &impl Fn(&T) -> bool,
+) -> Vec<Pos> {
+    let matches = |p: Pos| grid.at(p).is_some_and(predicate);
+    if !matches(pos) {
+        return vec![];
+    }
+    let mut start = pos;
+    while matches(start.step(axis.opposite())) {
+        start = start.step(axis.opposite());
+    }
+    let mut line = vec![start];
+    while matches(line[line.len() - 1].step(axis)) {
+        line.push(line[line.len() - 1].step(axis));
+    }
+    line
+}
+
+/// The runs through `pos` along each of the four axes whose contents
+/// satisfy `predicate`, some of which may be a single cell. Empty if `pos`
+/// itself doesn't satisfy it.
+pub fn lines_through<T>(grid: &Grid<T>, pos: Pos, predicate: impl Fn(&T) -> bool) -> Vec<Vec<Pos>> {
+    LINE_AXES
+        .into_iter()
+        .map(|axis| run_through(grid, pos, axis, &predicate))
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// A line of at least `n` cells through `pos` whose contents satisfy
+/// `predicate`, horizontally, vertically or diagonally. The whole line is
+/// returned, so it may be longer than `n`. Checking around the last move is
+/// enough to detect wins in n-in-a-row games.
+pub fn find_line<T>(
+    grid: &Grid<T>,
+    pos: Pos,
+    n: usize,
+    predicate: impl Fn(&T) -> bool,
+) -> Option<Vec<Pos>> {
+    LINE_AXES
+        .into_iter()
+        .map(|axis| run_through(grid, pos, axis, &predicate))
+        .find(|line| !line.is_empty() && line.len() >= n)
+}
+
+/// The longest line through `pos` whose contents satisfy `predicate`, the
+/// first axis in east, south, south-east, north-east order winning ties.
+pub fn longest_line<T>(grid: &Grid<T>, pos: Pos, predicate: impl Fn(&T) -> bool) -> Vec<Pos> {
+    LINE_AXES
+        .into_iter()
+        .map(|axis| run_through(grid, pos, axis, &predicate))
+        .reduce(|longest, line| {
+            if line.len() > longest.len() {
+                line
+            } else {
+                longest
+            }
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    #[test]
+    fn get_set_and_bounds() {
+        let mut grid = Grid::new(3, 2, '.');
+        assert_eq!(grid.set(2, 1, 'x'), Some('.'));
+        assert_eq!(grid.set(3, 0, 'x'), None);
+        assert_eq!(grid.get(2, 1), Some(&'x'));
+        assert_eq!(grid.get(0, 2), None);
+        assert!(grid.in_bounds(2, 1));
+        assert!(!grid.in_bounds(-1, 0) && !grid.in_bounds(0, 2));
+        grid[(0, 0)] = 'o';
+        assert_eq!(grid[(0, 0)], 'o');
+
+        let pos = Pos::new(2, 1);
+        assert_eq!(grid.at(pos), Some(&'x'));
+        assert_eq!(grid.at(pos + Pos::new(1, 0)), None);
+        assert_eq!(grid.at(Pos::new(-1, 0)), None);
+        grid[pos] = 'y';
+        assert_eq!(grid[(2, 1)], 'y');
+        assert_eq!(grid.positions().nth(4), Some(Pos::new(1, 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "(3, 0) is outside the 3x2 grid")]
+    fn index_checks_columns() {
+        // (3, 0) would be (0, 1) in the flat storage.
+        let grid = Grid::new(3, 2, 0);
+        let _ = grid[(3, 0)];
+    }
+
+    #[test]
+    fn rows_and_columns() {
+        let grid = Grid::from_fn(3, 2, |x, y| x + 10 * y);
+        assert_eq!(grid.rows().collect::<Vec<_>>(), [[0, 1, 2], [10, 11, 12]]);
+        assert_eq!(grid.row(1), Some(&[10, 11, 12][..]));
+        assert_eq!(grid.row(2), None);
+        assert_eq!(grid.column(1).copied().collect::<Vec<_>>(), [1, 11]);
+        assert_eq!(grid.column(3).count(), 0);
+        assert_eq!(grid.columns().count(), 3);
+        assert_eq!(grid.iter().nth(4), Some((1, 1, &11)));
+        assert_eq!(grid.map(|v| v * 2)[(2, 1)], 24);
+
+        let rows = grid.clone().into_rows();
+        assert_eq!(Grid::from_rows(rows), Ok(grid));
+        assert_eq!(Grid::from_rows(vec![vec![1, 2], vec![3]]), Err(RaggedRows));
+    }
+
+    /// A board from rows separated by whitespace.
+    pub(crate) fn board(rows: &str) -> Grid<char> {
+        Grid::from_rows(
+            rows.split_whitespace()
+                .map(|row| row.chars().collect())
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn lines() {
+        let grid = board("x..o .x.o ..xo o..o");
+        let is = |c| move |cell: &char| *cell == c;
+
+        // Diagonal, found from any cell on it, not only the ends.
+        let diagonal = vec![Pos::new(0, 0), Pos::new(1, 1), Pos::new(2, 2)];
+        assert_eq!(find_line(&grid, Pos::new(1, 1), 3, is('x')), Some(diagonal));
+        assert_eq!(find_line(&grid, Pos::new(1, 1), 4, is('x')), None);
+        // Along the edge of the board.
+        assert_eq!(
+            find_line(&grid, Pos::new(3, 3), 4, is('o')).map(|line| line.len()),
+            Some(4)
+        );
+        // Cells with gaps between them are no line.
+        assert_eq!(
+            longest_line(&grid, Pos::new(0, 3), is('o')),
+            vec![Pos::new(0, 3)]
+        );
+        assert_eq!(find_line(&grid, Pos::new(1, 0), 1, is('x')), None);
+        assert_eq!(longest_line(&grid, Pos::new(-1, 0), is('x')), vec![]);
+
+        let grid = board("...o ..o. .o.. o...");
+        assert_eq!(
+            longest_line(&grid, Pos::new(2, 1), |c| *c == 'o'),
+            vec![
+                Pos::new(0, 3),
+                Pos::new(1, 2),
+                Pos::new(2, 1),
+                Pos::new(3, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn neighbors_at_edges() {
+        let grid = Grid::new(3, 3, ());
+        let corner = Pos::new(0, 0);
+        let neighbors = |adjacency, boundary| -> Vec<Pos> {
+            grid.neighbors(corner, adjacency, boundary).collect()
+        };
+        assert_eq!(
+            neighbors(Adjacency::Orthogonal, Boundary::Skip),
+            [Pos::new(1, 0), Pos::new(0, 1)]
+        );
+        assert_eq!(
+            neighbors(Adjacency::Orthogonal, Boundary::Wrap),
+            [
+                Pos::new(0, 2),
+                Pos::new(1, 0),
+                Pos::new(0, 1),
+                Pos::new(2, 0)
+            ]
+        );
+        assert_eq!(
+            neighbors(Adjacency::Orthogonal, Boundary::Clamp),
+            [corner, Pos::new(1, 0), Pos::new(0, 1), corner]
+        );
+        assert_eq!(neighbors(Adjacency::Diagonal, Boundary::Skip).len(), 3);
+        assert_eq!(
+            neighbors(Adjacency::Diagonal, Boundary::Wrap)[7],
+            Pos::new(2, 2)
+        );
+        assert_eq!(
+            grid.neighbors(Pos::new(1, 1), Adjacency::Hex, Boundary::Skip)
+                .count(),
+            6
+        );
+        assert_eq!(neighbors(Adjacency::Hex, Boundary::Skip).len(), 2);
+    }
+
+    #[test]
+    fn wraps_around_edges() {
+        let mut grid = board("abc def");
+        assert_eq!(grid.wrap(Pos::new(-1, 2)), Pos::new(2, 0));
+        assert_eq!(grid.wrap(Pos::new(7, -3)), Pos::new(1, 1));
+        assert_eq!(
+            grid.step_wrapped(Pos::new(2, 1), Direction::East),
+            Pos::new(0, 1)
+        );
+        assert_eq!(
+            grid.step_wrapped(Pos::new(0, 0), Direction::NorthWest),
+            Pos::new(2, 1)
+        );
+        assert_eq!(*grid.at_wrapped(Pos::new(-3, -1)), 'd');
+        *grid.at_wrapped_mut(Pos::new(3, 2)) = 'x';
+        assert_eq!(grid[(0, 0)], 'x');
+    }
+}
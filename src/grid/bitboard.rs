@@ -0,0 +1,320 @@
+//! Boards of at most 64 or 128 cells packed into one integer, one bit per
+//! cell, so that bots can test, count and move whole sets of cells in a few
+//! instructions. Othello move generation, for one, is a handful of shifts
+//! and masks per direction.
+
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not, Shl, Shr};
+
+use super::Grid;
+use crate::pos::{Direction, Pos};
+
+/// The integers a `BitBoard` can be packed into.
+pub trait Bits:
+    Copy
+    + Eq
+    + fmt::Debug
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+    + Shl<u32, Output = Self>
+    + Shr<u32, Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const BITS: u32;
+
+    fn count_ones(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+}
+
+macro_rules! impl_bits {
+    ($($t:ty),*) => {$(
+        impl Bits for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            const BITS: u32 = <$t>::BITS;
+
+            fn count_ones(self) -> u32 {
+                <$t>::count_ones(self)
+            }
+
+            fn trailing_zeros(self) -> u32 {
+                <$t>::trailing_zeros(self)
+            }
+        }
+    )*};
+}
+
+impl_bits!(u64, u128);
+
+/// A set of cells on a `width` by `height` board, cell `(x, y)` being bit
+/// `y * width + x`. Combine boards of the same size with `&`, `|`, `^` and
+/// `!`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BitBoard<B = u64> {
+    width: u32,
+    height: u32,
+    bits: B,
+}
+
+impl<B: Bits> BitBoard<B> {
+    /// An empty board. Panics when the board has more cells than `B` has
+    /// bits.
+    pub fn new(width: usize, height: usize) -> Self {
+        assert!(
+            width * height <= B::BITS as usize,
+            "a {width}x{height} board doesn't fit in {} bits",
+            B::BITS
+        );
+        Self {
+            width: width as u32,
+            height: height as u32,
+            bits: B::ZERO,
+        }
+    }
+
+    /// A board with every cell set.
+    pub fn full(width: usize, height: usize) -> Self {
+        let board = Self::new(width, height);
+        Self {
+            bits: board.mask(),
+            ..board
+        }
+    }
+
+    /// The cells of `grid` matching `predicate`.
+    pub fn from_grid<T>(grid: &Grid<T>, predicate: impl Fn(&T) -> bool) -> Self {
+        let mut board = Self::new(grid.width(), grid.height());
+        for pos in grid.positions() {
+            board.set(pos, predicate(&grid[pos]));
+        }
+        board
+    }
+
+    pub fn to_grid(&self) -> Grid<bool> {
+        Grid::from_fn(self.width(), self.height(), |x, y| {
+            self.test(Pos::new(x as i32, y as i32))
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width as usize
+    }
+
+    pub fn height(&self) -> usize {
+        self.height as usize
+    }
+
+    /// The packed cells. Bits past the last cell are always zero.
+    pub fn bits(&self) -> B {
+        self.bits
+    }
+
+    fn mask(&self) -> B {
+        let cells = self.width * self.height;
+        match cells {
+            0 => B::ZERO,
+            _ => !B::ZERO >> (B::BITS - cells),
+        }
+    }
+
+    fn column_mask(&self, x: u32) -> B {
+        (0..self.height).fold(B::ZERO, |mask, y| mask | B::ONE << (y * self.width + x))
+    }
+
+    fn bit(&self, pos: Pos) -> Option<B> {
+        let on_board =
+            (0..self.width as i32).contains(&pos.x) && (0..self.height as i32).contains(&pos.y);
+        on_board.then(|| B::ONE << (pos.y as u32 * self.width + pos.x as u32))
+    }
+
+    /// Whether the cell is set, false off the board.
+    pub fn test(&self, pos: Pos) -> bool {
+        self.bit(pos).is_some_and(|bit| self.bits & bit != B::ZERO)
+    }
+
+    /// Sets or clears a cell. Panics when `pos` is off the board.
+    pub fn set(&mut self, pos: Pos, value: bool) {
+        let Some(bit) = self.bit(pos) else {
+            panic!(
+                "({}, {}) is outside the {}x{} board",
+                pos.x, pos.y, self.width, self.height
+            );
+        };
+        self.bits = match value {
+            true => self.bits | bit,
+            false => self.bits & !bit,
+        };
+    }
+
+    pub fn count(&self) -> usize {
+        self.bits.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == B::ZERO
+    }
+
+    /// Every set cell moved one step towards `direction`. Cells stepping off
+    /// the board are dropped rather than wrapping to the other side.
+    pub fn shift(&self, direction: Direction) -> Self {
+        if self.width == 0 || self.height == 0 {
+            return *self;
+        }
+        let offset = direction.offset();
+        let mut bits = self.bits;
+        // Clear the column that would wrap onto the other edge first.
+        match offset.x {
+            1 => bits = bits & !self.column_mask(self.width - 1),
+            -1 => bits = bits & !self.column_mask(0),
+            _ => {}
+        }
+        let step = offset.y * self.width as i32 + offset.x;
+        bits = match step.signum() {
+            1 => bits << step as u32,
+            -1 => bits >> step.unsigned_abs(),
+            _ => bits,
+        };
+        Self {
+            bits: bits & self.mask(),
+            ..*self
+        }
+    }
+
+    /// The set cells, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = Pos> {
+        let width = self.width;
+        let mut bits = self.bits;
+        std::iter::from_fn(move || {
+            if bits == B::ZERO {
+                return None;
+            }
+            let i = bits.trailing_zeros();
+            bits = bits & !(B::ONE << i);
+            Some(Pos::new((i % width) as i32, (i / width) as i32))
+        })
+    }
+
+    fn with_bits(&self, other: &Self, bits: B) -> Self {
+        debug_assert_eq!(
+            (self.width, self.height),
+            (other.width, other.height),
+            "boards of different sizes"
+        );
+        Self { bits, ..*self }
+    }
+}
+
+impl<B: Bits> BitAnd for BitBoard<B> {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        self.with_bits(&rhs, self.bits & rhs.bits)
+    }
+}
+
+impl<B: Bits> BitOr for BitBoard<B> {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.with_bits(&rhs, self.bits | rhs.bits)
+    }
+}
+
+impl<B: Bits> BitXor for BitBoard<B> {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self {
+        self.with_bits(&rhs, self.bits ^ rhs.bits)
+    }
+}
+
+impl<B: Bits> BitAndAssign for BitBoard<B> {
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = *self & rhs;
+    }
+}
+
+impl<B: Bits> BitOrAssign for BitBoard<B> {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+/// The cells that aren't set.
+impl<B: Bits> Not for BitBoard<B> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self {
+            bits: !self.bits & self.mask(),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::test::board;
+
+    #[test]
+    fn set_count_and_grid() {
+        let grid = board("x..x ..x. x...");
+        let bits: BitBoard = BitBoard::from_grid(&grid, |c| *c == 'x');
+        assert_eq!(bits.count(), 4);
+        assert!(bits.test(Pos::new(2, 1)));
+        assert!(!bits.test(Pos::new(1, 1)) && !bits.test(Pos::new(4, 0)));
+        assert_eq!(bits.to_grid(), grid.map(|c| *c == 'x'));
+        assert_eq!(
+            bits.positions().collect::<Vec<_>>(),
+            [
+                Pos::new(0, 0),
+                Pos::new(3, 0),
+                Pos::new(2, 1),
+                Pos::new(0, 2)
+            ]
+        );
+        assert_eq!((!bits).count(), 8);
+        assert_eq!(BitBoard::<u64>::full(8, 8).count(), 64);
+        assert_eq!(BitBoard::<u128>::full(11, 11).count(), 121);
+    }
+
+    #[test]
+    fn shifts_stop_at_edges() {
+        let mut bits = BitBoard::<u128>::new(10, 10);
+        bits.set(Pos::new(9, 0), true);
+        bits.set(Pos::new(0, 9), true);
+        assert_eq!(
+            bits.shift(Direction::East).positions().collect::<Vec<_>>(),
+            [Pos::new(1, 9)]
+        );
+        assert!(bits.shift(Direction::NorthWest).is_empty());
+        assert_eq!(
+            bits.shift(Direction::West).positions().collect::<Vec<_>>(),
+            [Pos::new(8, 0)]
+        );
+        assert_eq!(
+            bits.shift(Direction::SouthWest)
+                .positions()
+                .collect::<Vec<_>>(),
+            [Pos::new(8, 1)]
+        );
+        assert_eq!(
+            bits.shift(Direction::North).positions().collect::<Vec<_>>(),
+            [Pos::new(0, 8)]
+        );
+        let full = BitBoard::<u64>::full(8, 8);
+        assert_eq!(full.shift(Direction::SouthEast).count(), 49);
+        assert_eq!(full.shift(Direction::West) & full.shift(Direction::East), {
+            let mut middle = BitBoard::from_grid(&Grid::new(8, 8, true), |_| true);
+            for y in 0..8 {
+                middle.set(Pos::new(0, y), false);
+                middle.set(Pos::new(7, y), false);
+            }
+            middle
+        });
+    }
+}
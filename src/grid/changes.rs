@@ -0,0 +1,170 @@
+//! Boards that keep track of which cells changed, so a game broadcasting
+//! every tick can send what changed since the last broadcast without
+//! comparing the whole board to a copy of the old one.
+
+use serde::{Deserialize, Serialize};
+
+use crate::pos::Pos;
+
+use super::Grid;
+
+/// Cells that changed, each with its new value, in the order they first
+/// changed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridDelta<T> {
+    pub cells: Vec<(Pos, T)>,
+}
+
+impl<T> GridDelta<T> {
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
+
+impl<T: Clone> GridDelta<T> {
+    /// Brings a copy of the board as it was up to date. Cells off `grid`
+    /// are skipped.
+    pub fn apply(&self, grid: &mut Grid<T>) {
+        for (pos, value) in &self.cells {
+            if let Some(cell) = grid.at_mut(*pos) {
+                *cell = value.clone();
+            }
+        }
+    }
+}
+
+/// A grid that remembers which cells were set since changes were last
+/// taken. Taking them costs as much as the number changed, not the size of
+/// the board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrackedGrid<T> {
+    grid: Grid<T>,
+    dirty: Grid<bool>,
+    changed: Vec<Pos>,
+}
+
+impl<T> TrackedGrid<T> {
+    /// Starts with no changes: whoever receives the deltas is assumed to
+    /// have `grid` already.
+    pub fn new(grid: Grid<T>) -> Self {
+        let dirty = Grid::new(grid.width(), grid.height(), false);
+        Self {
+            grid,
+            dirty,
+            changed: vec![],
+        }
+    }
+
+    pub fn grid(&self) -> &Grid<T> {
+        &self.grid
+    }
+
+    pub fn into_grid(self) -> Grid<T> {
+        self.grid
+    }
+
+    /// Changes `pos` in place, marking it changed whatever `f` does. Returns
+    /// `None` when off the board.
+    pub fn update<R>(&mut self, pos: Pos, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let result = f(self.grid.at_mut(pos)?);
+        self.mark(pos);
+        Some(result)
+    }
+
+    fn mark(&mut self, pos: Pos) {
+        if !std::mem::replace(&mut self.dirty[pos], true) {
+            self.changed.push(pos);
+        }
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.changed.is_empty()
+    }
+
+    pub fn changed(&self) -> &[Pos] {
+        &self.changed
+    }
+
+    /// Forgets the changes, as when the whole board is sent anyway.
+    pub fn clear_changes(&mut self) {
+        for pos in self.changed.drain(..) {
+            self.dirty[pos] = false;
+        }
+    }
+}
+
+impl<T: PartialEq> TrackedGrid<T> {
+    /// Replaces the cell at `pos`, returning what was there, or `None` when
+    /// off the board. Setting a cell to what it already holds isn't a
+    /// change.
+    pub fn set(&mut self, pos: Pos, value: T) -> Option<T> {
+        let cell = self.grid.at_mut(pos)?;
+        if *cell == value {
+            return Some(value);
+        }
+        let old = std::mem::replace(cell, value);
+        self.mark(pos);
+        Some(old)
+    }
+}
+
+impl<T: Clone> TrackedGrid<T> {
+    /// The cells changed since the last time, and starts over.
+    pub fn take_changes(&mut self) -> GridDelta<T> {
+        let cells = self
+            .changed
+            .iter()
+            .map(|pos| (*pos, self.grid[*pos].clone()))
+            .collect();
+        self.clear_changes();
+        GridDelta { cells }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn takes_each_change_once() {
+        let mut board = TrackedGrid::new(Grid::new(100, 100, 0));
+        assert_eq!(board.set(Pos::new(3, 4), 1), Some(0));
+        assert_eq!(board.set(Pos::new(99, 0), 2), Some(0));
+        assert_eq!(board.set(Pos::new(3, 4), 5), Some(1));
+        assert_eq!(board.set(Pos::new(7, 7), 0), Some(0));
+        assert_eq!(board.set(Pos::new(100, 0), 1), None);
+        assert_eq!(board.update(Pos::new(0, 0), |cell| *cell += 1), Some(()));
+
+        let delta = board.take_changes();
+        assert_eq!(
+            delta.cells,
+            [
+                (Pos::new(3, 4), 5),
+                (Pos::new(99, 0), 2),
+                (Pos::new(0, 0), 1)
+            ]
+        );
+        assert!(!board.has_changes());
+        assert!(board.take_changes().is_empty());
+
+        board.set(Pos::new(3, 4), 6);
+        assert_eq!(board.take_changes().cells, [(Pos::new(3, 4), 6)]);
+    }
+
+    #[test]
+    fn deltas_catch_up_copies() {
+        let mut board = TrackedGrid::new(Grid::new(5, 5, '.'));
+        let mut copy = board.grid().clone();
+        for (i, pos) in [Pos::new(1, 1), Pos::new(2, 3), Pos::new(1, 1)]
+            .into_iter()
+            .enumerate()
+        {
+            board.set(pos, (b'a' + i as u8) as char);
+            if i == 1 {
+                board.take_changes().apply(&mut copy);
+            }
+        }
+        board.take_changes().apply(&mut copy);
+        assert_eq!(&copy, board.grid());
+    }
+}
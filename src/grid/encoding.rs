@@ -0,0 +1,209 @@
+//! Boards as short strings, in the spirit of chess FEN: rows top to bottom
+//! separated by `/`, each row a run-length encoded string of cell symbols
+//! (`3.x` for three empty cells and an `x`), optionally followed by
+//! space-separated metadata fields such as whose turn it is.
+//!
+//! `..x/2o.` is a 3x2 board; `..x/2o. o 12` is the same board with two
+//! metadata fields.
+
+use super::Grid;
+
+/// How a cell is written. Symbols can't be digits, `/` or whitespace.
+pub trait CellSymbol: Sized {
+    fn to_symbol(&self) -> char;
+    fn from_symbol(symbol: char) -> Option<Self>;
+}
+
+impl CellSymbol for char {
+    fn to_symbol(&self) -> char {
+        *self
+    }
+
+    fn from_symbol(symbol: char) -> Option<Self> {
+        Some(symbol)
+    }
+}
+
+/// Empty cells are written as `.`, which `T` mustn't use itself.
+impl<T: CellSymbol> CellSymbol for Option<T> {
+    fn to_symbol(&self) -> char {
+        self.as_ref().map_or('.', T::to_symbol)
+    }
+
+    fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '.' => Some(None),
+            symbol => T::from_symbol(symbol).map(Some),
+        }
+    }
+}
+
+/// The widest row `decode` accepts, so that a short string can't ask for
+/// more cells than fit in memory.
+pub const MAX_ROW_WIDTH: usize = 1 << 16;
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeError {
+    #[error("invalid cell symbol {0:?}")]
+    InvalidSymbol(char),
+    /// A count of zero, one not followed by a symbol, or one making the row
+    /// wider than `MAX_ROW_WIDTH`.
+    #[error("invalid cell count")]
+    InvalidCount,
+    /// The given row (from 0) has a different width than the first.
+    #[error("row {0} has a different width than the first")]
+    RaggedRow(usize),
+}
+
+pub fn encode<T: CellSymbol>(grid: &Grid<T>) -> String {
+    let rows: Vec<String> = grid.rows().map(encode_row).collect();
+    rows.join("/")
+}
+
+fn encode_row<T: CellSymbol>(row: &[T]) -> String {
+    let mut out = String::new();
+    let mut symbols = row.iter().map(T::to_symbol).peekable();
+    while let Some(symbol) = symbols.next() {
+        let mut count = 1;
+        while symbols.next_if_eq(&symbol).is_some() {
+            count += 1;
+        }
+        if count > 1 {
+            out.push_str(&count.to_string());
+        }
+        out.push(symbol);
+    }
+    out
+}
+
+/// The board followed by `metadata` fields, which can't contain spaces.
+pub fn encode_with_metadata<T: CellSymbol>(grid: &Grid<T>, metadata: &[&str]) -> String {
+    let mut out = encode(grid);
+    for field in metadata {
+        debug_assert!(!field.contains(char::is_whitespace));
+        out.push(' ');
+        out.push_str(field);
+    }
+    out
+}
+
+/// Decodes a board, ignoring any metadata.
+pub fn decode<T: CellSymbol>(code: &str) -> Result<Grid<T>, DecodeError> {
+    decode_with_metadata(code).map(|(grid, _)| grid)
+}
+
+pub fn decode_with_metadata<T: CellSymbol>(
+    code: &str,
+) -> Result<(Grid<T>, Vec<String>), DecodeError> {
+    let mut fields = code.split_whitespace();
+    let board = fields.next().unwrap_or("");
+    let metadata = fields.map(str::to_string).collect();
+
+    let mut rows = vec![];
+    for (i, row) in board.split('/').enumerate().filter(|_| !board.is_empty()) {
+        let row = decode_row(row)?;
+        if rows
+            .first()
+            .is_some_and(|first: &Vec<T>| first.len() != row.len())
+        {
+            return Err(DecodeError::RaggedRow(i));
+        }
+        rows.push(row);
+    }
+    let grid = Grid::from_rows(rows).expect("row widths were checked");
+    Ok((grid, metadata))
+}
+
+fn decode_row<T: CellSymbol>(row: &str) -> Result<Vec<T>, DecodeError> {
+    let mut cells = vec![];
+    let mut count: Option<usize> = None;
+    for c in row.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            let n = count.unwrap_or(0) * 10 + digit as usize;
+            if n > MAX_ROW_WIDTH {
+                return Err(DecodeError::InvalidCount);
+            }
+            count = Some(n);
+            continue;
+        }
+        let n = match count.take() {
+            Some(0) => return Err(DecodeError::InvalidCount),
+            Some(n) if cells.len() + n > MAX_ROW_WIDTH => return Err(DecodeError::InvalidCount),
+            Some(n) => n,
+            None if cells.len() == MAX_ROW_WIDTH => return Err(DecodeError::InvalidCount),
+            None => 1,
+        };
+        for _ in 0..n {
+            cells.push(T::from_symbol(c).ok_or(DecodeError::InvalidSymbol(c))?);
+        }
+    }
+    if count.is_some() {
+        return Err(DecodeError::InvalidCount);
+    }
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::test::board;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Stone {
+        Black,
+        White,
+    }
+
+    impl CellSymbol for Stone {
+        fn to_symbol(&self) -> char {
+            match self {
+                Stone::Black => 'b',
+                Stone::White => 'w',
+            }
+        }
+
+        fn from_symbol(symbol: char) -> Option<Self> {
+            match symbol {
+                'b' => Some(Stone::Black),
+                'w' => Some(Stone::White),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let grid = board("..x.......... oox..........");
+        let code = encode(&grid);
+        assert_eq!(code, "2.x10./2ox10.");
+        assert_eq!(decode::<char>(&code), Ok(grid));
+
+        let mut stones: Grid<Option<Stone>> = Grid::new(3, 2, None);
+        stones[(1, 1)] = Some(Stone::White);
+        stones[(2, 1)] = Some(Stone::Black);
+        let code = encode_with_metadata(&stones, &["b", "7"]);
+        assert_eq!(code, "3./.wb b 7");
+        assert_eq!(
+            decode_with_metadata(&code),
+            Ok((stones, vec!["b".to_string(), "7".to_string()]))
+        );
+        assert_eq!(decode::<char>("").map(|g| g.width()), Ok(0));
+    }
+
+    #[test]
+    fn rejects_bad_codes() {
+        assert_eq!(
+            decode::<Option<Stone>>("2.x"),
+            Err(DecodeError::InvalidSymbol('x'))
+        );
+        assert_eq!(decode::<char>("0."), Err(DecodeError::InvalidCount));
+        assert_eq!(decode::<char>("2.3"), Err(DecodeError::InvalidCount));
+        assert_eq!(
+            decode::<char>("99999999999999999999999."),
+            Err(DecodeError::InvalidCount)
+        );
+        assert_eq!(decode::<char>("65536.x"), Err(DecodeError::InvalidCount));
+        assert_eq!(decode::<char>("65536.").unwrap().width(), MAX_ROW_WIDTH);
+        assert_eq!(decode::<char>("3./2./3."), Err(DecodeError::RaggedRow(1)));
+    }
+}
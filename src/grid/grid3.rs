@@ -0,0 +1,256 @@
+//! A stack of equally sized `Grid` layers, for games played in three
+//! dimensions. Layer `z` 0 is the bottom one.
+
+use std::ops::{Index, IndexMut};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Adjacency, Boundary, Grid, RaggedRows};
+use crate::pos::{Pos, Pos3};
+
+/// Serialized as a list of layers, each a list of rows.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(
+    into = "Vec<Vec<Vec<T>>>",
+    try_from = "Vec<Vec<Vec<T>>>",
+    bound(
+        serialize = "T: Clone + Serialize",
+        deserialize = "T: Deserialize<'de>"
+    )
+)]
+pub struct Grid3<T> {
+    width: usize,
+    height: usize,
+    layers: Vec<Grid<T>>,
+}
+
+impl<T> Grid3<T> {
+    pub fn new(width: usize, height: usize, depth: usize, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            width,
+            height,
+            layers: vec![Grid::new(width, height, fill); depth],
+        }
+    }
+
+    pub fn from_fn(
+        width: usize,
+        height: usize,
+        depth: usize,
+        mut f: impl FnMut(usize, usize, usize) -> T,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            layers: (0..depth)
+                .map(|z| Grid::from_fn(width, height, |x, y| f(x, y, z)))
+                .collect(),
+        }
+    }
+
+    /// Fails when the layers or their rows differ in size.
+    pub fn from_layers(layers: Vec<Grid<T>>) -> Result<Self, RaggedRows> {
+        let (width, height) = layers
+            .first()
+            .map_or((0, 0), |layer| (layer.width(), layer.height()));
+        if layers
+            .iter()
+            .any(|layer| (layer.width(), layer.height()) != (width, height))
+        {
+            return Err(RaggedRows);
+        }
+        Ok(Self {
+            width,
+            height,
+            layers,
+        })
+    }
+
+    pub fn into_layers(self) -> Vec<Grid<T>> {
+        self.layers
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn depth(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn contains(&self, pos: Pos3) -> bool {
+        pos.z >= 0
+            && (pos.z as usize) < self.depth()
+            && self.layers[pos.z as usize].contains(pos.xy())
+    }
+
+    pub fn at(&self, pos: Pos3) -> Option<&T> {
+        self.layer(pos.z.try_into().ok()?)?.at(pos.xy())
+    }
+
+    pub fn at_mut(&mut self, pos: Pos3) -> Option<&mut T> {
+        self.layer_mut(pos.z.try_into().ok()?)?.at_mut(pos.xy())
+    }
+
+    pub fn layer(&self, z: usize) -> Option<&Grid<T>> {
+        self.layers.get(z)
+    }
+
+    pub fn layer_mut(&mut self, z: usize) -> Option<&mut Grid<T>> {
+        self.layers.get_mut(z)
+    }
+
+    /// The layers from the bottom up.
+    pub fn layers(&self) -> &[Grid<T>] {
+        &self.layers
+    }
+
+    /// Every position, layer by layer from the bottom and row by row within
+    /// a layer.
+    pub fn positions(&self) -> impl Iterator<Item = Pos3> + '_ {
+        self.layers.iter().enumerate().flat_map(|(z, layer)| {
+            layer
+                .positions()
+                .map(move |pos| Pos3::new(pos.x, pos.y, z as i32))
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Pos3, &T)> + '_ {
+        self.positions()
+            .zip(self.layers.iter().flat_map(Grid::cells))
+    }
+
+    /// The neighbors of `pos`: those in its own layer as for
+    /// `Grid::neighbors`, then those in the layer below and the layer above.
+    /// `Orthogonal` and `Hex` add only the cells straight below and above,
+    /// `Diagonal` adds those and their eight neighbors in each layer, for
+    /// 26 in all.
+    pub fn neighbors(
+        &self,
+        pos: Pos3,
+        adjacency: Adjacency,
+        boundary: Boundary,
+    ) -> impl Iterator<Item = Pos3> + '_ {
+        let in_layer = adjacency.offsets();
+        let across = match adjacency {
+            Adjacency::Diagonal => in_layer,
+            _ => &[],
+        };
+        let offsets = in_layer
+            .iter()
+            .map(|offset| Pos3::new(offset.x, offset.y, 0))
+            .chain([-1, 1].into_iter().flat_map(move |z| {
+                std::iter::once(Pos::ORIGIN)
+                    .chain(across.iter().copied())
+                    .map(move |offset| Pos3::new(offset.x, offset.y, z))
+            }));
+        offsets.filter_map(move |offset| {
+            let next = pos + offset;
+            Some(Pos3::new(
+                boundary.resolve(next.x, self.width)?,
+                boundary.resolve(next.y, self.height)?,
+                boundary.resolve(next.z, self.depth())?,
+            ))
+        })
+    }
+
+    pub fn map<U>(&self, mut f: impl FnMut(&T) -> U) -> Grid3<U> {
+        Grid3 {
+            width: self.width,
+            height: self.height,
+            layers: self.layers.iter().map(|layer| layer.map(&mut f)).collect(),
+        }
+    }
+}
+
+impl<T> Index<Pos3> for Grid3<T> {
+    type Output = T;
+
+    fn index(&self, pos: Pos3) -> &T {
+        match self.at(pos) {
+            Some(cell) => cell,
+            None => panic!(
+                "{pos:?} is outside the {}x{}x{} grid",
+                self.width,
+                self.height,
+                self.depth()
+            ),
+        }
+    }
+}
+
+impl<T> IndexMut<Pos3> for Grid3<T> {
+    fn index_mut(&mut self, pos: Pos3) -> &mut T {
+        let (width, height, depth) = (self.width, self.height, self.depth());
+        match self.at_mut(pos) {
+            Some(cell) => cell,
+            None => panic!("{pos:?} is outside the {width}x{height}x{depth} grid"),
+        }
+    }
+}
+
+impl<T> From<Grid3<T>> for Vec<Vec<Vec<T>>> {
+    fn from(grid: Grid3<T>) -> Self {
+        grid.layers.into_iter().map(Grid::into_rows).collect()
+    }
+}
+
+impl<T> TryFrom<Vec<Vec<Vec<T>>>> for Grid3<T> {
+    type Error = RaggedRows;
+
+    fn try_from(layers: Vec<Vec<Vec<T>>>) -> Result<Self, RaggedRows> {
+        let layers = layers
+            .into_iter()
+            .map(Grid::from_rows)
+            .collect::<Result<_, _>>()?;
+        Self::from_layers(layers)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn layers_and_positions() {
+        let mut grid = Grid3::from_fn(3, 2, 4, |x, y, z| x + 10 * y + 100 * z);
+        assert_eq!((grid.width(), grid.height(), grid.depth()), (3, 2, 4));
+        assert_eq!(grid[Pos3::new(2, 1, 3)], 312);
+        assert_eq!(grid.at(Pos3::new(0, 0, 4)), None);
+        assert_eq!(grid.at(Pos3::new(0, 0, -1)), None);
+        grid[Pos3::new(1, 0, 1)] = 0;
+        assert_eq!(grid.layer(1).unwrap()[(1, 0)], 0);
+        assert_eq!(grid.positions().nth(7), Some(Pos3::new(1, 0, 1)));
+        assert_eq!(grid.iter().nth(7), Some((Pos3::new(1, 0, 1), &0)));
+        assert_eq!(grid.map(|n| n % 10)[Pos3::new(2, 1, 3)], 2);
+
+        let rows: Vec<Vec<Vec<usize>>> = grid.clone().into();
+        assert_eq!(Grid3::try_from(rows), Ok(grid));
+        assert_eq!(
+            Grid3::from_layers(vec![Grid::new(2, 2, 0), Grid::new(2, 1, 0)]),
+            Err(RaggedRows)
+        );
+    }
+
+    #[test]
+    fn neighbors() {
+        let grid = Grid3::new(3, 3, 3, ());
+        let count = |pos, adjacency| grid.neighbors(pos, adjacency, Boundary::Skip).count();
+        let center = Pos3::new(1, 1, 1);
+        assert_eq!(count(center, Adjacency::Orthogonal), 6);
+        assert_eq!(count(center, Adjacency::Diagonal), 26);
+        assert_eq!(count(center, Adjacency::Hex), 8);
+        assert_eq!(count(Pos3::ORIGIN, Adjacency::Diagonal), 7);
+        let wrapped: Vec<_> = grid
+            .neighbors(Pos3::ORIGIN, Adjacency::Orthogonal, Boundary::Wrap)
+            .collect();
+        assert_eq!(wrapped[4], Pos3::new(0, 0, 2));
+    }
+}
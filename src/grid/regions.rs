@@ -0,0 +1,214 @@
+//! Connected areas of a `Grid`: flood fill from a cell, labeling all regions
+//! at once, and checking that a structure is in one piece.
+
+use std::collections::VecDeque;
+
+use super::Grid;
+use crate::pos::Pos;
+
+/// Which cells count as touching.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Orthogonal neighbors only.
+    #[default]
+    Four,
+    /// Diagonal neighbors too.
+    Eight,
+}
+
+impl Connectivity {
+    pub fn neighbors(self, pos: Pos) -> Vec<Pos> {
+        match self {
+            Connectivity::Four => pos.neighbors4().to_vec(),
+            Connectivity::Eight => pos.neighbors8().to_vec(),
+        }
+    }
+}
+
+/// The cells reachable from `start` through cells satisfying `include`,
+/// nearest first. Empty if `start` itself isn't included.
+pub fn flood_fill<T>(
+    grid: &Grid<T>,
+    start: Pos,
+    connectivity: Connectivity,
+    include: impl Fn(&T) -> bool,
+) -> Vec<Pos> {
+    if !grid.at(start).is_some_and(&include) {
+        return vec![];
+    }
+    let mut seen = grid.map(|_| false);
+    seen[start] = true;
+    let mut queue = VecDeque::from([start]);
+    let mut region = vec![];
+    while let Some(pos) = queue.pop_front() {
+        region.push(pos);
+        for next in connectivity.neighbors(pos) {
+            if grid.at(next).is_some_and(&include) && !seen[next] {
+                seen[next] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    region
+}
+
+/// Sets the region of cells equal to the one at `start` to `value`, like a
+/// paint bucket. Returns the number of cells changed.
+pub fn fill<T: PartialEq + Clone>(
+    grid: &mut Grid<T>,
+    start: Pos,
+    connectivity: Connectivity,
+    value: T,
+) -> usize {
+    let Some(target) = grid.at(start).cloned() else {
+        return 0;
+    };
+    if target == value {
+        return 0;
+    }
+    let region = flood_fill(grid, start, connectivity, |cell| *cell == target);
+    for pos in &region {
+        grid[*pos] = value.clone();
+    }
+    region.len()
+}
+
+/// Every region of a grid, numbered from 0 in the order their first cell
+/// comes row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Regions {
+    labels: Grid<Option<usize>>,
+    areas: Vec<usize>,
+}
+
+impl Regions {
+    /// Groups touching cells with equal keys. Cells keyed `None` belong to
+    /// no region.
+    pub fn label<T, K: PartialEq>(
+        grid: &Grid<T>,
+        connectivity: Connectivity,
+        key: impl Fn(&T) -> Option<K>,
+    ) -> Self {
+        let keys = grid.map(key);
+        let mut labels = grid.map(|_| None);
+        let mut areas = vec![];
+        for start in grid.positions() {
+            let Some(start_key) = &keys[start] else {
+                continue;
+            };
+            if labels[start].is_some() {
+                continue;
+            }
+            let region = flood_fill(&keys, start, connectivity, |k| {
+                k.as_ref() == Some(start_key)
+            });
+            for pos in &region {
+                labels[*pos] = Some(areas.len());
+            }
+            areas.push(region.len());
+        }
+        Self { labels, areas }
+    }
+
+    pub fn len(&self) -> usize {
+        self.areas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.areas.is_empty()
+    }
+
+    /// The region `pos` is in.
+    pub fn region_at(&self, pos: Pos) -> Option<usize> {
+        self.labels.at(pos).copied().flatten()
+    }
+
+    /// Number of cells in `region`.
+    pub fn area(&self, region: usize) -> usize {
+        self.areas.get(region).copied().unwrap_or(0)
+    }
+
+    pub fn areas(&self) -> &[usize] {
+        &self.areas
+    }
+
+    pub fn positions(&self, region: usize) -> impl Iterator<Item = Pos> + '_ {
+        self.labels
+            .positions()
+            .filter(move |pos| self.labels[*pos] == Some(region))
+    }
+
+    pub fn labels(&self) -> &Grid<Option<usize>> {
+        &self.labels
+    }
+}
+
+/// Whether the cells satisfying `include` form a single piece. No cells at
+/// all counts as connected.
+pub fn is_connected<T>(
+    grid: &Grid<T>,
+    connectivity: Connectivity,
+    include: impl Fn(&T) -> bool,
+) -> bool {
+    let Some(start) = grid.positions().find(|pos| include(&grid[*pos])) else {
+        return true;
+    };
+    let total = grid.cells().iter().filter(|cell| include(cell)).count();
+    flood_fill(grid, start, connectivity, include).len() == total
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::test::board;
+
+    #[test]
+    fn flood_fill_and_paint() {
+        let mut grid = board("..x. .x.. x...");
+        let empty = |c: &char| *c == '.';
+        assert_eq!(
+            flood_fill(&grid, Pos::new(0, 0), Connectivity::Four, empty),
+            vec![Pos::new(0, 0), Pos::new(1, 0), Pos::new(0, 1)]
+        );
+        assert_eq!(
+            flood_fill(&grid, Pos::new(2, 0), Connectivity::Four, empty),
+            vec![]
+        );
+        // The x diagonal walls the corner off only for orthogonal moves.
+        assert_eq!(
+            flood_fill(&grid, Pos::new(0, 0), Connectivity::Eight, empty).len(),
+            9
+        );
+
+        assert_eq!(fill(&mut grid, Pos::new(3, 2), Connectivity::Four, 'o'), 6);
+        assert_eq!(grid, board("..xo .xoo xooo"));
+        assert_eq!(fill(&mut grid, Pos::new(3, 2), Connectivity::Four, 'o'), 0);
+    }
+
+    #[test]
+    fn labels_regions() {
+        let grid = board("aab. a.bb ..b.");
+        let regions = Regions::label(&grid, Connectivity::Four, |c| (*c != '.').then_some(*c));
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions.areas(), &[3, 4]);
+        assert_eq!(regions.region_at(Pos::new(2, 2)), Some(1));
+        assert_eq!(regions.region_at(Pos::new(3, 0)), None);
+        assert_eq!(
+            regions.positions(0).collect::<Vec<_>>(),
+            vec![Pos::new(0, 0), Pos::new(1, 0), Pos::new(0, 1)]
+        );
+
+        let empty = Regions::label(&grid, Connectivity::Four, |c| (*c == '.').then_some(()));
+        assert_eq!(empty.areas(), &[1, 3, 1]);
+    }
+
+    #[test]
+    fn connected_structures() {
+        let grid = board("#.. ##. .#.");
+        assert!(is_connected(&grid, Connectivity::Four, |c| *c == '#'));
+        let grid = board("#.. .#. ..#");
+        assert!(!is_connected(&grid, Connectivity::Four, |c| *c == '#'));
+        assert!(is_connected(&grid, Connectivity::Eight, |c| *c == '#'));
+        assert!(is_connected(&grid, Connectivity::Four, |c| *c == 'x'));
+    }
+}
@@ -0,0 +1,111 @@
+//! Go-style scoring. An empty region belongs to a player when all the stones
+//! around it are theirs; regions touching several players, or none, are
+//! neutral. Area scoring then counts a player's stones plus their territory.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use super::{Connectivity, Grid, Regions};
+
+#[derive(Clone, Debug)]
+pub struct Territory<P> {
+    /// The owner of each empty cell, `None` for stones and neutral cells.
+    pub owners: Grid<Option<P>>,
+    /// Territory cells per player. Players without territory are missing.
+    pub counts: HashMap<P, usize>,
+}
+
+/// The territory on `grid`, `owner` telling which player's stone a cell
+/// holds, if any.
+pub fn territory<T, P: Clone + Eq + Hash>(
+    grid: &Grid<T>,
+    connectivity: Connectivity,
+    owner: impl Fn(&T) -> Option<P>,
+) -> Territory<P> {
+    let regions = Regions::label(grid, connectivity, |cell| {
+        owner(cell).is_none().then_some(())
+    });
+    let mut borders: Vec<HashSet<P>> = vec![HashSet::new(); regions.len()];
+    for pos in grid.positions() {
+        let Some(region) = regions.region_at(pos) else {
+            continue;
+        };
+        for next in connectivity.neighbors(pos) {
+            if let Some(player) = grid.at(next).and_then(&owner) {
+                borders[region].insert(player);
+            }
+        }
+    }
+    let region_owners: Vec<Option<P>> = borders
+        .into_iter()
+        .map(|players| match players.len() {
+            1 => players.into_iter().next(),
+            _ => None,
+        })
+        .collect();
+
+    let mut counts = HashMap::new();
+    let owners = regions.labels().map(|region| {
+        let player = region_owners[(*region)?].clone()?;
+        *counts.entry(player.clone()).or_insert(0) += 1;
+        Some(player)
+    });
+    Territory { owners, counts }
+}
+
+/// Each player's stones plus their territory.
+pub fn area_score<T, P: Clone + Eq + Hash>(
+    grid: &Grid<T>,
+    connectivity: Connectivity,
+    owner: impl Fn(&T) -> Option<P>,
+) -> HashMap<P, usize> {
+    let mut scores = territory(grid, connectivity, &owner).counts;
+    for cell in grid.cells() {
+        if let Some(player) = owner(cell) {
+            *scores.entry(player).or_insert(0) += 1;
+        }
+    }
+    scores
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::test::board;
+    use crate::pos::Pos;
+
+    fn stone(c: &char) -> Option<char> {
+        (*c != '.').then_some(*c)
+    }
+
+    #[test]
+    fn scores_territory() {
+        let grid = board(
+            "..b.w.. \
+             bbb.www \
+             ...bw.. \
+             .b.bw.w",
+        );
+        let result = territory(&grid, Connectivity::Four, stone);
+        // b surrounds the top left corner and the bottom left, w the two
+        // regions on the right. The gap in the middle touches both.
+        assert_eq!(result.counts[&'b'], 7);
+        assert_eq!(result.counts[&'w'], 5);
+        assert_eq!(result.owners[Pos::new(0, 0)], Some('b'));
+        assert_eq!(result.owners[Pos::new(3, 0)], None);
+        assert_eq!(result.owners[Pos::new(6, 3)], None);
+        assert_eq!(result.owners[Pos::new(0, 3)], Some('b'));
+
+        let scores = area_score(&grid, Connectivity::Four, stone);
+        assert_eq!(scores[&'b'], 7 + 7);
+        assert_eq!(scores[&'w'], 7 + 5);
+    }
+
+    #[test]
+    fn empty_board_is_neutral() {
+        let grid = board("... ...");
+        let result = territory(&grid, Connectivity::Four, stone);
+        assert!(result.counts.is_empty());
+        assert!(area_score(&grid, Connectivity::Four, stone).is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+//! The eight ways to turn and mirror a board. Positions that differ only by
+//! one of them are the same position to most games, so reducing a board to
+//! its canonical form lets an opening book or a search store it once.
+
+use serde::{Deserialize, Serialize};
+
+use super::Grid;
+use crate::pos::Pos;
+
+/// A rotation or reflection of a board. Rotations are clockwise on screen,
+/// with `y` growing downward.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transform {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Left and right swapped.
+    FlipHorizontal,
+    /// Top and bottom swapped.
+    FlipVertical,
+    /// Mirrored in the diagonal from the top left corner.
+    Transpose,
+    /// Mirrored in the diagonal from the top right corner.
+    AntiTranspose,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 8] = [
+        Transform::Identity,
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+        Transform::Transpose,
+        Transform::AntiTranspose,
+    ];
+
+    pub fn inverse(self) -> Self {
+        match self {
+            Transform::Rotate90 => Transform::Rotate270,
+            Transform::Rotate270 => Transform::Rotate90,
+            other => other,
+        }
+    }
+
+    /// Whether width and height trade places.
+    pub fn swaps_axes(self) -> bool {
+        matches!(
+            self,
+            Transform::Rotate90
+                | Transform::Rotate270
+                | Transform::Transpose
+                | Transform::AntiTranspose
+        )
+    }
+
+    /// Where `pos` on a `width` by `height` board ends up on the transformed
+    /// board.
+    pub fn apply(self, pos: Pos, width: usize, height: usize) -> Pos {
+        let (right, bottom) = (width as i32 - 1, height as i32 - 1);
+        let Pos { x, y } = pos;
+        match self {
+            Transform::Identity => Pos::new(x, y),
+            Transform::Rotate90 => Pos::new(bottom - y, x),
+            Transform::Rotate180 => Pos::new(right - x, bottom - y),
+            Transform::Rotate270 => Pos::new(y, right - x),
+            Transform::FlipHorizontal => Pos::new(right - x, y),
+            Transform::FlipVertical => Pos::new(x, bottom - y),
+            Transform::Transpose => Pos::new(y, x),
+            Transform::AntiTranspose => Pos::new(bottom - y, right - x),
+        }
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn transformed(&self, transform: Transform) -> Grid<T> {
+        let (width, height) = match transform.swaps_axes() {
+            true => (self.height(), self.width()),
+            false => (self.width(), self.height()),
+        };
+        let inverse = transform.inverse();
+        Grid::from_fn(width, height, |x, y| {
+            let pos = inverse.apply(Pos::new(x as i32, y as i32), width, height);
+            self[pos].clone()
+        })
+    }
+
+    /// The smallest of the board's eight transforms, comparing size first and
+    /// then cells row by row, along with the transform that gives it. Boards
+    /// that are turned or mirrored copies of each other share a canonical
+    /// form.
+    pub fn canonical_form(&self) -> (Grid<T>, Transform)
+    where
+        T: Ord,
+    {
+        Transform::ALL
+            .into_iter()
+            .map(|transform| (self.transformed(transform), transform))
+            .min_by(|(a, _), (b, _)| {
+                (a.width(), a.height(), a.cells()).cmp(&(b.width(), b.height(), b.cells()))
+            })
+            .expect("there are eight transforms")
+    }
+
+    /// The transforms that leave the board as it is, always including
+    /// `Identity`. A generated map is fair to players placed at mirrored
+    /// starts when the matching transform is in here.
+    pub fn symmetries(&self) -> Vec<Transform>
+    where
+        T: PartialEq,
+    {
+        Transform::ALL
+            .into_iter()
+            .filter(|transform| self.transformed(*transform) == *self)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::test::board;
+
+    #[test]
+    fn transforms() {
+        let grid = board("abc def");
+        assert_eq!(grid.transformed(Transform::Rotate90), board("da eb fc"));
+        assert_eq!(grid.transformed(Transform::Rotate180), board("fed cba"));
+        assert_eq!(grid.transformed(Transform::Transpose), board("ad be cf"));
+        assert_eq!(
+            grid.transformed(Transform::AntiTranspose),
+            board("fc eb da")
+        );
+        for transform in Transform::ALL {
+            let there = grid.transformed(transform);
+            assert_eq!(
+                there.transformed(transform.inverse()),
+                grid,
+                "{transform:?}"
+            );
+            let pos = Pos::new(2, 0);
+            assert_eq!(
+                there[transform.apply(pos, 3, 2)],
+                grid[pos],
+                "{transform:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn canonical_form() {
+        let corner = board("x.. ... ...");
+        let (canonical, _) = corner.canonical_form();
+        for transform in Transform::ALL {
+            let (other, back) = corner.transformed(transform).canonical_form();
+            assert_eq!(other, canonical);
+            assert_eq!(corner.transformed(transform).transformed(back), canonical);
+        }
+        assert_eq!(canonical, board("... ... ..x"));
+        assert_ne!(
+            board("x.. ... ...").canonical_form().0,
+            board(".x. ... ...").canonical_form().0
+        );
+
+        assert_eq!(board("x.x ... x.x").symmetries(), Transform::ALL.to_vec());
+        assert_eq!(
+            board("x.. ..x").symmetries(),
+            [Transform::Identity, Transform::Rotate180]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Zobrist hashing: every (cell, contents) pair gets a random 64 bit key and
+//! a board's hash is the xor of the keys of what is on it. Changing a cell
+//! only takes two xors, so games can keep a position's hash up to date on
+//! every move and spot repeated positions by comparing hashes.
+
+use std::sync::Arc;
+
+use super::Grid;
+use crate::pos::Pos;
+use crate::rng::{GameRng, SplitMix64};
+
+/// What a cell contributes to the hash: `None` for an empty cell, or an
+/// index below the `states` the keys were made for.
+pub trait ZobristCell {
+    fn zobrist_index(&self) -> Option<usize>;
+}
+
+impl<T: ZobristCell> ZobristCell for Option<T> {
+    fn zobrist_index(&self) -> Option<usize> {
+        self.as_ref().and_then(T::zobrist_index)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZobristKeys {
+    width: usize,
+    states: usize,
+    keys: Vec<u64>,
+    turn: u64,
+}
+
+impl ZobristKeys {
+    /// Keys for a board of the given size whose cells can each be in one of
+    /// `states` non-empty states. The same seed gives the same keys, so
+    /// hashes can be compared across processes.
+    pub fn new(width: usize, height: usize, states: usize, seed: u64) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        Self {
+            width,
+            states,
+            keys: (0..width * height * states)
+                .map(|_| rng.next_u64())
+                .collect(),
+            turn: rng.next_u64(),
+        }
+    }
+
+    /// The key for `state` at `pos`, which must be on the board.
+    pub fn key(&self, pos: Pos, state: usize) -> u64 {
+        assert!(state < self.states, "state {state} out of range");
+        self.keys[(pos.y as usize * self.width + pos.x as usize) * self.states + state]
+    }
+
+    fn cell_key(&self, pos: Pos, cell: &impl ZobristCell) -> u64 {
+        cell.zobrist_index().map_or(0, |state| self.key(pos, state))
+    }
+
+    /// Xored in when it is the second player's turn, so the same stones with
+    /// a different player to move hash differently.
+    pub fn turn_key(&self) -> u64 {
+        self.turn
+    }
+
+    /// The hash of a whole board, for starting off or checking an
+    /// incrementally updated one.
+    pub fn hash<T: ZobristCell>(&self, grid: &Grid<T>) -> u64 {
+        grid.positions()
+            .fold(0, |hash, pos| hash ^ self.cell_key(pos, &grid[pos]))
+    }
+}
+
+/// A grid that keeps its Zobrist hash up to date as cells change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashedGrid<T> {
+    grid: Grid<T>,
+    keys: Arc<ZobristKeys>,
+    hash: u64,
+}
+
+impl<T: ZobristCell> HashedGrid<T> {
+    pub fn new(grid: Grid<T>, keys: Arc<ZobristKeys>) -> Self {
+        let hash = keys.hash(&grid);
+        Self { grid, keys, hash }
+    }
+
+    pub fn grid(&self) -> &Grid<T> {
+        &self.grid
+    }
+
+    pub fn into_grid(self) -> Grid<T> {
+        self.grid
+    }
+
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Replaces the cell at `pos`, returning what was there, or `None` when
+    /// off the board.
+    pub fn set(&mut self, pos: Pos, value: T) -> Option<T> {
+        if !self.grid.contains(pos) {
+            return None;
+        }
+        self.hash ^= self.keys.cell_key(pos, &value);
+        let old = std::mem::replace(&mut self.grid[pos], value);
+        self.hash ^= self.keys.cell_key(pos, &old);
+        Some(old)
+    }
+
+    pub fn toggle_turn(&mut self) {
+        self.hash ^= self.keys.turn_key();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Stone {
+        Black,
+        White,
+    }
+
+    impl ZobristCell for Stone {
+        fn zobrist_index(&self) -> Option<usize> {
+            Some(*self as usize)
+        }
+    }
+
+    #[test]
+    fn incremental_matches_full_hash() {
+        let keys = Arc::new(ZobristKeys::new(3, 3, 2, 1));
+        let mut board = HashedGrid::new(Grid::new(3, 3, None), keys.clone());
+        assert_eq!(board.hash(), 0);
+
+        board.set(Pos::new(1, 1), Some(Stone::Black));
+        board.set(Pos::new(0, 2), Some(Stone::White));
+        assert_eq!(board.hash(), keys.hash(board.grid()));
+        let position = board.hash();
+
+        // Capturing and replaying the same stone comes back to the same hash.
+        assert_eq!(board.set(Pos::new(1, 1), None), Some(Some(Stone::Black)));
+        assert_ne!(board.hash(), position);
+        board.set(Pos::new(1, 1), Some(Stone::Black));
+        assert_eq!(board.hash(), position);
+
+        board.toggle_turn();
+        assert_ne!(board.hash(), position);
+        board.toggle_turn();
+        assert_eq!(board.hash(), position);
+
+        assert_eq!(board.set(Pos::new(3, 0), Some(Stone::White)), None);
+        assert_eq!(ZobristKeys::new(3, 3, 2, 1), *keys);
+    }
+}
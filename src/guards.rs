@@ -0,0 +1,148 @@
+//! Ends games the rules would let go on forever: positions repeating, a
+//! game dragging on past a move cap, or a player left with no legal move
+//! in a game that didn't see it coming. Two bots shuffling back and forth
+//! would otherwise hold a server slot for good.
+
+use std::collections::HashMap;
+
+use crate::games::LegalMoves;
+
+/// Why a guard stopped the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// The position came up for the `times`th time.
+    Repetition {
+        times: usize,
+    },
+    MoveCap {
+        moves: usize,
+    },
+    /// The game isn't over but the player to move has no legal move.
+    NoLegalMoves,
+}
+
+/// Checks a game after every move, one set of guards per game. No limits
+/// are set until some are added.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EndGuards {
+    max_moves: Option<usize>,
+    max_repetitions: Option<usize>,
+    moves: usize,
+    /// How many times each position came up, by `LegalMoves::position_hash`.
+    seen: HashMap<u64, usize>,
+}
+
+impl EndGuards {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_moves(mut self, moves: usize) -> Self {
+        self.max_moves = Some(moves);
+        self
+    }
+
+    /// Stops the game when a position comes up for the `times`th time, 3
+    /// as in chess. Only games with a `position_hash` are checked.
+    pub fn repetitions(mut self, times: usize) -> Self {
+        self.max_repetitions = Some(times);
+        self
+    }
+
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+
+    /// Forgets the last game and counts `game`'s starting position.
+    pub fn start<G: LegalMoves>(&mut self, game: &G) {
+        self.moves = 0;
+        self.seen.clear();
+        self.count_position(game);
+    }
+
+    /// Checks `game` after a move that didn't end it.
+    pub fn after_move<G: LegalMoves>(&mut self, game: &G) -> Option<Stop> {
+        self.moves += 1;
+        if game.legal_moves().is_empty() {
+            return Some(Stop::NoLegalMoves);
+        }
+        if let Some(times) = self.count_position(game) {
+            if self.max_repetitions.is_some_and(|max| times >= max) {
+                return Some(Stop::Repetition { times });
+            }
+        }
+        if self.max_moves.is_some_and(|max| self.moves >= max) {
+            return Some(Stop::MoveCap { moves: self.moves });
+        }
+        None
+    }
+
+    fn count_position<G: LegalMoves>(&mut self, game: &G) -> Option<usize> {
+        self.max_repetitions?;
+        let times = self.seen.entry(game.position_hash()?).or_insert(0);
+        *times += 1;
+        Some(*times)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A token walking between two squares, or stuck once `stuck` is set.
+    struct Shuffle {
+        at: u64,
+        stuck: bool,
+    }
+
+    impl LegalMoves for Shuffle {
+        type Move = u64;
+
+        fn legal_moves(&self) -> Vec<u64> {
+            if self.stuck {
+                vec![]
+            } else {
+                vec![1 - self.at]
+            }
+        }
+
+        fn position_hash(&self) -> Option<u64> {
+            Some(self.at)
+        }
+    }
+
+    #[test]
+    fn stops_repeated_positions() {
+        let mut game = Shuffle {
+            at: 0,
+            stuck: false,
+        };
+        let mut guards = EndGuards::new().repetitions(3);
+        guards.start(&game);
+        let mut stops = vec![];
+        for _ in 0..4 {
+            game.at = 1 - game.at;
+            stops.push(guards.after_move(&game));
+        }
+        assert_eq!(
+            stops,
+            [None, None, None, Some(Stop::Repetition { times: 3 })]
+        );
+    }
+
+    #[test]
+    fn stops_at_the_cap_and_when_stuck() {
+        let mut game = Shuffle {
+            at: 0,
+            stuck: false,
+        };
+        let mut guards = EndGuards::new().max_moves(2);
+        guards.start(&game);
+        assert_eq!(guards.after_move(&game), None);
+        assert_eq!(guards.after_move(&game), Some(Stop::MoveCap { moves: 2 }));
+
+        guards.start(&game);
+        game.stuck = true;
+        assert_eq!(guards.after_move(&game), Some(Stop::NoLegalMoves));
+    }
+}
@@ -0,0 +1,129 @@
+//! Head starts for weaker players, so bots far apart in strength still
+//! have a game worth watching. A `HandicapScale` works out each player's
+//! `Handicap` from their ratings, and the server hands it to the game with
+//! `GameTrait::apply_handicap` between `reset` and the start.
+//!
+//! A handicap can be worth material, score and time. Each game takes what
+//! it has a use for: Othello gives corner discs, rock paper scissors
+//! rounds already won and Connect Four clock time.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Handicap {
+    /// Pieces on the board before the first move, or whatever the game
+    /// counts as material.
+    pub extra_material: u32,
+    /// Added to the player's score from the start.
+    pub score_offset: i64,
+    /// Added to the player's clock, in timed games.
+    pub time_bonus_ms: u64,
+}
+
+impl Handicap {
+    pub const NONE: Handicap = Handicap {
+        extra_material: 0,
+        score_offset: 0,
+        time_bonus_ms: 0,
+    };
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    pub fn time_bonus(&self) -> Duration {
+        Duration::from_millis(self.time_bonus_ms)
+    }
+
+    /// `steps` of this handicap together.
+    pub fn times(&self, steps: u32) -> Handicap {
+        Handicap {
+            extra_material: self.extra_material.saturating_mul(steps),
+            score_offset: self.score_offset.saturating_mul(steps as i64),
+            time_bonus_ms: self.time_bonus_ms.saturating_mul(steps as u64),
+        }
+    }
+}
+
+/// How much head start a gap in ratings is worth: a `step` for every
+/// `rating_per_step` a player is below the best rated, up to `max_steps`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HandicapScale {
+    pub rating_per_step: f64,
+    pub max_steps: u32,
+    pub step: Handicap,
+}
+
+impl HandicapScale {
+    /// The handicap of each player with any, by name. The best rated get
+    /// none.
+    pub fn handicaps<'a>(
+        &self,
+        ratings: impl IntoIterator<Item = (&'a str, f64)>,
+    ) -> BTreeMap<String, Handicap> {
+        let ratings: Vec<_> = ratings.into_iter().collect();
+        let best = ratings
+            .iter()
+            .map(|(_, rating)| *rating)
+            .fold(f64::NEG_INFINITY, f64::max);
+        ratings
+            .into_iter()
+            .filter_map(|(player, rating)| {
+                let steps = ((best - rating) / self.rating_per_step).floor();
+                let steps = (steps.max(0.0) as u32).min(self.max_steps);
+                let handicap = self.step.times(steps);
+                (!handicap.is_none()).then(|| (player.to_string(), handicap))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn steps_with_the_rating_gap() {
+        let scale = HandicapScale {
+            rating_per_step: 100.0,
+            max_steps: 3,
+            step: Handicap {
+                extra_material: 1,
+                time_bonus_ms: 10_000,
+                ..Handicap::NONE
+            },
+        };
+        let handicaps = scale.handicaps([
+            ("strong", 1800.0),
+            ("close", 1750.0),
+            ("weak", 1590.0),
+            ("beginner", 1000.0),
+        ]);
+        assert_eq!(
+            handicaps,
+            BTreeMap::from([
+                (
+                    "beginner".to_string(),
+                    Handicap {
+                        extra_material: 3,
+                        score_offset: 0,
+                        time_bonus_ms: 30_000
+                    }
+                ),
+                (
+                    "weak".to_string(),
+                    Handicap {
+                        extra_material: 2,
+                        score_offset: 0,
+                        time_bonus_ms: 20_000
+                    }
+                ),
+            ])
+        );
+    }
+}
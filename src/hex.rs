@@ -0,0 +1,209 @@
+//! Hex coordinates for hex boards, in the axial system of
+//! <https://www.redblobgames.com/grids/hexagons/>: `q` runs along a row and
+//! `r` down the board, the third cube coordinate being `s = -q - r`. Turning
+//! hexes into pixels and back is done by `draw::layout::HexLayout`.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use crate::draw::layout::HexLayout;
+use crate::draw::Point;
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32,
+}
+
+/// The six neighbor offsets, counter-clockwise starting east (pointy top) or
+/// south-east (flat top).
+pub const DIRECTIONS: [Hex; 6] = [
+    Hex::new(1, 0),
+    Hex::new(1, -1),
+    Hex::new(0, -1),
+    Hex::new(-1, 0),
+    Hex::new(-1, 1),
+    Hex::new(0, 1),
+];
+
+impl Hex {
+    pub const ORIGIN: Hex = Hex::new(0, 0);
+
+    pub const fn new(q: i32, r: i32) -> Self {
+        Self { q, r }
+    }
+
+    /// From cube coordinates, which must sum to zero.
+    pub fn from_cube(q: i32, r: i32, s: i32) -> Self {
+        debug_assert_eq!(q + r + s, 0, "cube coordinates must sum to zero");
+        Self { q, r }
+    }
+
+    pub fn s(self) -> i32 {
+        -self.q - self.r
+    }
+
+    pub fn cube(self) -> (i32, i32, i32) {
+        (self.q, self.r, self.s())
+    }
+
+    /// The hex nearest to fractional axial coordinates, rounding through
+    /// cube coordinates so the three axes stay consistent.
+    pub fn round(q: f64, r: f64) -> Self {
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        Self::new(rq as i32, rr as i32)
+    }
+
+    /// The neighbor in `DIRECTIONS[direction % 6]`.
+    pub fn neighbor(self, direction: usize) -> Self {
+        self + DIRECTIONS[direction % 6]
+    }
+
+    pub fn neighbors(self) -> [Hex; 6] {
+        DIRECTIONS.map(|d| self + d)
+    }
+
+    /// Number of steps between the hexes.
+    pub fn distance(self, other: Hex) -> u32 {
+        let d = self - other;
+        (d.q.unsigned_abs() + d.r.unsigned_abs() + d.s().unsigned_abs()) / 2
+    }
+
+    /// The hexes exactly `radius` steps away, going around counter-clockwise.
+    /// A radius of 0 gives just this hex.
+    pub fn ring(self, radius: u32) -> impl Iterator<Item = Hex> {
+        let start = self + DIRECTIONS[4] * radius as i32;
+        let steps =
+            (0..6).flat_map(move |side| std::iter::repeat_n(DIRECTIONS[side], radius as usize));
+        let ring = steps.scan(start, |hex, step| {
+            let current = *hex;
+            *hex = *hex + step;
+            Some(current)
+        });
+        std::iter::once(self)
+            .filter(move |_| radius == 0)
+            .chain(ring)
+    }
+
+    /// All hexes within `radius` steps, ring by ring from this one outwards.
+    pub fn spiral(self, radius: u32) -> impl Iterator<Item = Hex> {
+        (0..=radius).flat_map(move |r| self.ring(r))
+    }
+
+    pub fn to_pixel(self, layout: &HexLayout) -> Point {
+        layout.center(self.q, self.r)
+    }
+
+    pub fn from_pixel(layout: &HexLayout, p: Point) -> Self {
+        let (q, r) = layout.hex_at(p);
+        Self::new(q, r)
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.q + other.q, self.r + other.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.q - other.q, self.r - other.r)
+    }
+}
+
+impl Neg for Hex {
+    type Output = Hex;
+
+    fn neg(self) -> Hex {
+        Hex::new(-self.q, -self.r)
+    }
+}
+
+impl Mul<i32> for Hex {
+    type Output = Hex;
+
+    fn mul(self, k: i32) -> Hex {
+        Hex::new(self.q * k, self.r * k)
+    }
+}
+
+impl From<(i32, i32)> for Hex {
+    fn from((q, r): (i32, i32)) -> Self {
+        Self::new(q, r)
+    }
+}
+
+impl From<Hex> for (i32, i32) {
+    fn from(hex: Hex) -> Self {
+        (hex.q, hex.r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::draw::layout::HexOrientation;
+    use crate::draw::Size;
+
+    #[test]
+    fn neighbors_and_distance() {
+        let hex = Hex::new(2, -1);
+        assert_eq!(hex.cube(), (2, -1, -1));
+        for neighbor in hex.neighbors() {
+            assert_eq!(hex.distance(neighbor), 1);
+        }
+        assert_eq!(hex.neighbor(6), hex.neighbor(0));
+        assert_eq!(Hex::ORIGIN.distance(Hex::new(3, -5)), 5);
+        assert_eq!(Hex::round(0.4, 0.4), Hex::new(0, 1));
+    }
+
+    #[test]
+    fn rings_and_spirals() {
+        let center = Hex::new(1, 1);
+        assert_eq!(center.ring(0).collect::<Vec<_>>(), [center]);
+        for radius in 1..4 {
+            let ring: Vec<_> = center.ring(radius).collect();
+            assert_eq!(ring.len(), 6 * radius as usize);
+            assert!(ring.iter().all(|h| center.distance(*h) == radius));
+            // Consecutive hexes are adjacent, all the way around.
+            for (i, h) in ring.iter().enumerate() {
+                assert_eq!(h.distance(ring[(i + 1) % ring.len()]), 1);
+            }
+        }
+        let spiral: Vec<_> = center.spiral(2).collect();
+        assert_eq!(spiral.len(), 19);
+        assert_eq!(spiral.iter().collect::<HashSet<_>>().len(), 19);
+        assert_eq!(spiral[0], center);
+    }
+
+    #[test]
+    fn pixels() {
+        let hexes: Vec<_> = Hex::ORIGIN.spiral(2).collect();
+        let layout = HexLayout::fit(
+            Size::new(200.0, 200.0),
+            HexOrientation::FlatTop,
+            hexes.iter().map(|h| (*h).into()),
+            0.0,
+        );
+        for hex in hexes {
+            assert_eq!(Hex::from_pixel(&layout, hex.to_pixel(&layout)), hex);
+        }
+    }
+}
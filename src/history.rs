@@ -0,0 +1,173 @@
+use std::convert::Infallible;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::replay::{GameOutcome, Replay};
+use crate::rules::RulesVersion;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+
+pub type MatchId = i64;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchRecord {
+    pub game: String,
+    pub players: Vec<String>,
+    pub outcome: Option<GameOutcome>,
+    pub played_at: SystemTime,
+    /// See `Replay::rules`.
+    #[serde(default)]
+    pub rules: Option<RulesVersion>,
+}
+
+/// Filters for `MatchHistoryStore::find`, every field left as `None` matches
+/// all records. `until` is exclusive.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MatchQuery {
+    pub player: Option<String>,
+    pub game: Option<String>,
+    pub since: Option<SystemTime>,
+    pub until: Option<SystemTime>,
+}
+
+impl MatchQuery {
+    pub fn matches(&self, record: &MatchRecord) -> bool {
+        self.player
+            .as_ref()
+            .is_none_or(|p| record.players.contains(p))
+            && self.game.as_ref().is_none_or(|g| &record.game == g)
+            && self.since.is_none_or(|t| record.played_at >= t)
+            && self.until.is_none_or(|t| record.played_at < t)
+    }
+}
+
+pub trait MatchHistoryStore {
+    type Error;
+
+    fn insert(&mut self, record: &MatchRecord, replay: &Replay) -> Result<MatchId, Self::Error>;
+
+    /// Matching records, oldest first.
+    fn find(&self, query: &MatchQuery) -> Result<Vec<(MatchId, MatchRecord)>, Self::Error>;
+
+    fn replay(&self, id: MatchId) -> Result<Option<Replay>, Self::Error>;
+}
+
+/// Keeps the history in memory, for tests and servers that don't need it to
+/// outlive the process.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryHistory {
+    matches: Vec<(MatchRecord, Replay)>,
+}
+
+impl MatchHistoryStore for MemoryHistory {
+    type Error = Infallible;
+
+    fn insert(&mut self, record: &MatchRecord, replay: &Replay) -> Result<MatchId, Self::Error> {
+        self.matches.push((record.clone(), replay.clone()));
+        Ok(self.matches.len() as MatchId)
+    }
+
+    fn find(&self, query: &MatchQuery) -> Result<Vec<(MatchId, MatchRecord)>, Self::Error> {
+        let mut found: Vec<_> = self
+            .matches
+            .iter()
+            .enumerate()
+            .filter(|(_, (record, _))| query.matches(record))
+            .map(|(i, (record, _))| (i as MatchId + 1, record.clone()))
+            .collect();
+        found.sort_by_key(|(id, record)| (record.played_at, *id));
+        Ok(found)
+    }
+
+    fn replay(&self, id: MatchId) -> Result<Option<Replay>, Self::Error> {
+        let index = usize::try_from(id - 1).ok();
+        Ok(index
+            .and_then(|i| self.matches.get(i))
+            .map(|(_, replay)| replay.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+    use crate::replay::RecordedMove;
+
+    pub(crate) fn record(game: &str, players: &[&str], secs: u64) -> MatchRecord {
+        MatchRecord {
+            game: game.to_string(),
+            players: players.iter().map(|p| p.to_string()).collect(),
+            outcome: Some(GameOutcome::Draw),
+            played_at: UNIX_EPOCH + Duration::from_secs(secs),
+            rules: Some(RulesVersion::INITIAL),
+        }
+    }
+
+    pub(crate) fn check_store<S: MatchHistoryStore>(store: &mut S)
+    where
+        S::Error: std::fmt::Debug,
+    {
+        let mut replay = Replay::new(vec![]);
+        replay.moves.push(RecordedMove {
+            player: "p1".to_string(),
+            player_move: "up".to_string(),
+            think_ms: Some(250),
+            annotations: vec![],
+        });
+        let a = store
+            .insert(&record("snake", &["p1", "p2"], 300), &replay)
+            .unwrap();
+        let b = store
+            .insert(&record("snake", &["p2", "p3"], 100), &replay)
+            .unwrap();
+        let c = store
+            .insert(&record("tic-tac-toe", &["p1", "p3"], 200), &replay)
+            .unwrap();
+
+        let ids = |query: MatchQuery| -> Vec<MatchId> {
+            store
+                .find(&query)
+                .unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect()
+        };
+        assert_eq!(ids(MatchQuery::default()), vec![b, c, a]);
+        assert_eq!(
+            ids(MatchQuery {
+                player: Some("p1".to_string()),
+                ..Default::default()
+            }),
+            vec![c, a]
+        );
+        assert_eq!(
+            ids(MatchQuery {
+                game: Some("snake".to_string()),
+                ..Default::default()
+            }),
+            vec![b, a]
+        );
+        assert_eq!(
+            ids(MatchQuery {
+                since: Some(UNIX_EPOCH + Duration::from_secs(200)),
+                until: Some(UNIX_EPOCH + Duration::from_secs(300)),
+                ..Default::default()
+            }),
+            vec![c]
+        );
+
+        let (_, found) = store.find(&MatchQuery::default()).unwrap().remove(0);
+        assert_eq!(found, record("snake", &["p2", "p3"], 100));
+        assert_eq!(store.replay(a).unwrap(), Some(replay));
+        assert_eq!(store.replay(a + b + c).unwrap(), None);
+    }
+
+    #[test]
+    fn memory_history() {
+        check_store(&mut MemoryHistory::default());
+    }
+}
@@ -0,0 +1,205 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection};
+
+use super::{MatchHistoryStore, MatchId, MatchQuery, MatchRecord};
+use crate::replay::{GameOutcome, Replay};
+use crate::rules::RulesVersion;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS matches (
+    id INTEGER PRIMARY KEY,
+    game TEXT NOT NULL,
+    -- Nanoseconds since the Unix epoch.
+    played_at INTEGER NOT NULL,
+    outcome TEXT,
+    replay TEXT NOT NULL,
+    rules TEXT
+);
+CREATE TABLE IF NOT EXISTS match_players (
+    match_id INTEGER NOT NULL REFERENCES matches(id),
+    seat INTEGER NOT NULL,
+    player TEXT NOT NULL,
+    PRIMARY KEY (match_id, seat)
+);
+CREATE INDEX IF NOT EXISTS matches_game ON matches(game, played_at);
+CREATE INDEX IF NOT EXISTS matches_played_at ON matches(played_at);
+CREATE INDEX IF NOT EXISTS match_players_player ON match_players(player);
+PRAGMA user_version = 1;
+";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("invalid data in the history: {0}")]
+    InvalidData(String),
+}
+
+/// Match history in an SQLite database. Replays are stored in their text
+/// notation so the database stays readable with the `sqlite3` shell.
+pub struct SqliteHistory {
+    conn: Connection,
+}
+
+impl SqliteHistory {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    pub fn with_connection(conn: Connection) -> Result<Self, Error> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    fn players(&self, id: MatchId) -> Result<Vec<String>, Error> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT player FROM match_players WHERE match_id = ?1 ORDER BY seat")?;
+        let players = stmt
+            .query_map(params![id], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(players)
+    }
+}
+
+impl MatchHistoryStore for SqliteHistory {
+    type Error = Error;
+
+    fn insert(&mut self, record: &MatchRecord, replay: &Replay) -> Result<MatchId, Self::Error> {
+        let outcome = record
+            .outcome
+            .as_ref()
+            .map(|o| serde_json::to_string(o).unwrap());
+
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO matches (game, played_at, outcome, replay, rules)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                record.game,
+                to_timestamp(record.played_at),
+                outcome,
+                replay.to_notation(),
+                record.rules.map(|rules| rules.to_string())
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        for (seat, player) in record.players.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO match_players (match_id, seat, player) VALUES (?1, ?2, ?3)",
+                params![id, seat as i64, player],
+            )?;
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    fn find(&self, query: &MatchQuery) -> Result<Vec<(MatchId, MatchRecord)>, Self::Error> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, game, played_at, outcome, rules FROM matches
+             WHERE (?1 IS NULL OR game = ?1)
+               AND (?2 IS NULL OR played_at >= ?2)
+               AND (?3 IS NULL OR played_at < ?3)
+               AND (?4 IS NULL OR id IN (SELECT match_id FROM match_players WHERE player = ?4))
+             ORDER BY played_at, id",
+        )?;
+        let rows = stmt
+            .query_map(
+                params![
+                    query.game,
+                    query.since.map(to_timestamp),
+                    query.until.map(to_timestamp),
+                    query.player
+                ],
+                |row| {
+                    Ok((
+                        row.get::<_, MatchId>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(id, game, played_at, outcome, rules)| {
+                let outcome = outcome
+                    .map(|o| serde_json::from_str::<GameOutcome>(&o))
+                    .transpose()
+                    .map_err(|e| Error::InvalidData(format!("match {id} outcome: {e}")))?;
+                let rules = rules
+                    .map(|r| r.parse::<RulesVersion>())
+                    .transpose()
+                    .map_err(|e| Error::InvalidData(format!("match {id} rules: {e}")))?;
+                let record = MatchRecord {
+                    game,
+                    players: self.players(id)?,
+                    outcome,
+                    played_at: from_timestamp(played_at),
+                    rules,
+                };
+                Ok((id, record))
+            })
+            .collect()
+    }
+
+    fn replay(&self, id: MatchId) -> Result<Option<Replay>, Self::Error> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT replay FROM matches WHERE id = ?1")?;
+        let mut rows = stmt.query(params![id])?;
+        let Some(row) = rows.next()? else {
+            return Ok(None);
+        };
+        let text: String = row.get(0)?;
+        Replay::from_notation(&text).map(Some).map_err(|e| {
+            Error::InvalidData(format!("match {id} replay line {}: {}", e.line, e.reason))
+        })
+    }
+}
+
+/// Nanoseconds since the epoch, which reach to the year 2262.
+fn to_timestamp(time: SystemTime) -> i64 {
+    let nanos = |d: Duration| i64::try_from(d.as_nanos()).unwrap_or(i64::MAX);
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => nanos(d),
+        Err(e) => -nanos(e.duration()),
+    }
+}
+
+fn from_timestamp(nanos: i64) -> SystemTime {
+    if nanos >= 0 {
+        UNIX_EPOCH + Duration::from_nanos(nanos as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_nanos(nanos.unsigned_abs())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::history::test::{check_store, record};
+
+    #[test]
+    fn sqlite_history() {
+        check_store(&mut SqliteHistory::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn keeps_times_to_the_nanosecond() {
+        let mut history = SqliteHistory::open_in_memory().unwrap();
+        let mut played = record("snake", &["p1", "p2"], 0);
+        played.played_at = UNIX_EPOCH + Duration::new(100, 123_456_789);
+        history.insert(&played, &Replay::new(vec![])).unwrap();
+        let found = history.find(&MatchQuery::default()).unwrap();
+        assert_eq!(found[0].1, played);
+    }
+}
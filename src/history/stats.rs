@@ -0,0 +1,250 @@
+//! A player's record for their profile page: how often they win each game,
+//! how long they take over a move, how often they run out of time and the
+//! move they like to open with.
+//!
+//! Results and openings come from the match history. Move times and
+//! timeouts aren't kept there, so the server adds them as games are played.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::{MatchHistoryStore, MatchQuery, MatchRecord};
+use crate::replay::{GameOutcome, Replay};
+
+/// Sums up one player's games, to be turned into a `PlayerProfile`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlayerStats {
+    player: String,
+    games: BTreeMap<String, GameStats>,
+    moves: u64,
+    move_time: Duration,
+    timeouts: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct GameStats {
+    played: u32,
+    won: u32,
+    drawn: u32,
+    /// How many times the player opened with each move.
+    openings: BTreeMap<String, u32>,
+}
+
+impl PlayerStats {
+    pub fn new(player: impl Into<String>) -> Self {
+        Self {
+            player: player.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The player's finished matches in `store`, with their openings.
+    pub fn from_history<S: MatchHistoryStore>(
+        store: &S,
+        player: impl Into<String>,
+    ) -> Result<Self, S::Error> {
+        let mut stats = Self::new(player);
+        let query = MatchQuery {
+            player: Some(stats.player.clone()),
+            ..MatchQuery::default()
+        };
+        for (id, record) in store.find(&query)? {
+            let replay = store.replay(id)?;
+            stats.add_match(&record, replay.as_ref());
+        }
+        Ok(stats)
+    }
+
+    pub fn player(&self) -> &str {
+        &self.player
+    }
+
+    /// Counts the match if the player was in it and it finished, and the
+    /// first move they made in `replay`, if given, as their opening.
+    pub fn add_match(&mut self, record: &MatchRecord, replay: Option<&Replay>) {
+        let Some(outcome) = &record.outcome else {
+            return;
+        };
+        if !record.players.contains(&self.player) {
+            return;
+        }
+        let game = self.games.entry(record.game.clone()).or_default();
+        game.played += 1;
+        match outcome {
+            GameOutcome::Win { winner } if *winner == self.player => game.won += 1,
+            GameOutcome::Win { .. } => {}
+            GameOutcome::Draw | GameOutcome::AgreedDraw => game.drawn += 1,
+        }
+        let opening =
+            replay.and_then(|replay| replay.moves.iter().find(|m| m.player == self.player));
+        if let Some(opening) = opening {
+            *game
+                .openings
+                .entry(opening.player_move.clone())
+                .or_default() += 1;
+        }
+    }
+
+    /// A move the player took `elapsed` to make.
+    pub fn add_move(&mut self, elapsed: Duration) {
+        self.moves += 1;
+        self.move_time += elapsed;
+    }
+
+    /// The player ran out of time.
+    pub fn add_timeout(&mut self) {
+        self.timeouts += 1;
+    }
+
+    pub fn average_move_time(&self) -> Option<Duration> {
+        let moves = u32::try_from(self.moves).ok().filter(|n| *n > 0)?;
+        Some(self.move_time / moves)
+    }
+
+    pub fn profile(&self) -> PlayerProfile {
+        PlayerProfile {
+            player: self.player.clone(),
+            games: self
+                .games
+                .iter()
+                .map(|(name, game)| (name.clone(), game.profile()))
+                .collect(),
+            moves: self.moves,
+            average_move_ms: self.average_move_time().map(|t| t.as_millis() as u64),
+            timeouts: self.timeouts,
+        }
+    }
+}
+
+impl GameStats {
+    fn profile(&self) -> GameProfile {
+        GameProfile {
+            played: self.played,
+            won: self.won,
+            drawn: self.drawn,
+            lost: self.played - self.won - self.drawn,
+            win_rate: match self.played {
+                0 => 0.0,
+                played => f64::from(self.won) / f64::from(played),
+            },
+            // The most played, and of those the first in order.
+            favorite_opening: self
+                .openings
+                .iter()
+                .fold(
+                    None,
+                    |best: Option<(&String, u32)>, (opening, count)| match best {
+                        Some((_, most)) if most >= *count => best,
+                        _ => Some((opening, *count)),
+                    },
+                )
+                .map(|(opening, _)| opening.clone()),
+        }
+    }
+}
+
+/// What a profile page shows of a player.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PlayerProfile {
+    pub player: String,
+    /// By game name, as in `MatchRecord::game`.
+    pub games: BTreeMap<String, GameProfile>,
+    pub moves: u64,
+    pub average_move_ms: Option<u64>,
+    pub timeouts: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GameProfile {
+    pub played: u32,
+    pub won: u32,
+    pub drawn: u32,
+    pub lost: u32,
+    /// Wins over games played, from 0 to 1.
+    pub win_rate: f64,
+    /// The first move the player makes most often, as sent.
+    pub favorite_opening: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::history::test::record;
+    use crate::history::MemoryHistory;
+    use crate::replay::RecordedMove;
+
+    fn replay(moves: &[(&str, &str)]) -> Replay {
+        let mut replay = Replay::new(vec![]);
+        replay.moves = moves
+            .iter()
+            .map(|(player, player_move)| RecordedMove {
+                player: player.to_string(),
+                player_move: player_move.to_string(),
+                think_ms: None,
+                annotations: vec![],
+            })
+            .collect();
+        replay
+    }
+
+    fn won_by(mut record: MatchRecord, winner: &str) -> MatchRecord {
+        record.outcome = Some(GameOutcome::Win {
+            winner: winner.to_string(),
+        });
+        record
+    }
+
+    #[test]
+    fn sums_up_a_players_matches() {
+        let mut history = MemoryHistory::default();
+        let matches = [
+            (won_by(record("connect-four", &["p1", "p2"], 1), "p1"), "3"),
+            (won_by(record("connect-four", &["p2", "p1"], 2), "p2"), "2"),
+            (record("connect-four", &["p1", "p3"], 3), "3"),
+            (won_by(record("snake", &["p1", "p2"], 4), "p1"), "up"),
+            (won_by(record("snake", &["p2", "p3"], 5), "p2"), "down"),
+        ];
+        for (record, opening) in matches {
+            let first = record.players[0].clone();
+            let moves = replay(&[(&first, opening), ("p1", "1"), ("p2", "5")]);
+            history.insert(&record, &moves).unwrap();
+        }
+        let mut unfinished = record("snake", &["p1", "p2"], 6);
+        unfinished.outcome = None;
+        history.insert(&unfinished, &replay(&[])).unwrap();
+
+        let mut stats = PlayerStats::from_history(&history, "p1").unwrap();
+        stats.add_move(Duration::from_millis(100));
+        stats.add_move(Duration::from_millis(300));
+        stats.add_timeout();
+
+        let profile = stats.profile();
+        assert_eq!(profile.average_move_ms, Some(200));
+        assert_eq!(profile.timeouts, 1);
+        assert_eq!(
+            profile.games["connect-four"],
+            GameProfile {
+                played: 3,
+                won: 1,
+                drawn: 1,
+                lost: 1,
+                win_rate: 1.0 / 3.0,
+                favorite_opening: Some("3".to_string()),
+            }
+        );
+        let snake = &profile.games["snake"];
+        assert_eq!((snake.played, snake.won), (1, 1));
+        assert_eq!(snake.favorite_opening.as_deref(), Some("up"));
+    }
+
+    #[test]
+    fn no_moves_no_average() {
+        let stats = PlayerStats::new("p1");
+        assert_eq!(stats.average_move_time(), None);
+        assert!(stats.profile().games.is_empty());
+    }
+}
@@ -0,0 +1,1087 @@
+//! Runs many games at once on a pool of worker threads. Each game has its
+//! own mailbox of commands, handled in order by one worker at a time, and
+//! workers take whichever game has mail next, so a game that takes long
+//! over a move only holds up its own mailbox.
+//!
+//! What comes out of the games, turns to hand out and results of moves,
+//! arrives on a single channel of events tagged with the game's id, for
+//! the server to pass on to the players.
+//!
+//! Whatever a game logs while handling a command is inside a `game` span
+//! with its id, and moves and timeouts inside a `turn` span with the
+//! player, so the logs of many games running at once can be told apart.
+//!
+//! The host keeps each game's `GameLifecycle`, refuses commands it doesn't
+//! allow, such as moves in a paused game, and sends an event for every
+//! change after the event for the command that caused it.
+//!
+//! Players joining, turns, moves taken, scores and results also go to the
+//! host's `EventBus`, for whoever else wants to know.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span, error, info_span};
+
+use crate::bus::{EventBus, GameEvent};
+use crate::draw_offers::{DrawOfferError, DrawOffers};
+use crate::forfeit::Forfeit;
+use crate::gametraits::{GameTrait, PlayerMove, PlayerMoveResult, PlayerTurn, TurnToken, User};
+use crate::handicap::Handicap;
+use crate::lifecycle::{GameLifecycle, LifecycleError, LifecycleEvent};
+use crate::messages::ToClient;
+#[cfg(feature = "metrics")]
+use crate::metrics::prometheus;
+use crate::metrics::{MessageRate, Metric, Metrics};
+use crate::replay::GameOutcome;
+use crate::time::Instant;
+
+/// Commands a worker handles for one game before giving others a turn.
+const BATCH: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct GameId(u64);
+
+impl GameId {
+    /// The id of a game run outside a host, for publishing its events on
+    /// a bus. Hosts number their games from 0.
+    pub fn from_u64(id: u64) -> Self {
+        Self(id)
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for GameId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "game {}", self.0)
+    }
+}
+
+/// A call to make on a game, one for each method of `GameTrait`.
+#[derive(Debug)]
+pub enum Command {
+    Connect(User),
+    Disconnect(String),
+    Reset(Vec<User>),
+    Start,
+    Move(TurnToken, PlayerMove),
+    /// The current player ran out of time or dropped.
+    TimedOut(TurnToken),
+    Resign(String),
+    /// The player, and why they're thrown out.
+    Disqualify(String, String),
+    Pause,
+    Resume,
+    /// The player, and milliseconds to add to their clock, or take off if
+    /// negative. Games without a clock ignore it.
+    AdjustClock(String, i64),
+    /// Resets the game with the players it has and starts it again.
+    Restart,
+    /// Gives the player a head start. Sent between `Reset` and `Start`.
+    Handicap(String, Handicap),
+    /// The player offers a draw, or accepts the one already offered.
+    OfferDraw(String),
+    AcceptDraw(String),
+    DeclineDraw(String),
+}
+
+/// What a game made of a command. Commands that return nothing send no
+/// event.
+#[derive(Debug)]
+pub enum Event {
+    Started(Option<PlayerTurn>),
+    Moved(PlayerMoveResult),
+    TimedOut(Option<PlayerTurn>),
+    Forfeited(Forfeit),
+    /// What to tell everyone in the game of a draw offer, accept or
+    /// decline, or why it was refused.
+    DrawOffer(Result<ToClient, DrawOfferError>),
+    Lifecycle(LifecycleEvent),
+    /// The command wasn't allowed where the game is in its lifecycle, and
+    /// was dropped.
+    Refused(LifecycleError),
+    /// The game panicked and was removed, with the panic message.
+    Crashed(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum HostError {
+    /// No such game, or it was removed or crashed.
+    #[error("no {0}")]
+    UnknownGame(GameId),
+}
+
+struct Slot {
+    id: GameId,
+    /// `None` once the game is removed.
+    game: Mutex<Option<Box<dyn GameTrait>>>,
+    lifecycle: Mutex<GameLifecycle>,
+    offers: Mutex<DrawOffers>,
+    /// Players in the game's turn tracker after the last command, readable
+    /// without waiting for the game.
+    seated: AtomicUsize,
+    /// `GameTrait::player_scores` after the last command, to publish only
+    /// the scores that changed.
+    scores: Mutex<HashMap<String, i64>>,
+    /// When the last turn was handed out, for timing turns.
+    #[cfg(feature = "metrics")]
+    turn_started: Mutex<Option<Instant>>,
+    mailbox: Mutex<VecDeque<Command>>,
+    /// Set while the game is queued for or held by a worker, so that only
+    /// one worker handles it at a time.
+    scheduled: AtomicBool,
+}
+
+#[derive(Default)]
+struct Queue {
+    ready: VecDeque<Arc<Slot>>,
+    closing: bool,
+}
+
+struct Shared {
+    games: RwLock<HashMap<GameId, Arc<Slot>>>,
+    queue: Mutex<Queue>,
+    wake: Condvar,
+    events: Mutex<Sender<(GameId, Event)>>,
+    bus: EventBus,
+}
+
+impl Shared {
+    fn schedule(&self, slot: Arc<Slot>) {
+        self.queue.lock().unwrap().ready.push_back(slot);
+        self.wake.notify_one();
+    }
+
+    fn emit(&self, id: GameId, event: Event) {
+        // Nobody listening is fine, the games run all the same.
+        let _ = self.events.lock().unwrap().send((id, event));
+    }
+}
+
+/// Games hosted on a pool of threads, the `GameTrait` calls on each made
+/// through `send` and answered on `events`.
+pub struct GameHost {
+    shared: Arc<Shared>,
+    events: Receiver<(GameId, Event)>,
+    workers: Vec<JoinHandle<()>>,
+    next_id: AtomicU64,
+    /// Commands sent, for `Metrics`.
+    sent: MessageRate,
+}
+
+impl GameHost {
+    /// Starts `workers` threads, at least one.
+    pub fn new(workers: usize) -> Self {
+        let (events_tx, events) = mpsc::channel();
+        let shared = Arc::new(Shared {
+            games: RwLock::new(HashMap::new()),
+            queue: Mutex::new(Queue::default()),
+            wake: Condvar::new(),
+            events: Mutex::new(events_tx),
+            bus: EventBus::default(),
+        });
+        let workers = (0..workers.max(1))
+            .map(|i| {
+                let shared = shared.clone();
+                thread::Builder::new()
+                    .name(format!("game-host-{i}"))
+                    .spawn(move || work(&shared))
+                    .expect("failed to spawn game host worker")
+            })
+            .collect();
+        Self {
+            shared,
+            events,
+            workers,
+            next_id: AtomicU64::new(0),
+            sent: MessageRate::new(),
+        }
+    }
+
+    pub fn add(&self, game: Box<dyn GameTrait>) -> GameId {
+        let id = GameId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let slot = Slot {
+            id,
+            game: Mutex::new(Some(game)),
+            lifecycle: Mutex::new(GameLifecycle::default()),
+            offers: Mutex::new(DrawOffers::new()),
+            seated: AtomicUsize::new(0),
+            scores: Mutex::new(HashMap::new()),
+            #[cfg(feature = "metrics")]
+            turn_started: Mutex::new(None),
+            mailbox: Mutex::new(VecDeque::new()),
+            scheduled: AtomicBool::new(false),
+        };
+        let mut games = self.shared.games.write().unwrap();
+        games.insert(id, Arc::new(slot));
+        id
+    }
+
+    /// Takes the game out of the host, waiting for any command it's in the
+    /// middle of. Commands still in its mailbox are dropped.
+    pub fn remove(&self, id: GameId) -> Option<Box<dyn GameTrait>> {
+        let slot = self.shared.games.write().unwrap().remove(&id)?;
+        drop_mail(&slot);
+        let game = slot.game.lock().unwrap().take();
+        game
+    }
+
+    pub fn contains(&self, id: GameId) -> bool {
+        self.shared.games.read().unwrap().contains_key(&id)
+    }
+
+    /// Where the game is, as of the last command handled.
+    pub fn lifecycle(&self, id: GameId) -> Result<GameLifecycle, HostError> {
+        // Not holding the games while waiting, as a worker may hold the
+        // lifecycle while it removes a crashed game.
+        let slot = self
+            .shared
+            .games
+            .read()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or(HostError::UnknownGame(id))?;
+        let lifecycle = slot.lifecycle.lock().unwrap().clone();
+        Ok(lifecycle)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.games.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Queues `command` for the game without waiting for it to be handled.
+    pub fn send(&self, id: GameId, command: Command) -> Result<(), HostError> {
+        let games = self.shared.games.read().unwrap();
+        let slot = games.get(&id).ok_or(HostError::UnknownGame(id))?;
+        slot.mailbox.lock().unwrap().push_back(command);
+        #[cfg(feature = "metrics")]
+        prometheus::HOST_QUEUE_DEPTH.add(1);
+        self.sent.record(Instant::now());
+        if !slot.scheduled.swap(true, Ordering::AcqRel) {
+            self.shared.schedule(slot.clone());
+        }
+        Ok(())
+    }
+
+    /// Events from all games, in the order they happened within each game.
+    pub fn events(&self) -> &Receiver<(GameId, Event)> {
+        &self.events
+    }
+
+    /// Where the host publishes what happens in its games. Clone it to
+    /// publish from elsewhere into the same bus.
+    pub fn bus(&self) -> &EventBus {
+        &self.shared.bus
+    }
+}
+
+/// The games hosted, the players seated in each and how many commands were
+/// sent in the last second.
+impl Metrics for GameHost {
+    fn metrics(&self, now: Instant) -> Vec<Metric> {
+        let games = self.shared.games.read().unwrap();
+        let mut seated: Vec<_> = games
+            .values()
+            .map(|slot| (slot.id, slot.seated.load(Ordering::Relaxed) as u64))
+            .collect();
+        seated.sort();
+        let mut metrics = vec![
+            Metric::total("games", games.len() as u64),
+            Metric::total("players_seated", seated.iter().map(|(_, n)| n).sum()),
+            Metric::total("messages_per_second", self.sent.per_second(now)),
+        ];
+        metrics.extend(
+            seated
+                .into_iter()
+                .map(|(id, n)| Metric::per_game("players_seated", id, n)),
+        );
+        metrics
+    }
+}
+
+/// Finishes the commands already sent, then stops the workers.
+impl Drop for GameHost {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().closing = true;
+        self.shared.wake.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn work(shared: &Shared) {
+    loop {
+        let slot = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if let Some(slot) = queue.ready.pop_front() {
+                    break slot;
+                }
+                if queue.closing {
+                    return;
+                }
+                queue = shared.wake.wait(queue).unwrap();
+            }
+        };
+        if handle(shared, &slot) {
+            shared.schedule(slot);
+        }
+    }
+}
+
+/// Handles up to a batch of the game's commands, and says whether it has
+/// more waiting.
+fn handle(shared: &Shared, slot: &Arc<Slot>) -> bool {
+    let _span = info_span!("game", id = slot.id.0).entered();
+    let mut game = slot.game.lock().unwrap();
+    let mut lifecycle = slot.lifecycle.lock().unwrap();
+    let mut offers = slot.offers.lock().unwrap();
+    let mut crashed = None;
+    for _ in 0..BATCH {
+        let Some(command) = slot.mailbox.lock().unwrap().pop_front() else {
+            break;
+        };
+        #[cfg(feature = "metrics")]
+        prometheus::HOST_QUEUE_DEPTH.sub(1);
+        let Some(inner) = game.as_mut() else {
+            break;
+        };
+        let mut events = vec![];
+        let mut published = vec![];
+        let ran = panic::catch_unwind(AssertUnwindSafe(|| {
+            run(
+                inner.as_mut(),
+                &mut lifecycle,
+                &mut offers,
+                command,
+                &mut events,
+                &mut published,
+            )
+        }));
+        match ran {
+            Ok(refused) => {
+                let seated = inner.turn_tracker().map_or(0, |t| t.players().count());
+                slot.seated.store(seated, Ordering::Relaxed);
+                let scores = inner.player_scores();
+                let mut before = slot.scores.lock().unwrap();
+                published.extend(changed_scores(&before, &scores));
+                *before = scores;
+                published.extend(events.iter().filter_map(|event| match event {
+                    Event::Lifecycle(LifecycleEvent {
+                        to: GameLifecycle::Finished { outcome },
+                        ..
+                    }) => Some(GameEvent::GameOver {
+                        outcome: outcome.clone(),
+                    }),
+                    _ => None,
+                }));
+                events.extend(refused.err().map(Event::Refused));
+                #[cfg(feature = "metrics")]
+                time_turns(slot, &published);
+                // Onto the bus first, so it has everything the events
+                // channel has told of.
+                for event in published {
+                    shared.bus.publish(slot.id, event);
+                }
+                for event in events {
+                    shared.emit(slot.id, event);
+                }
+            }
+            Err(panic) => {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                error!(panic = %message, "game crashed");
+                *game = None;
+                drop_mail(slot);
+                crashed = Some(message);
+                break;
+            }
+        }
+    }
+    drop(offers);
+    drop(lifecycle);
+    drop(game);
+    // Only once the slot is let go of, so as not to wait on it holding the
+    // games.
+    if let Some(message) = crashed {
+        shared.games.write().unwrap().remove(&slot.id);
+        shared.emit(slot.id, Event::Crashed(message));
+    }
+    // Unschedule, unless mail came in since the mailbox was last empty.
+    let mailbox = slot.mailbox.lock().unwrap();
+    if mailbox.is_empty() {
+        slot.scheduled.store(false, Ordering::Release);
+        false
+    } else {
+        true
+    }
+}
+
+fn drop_mail(slot: &Slot) {
+    let mut mailbox = slot.mailbox.lock().unwrap();
+    #[cfg(feature = "metrics")]
+    prometheus::HOST_QUEUE_DEPTH.sub(mailbox.len() as i64);
+    mailbox.clear();
+}
+
+/// Times each move taken from when its turn was handed out.
+#[cfg(feature = "metrics")]
+fn time_turns(slot: &Slot, published: &[GameEvent]) {
+    let now = Instant::now();
+    let mut turn_started = slot.turn_started.lock().unwrap();
+    for event in published {
+        match event {
+            GameEvent::MoveApplied { .. } => {
+                if let Some(started) = turn_started.take() {
+                    prometheus::TURN_DURATION.observe(now.saturating_duration_since(started));
+                }
+            }
+            GameEvent::TurnStarted { .. } => *turn_started = Some(now),
+            _ => {}
+        }
+    }
+}
+
+/// Runs `command`, adding what came of it to `events` and what the bus is
+/// told of it to `published`.
+fn run(
+    game: &mut dyn GameTrait,
+    lifecycle: &mut GameLifecycle,
+    offers: &mut DrawOffers,
+    command: Command,
+    events: &mut Vec<Event>,
+    published: &mut Vec<GameEvent>,
+) -> Result<(), LifecycleError> {
+    match command {
+        Command::Connect(user) => {
+            published.push(GameEvent::PlayerJoined {
+                player: user.name.clone(),
+            });
+            game.player_connected(user);
+        }
+        Command::Disconnect(name) => {
+            offers.withdraw();
+            game.player_disconnected(&name);
+        }
+        Command::Reset(users) => {
+            offers.withdraw();
+            game.reset(users);
+            if *lifecycle != GameLifecycle::WaitingForPlayers {
+                change(lifecycle, GameLifecycle::WaitingForPlayers, events)?;
+            }
+        }
+        Command::Start => {
+            change(lifecycle, GameLifecycle::Starting, events)?;
+            let turn = game.try_start_game();
+            published.extend(turn.as_ref().map(turn_started));
+            let to = match turn {
+                Some(_) => GameLifecycle::InProgress,
+                None => GameLifecycle::WaitingForPlayers,
+            };
+            events.push(Event::Started(turn));
+            change(lifecycle, to, events)?;
+        }
+        Command::Move(token, player_move) => {
+            lifecycle.check_move()?;
+            let _turn = debug_span!("turn", player = %token.user.name).entered();
+            let mover = token.user.name.clone();
+            let serialized = player_move.serialized.clone();
+            let result = game.player_moves(token, player_move);
+            if let PlayerMoveResult::Ok(_) | PlayerMoveResult::Win | PlayerMoveResult::Draw = result
+            {
+                published.push(GameEvent::MoveApplied {
+                    player: mover.clone(),
+                    player_move: serialized,
+                });
+            }
+            if let PlayerMoveResult::Ok(turn) = &result {
+                published.push(turn_started(turn));
+            }
+            let outcome = match &result {
+                PlayerMoveResult::Win => Some(GameOutcome::Win { winner: mover }),
+                PlayerMoveResult::Draw => Some(GameOutcome::Draw),
+                PlayerMoveResult::OutOfTime(outcome) => Some(outcome.clone()),
+                _ => None,
+            };
+            events.push(Event::Moved(result));
+            if outcome.is_some() {
+                finish(lifecycle, outcome, events)?;
+            }
+        }
+        Command::TimedOut(token) => {
+            lifecycle.check_move()?;
+            let _turn = debug_span!("turn", player = %token.user.name, timed_out = true).entered();
+            let turn = game.current_player_disconnected(token);
+            published.extend(turn.as_ref().map(turn_started));
+            events.push(Event::TimedOut(turn));
+        }
+        Command::Resign(player) => {
+            offers.withdraw();
+            forfeited(lifecycle, game.resign(&player), events)?
+        }
+        Command::Disqualify(player, reason) => {
+            offers.withdraw();
+            forfeited(lifecycle, game.disqualify(&player, &reason), events)?
+        }
+        Command::Pause => change(lifecycle, GameLifecycle::Paused, events)?,
+        Command::Resume => change(lifecycle, GameLifecycle::InProgress, events)?,
+        Command::AdjustClock(player, by_ms) => {
+            let by = Duration::from_millis(by_ms.unsigned_abs());
+            match game.clock_mut() {
+                Some(clock) if by_ms >= 0 => clock.add_time(&player, by),
+                Some(clock) => clock.take_time(&player, by),
+                None => {}
+            }
+        }
+        Command::Restart => {
+            let users = game
+                .turn_tracker()
+                .map(|tracker| tracker.players().cloned().collect())
+                .unwrap_or_default();
+            run(
+                game,
+                lifecycle,
+                offers,
+                Command::Reset(users),
+                events,
+                published,
+            )?;
+            run(game, lifecycle, offers, Command::Start, events, published)?;
+        }
+        Command::Handicap(player, handicap) => {
+            let unused = game.apply_handicap(&player, handicap);
+            if !unused.is_none() {
+                debug!(player, ?unused, "handicap the game has no use for");
+            }
+        }
+        Command::OfferDraw(player) => {
+            lifecycle.check_move()?;
+            offered(lifecycle, offers.offer(game, &player), events)?
+        }
+        Command::AcceptDraw(player) => {
+            lifecycle.check_move()?;
+            offered(lifecycle, offers.accept(game, &player), events)?
+        }
+        Command::DeclineDraw(player) => offered(lifecycle, offers.decline(game, &player), events)?,
+    }
+    Ok(())
+}
+
+fn turn_started(turn: &PlayerTurn) -> GameEvent {
+    GameEvent::TurnStarted {
+        player: turn.token.user.name.clone(),
+    }
+}
+
+/// The scores in `now` that aren't in `before`, by player.
+fn changed_scores(before: &HashMap<String, i64>, now: &HashMap<String, i64>) -> Vec<GameEvent> {
+    let mut changed: Vec<_> = now
+        .iter()
+        .filter(|(player, score)| before.get(*player) != Some(*score))
+        .collect();
+    changed.sort();
+    changed
+        .into_iter()
+        .map(|(player, score)| GameEvent::ScoreChanged {
+            player: player.clone(),
+            score: *score,
+        })
+        .collect()
+}
+
+fn forfeited(
+    lifecycle: &mut GameLifecycle,
+    forfeit: Forfeit,
+    events: &mut Vec<Event>,
+) -> Result<(), LifecycleError> {
+    let outcome = forfeit.outcome.clone();
+    events.push(Event::Forfeited(forfeit));
+    if outcome.is_some() {
+        finish(lifecycle, outcome, events)?;
+    }
+    Ok(())
+}
+
+/// Finishes a game that was being played. Games that ended some other way,
+/// such as by moves sent before anyone started them, are left as they are.
+/// Sends what came of a draw offer, and finishes the game once everyone
+/// agreed.
+fn offered(
+    lifecycle: &mut GameLifecycle,
+    told: Result<ToClient, DrawOfferError>,
+    events: &mut Vec<Event>,
+) -> Result<(), LifecycleError> {
+    let agreed = matches!(told, Ok(ToClient::GameOver(_)));
+    events.push(Event::DrawOffer(told));
+    if agreed {
+        finish(lifecycle, Some(GameOutcome::AgreedDraw), events)?;
+    }
+    Ok(())
+}
+
+fn finish(
+    lifecycle: &mut GameLifecycle,
+    outcome: Option<GameOutcome>,
+    events: &mut Vec<Event>,
+) -> Result<(), LifecycleError> {
+    match lifecycle {
+        GameLifecycle::InProgress | GameLifecycle::Paused => {
+            change(lifecycle, GameLifecycle::Finished { outcome }, events)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn change(
+    lifecycle: &mut GameLifecycle,
+    to: GameLifecycle,
+    events: &mut Vec<Event>,
+) -> Result<(), LifecycleError> {
+    events.push(Event::Lifecycle(lifecycle.transition(to)?));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::Any;
+
+    use super::*;
+    use crate::games::connect_four::ConnectFour;
+    use crate::gametraits::Paint;
+    use crate::replay::test_game::TakeAway;
+    use crate::test_support::users;
+
+    /// Take-away whose moves wait for the gate to open.
+    #[derive(Clone, Debug)]
+    struct Gated {
+        game: TakeAway,
+        gate: Arc<(Mutex<bool>, Condvar)>,
+    }
+
+    impl GameTrait for Gated {
+        fn player_moves(&mut self, token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+            let (open, opened) = &*self.gate;
+            let mut open = open.lock().unwrap();
+            while !*open {
+                open = opened.wait(open).unwrap();
+            }
+            self.game.player_moves(token, player_move)
+        }
+
+        fn current_player_disconnected(&mut self, token: TurnToken) -> Option<PlayerTurn> {
+            self.game.current_player_disconnected(token)
+        }
+
+        fn try_start_game(&mut self) -> Option<PlayerTurn> {
+            self.game.try_start_game()
+        }
+
+        fn player_connected(&mut self, user: User) {
+            self.game.player_connected(user)
+        }
+
+        fn player_disconnected(&mut self, user: &str) {
+            self.game.player_disconnected(user)
+        }
+
+        fn reset(&mut self, users: Vec<User>) {
+            self.game.reset(users)
+        }
+    }
+
+    impl Paint for Gated {
+        fn eq(&self, _other: &dyn Paint) -> bool {
+            false
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    /// Take-away that panics on any move.
+    #[derive(Clone, Debug)]
+    struct Crashing(TakeAway);
+
+    impl GameTrait for Crashing {
+        fn player_moves(&mut self, _token: TurnToken, _move: PlayerMove) -> PlayerMoveResult {
+            panic!("crashing on purpose");
+        }
+
+        fn current_player_disconnected(&mut self, token: TurnToken) -> Option<PlayerTurn> {
+            self.0.current_player_disconnected(token)
+        }
+
+        fn try_start_game(&mut self) -> Option<PlayerTurn> {
+            self.0.try_start_game()
+        }
+
+        fn player_connected(&mut self, user: User) {
+            self.0.player_connected(user)
+        }
+
+        fn player_disconnected(&mut self, user: &str) {
+            self.0.player_disconnected(user)
+        }
+
+        fn reset(&mut self, users: Vec<User>) {
+            self.0.reset(users)
+        }
+    }
+
+    impl Paint for Crashing {
+        fn eq(&self, _other: &dyn Paint) -> bool {
+            false
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    fn take(n: u32) -> PlayerMove {
+        PlayerMove {
+            serialized: n.to_string(),
+        }
+    }
+
+    fn next_any(host: &GameHost) -> (GameId, Event) {
+        host.events()
+            .recv_timeout(Duration::from_secs(5))
+            .expect("no event")
+    }
+
+    /// The next event that isn't a lifecycle change.
+    fn next(host: &GameHost) -> (GameId, Event) {
+        loop {
+            match next_any(host) {
+                (_, Event::Lifecycle(_)) => {}
+                event => break event,
+            }
+        }
+    }
+
+    fn started(host: &GameHost, id: GameId) -> PlayerTurn {
+        host.send(id, Command::Reset(users(2))).unwrap();
+        host.send(id, Command::Start).unwrap();
+        match next(host) {
+            (from, Event::Started(Some(turn))) if from == id => turn,
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn plays_games_through_mailboxes() {
+        let host = GameHost::new(2);
+        let id = host.add(Box::new(TakeAway::new(4)));
+        let turn = started(&host, id);
+        host.send(id, Command::Move(turn.token, take(3))).unwrap();
+        let (_, Event::Moved(PlayerMoveResult::Ok(turn))) = next(&host) else {
+            panic!()
+        };
+        assert_eq!(turn.token.user.name, "p2");
+        host.send(id, Command::Move(turn.token, take(1))).unwrap();
+        assert!(matches!(
+            next(&host),
+            (_, Event::Moved(PlayerMoveResult::Win))
+        ));
+
+        let game = host.remove(id).unwrap();
+        assert!(
+            game.as_any()
+                .downcast_ref::<TakeAway>()
+                .unwrap()
+                .remaining()
+                == 0
+        );
+        assert_eq!(
+            host.send(id, Command::Start),
+            Err(HostError::UnknownGame(id))
+        );
+    }
+
+    #[test]
+    fn slow_games_dont_hold_up_others() {
+        let host = GameHost::new(2);
+        let gate = Arc::new((Mutex::new(false), Condvar::new()));
+        let slow = host.add(Box::new(Gated {
+            game: TakeAway::new(4),
+            gate: gate.clone(),
+        }));
+        let fast = host.add(Box::new(TakeAway::new(2)));
+        let slow_turn = started(&host, slow);
+        let fast_turn = started(&host, fast);
+
+        host.send(slow, Command::Move(slow_turn.token, take(1)))
+            .unwrap();
+        host.send(fast, Command::Move(fast_turn.token, take(2)))
+            .unwrap();
+        let (id, event) = next(&host);
+        assert_eq!(id, fast);
+        assert!(matches!(event, Event::Moved(PlayerMoveResult::Win)));
+
+        *gate.0.lock().unwrap() = true;
+        gate.1.notify_all();
+        assert_eq!(next(&host).0, slow);
+    }
+
+    #[test]
+    fn crashed_games_are_removed() {
+        let host = GameHost::new(1);
+        let crashing = host.add(Box::new(Crashing(TakeAway::new(4))));
+        let other = host.add(Box::new(TakeAway::new(4)));
+        let turn = started(&host, crashing);
+        host.send(crashing, Command::Move(turn.token, take(1)))
+            .unwrap();
+        assert!(matches!(next(&host), (id, Event::Crashed(_)) if id == crashing));
+        assert!(!host.contains(crashing));
+        started(&host, other);
+        assert_eq!(host.len(), 1);
+    }
+
+    #[test]
+    fn moves_before_the_start_are_rejected() {
+        let host = GameHost::new(1);
+        let id = host.add(Box::new(TakeAway::new(4)));
+        let nobody = TurnToken {
+            user: crate::test_support::user("nobody"),
+        };
+        host.send(id, Command::Move(nobody, take(1))).unwrap();
+        assert!(matches!(
+            next(&host),
+            (_, Event::Moved(PlayerMoveResult::InvalidMove(None)))
+        ));
+        let game = host.remove(id).unwrap();
+        assert_eq!(
+            game.as_any()
+                .downcast_ref::<TakeAway>()
+                .unwrap()
+                .remaining(),
+            4
+        );
+    }
+
+    #[test]
+    fn tracks_lifecycles() {
+        let host = GameHost::new(1);
+        let id = host.add(Box::new(TakeAway::new(1)));
+        let turn = started(&host, id);
+        assert_eq!(host.lifecycle(id), Ok(GameLifecycle::InProgress));
+
+        host.send(id, Command::Pause).unwrap();
+        host.send(
+            id,
+            Command::Move(
+                TurnToken {
+                    user: turn.token.user.clone(),
+                },
+                take(1),
+            ),
+        )
+        .unwrap();
+        assert!(matches!(
+            next(&host),
+            (_, Event::Refused(LifecycleError::Paused))
+        ));
+        host.send(id, Command::Resume).unwrap();
+        host.send(id, Command::Move(turn.token, take(1))).unwrap();
+        assert!(matches!(
+            next(&host),
+            (_, Event::Moved(PlayerMoveResult::Win))
+        ));
+        let (_, Event::Lifecycle(finished)) = next_any(&host) else {
+            panic!()
+        };
+        assert_eq!(
+            finished.to,
+            GameLifecycle::Finished {
+                outcome: Some(GameOutcome::Win {
+                    winner: "p1".to_string()
+                })
+            }
+        );
+
+        host.send(id, Command::Start).unwrap();
+        assert!(matches!(
+            next(&host),
+            (_, Event::Refused(LifecycleError::IllegalTransition { .. }))
+        ));
+    }
+
+    #[test]
+    fn agreed_draws_finish_the_game() {
+        let host = GameHost::new(1);
+        let id = host.add(Box::new(ConnectFour::default()));
+        started(&host, id);
+
+        host.send(id, Command::AcceptDraw("p2".to_string()))
+            .unwrap();
+        assert!(matches!(
+            next(&host),
+            (_, Event::DrawOffer(Err(DrawOfferError::NoOffer)))
+        ));
+        host.send(id, Command::OfferDraw("p1".to_string())).unwrap();
+        assert!(matches!(
+            next(&host),
+            (_, Event::DrawOffer(Ok(ToClient::DrawOffered(_))))
+        ));
+        host.send(id, Command::DeclineDraw("p2".to_string()))
+            .unwrap();
+        assert!(matches!(
+            next(&host),
+            (_, Event::DrawOffer(Ok(ToClient::DrawDeclined(_))))
+        ));
+        assert_eq!(host.lifecycle(id), Ok(GameLifecycle::InProgress));
+
+        host.send(id, Command::OfferDraw("p2".to_string())).unwrap();
+        next(&host);
+        host.send(id, Command::AcceptDraw("p1".to_string()))
+            .unwrap();
+        assert!(matches!(
+            next(&host),
+            (_, Event::DrawOffer(Ok(ToClient::GameOver(_))))
+        ));
+        let (_, Event::Lifecycle(finished)) = next_any(&host) else {
+            panic!()
+        };
+        assert_eq!(
+            finished.to,
+            GameLifecycle::Finished {
+                outcome: Some(GameOutcome::AgreedDraw)
+            }
+        );
+    }
+
+    #[test]
+    fn running_out_of_time_finishes_the_game() {
+        let host = GameHost::new(1);
+        let game = ConnectFour::default().with_clock(Duration::from_secs(15));
+        let id = host.add(Box::new(game));
+        let turn = started(&host, id);
+        host.send(id, Command::AdjustClock("p1".to_string(), -15_000))
+            .unwrap();
+        let column = PlayerMove {
+            serialized: "{\"move\":3}".to_string(),
+        };
+        host.send(id, Command::Move(turn.token, column)).unwrap();
+        let p2 = GameOutcome::Win {
+            winner: "p2".to_string(),
+        };
+        assert!(matches!(
+            next(&host),
+            (_, Event::Moved(PlayerMoveResult::OutOfTime(outcome))) if outcome == p2
+        ));
+        assert_eq!(
+            host.lifecycle(id),
+            Ok(GameLifecycle::Finished { outcome: Some(p2) })
+        );
+    }
+
+    #[test]
+    fn restarts_with_the_same_players() {
+        let host = GameHost::new(1);
+        let id = host.add(Box::new(TakeAway::new(1)));
+        let turn = started(&host, id);
+        host.send(id, Command::Move(turn.token, take(1))).unwrap();
+        next(&host);
+
+        host.send(id, Command::Restart).unwrap();
+        let (_, Event::Started(Some(turn))) = next(&host) else {
+            panic!()
+        };
+        assert_eq!(turn.token.user.name, "p1");
+        assert_eq!(host.lifecycle(id), Ok(GameLifecycle::InProgress));
+    }
+
+    #[test]
+    fn counts_games_and_players() {
+        let host = GameHost::new(1);
+        let id = host.add(Box::new(TakeAway::new(4)));
+        host.add(Box::new(TakeAway::new(4)));
+        started(&host, id);
+        let metrics = host.metrics(Instant::now());
+        assert!(metrics.contains(&Metric::total("games", 2)));
+        assert!(metrics.contains(&Metric::total("players_seated", 2)));
+        assert!(metrics.contains(&Metric::per_game("players_seated", id, 2)));
+    }
+
+    #[test]
+    fn publishes_to_the_bus() {
+        let host = GameHost::new(1);
+        let mut bus = host.bus().subscribe();
+        let id = host.add(Box::new(TakeAway::new(1)));
+        for name in ["p1", "p2"] {
+            host.send(id, Command::Connect(crate::test_support::user(name)))
+                .unwrap();
+        }
+        host.send(id, Command::Start).unwrap();
+        let (_, Event::Started(Some(turn))) = next(&host) else {
+            panic!()
+        };
+        host.send(id, Command::Move(turn.token, take(1))).unwrap();
+        next(&host);
+        next_any(&host);
+
+        let player = |name: &str| name.to_string();
+        let mut published = vec![];
+        while let Ok((from, event)) = bus.try_recv() {
+            assert_eq!(from, id);
+            published.push(event);
+        }
+        assert_eq!(
+            published,
+            [
+                GameEvent::PlayerJoined {
+                    player: player("p1")
+                },
+                GameEvent::PlayerJoined {
+                    player: player("p2")
+                },
+                GameEvent::TurnStarted {
+                    player: player("p1")
+                },
+                GameEvent::MoveApplied {
+                    player: player("p1"),
+                    player_move: "1".to_string()
+                },
+                GameEvent::GameOver {
+                    outcome: Some(GameOutcome::Win {
+                        winner: player("p1")
+                    })
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn publishes_only_changed_scores() {
+        let before = HashMap::from([("p1".to_string(), 2), ("p2".to_string(), 2)]);
+        let now = HashMap::from([("p1".to_string(), 5), ("p2".to_string(), 2)]);
+        assert_eq!(
+            changed_scores(&before, &now),
+            [GameEvent::ScoreChanged {
+                player: "p1".to_string(),
+                score: 5
+            }]
+        );
+    }
+}
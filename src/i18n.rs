@@ -0,0 +1,220 @@
+//! Translations of the strings people read: errors sent to bots, names
+//! refused and game stages. Servers load a `Catalog` per language from
+//! files in a small subset of Fluent, one message per line:
+//!
+//! ```text
+//! # Comments start with a hash.
+//! error-invalid-move = coup invalide
+//! name-taken = { $name } est déjà pris
+//! ```
+//!
+//! English is built in, and is used for any key a catalog doesn't have.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::lifecycle::GameLifecycle;
+use crate::messages;
+use crate::names::{NameError, MAX_NAME_LEN};
+
+const ENGLISH: &str = include_str!("i18n/en.ftl");
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Catalog {
+    /// A language tag, such as `sv` or `pt-BR`.
+    pub locale: String,
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn english() -> Self {
+        Self {
+            locale: "en".to_string(),
+            messages: parse_messages(ENGLISH).expect("the English catalog parses"),
+        }
+    }
+
+    /// `source`'s messages, over English for the keys it leaves out.
+    pub fn parse(locale: impl Into<String>, source: &str) -> Result<Self, CatalogError> {
+        let mut catalog = Self::english();
+        catalog.locale = locale.into();
+        catalog.messages.extend(parse_messages(source)?);
+        Ok(catalog)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+
+    /// The message for `key` with each `{ $name }` filled in from `args`.
+    /// Unknown keys come back as themselves, so a missing translation shows
+    /// up without breaking anything.
+    pub fn text(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let Some(mut rest) = self.get(key) else {
+            return key.to_string();
+        };
+        let mut text = String::new();
+        while let Some((before, after)) = rest.split_once('{') {
+            text.push_str(before);
+            let Some((placeable, after)) = after.split_once('}') else {
+                rest = after;
+                text.push('{');
+                break;
+            };
+            let name = placeable.trim().trim_start_matches('$');
+            match args.iter().find(|(arg, _)| *arg == name) {
+                Some((_, value)) => text.push_str(&value.to_string()),
+                None => {
+                    text.push('{');
+                    text.push_str(placeable);
+                    text.push('}');
+                }
+            }
+            rest = after;
+        }
+        text.push_str(rest);
+        text
+    }
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("line {line} of the catalog: {reason}")]
+pub struct CatalogError {
+    /// From 1.
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+fn parse_messages(source: &str) -> Result<HashMap<String, String>, CatalogError> {
+    let mut messages = HashMap::new();
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |reason| CatalogError {
+            line: i + 1,
+            reason,
+        };
+        let (key, value) = line
+            .split_once('=')
+            .ok_or(error("expected `key = value`"))?;
+        let key = key.trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(error("keys are letters, digits, `-` and `_`"));
+        }
+        messages.insert(key.to_string(), value.trim().to_string());
+    }
+    Ok(messages)
+}
+
+/// Things with a message for people to read, in a catalog's language.
+pub trait Localize {
+    fn localize(&self, catalog: &Catalog) -> String;
+}
+
+impl Localize for messages::Error {
+    fn localize(&self, catalog: &Catalog) -> String {
+        catalog.text(&format!("error-{}", self.reason.replace(' ', "-")), &[])
+    }
+}
+
+impl Localize for NameError {
+    fn localize(&self, catalog: &Catalog) -> String {
+        match self {
+            NameError::Empty => catalog.text("name-empty", &[]),
+            NameError::TooLong => catalog.text("name-too-long", &[("max", &MAX_NAME_LEN)]),
+            NameError::InvalidChar(c) => {
+                catalog.text("name-invalid-char", &[("char", &format!("{c:?}"))])
+            }
+            NameError::Reserved(name) => catalog.text("name-reserved", &[("name", name)]),
+            NameError::Taken(name) => catalog.text("name-taken", &[("name", name)]),
+        }
+    }
+}
+
+impl Localize for GameLifecycle {
+    fn localize(&self, catalog: &Catalog) -> String {
+        catalog.text(
+            &format!("lifecycle-{}", self.to_string().replace(' ', "-")),
+            &[],
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::{ToClient, INVALID_MOVE};
+
+    const SWEDISH: &str = "
+        # Svenska
+        error-invalid-move = ogiltigt drag
+        name-taken = { $name } är upptaget
+    ";
+
+    #[test]
+    fn english_matches_the_crate() {
+        let english = Catalog::english();
+        let names = [
+            NameError::Empty,
+            NameError::TooLong,
+            NameError::InvalidChar(' '),
+            NameError::Reserved("admin".to_string()),
+            NameError::Taken("bob".to_string()),
+        ];
+        for error in names {
+            assert_eq!(error.localize(&english), error.to_string());
+        }
+        let stage = GameLifecycle::WaitingForPlayers;
+        assert_eq!(stage.localize(&english), stage.to_string());
+    }
+
+    #[test]
+    fn translates_with_english_to_fall_back_on() {
+        let swedish = Catalog::parse("sv", SWEDISH).unwrap();
+        assert_eq!(
+            NameError::Taken("bob".to_string()).localize(&swedish),
+            "bob är upptaget"
+        );
+        assert_eq!(NameError::Empty.localize(&swedish), "the name is empty");
+        assert_eq!(swedish.text("no-such-key", &[]), "no-such-key");
+
+        let ToClient::Error(error) = INVALID_MOVE.localized(&swedish) else {
+            panic!()
+        };
+        assert_eq!(error.reason, "invalid move");
+        assert_eq!(error.message.as_deref(), Some("ogiltigt drag"));
+    }
+
+    #[test]
+    fn leaves_unknown_placeables() {
+        let catalog = Catalog::parse("en", "greeting = hi { $who }, { $name }!").unwrap();
+        assert_eq!(
+            catalog.text("greeting", &[("name", &"bob")]),
+            "hi { $who }, bob!"
+        );
+    }
+
+    #[test]
+    fn reports_bad_lines() {
+        assert_eq!(
+            Catalog::parse("sv", "ok = fine\n\nno equals sign"),
+            Err(CatalogError {
+                line: 3,
+                reason: "expected `key = value`"
+            })
+        );
+        assert!(Catalog::parse("sv", "bad key = x").is_err());
+    }
+}
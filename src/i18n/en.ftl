@@ -0,0 +1,23 @@
+# English, the fallback for every other language. Keys missing from a
+# translation are shown from here.
+
+# Errors sent to bots, keyed by their `reason`.
+error-invalid-message-format = invalid message format
+error-wrong-password = wrong password
+error-invalid-move = invalid move
+error-invalid-username = invalid username
+
+# Why a name was refused.
+name-empty = the name is empty
+name-too-long = the name is longer than { $max } characters
+name-invalid-char = { $char } isn't allowed in names
+name-reserved = { $name } is reserved
+name-taken = { $name } is taken
+
+# Where a game is, as UIs show it.
+lifecycle-waiting-for-players = waiting for players
+lifecycle-starting = starting
+lifecycle-in-progress = in progress
+lifecycle-paused = paused
+lifecycle-finished = finished
+lifecycle-archived = archived
@@ -1,5 +1,105 @@
+//! Types shared by the game server, its games and the clients and bots
+//! playing them. Only `core` builds without the `std` feature, which is on
+//! by default; the crate is then `no_std` with `alloc`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod admin;
+#[cfg(feature = "std")]
+pub mod anim;
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+#[cfg(feature = "std")]
+pub mod async_game;
+#[cfg(feature = "std")]
+pub mod auction;
+#[cfg(feature = "std")]
+pub mod bot;
+#[cfg(feature = "std")]
+pub mod bus;
+#[cfg(feature = "cabi")]
+pub mod cabi;
+#[cfg(feature = "std")]
+pub mod cards;
+#[cfg(feature = "std")]
+pub mod clock;
+#[cfg(feature = "std")]
+pub mod color;
+#[cfg(feature = "std")]
+pub mod config;
+pub mod core;
+#[cfg(feature = "std")]
+pub mod dice;
+#[cfg(feature = "std")]
+pub mod draw;
+#[cfg(feature = "std")]
+pub mod draw_offers;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod forfeit;
+#[cfg(any(all(test, feature = "std"), feature = "fuzzing"))]
+pub mod fuzz;
+#[cfg(feature = "std")]
+pub mod games;
+#[cfg(feature = "std")]
 pub mod gametraits;
+#[cfg(feature = "std")]
+pub mod grid;
+#[cfg(feature = "std")]
+pub mod guards;
+#[cfg(feature = "std")]
+pub mod handicap;
+#[cfg(feature = "std")]
+pub mod hex;
+#[cfg(feature = "std")]
+pub mod history;
+#[cfg(feature = "std")]
+pub mod host;
+#[cfg(feature = "std")]
+pub mod i18n;
+#[cfg(feature = "std")]
+pub mod lifecycle;
+#[cfg(feature = "std")]
+pub mod mapgen;
+#[cfg(feature = "std")]
 pub mod messages;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod names;
+#[cfg(feature = "std")]
+pub mod pathfinding;
+#[cfg(feature = "std")]
+pub mod private;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod replay;
+#[cfg(feature = "std")]
+pub mod rng;
+#[cfg(feature = "std")]
+pub mod rules;
+#[cfg(feature = "std")]
+pub mod simulate;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "std")]
+pub mod spectators;
+#[cfg(any(all(test, feature = "std"), feature = "test-support"))]
+pub mod test_support;
+#[cfg(feature = "std")]
+pub mod time;
+#[cfg(feature = "std")]
 pub mod turn_tracker;
+#[cfg(feature = "std")]
+pub mod undo;
 
+pub use crate::core::pos;
+#[cfg(feature = "std")]
+pub use error::{Error, Result};
+#[cfg(feature = "std")]
 pub use turn_tracker::TurnTracker;
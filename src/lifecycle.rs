@@ -0,0 +1,186 @@
+//! Where a hosted game is in its life, from gathering players to being put
+//! away, with only the moves between stages that make sense allowed. Each
+//! change comes back as a `LifecycleEvent` for the server to pass on.
+//!
+//! ```text
+//! WaitingForPlayers -> Starting -> InProgress <-> Paused
+//!        ^                |            |            |
+//!        +-- not enough --+            +-> Finished <+
+//!                                           |
+//!                                           +-> Archived
+//! ```
+//!
+//! A reset takes any game not yet archived back to waiting for players, and
+//! a game nobody started can be archived straight away.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::replay::GameOutcome;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GameLifecycle {
+    #[default]
+    WaitingForPlayers,
+    /// Asked to start, and waiting on the game to say whether it can.
+    Starting,
+    InProgress,
+    /// No moves are taken until it's resumed.
+    Paused,
+    /// `None` for games that ended without a result, as when everyone
+    /// left.
+    Finished {
+        outcome: Option<GameOutcome>,
+    },
+    /// Done with for good, kept only for its record.
+    Archived,
+}
+
+impl GameLifecycle {
+    pub fn can_become(&self, to: &GameLifecycle) -> bool {
+        use GameLifecycle::*;
+        matches!(
+            (self, to),
+            (WaitingForPlayers, Starting | Archived)
+                | (Starting, InProgress)
+                | (InProgress, Paused)
+                | (Paused, InProgress)
+                | (InProgress | Paused, Finished { .. })
+                | (Finished { .. }, Archived)
+                | (
+                    Starting | InProgress | Paused | Finished { .. },
+                    WaitingForPlayers
+                )
+        )
+    }
+
+    /// Moves on to `to`, if it's allowed from here.
+    pub fn transition(&mut self, to: GameLifecycle) -> Result<LifecycleEvent, LifecycleError> {
+        if !self.can_become(&to) {
+            return Err(LifecycleError::IllegalTransition {
+                from: self.clone(),
+                to,
+            });
+        }
+        let from = std::mem::replace(self, to.clone());
+        Ok(LifecycleEvent { from, to })
+    }
+
+    /// Whether players can move, which they can't while the game is paused.
+    /// Games that haven't started or are over say so themselves.
+    pub fn check_move(&self) -> Result<(), LifecycleError> {
+        match self {
+            GameLifecycle::Paused => Err(LifecycleError::Paused),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            GameLifecycle::Finished { .. } | GameLifecycle::Archived
+        )
+    }
+}
+
+impl fmt::Display for GameLifecycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            GameLifecycle::WaitingForPlayers => "waiting for players",
+            GameLifecycle::Starting => "starting",
+            GameLifecycle::InProgress => "in progress",
+            GameLifecycle::Paused => "paused",
+            GameLifecycle::Finished { .. } => "finished",
+            GameLifecycle::Archived => "archived",
+        })
+    }
+}
+
+/// A game moving from one stage to the next.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LifecycleEvent {
+    pub from: GameLifecycle,
+    pub to: GameLifecycle,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum LifecycleError {
+    #[error("a game can't go from {from} to {to}")]
+    IllegalTransition {
+        from: GameLifecycle,
+        to: GameLifecycle,
+    },
+    #[error("the game is paused")]
+    Paused,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use GameLifecycle::*;
+
+    fn finished() -> GameLifecycle {
+        Finished {
+            outcome: Some(GameOutcome::Draw),
+        }
+    }
+
+    #[test]
+    fn goes_through_a_game() {
+        let mut lifecycle = GameLifecycle::default();
+        let mut events = vec![];
+        for to in [
+            Starting,
+            InProgress,
+            Paused,
+            InProgress,
+            finished(),
+            Archived,
+        ] {
+            events.push(lifecycle.transition(to).unwrap());
+        }
+        assert_eq!(lifecycle, Archived);
+        assert_eq!(
+            events[4],
+            LifecycleEvent {
+                from: InProgress,
+                to: finished()
+            }
+        );
+        assert!(lifecycle.is_finished());
+    }
+
+    #[test]
+    fn refuses_skipping_ahead_or_going_back() {
+        for (from, to) in [
+            (WaitingForPlayers, InProgress),
+            (WaitingForPlayers, finished()),
+            (Starting, Paused),
+            (finished(), InProgress),
+            (Archived, WaitingForPlayers),
+            (InProgress, InProgress),
+        ] {
+            let mut lifecycle = from.clone();
+            assert_eq!(
+                lifecycle.transition(to.clone()),
+                Err(LifecycleError::IllegalTransition {
+                    from: from.clone(),
+                    to
+                })
+            );
+            assert_eq!(lifecycle, from);
+        }
+    }
+
+    #[test]
+    fn resets_and_pauses() {
+        let mut lifecycle = Paused;
+        assert_eq!(lifecycle.check_move(), Err(LifecycleError::Paused));
+        lifecycle.transition(WaitingForPlayers).unwrap();
+        assert_eq!(lifecycle.check_move(), Ok(()));
+        lifecycle.transition(Archived).unwrap();
+    }
+}
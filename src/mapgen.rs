@@ -0,0 +1,191 @@
+//! Reproducible maps. A generator turns a seed into a board, so a match can
+//! record just the seed, and the symmetric generators give every player the
+//! same terrain from their side.
+
+use serde::{Deserialize, Serialize};
+
+use crate::grid::{is_connected, CellSymbol, Connectivity, Grid};
+use crate::pos::Pos;
+use crate::rng::{GameRng, SplitMix64};
+
+pub trait MapGenerator {
+    type Cell;
+
+    /// The same seed always gives the same map.
+    fn generate(&self, seed: u64) -> Grid<Self::Cell>;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Tile {
+    #[default]
+    Floor,
+    Wall,
+}
+
+impl CellSymbol for Tile {
+    fn to_symbol(&self) -> char {
+        match self {
+            Tile::Floor => '.',
+            Tile::Wall => '#',
+        }
+    }
+
+    fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '.' => Some(Tile::Floor),
+            '#' => Some(Tile::Wall),
+            _ => None,
+        }
+    }
+}
+
+/// Walls scattered over an open floor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RandomObstacles {
+    pub width: usize,
+    pub height: usize,
+    /// Share of the cells to turn into walls, from 0 to 1.
+    pub density: f64,
+    /// Leaves out walls that would cut the floor in pieces, so every floor
+    /// cell can reach every other orthogonally.
+    pub keep_connected: bool,
+}
+
+impl MapGenerator for RandomObstacles {
+    type Cell = Tile;
+
+    fn generate(&self, seed: u64) -> Grid<Tile> {
+        let mut rng = SplitMix64::new(seed);
+        let mut grid = Grid::new(self.width, self.height, Tile::Floor);
+        let mut positions: Vec<Pos> = grid.positions().collect();
+        rng.shuffle(&mut positions);
+
+        let walls = (positions.len() as f64 * self.density.clamp(0.0, 1.0)).round() as usize;
+        let mut placed = 0;
+        for pos in positions {
+            if placed == walls {
+                break;
+            }
+            grid[pos] = Tile::Wall;
+            if self.keep_connected
+                && !is_connected(&grid, Connectivity::Four, |t| *t == Tile::Floor)
+            {
+                grid[pos] = Tile::Floor;
+                continue;
+            }
+            placed += 1;
+        }
+        grid
+    }
+}
+
+/// How a symmetric map repeats itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Symmetry {
+    /// The right half mirrors the left.
+    Horizontal,
+    /// The bottom half mirrors the top.
+    Vertical,
+    /// Turned half a circle about the center, the usual choice for two
+    /// players starting in opposite corners.
+    Rotational,
+}
+
+impl Symmetry {
+    /// Where `pos` ends up on a board of the given size, for placing the
+    /// second player's start opposite the first's.
+    pub fn mirror(self, pos: Pos, width: usize, height: usize) -> Pos {
+        let (right, bottom) = (width as i32 - 1, height as i32 - 1);
+        match self {
+            Symmetry::Horizontal => Pos::new(right - pos.x, pos.y),
+            Symmetry::Vertical => Pos::new(pos.x, bottom - pos.y),
+            Symmetry::Rotational => Pos::new(right - pos.x, bottom - pos.y),
+        }
+    }
+
+    /// Whether `pos` is in the half that gets overwritten by the mirror
+    /// image of the other.
+    fn is_mirrored(self, pos: Pos, width: usize, height: usize) -> bool {
+        let index = |p: Pos| p.y as usize * width + p.x as usize;
+        match self {
+            Symmetry::Horizontal => pos.x as usize >= width.div_ceil(2),
+            Symmetry::Vertical => pos.y as usize >= height.div_ceil(2),
+            Symmetry::Rotational => index(pos) > index(self.mirror(pos, width, height)),
+        }
+    }
+}
+
+/// Any generator's map made symmetric by copying one half onto the other.
+/// Mirroring can disconnect a map the inner generator kept connected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symmetric<G> {
+    pub inner: G,
+    pub symmetry: Symmetry,
+}
+
+impl<G: MapGenerator> MapGenerator for Symmetric<G>
+where
+    G::Cell: Clone,
+{
+    type Cell = G::Cell;
+
+    fn generate(&self, seed: u64) -> Grid<G::Cell> {
+        let mut grid = self.inner.generate(seed);
+        let (width, height) = (grid.width(), grid.height());
+        for pos in grid.positions().collect::<Vec<_>>() {
+            if self.symmetry.is_mirrored(pos, width, height) {
+                grid[pos] = grid[self.symmetry.mirror(pos, width, height)].clone();
+            }
+        }
+        grid
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::encode;
+
+    fn obstacles(keep_connected: bool) -> RandomObstacles {
+        RandomObstacles {
+            width: 9,
+            height: 7,
+            density: 0.3,
+            keep_connected,
+        }
+    }
+
+    #[test]
+    fn random_obstacles() {
+        let map = obstacles(true).generate(7);
+        assert_eq!(encode(&map), encode(&obstacles(true).generate(7)));
+        assert_ne!(encode(&map), encode(&obstacles(true).generate(8)));
+        assert!(is_connected(&map, Connectivity::Four, |t| *t == Tile::Floor));
+        let walls = map.cells().iter().filter(|t| **t == Tile::Wall).count();
+        assert_eq!(walls, 19);
+    }
+
+    #[test]
+    fn symmetric_maps() {
+        for symmetry in [
+            Symmetry::Horizontal,
+            Symmetry::Vertical,
+            Symmetry::Rotational,
+        ] {
+            let generator = Symmetric {
+                inner: obstacles(false),
+                symmetry,
+            };
+            let map = generator.generate(3);
+            for pos in map.positions() {
+                assert_eq!(map[pos], map[symmetry.mirror(pos, 9, 7)], "{symmetry:?}");
+            }
+        }
+        assert_eq!(
+            Symmetry::Rotational.mirror(Pos::new(1, 2), 9, 7),
+            Pos::new(7, 4)
+        );
+    }
+}
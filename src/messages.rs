@@ -1,23 +1,84 @@
+use std::borrow::Cow;
+
 use serde::{Deserialize, Serialize};
 
+use crate::i18n::{Catalog, Localize};
+use crate::names::{validate_username, NameError};
+
+mod decode;
+
+pub use decode::{decode, AnyMessage, MalformedMessage};
+
 /// Client -> Server
+///
+/// Messages from clients borrow their strings from the buffer they're
+/// parsed from where they can, so `serde_json::from_str` on a message
+/// doesn't allocate. Strings with escapes still have to be copied.
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
-pub enum FromClient {
-    Auth(Auth),
+pub enum FromClient<'a> {
+    Auth(#[serde(borrow)] Auth<'a>),
+    /// Offers a draw, or agrees to one if another player offered first.
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    /// From a spectator: show the game as it was after `move_index` moves,
+    /// and hold live updates until `CatchUp`.
+    Rewind(Rewind),
+    CatchUp,
+}
+
+impl FromClient<'_> {
+    /// Copies out whatever was borrowed, to keep past the buffer.
+    pub fn into_owned(self) -> FromClient<'static> {
+        match self {
+            FromClient::Auth(auth) => FromClient::Auth(auth.into_owned()),
+            FromClient::OfferDraw => FromClient::OfferDraw,
+            FromClient::AcceptDraw => FromClient::AcceptDraw,
+            FromClient::DeclineDraw => FromClient::DeclineDraw,
+            FromClient::Rewind(rewind) => FromClient::Rewind(rewind),
+            FromClient::CatchUp => FromClient::CatchUp,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+/// A move as a client sends it. Moves are parsed with `to_player_move`,
+/// which lends out the player's message, so a move type with `&str` or
+/// `Cow<str>` fields borrows from it too.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum Move<T> {
     Move(T),
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Rewind {
+    pub move_index: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct Auth {
-    pub username: String,
-    pub password: String,
+pub struct Auth<'a> {
+    #[serde(borrow)]
+    pub username: Cow<'a, str>,
+    #[serde(borrow)]
+    pub password: Cow<'a, str>,
+}
+
+impl Auth<'_> {
+    /// Checks the name a client joins with, before it's let anywhere near
+    /// a game. Clients with bad names get `INVALID_USERNAME`.
+    pub fn validate(&self) -> Result<(), NameError> {
+        validate_username(&self.username)
+    }
+
+    pub fn into_owned(self) -> Auth<'static> {
+        Auth {
+            username: Cow::Owned(self.username.into_owned()),
+            password: Cow::Owned(self.password.into_owned()),
+        }
+    }
 }
 
 mod test {
@@ -28,13 +89,32 @@ mod test {
     fn serialized_str() {
         assert_eq!(
             serde_json::to_string(&FromClient::Auth(Auth {
-                username: "user".to_string(),
-                password: "pass".to_string(),
+                username: "user".into(),
+                password: "pass".into(),
             }))
             .unwrap(),
             r#"{"auth":{"username":"user","password":"pass"}}"#.to_string()
         );
     }
+
+    #[test]
+    fn borrows_from_the_message() {
+        let message = r#"{"auth":{"username":"user","password":"p\"ss"}}"#;
+        let FromClient::Auth(auth) = serde_json::from_str(message).unwrap() else {
+            panic!("not auth");
+        };
+        assert!(matches!(auth.username, Cow::Borrowed("user")));
+        // Unescaping needs a copy.
+        assert!(matches!(auth.password, Cow::Owned(ref p) if p == "p\"ss"));
+        let owned: FromClient<'static> = FromClient::Auth(auth).into_owned();
+        assert_eq!(
+            owned,
+            FromClient::Auth(Auth {
+                username: "user".into(),
+                password: "p\"ss".into(),
+            })
+        );
+    }
 }
 
 /// To Client
@@ -43,7 +123,16 @@ mod test {
 #[serde(rename_all = "kebab-case")]
 pub enum ToClient {
     Error(Error),
+    /// In place of `INVALID_MESSAGE_FORMAT`, for clients to be told what
+    /// exactly was wrong.
+    MalformedMessage(MalformedMessage),
     GameOver(GameOver),
+    TimeWarning(TimeWarning),
+    Forfeited(Forfeited),
+    DrawOffered(DrawOffer),
+    DrawDeclined(DrawOffer),
+    /// Sent to a spectator before the state they rewound to.
+    Rewound(Rewound),
 }
 
 #[derive(Serialize, Clone, PartialEq, Eq, Debug)]
@@ -52,23 +141,98 @@ pub struct GameOver {
     pub reason: String,
 }
 
+/// Where a rewound spectator is. `move_index` is clamped to the moves
+/// played, of which there were `moves` when they asked.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Rewound {
+    pub move_index: usize,
+    pub moves: usize,
+}
+
+/// Who offered or declined a draw. Agreed draws end the game with
+/// `GameOver`.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct DrawOffer {
+    pub player: String,
+}
+
+/// A player left the game for good, and won't be back.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Forfeited {
+    pub player: String,
+    pub reason: ForfeitReason,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForfeitReason {
+    Resigned,
+    /// Thrown out by the server, e.g. for breaking the protocol.
+    Disqualified {
+        reason: String,
+    },
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct TimeWarning {
+    pub remaining_ms: u64,
+}
+
 #[derive(Serialize, Clone, PartialEq, Eq, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub enum YourTurn<State> {
     YourTurn(State),
 }
 
+/// A message for one player only, sent as `private::Private::seal` makes
+/// it and never as part of a game state.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrivateMessage<T> {
+    Private(T),
+}
+
+/// `message` as a line to send, newline included.
+pub fn line<T: Serialize>(message: &T) -> String {
+    serde_json::to_string(message).unwrap() + "\n"
+}
+
+/// `reason` stays the same in every language, for bots to match on.
+/// `message` is for the people writing them, see `ToClient::localized`.
 #[derive(Serialize, Clone, PartialEq, Eq, Debug)]
 pub struct Error {
     pub reason: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl Error {
+    pub const fn new(reason: &'static str) -> Self {
+        Self {
+            reason,
+            message: None,
+        }
+    }
+}
+
+impl ToClient {
+    /// With errors explained in `catalog`'s language.
+    pub fn localized(self, catalog: &Catalog) -> Self {
+        match self {
+            ToClient::Error(error) => ToClient::Error(Error {
+                message: Some(error.localize(catalog)),
+                ..error
+            }),
+            message => message,
+        }
+    }
 }
 
-pub const INVALID_MESSAGE_FORMAT: ToClient = ToClient::Error(Error {
-    reason: "invalid message format",
-});
-pub const WRONG_PASSWORD: ToClient = ToClient::Error(Error {
-    reason: "wrong password",
-});
-pub const INVALID_MOVE: ToClient = ToClient::Error(Error {
-    reason: "invalid move",
-});
+pub const INVALID_MESSAGE_FORMAT: ToClient = ToClient::Error(Error::new("invalid message format"));
+pub const WRONG_PASSWORD: ToClient = ToClient::Error(Error::new("wrong password"));
+pub const INVALID_MOVE: ToClient = ToClient::Error(Error::new("invalid move"));
+pub const INVALID_USERNAME: ToClient = ToClient::Error(Error::new("invalid username"));
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Why a message didn't parse, precisely enough to tell whoever wrote the
+/// client what to fix.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, thiserror::Error)]
+#[serde(rename_all = "kebab-case")]
+#[error("{reason} at {path} (line {line}, column {column})")]
+pub struct MalformedMessage {
+    /// The field the error is in, as in `move.x` or `cells[3]`, or `.`
+    /// for the message as a whole.
+    pub path: String,
+    pub reason: String,
+    /// From 1, as are columns.
+    pub line: usize,
+    pub column: usize,
+    /// From the start of the message, in bytes.
+    pub offset: usize,
+}
+
+impl MalformedMessage {
+    fn new(json: &str, path: String, error: serde_json::Error) -> Self {
+        let (line, column) = (error.line(), error.column());
+        let message = error.to_string();
+        let reason = message
+            .strip_suffix(&format!(" at line {line} column {column}"))
+            .unwrap_or(&message)
+            .to_string();
+        Self {
+            path,
+            reason,
+            line,
+            column,
+            offset: byte_offset(json, line, column),
+        }
+    }
+}
+
+/// Parses JSON from a client, or says where and why it's wrong. Strings
+/// are borrowed from `json` where the type allows, as with
+/// `serde_json::from_str`.
+pub fn decode<'a, T: Deserialize<'a>>(json: &'a str) -> Result<T, MalformedMessage> {
+    let decoded = parse(json);
+    #[cfg(feature = "metrics")]
+    if decoded.is_err() {
+        crate::metrics::prometheus::DECODE_ERRORS.inc();
+    }
+    decoded
+}
+
+fn parse<'a, T: Deserialize<'a>>(json: &'a str) -> Result<T, MalformedMessage> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let value = serde_path_to_error::deserialize(&mut deserializer)
+        .map_err(|e| MalformedMessage::new(json, e.path().to_string(), e.into_inner()))?;
+    deserializer
+        .end()
+        .map_err(|e| MalformedMessage::new(json, ".".to_string(), e))?;
+    Ok(value)
+}
+
+/// Any message split into its kind and body, for clients passing messages
+/// on without knowing their types: `{"your-turn":{...}}` is kind
+/// `your-turn` with the state as body, and `"offer-draw"` has no body.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "Value")]
+pub struct AnyMessage {
+    pub kind: String,
+    pub body: Option<Value>,
+}
+
+impl TryFrom<Value> for AnyMessage {
+    type Error = &'static str;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(kind) => Ok(Self { kind, body: None }),
+            Value::Object(fields) if fields.len() == 1 => {
+                let (kind, body) = fields.into_iter().next().unwrap();
+                Ok(Self {
+                    kind,
+                    body: Some(body),
+                })
+            }
+            _ => Err("expected a message kind, alone or keying its body"),
+        }
+    }
+}
+
+/// Where serde_json's line and column point, which is the byte it stopped
+/// at. Columns count bytes, and 0 means before the first.
+fn byte_offset(json: &str, line: usize, column: usize) -> usize {
+    let line_start = match line {
+        0 | 1 => 0,
+        _ => json
+            .match_indices('\n')
+            .nth(line - 2)
+            .map_or(json.len(), |(i, _)| i + 1),
+    };
+    (line_start + column.saturating_sub(1)).min(json.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::Move;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Pos {
+        x: u32,
+        y: u32,
+    }
+
+    #[test]
+    fn points_at_the_field() {
+        let json = "{\"move\": {\"x\": 1,\n \"y\": \"two\"}}";
+        let error = decode::<Move<Pos>>(json).unwrap_err();
+        assert_eq!(error.path, "move.y");
+        assert_eq!(error.line, 2);
+        assert_eq!(&json[error.offset..error.offset + 1], "\"");
+        assert!(error.reason.starts_with("invalid type: string \"two\""));
+    }
+
+    #[test]
+    fn points_at_the_end_of_cut_off_messages() {
+        let json = "{\"move\": {\"x\": 1";
+        let error = decode::<Move<Pos>>(json).unwrap_err();
+        assert_eq!(error.offset, json.len() - 1);
+        assert!(error.reason.starts_with("EOF while parsing"));
+    }
+
+    #[test]
+    fn splits_any_message() {
+        let message = decode::<AnyMessage>(r#"{"game-over":{"reason":"won"}}"#).unwrap();
+        assert_eq!(message.kind, "game-over");
+        assert_eq!(message.body.unwrap().to_string(), r#"{"reason":"won"}"#);
+        assert_eq!(
+            decode::<AnyMessage>(r#""offer-draw""#).unwrap(),
+            AnyMessage {
+                kind: "offer-draw".to_string(),
+                body: None
+            }
+        );
+        assert!(decode::<AnyMessage>(r#"{"a":1,"b":2}"#).is_err());
+        assert!(decode::<AnyMessage>("{}").is_err());
+    }
+
+    #[test]
+    fn finds_offsets_from_lines_and_columns() {
+        let json = "ab\ncde\nf";
+        assert_eq!(byte_offset(json, 1, 1), 0);
+        assert_eq!(byte_offset(json, 2, 2), 4);
+        assert_eq!(byte_offset(json, 3, 1), 7);
+        assert_eq!(byte_offset(json, 3, 9), json.len());
+        assert_eq!(byte_offset(json, 0, 0), 0);
+    }
+}
@@ -0,0 +1,121 @@
+//! Live counters for dashboards showing how loaded a server is: games
+//! running, players seated, spectators watching and messages going through.
+//! Anything keeping such counts implements `Metrics`, and a dashboard polls
+//! each source and shows what comes back. With the `metrics` feature,
+//! `prometheus` exports them along with counters kept across the crate.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::host::GameId;
+use crate::time::Instant;
+
+#[cfg(feature = "metrics")]
+pub mod prometheus;
+
+/// One reading. Per-game readings name their game, totals don't.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Metric {
+    pub name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game: Option<GameId>,
+    pub value: u64,
+}
+
+impl Metric {
+    pub fn total(name: &'static str, value: u64) -> Self {
+        Self {
+            name,
+            game: None,
+            value,
+        }
+    }
+
+    pub fn per_game(name: &'static str, game: GameId, value: u64) -> Self {
+        Self {
+            name,
+            game: Some(game),
+            value,
+        }
+    }
+}
+
+pub trait Metrics {
+    /// Every reading as of `now`, totals first.
+    fn metrics(&self, now: Instant) -> Vec<Metric>;
+}
+
+/// Counts events and tells how many there were in the last whole second.
+/// Shared by reference, so whoever records doesn't need `&mut`.
+#[derive(Debug, Default)]
+pub struct MessageRate {
+    window: Mutex<Window>,
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    /// When the second being counted started.
+    start: Option<Instant>,
+    current: u64,
+    last: u64,
+}
+
+impl Window {
+    fn roll(&mut self, now: Instant) {
+        let start = *self.start.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed >= Duration::from_secs(2) {
+            self.last = 0;
+            self.current = 0;
+            self.start = Some(now);
+        } else if elapsed >= Duration::from_secs(1) {
+            self.last = self.current;
+            self.current = 0;
+            self.start = Some(start + Duration::from_secs(1));
+        }
+    }
+}
+
+impl MessageRate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, now: Instant) {
+        self.record_many(1, now);
+    }
+
+    pub fn record_many(&self, count: u64, now: Instant) {
+        let mut window = self.window.lock().unwrap();
+        window.roll(now);
+        window.current += count;
+    }
+
+    /// How many were recorded in the last whole second before `now`.
+    pub fn per_second(&self, now: Instant) -> u64 {
+        let mut window = self.window.lock().unwrap();
+        window.roll(now);
+        window.last
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counts_the_last_whole_second() {
+        let rate = MessageRate::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        rate.record_many(3, at(0));
+        rate.record(at(900));
+        assert_eq!(rate.per_second(at(950)), 0);
+        rate.record(at(1200));
+        assert_eq!(rate.per_second(at(1500)), 4);
+        assert_eq!(rate.per_second(at(2100)), 1);
+        assert_eq!(rate.per_second(at(5000)), 0);
+    }
+}
@@ -0,0 +1,242 @@
+//! Counters and histograms kept as the crate runs, and `render` to export
+//! them with the readings of any `Metrics` sources in Prometheus' text
+//! format, for a server to answer scrapes of `/metrics` with.
+//!
+//! The instruments are statics, so code deep in the crate, such as the
+//! protocol decoder, counts without being handed anything. They only exist
+//! with the `metrics` feature.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+use super::{Metric, Metrics};
+use crate::time::Instant;
+
+/// Put before every name exported.
+const PREFIX: &str = "code_challenge_";
+
+/// Messages from clients that didn't parse, see `messages::decode`.
+pub static DECODE_ERRORS: Counter = Counter::new(
+    "decode_errors_total",
+    "Messages from clients that failed to parse.",
+);
+
+/// Turns handed out by every `TurnTracker`.
+pub static TURNS: Counter = Counter::new("turns_total", "Turns handed out to players.");
+
+/// Commands sent to `GameHost` games that no worker has handled yet.
+pub static HOST_QUEUE_DEPTH: Gauge = Gauge::new(
+    "host_queue_depth",
+    "Commands waiting in hosted games' mailboxes.",
+);
+
+/// From `GameHost` handing out a turn to it taking the move that ends it.
+pub static TURN_DURATION: Histogram<9> = Histogram::new(
+    "turn_duration_seconds",
+    "Time from a turn being handed out to its move being taken.",
+    [0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0],
+);
+
+#[derive(Debug)]
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "counter");
+        writeln!(out, "{PREFIX}{} {}", self.name, self.get()).unwrap();
+    }
+}
+
+#[derive(Debug)]
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicI64,
+}
+
+impl Gauge {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            value: AtomicI64::new(0),
+        }
+    }
+
+    pub fn add(&self, n: i64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn sub(&self, n: i64) {
+        self.value.fetch_sub(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "gauge");
+        writeln!(out, "{PREFIX}{} {}", self.name, self.get()).unwrap();
+    }
+}
+
+/// Durations counted into `N` buckets by their upper bound in seconds,
+/// plus one for the rest.
+#[derive(Debug)]
+pub struct Histogram<const N: usize> {
+    name: &'static str,
+    help: &'static str,
+    bounds: [f64; N],
+    buckets: [AtomicU64; N],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl<const N: usize> Histogram<N> {
+    /// `bounds` go up.
+    pub const fn new(name: &'static str, help: &'static str, bounds: [f64; N]) -> Self {
+        Self {
+            name,
+            help,
+            bounds,
+            buckets: [const { AtomicU64::new(0) }; N],
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(i) = self.bounds.iter().position(|bound| seconds <= *bound) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "histogram");
+        let name = self.name;
+        let mut below = 0;
+        for (bound, bucket) in self.bounds.iter().zip(&self.buckets) {
+            below += bucket.load(Ordering::Relaxed);
+            writeln!(out, "{PREFIX}{name}_bucket{{le=\"{bound}\"}} {below}").unwrap();
+        }
+        let count = self.count();
+        writeln!(out, "{PREFIX}{name}_bucket{{le=\"+Inf\"}} {count}").unwrap();
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        writeln!(out, "{PREFIX}{name}_sum {sum}").unwrap();
+        writeln!(out, "{PREFIX}{name}_count {count}").unwrap();
+    }
+}
+
+/// Every instrument, then the readings of `sources` as of `now` as gauges,
+/// per-game readings labelled with their game.
+pub fn render(sources: &[&dyn Metrics], now: Instant) -> String {
+    let mut out = String::new();
+    DECODE_ERRORS.render(&mut out);
+    TURNS.render(&mut out);
+    HOST_QUEUE_DEPTH.render(&mut out);
+    TURN_DURATION.render(&mut out);
+
+    let mut readings: Vec<Metric> = sources
+        .iter()
+        .flat_map(|source| source.metrics(now))
+        .collect();
+    // Stable, so totals stay ahead of the games under each name.
+    readings.sort_by_key(|metric| metric.name);
+    let mut last = None;
+    for metric in readings {
+        if last != Some(metric.name) {
+            writeln!(out, "# TYPE {PREFIX}{} gauge", metric.name).unwrap();
+            last = Some(metric.name);
+        }
+        match metric.game {
+            Some(game) => writeln!(
+                out,
+                "{PREFIX}{}{{game=\"{}\"}} {}",
+                metric.name,
+                game.as_u64(),
+                metric.value
+            ),
+            None => writeln!(out, "{PREFIX}{} {}", metric.name, metric.value),
+        }
+        .unwrap();
+    }
+    out
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    writeln!(out, "# HELP {PREFIX}{name} {help}").unwrap();
+    writeln!(out, "# TYPE {PREFIX}{name} {kind}").unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renders_histograms_cumulatively() {
+        let histogram = Histogram::new("wait_seconds", "Waits.", [0.1, 1.0]);
+        histogram.observe(Duration::from_millis(50));
+        histogram.observe(Duration::from_millis(500));
+        histogram.observe(Duration::from_secs(3));
+        let mut out = String::new();
+        histogram.render(&mut out);
+        assert_eq!(
+            out,
+            "# HELP code_challenge_wait_seconds Waits.\n\
+             # TYPE code_challenge_wait_seconds histogram\n\
+             code_challenge_wait_seconds_bucket{le=\"0.1\"} 1\n\
+             code_challenge_wait_seconds_bucket{le=\"1\"} 2\n\
+             code_challenge_wait_seconds_bucket{le=\"+Inf\"} 3\n\
+             code_challenge_wait_seconds_sum 3.55\n\
+             code_challenge_wait_seconds_count 3\n"
+        );
+    }
+
+    #[test]
+    fn renders_sources_as_labelled_gauges() {
+        struct Source;
+
+        impl Metrics for Source {
+            fn metrics(&self, _now: Instant) -> Vec<Metric> {
+                vec![Metric::total("games", 1)]
+            }
+        }
+
+        let out = render(&[&Source], Instant::now());
+        assert!(out.contains("# TYPE code_challenge_turns_total counter\n"));
+        assert!(out.ends_with(
+            "# TYPE code_challenge_games gauge\n\
+             code_challenge_games 1\n"
+        ));
+    }
+}
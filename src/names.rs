@@ -0,0 +1,111 @@
+//! What players may call themselves. Names end up in HUDs, logs, replay
+//! notation and every message about a turn, so they're kept short, free of
+//! whitespace and control characters, and clear of names the server uses
+//! itself.
+
+/// Longest name, in characters.
+pub const MAX_NAME_LEN: usize = 32;
+
+/// Names that would pass for the server or staff in chat and logs. Checked
+/// ignoring case.
+pub const RESERVED_NAMES: &[&str] = &["server", "admin", "system"];
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum NameError {
+    #[error("the name is empty")]
+    Empty,
+    #[error("the name is longer than {MAX_NAME_LEN} characters")]
+    TooLong,
+    /// Letters and digits are allowed, and `_`, `-` and `.`.
+    #[error("{0:?} isn't allowed in names")]
+    InvalidChar(char),
+    #[error("{0} is reserved")]
+    Reserved(String),
+    #[error("{0} is taken")]
+    Taken(String),
+}
+
+pub fn validate_username(name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    // Counting stops early so a huge name costs no more than a long one.
+    if name.chars().nth(MAX_NAME_LEN).is_some() {
+        return Err(NameError::TooLong);
+    }
+    if let Some(c) = name.chars().find(|c| !is_name_char(*c)) {
+        return Err(NameError::InvalidChar(c));
+    }
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(name))
+    {
+        return Err(NameError::Reserved(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Validates `name` and checks it isn't `taken`, as by a player already
+/// connected or an account registered under it.
+pub fn validate_new_username(
+    name: &str,
+    taken: impl FnOnce(&str) -> bool,
+) -> Result<(), NameError> {
+    validate_username(name)?;
+    if taken(name) {
+        return Err(NameError::Taken(name.to_string()));
+    }
+    Ok(())
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accepts_ordinary_names() {
+        for name in ["p1", "alice", "Bob_the-2nd", "émile", "x"] {
+            assert_eq!(validate_username(name), Ok(()), "{name}");
+        }
+        assert_eq!(validate_username(&"a".repeat(MAX_NAME_LEN)), Ok(()));
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        assert_eq!(validate_username(""), Err(NameError::Empty));
+        assert_eq!(
+            validate_username(&"a".repeat(10_000)),
+            Err(NameError::TooLong)
+        );
+        assert_eq!(
+            validate_username("two words"),
+            Err(NameError::InvalidChar(' '))
+        );
+        assert_eq!(
+            validate_username("bell\u{7}"),
+            Err(NameError::InvalidChar('\u{7}'))
+        );
+        assert_eq!(
+            validate_username("Server"),
+            Err(NameError::Reserved("Server".to_string()))
+        );
+    }
+
+    #[test]
+    fn asks_whether_new_names_are_taken() {
+        let taken = |name: &str| name == "alice";
+        assert_eq!(validate_new_username("bob", taken), Ok(()));
+        assert_eq!(
+            validate_new_username("alice", taken),
+            Err(NameError::Taken("alice".to_string()))
+        );
+        assert_eq!(
+            validate_new_username("", |_| unreachable!()),
+            Err(NameError::Empty)
+        );
+    }
+}
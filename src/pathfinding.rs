@@ -0,0 +1,278 @@
+//! Shortest paths for bots and for validating moves. The searches are generic
+//! over the node type and take the moves out of a node as a closure, so they
+//! work on `Grid` positions, hexes or anything else; `grid_path` and
+//! `hex_path` wire them up for the common boards.
+//!
+//! Paths include both the start and the goal. Costs must be at least 1 per
+//! step for the A* helpers' heuristics to find the cheapest path.
+
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+
+use crate::grid::{Connectivity, Grid};
+use crate::hex::Hex;
+use crate::pos::Pos;
+
+/// The path with the fewest steps from `start` to a node satisfying
+/// `is_goal`.
+pub fn bfs<N, I>(
+    start: N,
+    mut neighbors: impl FnMut(N) -> I,
+    mut is_goal: impl FnMut(N) -> bool,
+) -> Option<Vec<N>>
+where
+    N: Copy + Eq + Hash,
+    I: IntoIterator<Item = N>,
+{
+    let mut came_from = HashMap::from([(start, start)]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        if is_goal(node) {
+            return Some(reconstruct(&came_from, node));
+        }
+        for next in neighbors(node) {
+            if let Entry::Vacant(entry) = came_from.entry(next) {
+                entry.insert(node);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Steps to every node reachable from `start`, for bots weighing several
+/// targets at once.
+pub fn bfs_distances<N, I>(start: N, mut neighbors: impl FnMut(N) -> I) -> HashMap<N, u32>
+where
+    N: Copy + Eq + Hash,
+    I: IntoIterator<Item = N>,
+{
+    let mut distances = HashMap::from([(start, 0)]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        let distance = distances[&node];
+        for next in neighbors(node) {
+            if let Entry::Vacant(entry) = distances.entry(next) {
+                entry.insert(distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+/// The cheapest path to a goal, `successors` giving each move with its cost.
+pub fn dijkstra<N, I>(
+    start: N,
+    successors: impl FnMut(N) -> I,
+    is_goal: impl FnMut(N) -> bool,
+) -> Option<(Vec<N>, u32)>
+where
+    N: Copy + Eq + Hash,
+    I: IntoIterator<Item = (N, u32)>,
+{
+    astar(start, successors, |_| 0, is_goal)
+}
+
+/// Dijkstra guided by `heuristic`, an estimate of the cost left to a goal
+/// that must never overestimate it.
+pub fn astar<N, I>(
+    start: N,
+    mut successors: impl FnMut(N) -> I,
+    mut heuristic: impl FnMut(N) -> u32,
+    mut is_goal: impl FnMut(N) -> bool,
+) -> Option<(Vec<N>, u32)>
+where
+    N: Copy + Eq + Hash,
+    I: IntoIterator<Item = (N, u32)>,
+{
+    let mut came_from = HashMap::from([(start, start)]);
+    let mut costs = HashMap::from([(start, 0u32)]);
+    let mut nodes = vec![start];
+    // Ordered by estimated total cost, then insertion so ties are settled
+    // the same way every run.
+    let mut open = BinaryHeap::from([Reverse((heuristic(start), 0usize))]);
+    while let Some(Reverse((_, index))) = open.pop() {
+        let node = nodes[index];
+        if is_goal(node) {
+            return Some((reconstruct(&came_from, node), costs[&node]));
+        }
+        let cost = costs[&node];
+        for (next, step) in successors(node) {
+            let next_cost = cost.saturating_add(step);
+            if costs.get(&next).is_none_or(|c| next_cost < *c) {
+                costs.insert(next, next_cost);
+                came_from.insert(next, node);
+                nodes.push(next);
+                open.push(Reverse((
+                    next_cost.saturating_add(heuristic(next)),
+                    nodes.len() - 1,
+                )));
+            }
+        }
+    }
+    None
+}
+
+fn reconstruct<N: Copy + Eq + Hash>(came_from: &HashMap<N, N>, goal: N) -> Vec<N> {
+    let mut path = vec![goal];
+    while let Some(&previous) = came_from.get(&path[path.len() - 1]) {
+        if previous == path[path.len() - 1] {
+            break;
+        }
+        path.push(previous);
+    }
+    path.reverse();
+    path
+}
+
+/// The shortest path between cells of `grid` whose contents are `passable`.
+/// `from` doesn't itself need to be passable, so a piece can path away from
+/// the square it occupies.
+pub fn grid_path<T>(
+    grid: &Grid<T>,
+    from: Pos,
+    to: Pos,
+    connectivity: Connectivity,
+    passable: impl Fn(&T) -> bool,
+) -> Option<Vec<Pos>> {
+    bfs(
+        from,
+        |pos| {
+            connectivity
+                .neighbors(pos)
+                .into_iter()
+                .filter(|next| grid.at(*next).is_some_and(&passable))
+        },
+        |pos| pos == to,
+    )
+}
+
+/// The cheapest path between cells of `grid`, `cost` giving the price of
+/// entering a cell or `None` if it can't be entered.
+pub fn grid_path_weighted<T>(
+    grid: &Grid<T>,
+    from: Pos,
+    to: Pos,
+    connectivity: Connectivity,
+    cost: impl Fn(&T) -> Option<u32>,
+) -> Option<(Vec<Pos>, u32)> {
+    astar(
+        from,
+        |pos| {
+            connectivity
+                .neighbors(pos)
+                .into_iter()
+                .filter_map(|next| Some((next, cost(grid.at(next)?)?)))
+                .collect::<Vec<_>>()
+        },
+        |pos| match connectivity {
+            Connectivity::Four => pos.manhattan(to),
+            Connectivity::Eight => pos.chebyshev(to),
+        },
+        |pos| pos == to,
+    )
+}
+
+/// The cheapest path between hexes, `cost` giving the price of entering a
+/// hex or `None` if it can't be entered. Hex boards have no edges of their
+/// own, so `cost` must return `None` outside the board or an unreachable
+/// goal searches forever.
+pub fn hex_path(from: Hex, to: Hex, cost: impl Fn(Hex) -> Option<u32>) -> Option<(Vec<Hex>, u32)> {
+    astar(
+        from,
+        |hex| {
+            hex.neighbors()
+                .into_iter()
+                .filter_map(|next| Some((next, cost(next)?)))
+                .collect::<Vec<_>>()
+        },
+        |hex| hex.distance(to),
+        |hex| hex == to,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::test::board;
+
+    #[test]
+    fn grid_paths() {
+        let grid = board("..#. .##. ....");
+        let open = |c: &char| *c != '#';
+        let path = grid_path(
+            &grid,
+            Pos::new(0, 0),
+            Pos::new(3, 0),
+            Connectivity::Four,
+            open,
+        )
+        .unwrap();
+        assert_eq!(path.len(), 8);
+        assert_eq!(path[0], Pos::new(0, 0));
+        assert_eq!(path[7], Pos::new(3, 0));
+        assert!(path.windows(2).all(|w| w[0].manhattan(w[1]) == 1));
+
+        let walled = board(".#. ##. ...");
+        let walled_path =
+            |connectivity| grid_path(&walled, Pos::new(0, 0), Pos::new(2, 2), connectivity, open);
+        assert_eq!(walled_path(Connectivity::Four), None);
+        assert_eq!(walled_path(Connectivity::Eight), None);
+
+        let distances = bfs_distances(Pos::new(0, 0), |pos| {
+            pos.neighbors4()
+                .into_iter()
+                .filter(|next| grid.at(*next).is_some_and(open))
+        });
+        assert_eq!(distances[&Pos::new(3, 0)], 7);
+        assert_eq!(distances.len(), 9);
+    }
+
+    #[test]
+    fn weighted_paths() {
+        // Going through the swamp is shorter but dearer than around it.
+        let grid = board("... ~~. ...");
+        let cost = |c: &char| match c {
+            '~' => Some(9),
+            _ => Some(1),
+        };
+        let (path, total) = grid_path_weighted(
+            &grid,
+            Pos::new(0, 0),
+            Pos::new(0, 2),
+            Connectivity::Four,
+            cost,
+        )
+        .unwrap();
+        assert_eq!(total, 6);
+        assert_eq!(path.len(), 7);
+
+        let (_, dijkstra_total) = dijkstra(
+            Pos::new(0, 0),
+            |pos| {
+                pos.neighbors4()
+                    .into_iter()
+                    .filter_map(|next| Some((next, cost(grid.at(next)?)?)))
+                    .collect::<Vec<_>>()
+            },
+            |pos| pos == Pos::new(0, 2),
+        )
+        .unwrap();
+        assert_eq!(dijkstra_total, total);
+    }
+
+    #[test]
+    fn hex_paths() {
+        let board = Hex::ORIGIN;
+        let blocked = Hex::new(1, 0);
+        let cost = |hex: Hex| (hex.distance(board) <= 2 && hex != blocked).then_some(1);
+        let (path, total) = hex_path(Hex::ORIGIN, Hex::new(2, 0), cost).unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(path.len(), 4);
+        assert!(!path.contains(&blocked));
+        assert_eq!(hex_path(Hex::ORIGIN, Hex::new(3, 0), cost), None);
+    }
+}
@@ -0,0 +1,164 @@
+//! Messages for one player only, such as their hand or an error about
+//! their own move, kept apart from the state everyone is sent.
+//!
+//! Private data is wrapped in `Private`, which is neither `Serialize` nor
+//! `Clone`: it can't be put in a game state, a `FilteredState` or a
+//! spectator `Update`, all of which need one or the other. The only way
+//! out is `seal`, which serializes it for a single named player, and the
+//! sealed message only goes into that player's `PrivateChannels` inbox.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::messages::{line, PrivateMessage};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Private<T>(T);
+
+impl<T> Private<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn get(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Serialize> Private<T> {
+    /// Serializes the message for `recipient`, as `{"private":...}`.
+    pub fn seal(self, recipient: &str) -> Sealed {
+        Sealed {
+            recipient: recipient.to_string(),
+            serialized: line(&PrivateMessage::Private(self.0)),
+        }
+    }
+}
+
+/// A private message ready to send, which only `PrivateChannels::send`
+/// takes.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Sealed {
+    recipient: String,
+    serialized: String,
+}
+
+impl Sealed {
+    pub fn recipient(&self) -> &str {
+        &self.recipient
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{0} has no private channel")]
+pub struct NoChannel(pub String);
+
+/// One player's end of their private channel. Dropping it closes the
+/// channel.
+#[derive(Debug)]
+pub struct Inbox {
+    player: String,
+    queue: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl Inbox {
+    pub fn player(&self) -> &str {
+        &self.player
+    }
+
+    /// Every message sent since the last take, oldest first, each a line
+    /// to write to the player's connection.
+    pub fn take(&self) -> Vec<String> {
+        self.queue.lock().unwrap().drain(..).collect()
+    }
+}
+
+/// A private channel per connected player, kept by the server next to the
+/// broadcasts.
+#[derive(Debug, Default)]
+pub struct PrivateChannels {
+    players: HashMap<String, Arc<Mutex<VecDeque<String>>>>,
+}
+
+impl PrivateChannels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens `player`'s channel, replacing any they had, as on
+    /// reconnecting.
+    pub fn open(&mut self, player: &str) -> Inbox {
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        self.players.insert(player.to_string(), queue.clone());
+        Inbox {
+            player: player.to_string(),
+            queue,
+        }
+    }
+
+    pub fn close(&mut self, player: &str) {
+        self.players.remove(player);
+    }
+
+    pub fn is_open(&self, player: &str) -> bool {
+        self.players
+            .get(player)
+            .is_some_and(|queue| Arc::strong_count(queue) > 1)
+    }
+
+    pub fn send(&mut self, message: Sealed) -> Result<(), NoChannel> {
+        if !self.is_open(&message.recipient) {
+            self.players.remove(&message.recipient);
+            return Err(NoChannel(message.recipient));
+        }
+        self.players[&message.recipient]
+            .lock()
+            .unwrap()
+            .push_back(message.serialized);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::messages::{ToClient, INVALID_MOVE};
+
+    #[test]
+    fn only_the_recipient_gets_it() {
+        let mut channels = PrivateChannels::new();
+        let p1 = channels.open("p1");
+        let p2 = channels.open("p2");
+        let hand = Private::new(vec!["AS", "KH"]);
+        assert_eq!(hand.get().len(), 2);
+        channels.send(hand.seal("p1")).unwrap();
+        channels
+            .send(Private::new(INVALID_MOVE).seal("p1"))
+            .unwrap();
+        assert_eq!(p1.take().len(), 2);
+        assert!(p2.take().is_empty());
+    }
+
+    #[test]
+    fn closed_channels_refuse_messages() {
+        let mut channels = PrivateChannels::new();
+        let inbox = channels.open("p1");
+        drop(inbox);
+        assert!(!channels.is_open("p1"));
+        let error: Private<ToClient> = Private::new(INVALID_MOVE);
+        assert_eq!(
+            channels.send(error.seal("p1")),
+            Err(NoChannel("p1".to_string()))
+        );
+        assert_eq!(
+            channels.send(Private::new(1).seal("p2")),
+            Err(NoChannel("p2".to_string()))
+        );
+    }
+}
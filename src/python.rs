@@ -0,0 +1,348 @@
+//! Python bindings for bot authors, built as the `code_challenge` extension
+//! module with maturin. Bots get the protocol as messages already split
+//! into kind and body, the board as a `Grid` they can index and search,
+//! and a builder for every message they send, so nobody has to put JSON
+//! together by hand.
+//!
+//! ```python
+//! import code_challenge as cc
+//!
+//! sock.sendall(cc.auth("bot", "secret").encode())
+//! for line in sock.makefile():
+//!     message = cc.decode(line)
+//!     if message.kind == "your-turn":
+//!         board = cc.Grid(message.body["board"])
+//!         x, y = board.find(None)[0].xy()
+//!         sock.sendall(cc.move_at(x, y).encode())
+//! ```
+//!
+//! Each builder returns a whole line, newline included.
+
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::cards::{Card, Suit};
+use crate::games::battleship::{Action, Placement};
+use crate::games::rps::Throw;
+use crate::grid::Grid;
+use crate::messages::{self, line, AnyMessage, Auth, FromClient, Rewind};
+use crate::pos::{Direction, Pos};
+
+#[pymodule]
+fn code_challenge(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyPos>()?;
+    m.add_class::<PyGrid>()?;
+    m.add_class::<Message>()?;
+    m.add_function(wrap_pyfunction!(decode, m)?)?;
+    m.add_function(wrap_pyfunction!(auth, m)?)?;
+    m.add_function(wrap_pyfunction!(offer_draw, m)?)?;
+    m.add_function(wrap_pyfunction!(accept_draw, m)?)?;
+    m.add_function(wrap_pyfunction!(decline_draw, m)?)?;
+    m.add_function(wrap_pyfunction!(rewind, m)?)?;
+    m.add_function(wrap_pyfunction!(catch_up, m)?)?;
+    m.add_function(wrap_pyfunction!(move_at, m)?)?;
+    m.add_function(wrap_pyfunction!(move_column, m)?)?;
+    m.add_function(wrap_pyfunction!(move_throw, m)?)?;
+    m.add_function(wrap_pyfunction!(move_card, m)?)?;
+    m.add_function(wrap_pyfunction!(move_heading, m)?)?;
+    m.add_function(wrap_pyfunction!(move_fire, m)?)?;
+    m.add_function(wrap_pyfunction!(move_place, m)?)?;
+    Ok(())
+}
+
+#[pyclass(name = "Pos", frozen, eq, hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PyPos(pub Pos);
+
+#[pymethods]
+impl PyPos {
+    #[new]
+    fn new(x: i32, y: i32) -> Self {
+        Self(Pos { x, y })
+    }
+
+    #[getter]
+    fn x(&self) -> i32 {
+        self.0.x
+    }
+
+    #[getter]
+    fn y(&self) -> i32 {
+        self.0.y
+    }
+
+    fn xy(&self) -> (i32, i32) {
+        (self.0.x, self.0.y)
+    }
+
+    /// One step towards `direction`, as in `"north-east"`.
+    fn step(&self, direction: &str) -> PyResult<Self> {
+        Ok(Self(self.0.step(parse(direction)?)))
+    }
+
+    fn neighbors4(&self) -> Vec<Self> {
+        self.0.neighbors4().map(Self).to_vec()
+    }
+
+    fn neighbors8(&self) -> Vec<Self> {
+        self.0.neighbors8().map(Self).to_vec()
+    }
+
+    fn manhattan(&self, other: &Self) -> u32 {
+        self.0.manhattan(other.0)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Pos({}, {})", self.0.x, self.0.y)
+    }
+}
+
+/// A board from a game state, indexed `board[x, y]` with `(0, 0)` in the
+/// top left.
+#[pyclass(name = "Grid")]
+pub struct PyGrid(Grid<PyObject>);
+
+#[pymethods]
+impl PyGrid {
+    /// From a list of rows, the way states send boards.
+    #[new]
+    fn new(rows: Vec<Vec<PyObject>>) -> PyResult<Self> {
+        Grid::from_rows(rows)
+            .map(Self)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.0.width()
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.0.height()
+    }
+
+    /// The cell at `(x, y)`, or `None` off the board.
+    fn get(&self, py: Python<'_>, x: i32, y: i32) -> Option<PyObject> {
+        self.0.at(Pos { x, y }).map(|cell| cell.clone_ref(py))
+    }
+
+    fn __getitem__(&self, py: Python<'_>, (x, y): (i32, i32)) -> PyResult<PyObject> {
+        self.get(py, x, y)
+            .ok_or_else(|| PyIndexError::new_err(format!("({x}, {y}) is off the board")))
+    }
+
+    fn __contains__(&self, (x, y): (i32, i32)) -> bool {
+        self.0.contains(Pos { x, y })
+    }
+
+    fn rows(&self, py: Python<'_>) -> Vec<Vec<PyObject>> {
+        self.0
+            .rows()
+            .map(|row| row.iter().map(|cell| cell.clone_ref(py)).collect())
+            .collect()
+    }
+
+    /// Every position, row by row.
+    fn positions(&self) -> Vec<PyPos> {
+        self.0.positions().map(PyPos).collect()
+    }
+
+    /// The positions of cells equal to `value`, row by row.
+    fn find(&self, py: Python<'_>, value: &Bound<'_, PyAny>) -> PyResult<Vec<PyPos>> {
+        let mut found = vec![];
+        for pos in self.0.positions() {
+            if self.0[pos].bind(py).eq(value)? {
+                found.push(PyPos(pos));
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// A message from the server, `{"your-turn": {...}}` coming out as kind
+/// `"your-turn"` and the state as its body.
+#[pyclass(frozen)]
+pub struct Message {
+    #[pyo3(get)]
+    kind: String,
+    /// As `json.loads` would give it, or `None` for messages without one.
+    #[pyo3(get)]
+    body: PyObject,
+}
+
+#[pymethods]
+impl Message {
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(format!(
+            "Message({:?}, {})",
+            self.kind,
+            self.body.bind(py).repr()?
+        ))
+    }
+}
+
+/// Parses a line from the server, raising `ValueError` with where and why
+/// for anything that isn't a message.
+#[pyfunction]
+fn decode(py: Python<'_>, line: &str) -> PyResult<Message> {
+    let message: AnyMessage =
+        messages::decode(line.trim_end()).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(Message {
+        kind: message.kind,
+        body: match message.body {
+            Some(body) => to_python(py, &body)?,
+            None => py.None(),
+        },
+    })
+}
+
+#[pyfunction]
+fn auth(username: &str, password: &str) -> PyResult<String> {
+    let auth = Auth {
+        username: username.into(),
+        password: password.into(),
+    };
+    auth.validate()
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(line(&FromClient::Auth(auth)))
+}
+
+#[pyfunction]
+fn offer_draw() -> String {
+    line(&FromClient::OfferDraw)
+}
+
+#[pyfunction]
+fn accept_draw() -> String {
+    line(&FromClient::AcceptDraw)
+}
+
+#[pyfunction]
+fn decline_draw() -> String {
+    line(&FromClient::DeclineDraw)
+}
+
+#[pyfunction]
+fn rewind(move_index: usize) -> String {
+    line(&FromClient::Rewind(Rewind { move_index }))
+}
+
+#[pyfunction]
+fn catch_up() -> String {
+    line(&FromClient::CatchUp)
+}
+
+/// Tic-tac-toe, gomoku and othello.
+#[pyfunction]
+fn move_at(x: i32, y: i32) -> String {
+    player_move(Pos { x, y })
+}
+
+/// Connect four.
+#[pyfunction]
+fn move_column(column: usize) -> String {
+    player_move(column)
+}
+
+/// Rock-paper-scissors: `"rock"`, `"paper"` or `"scissors"`.
+#[pyfunction]
+fn move_throw(throw: &str) -> PyResult<String> {
+    Ok(player_move(parse::<Throw>(throw)?))
+}
+
+/// Whist, with ranks from 2 to 14 for the ace and suits as in `"hearts"`.
+#[pyfunction]
+fn move_card(rank: u8, suit: &str) -> PyResult<String> {
+    Ok(player_move(Card {
+        rank,
+        suit: parse::<Suit>(suit)?,
+    }))
+}
+
+/// Snake, as in `"north"`.
+#[pyfunction]
+fn move_heading(direction: &str) -> PyResult<String> {
+    Ok(player_move(parse::<Direction>(direction)?))
+}
+
+/// Battleship.
+#[pyfunction]
+fn move_fire(x: i32, y: i32) -> String {
+    player_move(Action::Fire(Pos { x, y }))
+}
+
+/// Battleship: each ship's first cell and the direction the rest go.
+#[pyfunction]
+fn move_place(ships: Vec<((i32, i32), String)>) -> PyResult<String> {
+    let placements = ships
+        .into_iter()
+        .map(|((x, y), direction)| {
+            Ok(Placement {
+                start: Pos { x, y },
+                direction: parse(&direction)?,
+            })
+        })
+        .collect::<PyResult<_>>()?;
+    Ok(player_move(Action::Place(placements)))
+}
+
+fn player_move<T: Serialize>(p_move: T) -> String {
+    line(&messages::Move::Move(p_move))
+}
+
+/// A unit variant from its name on the wire.
+fn parse<T: DeserializeOwned>(name: &str) -> PyResult<T> {
+    serde_json::from_value(Value::String(name.to_string()))
+        .map_err(|_| PyValueError::new_err(format!("unknown name {name:?}")))
+}
+
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_py(py),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => i.into_py(py),
+            (None, Some(u)) => u.into_py(py),
+            (None, None) => n.as_f64().unwrap_or(f64::NAN).into_py(py),
+        },
+        Value::String(s) => s.into_py(py),
+        Value::Array(items) => {
+            let list = PyList::empty_bound(py);
+            for item in items {
+                list.append(to_python(py, item)?)?;
+            }
+            list.into_py(py)
+        }
+        Value::Object(fields) => {
+            let dict = PyDict::new_bound(py);
+            for (key, field) in fields {
+                dict.set_item(key, to_python(py, field)?)?;
+            }
+            dict.into_py(py)
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builds_lines_to_send() {
+        assert_eq!(move_at(1, 2), "{\"move\":{\"x\":1,\"y\":2}}\n");
+        assert_eq!(move_column(3), "{\"move\":3}\n");
+        assert_eq!(move_throw("rock").unwrap(), "{\"move\":\"rock\"}\n");
+        assert_eq!(
+            move_place(vec![((0, 0), "east".to_string())]).unwrap(),
+            "{\"move\":{\"place\":[{\"start\":{\"x\":0,\"y\":0},\"direction\":\"east\"}]}}\n"
+        );
+        assert_eq!(offer_draw(), "\"offer-draw\"\n");
+        assert!(move_heading("up").is_err());
+        assert!(auth("", "secret").is_err());
+    }
+}
@@ -0,0 +1,715 @@
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::GameClock;
+use crate::dice::DiceLog;
+use crate::forfeit::Forfeit;
+use crate::games::Viewer;
+use crate::gametraits::{
+    FilteredState, GameTrait, Paint, PlayerGameState, PlayerMove, PlayerMoveResult, PlayerTurn,
+    TurnToken, User,
+};
+use crate::handicap::Handicap;
+use crate::pos::Pos;
+use crate::rules::RulesVersion;
+use crate::time::Instant;
+use crate::TurnTracker;
+
+pub mod codec;
+mod json;
+mod notation;
+mod playback;
+#[cfg(test)]
+pub(crate) mod test_game;
+mod timing;
+mod verify;
+
+pub use crate::core::outcome::GameOutcome;
+pub use json::{ReplayJsonError, REPLAY_FORMAT_VERSION};
+pub use notation::NotationError;
+pub use playback::{PlaybackError, ReplayPlayer};
+pub use timing::{legal_move_counts, TimingCheck, TimingFlag};
+pub use verify::{verify_replay, Divergence};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedMove {
+    pub player: String,
+    pub player_move: String,
+    /// How long the player took, from being handed the turn to the move
+    /// arriving. Left out when the move wasn't timed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub think_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+}
+
+/// A note on a move, such as a coach's comment on a student's bot. Every
+/// part is optional.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Annotation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub text: String,
+    /// How good the position is after the move for the player who made it,
+    /// in hundredths of whatever the game's score counts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evaluation: Option<i32>,
+    /// Squares to draw attention to.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<Pos>,
+}
+
+impl Annotation {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    pub fn with_evaluation(mut self, evaluation: i32) -> Self {
+        self.evaluation = Some(evaluation);
+        self
+    }
+
+    pub fn with_highlights(mut self, highlights: impl IntoIterator<Item = Pos>) -> Self {
+        self.highlights.extend(highlights);
+        self
+    }
+}
+
+/// Something other than a move that changed the game, and how many main
+/// line moves had been made when it did.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RecordedEvent {
+    pub after_move: usize,
+    pub event: ReplayEvent,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReplayEvent {
+    Resigned {
+        player: String,
+    },
+    Disqualified {
+        player: String,
+        reason: String,
+    },
+    /// Everyone still playing agreed to a draw.
+    AgreedDraw,
+    /// The player ran out of time and lost.
+    OutOfTime {
+        player: String,
+    },
+    /// The player left the game after it started.
+    Disconnected {
+        player: String,
+    },
+}
+
+impl ReplayEvent {
+    /// Plays the event on `game`, handing on `turn` as it was live, and
+    /// returns the outcome if it ended the game.
+    pub(crate) fn apply(
+        &self,
+        game: &mut dyn GameTrait,
+        turn: &mut Option<PlayerTurn>,
+    ) -> Option<GameOutcome> {
+        let (player, forfeit) = match self {
+            ReplayEvent::Resigned { player } => (player, game.resign(player)),
+            ReplayEvent::Disqualified { player, reason } => {
+                (player, game.disqualify(player, reason))
+            }
+            // Clocks aren't replayed, so the player is out as if they resigned.
+            ReplayEvent::OutOfTime { player } => (player, game.resign(player)),
+            ReplayEvent::AgreedDraw => {
+                game.draw_agreed();
+                *turn = None;
+                return Some(GameOutcome::AgreedDraw);
+            }
+            ReplayEvent::Disconnected { player } => {
+                *turn = match turn.take() {
+                    Some(current) if &current.token.user.name == player => {
+                        game.current_player_disconnected(current.token)
+                    }
+                    other => {
+                        game.player_disconnected(player);
+                        other
+                    }
+                };
+                return None;
+            }
+        };
+        if forfeit.outcome.is_some() {
+            *turn = None;
+        } else if turn.as_ref().is_some_and(|t| &t.token.user.name == player) {
+            *turn = forfeit.next;
+        }
+        forfeit.outcome
+    }
+}
+
+/// An alternative line of play, branching off the main line after
+/// `from_move` moves.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variation {
+    pub from_move: usize,
+    pub moves: Vec<RecordedMove>,
+}
+
+/// Everything needed to reconstruct a finished (or in-progress) game: the
+/// players, the state the first player was shown, every accepted move and
+/// the outcome. `tags` holds free-form metadata such as the event name,
+/// `variations` "what if" lines added during analysis and `dice` the rolls
+/// of games of chance.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    pub tags: BTreeMap<String, String>,
+    #[serde(with = "crate::gametraits::stored_users")]
+    pub players: Vec<User>,
+    pub initial_state: Option<PlayerGameState>,
+    pub moves: Vec<RecordedMove>,
+    pub outcome: Option<GameOutcome>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variations: Vec<Variation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dice: Option<DiceLog>,
+    /// Left out of replays recorded before games had rules versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules: Option<RulesVersion>,
+    /// As given to the game, before it started.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub handicaps: BTreeMap<String, Handicap>,
+    /// Forfeits, agreed draws, players running out of time and players
+    /// leaving, in the order they happened.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<RecordedEvent>,
+}
+
+/// Renders `replay` into one paintable frame per position, see
+/// `ReplayPlayer::into_frames`.
+pub fn frames(
+    game: Box<dyn GameTrait>,
+    replay: Replay,
+) -> Result<Vec<Box<dyn GameTrait>>, PlaybackError> {
+    ReplayPlayer::new(game, replay)?.into_frames()
+}
+
+impl Replay {
+    pub fn new(players: Vec<User>) -> Self {
+        Self {
+            tags: BTreeMap::new(),
+            players,
+            initial_state: None,
+            moves: vec![],
+            outcome: None,
+            variations: vec![],
+            dice: None,
+            rules: None,
+            handicaps: BTreeMap::new(),
+            events: vec![],
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Adds a line branching off after `from_move` main line moves, returning
+    /// its index, or `None` if the main line is shorter than that.
+    pub fn add_variation(&mut self, from_move: usize, moves: Vec<RecordedMove>) -> Option<usize> {
+        if from_move > self.moves.len() {
+            return None;
+        }
+        self.variations.push(Variation { from_move, moves });
+        Some(self.variations.len() - 1)
+    }
+
+    /// Adds `annotation` to main line move `index`, returning whether there
+    /// is such a move.
+    pub fn annotate(&mut self, index: usize, annotation: Annotation) -> bool {
+        match self.moves.get_mut(index) {
+            Some(recorded) => {
+                recorded.annotations.push(annotation);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn variations_at(&self, from_move: usize) -> impl Iterator<Item = &Variation> {
+        self.variations
+            .iter()
+            .filter(move |v| v.from_move == from_move)
+    }
+
+    /// The replay as if variation `index` had been played, for stepping
+    /// through it with a `ReplayPlayer`. The outcome is left unknown.
+    pub fn variation_line(&self, index: usize) -> Option<Replay> {
+        let variation = self.variations.get(index)?;
+        let mut line = Replay::new(self.players.clone());
+        line.tags = self.tags.clone();
+        line.initial_state = self.initial_state.clone();
+        line.rules = self.rules;
+        line.handicaps = self.handicaps.clone();
+        line.events = self
+            .events
+            .iter()
+            .filter(|e| e.after_move <= variation.from_move)
+            .cloned()
+            .collect();
+        line.moves = self.moves[..variation.from_move].to_vec();
+        line.moves.extend(variation.moves.iter().cloned());
+        Some(line)
+    }
+}
+
+/// Wraps a game and records a `Replay` of everything that goes through it.
+///
+/// The recorder is itself a `GameTrait`, so it can be dropped in wherever the
+/// server holds a game. `as_any` forwards to the wrapped game so downcasts
+/// still see the concrete game type.
+#[derive(Clone, Debug)]
+pub struct GameRecorder {
+    game: Box<dyn GameTrait>,
+    replay: Replay,
+    /// When the turn being played was handed out, for timing moves.
+    turn_started: Option<Instant>,
+}
+
+impl GameRecorder {
+    pub fn new(game: Box<dyn GameTrait>) -> Self {
+        Self {
+            game,
+            replay: Replay::new(vec![]),
+            turn_started: None,
+        }
+    }
+
+    pub fn game(&self) -> &dyn GameTrait {
+        self.game.as_ref()
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn into_replay(self) -> Replay {
+        self.replay
+    }
+
+    /// Continues recording `game`, which has already been played up to the
+    /// end of `replay`.
+    pub fn resume(game: Box<dyn GameTrait>, replay: Replay) -> Self {
+        Self {
+            game,
+            replay,
+            turn_started: None,
+        }
+    }
+
+    /// A player over the moves recorded so far, for looking back at a game
+    /// still being played. The game is copied and played again from the
+    /// start, so the live game isn't touched.
+    pub fn rewind(&self) -> Result<ReplayPlayer, PlaybackError> {
+        ReplayPlayer::new(dyn_clone::clone_box(&*self.game), self.replay.clone())
+    }
+
+    /// The state as `viewer` would have seen it after `move_index` moves,
+    /// clamped to the moves played so far.
+    pub fn visible_at(
+        &self,
+        move_index: usize,
+        viewer: Viewer<'_>,
+    ) -> Result<Option<FilteredState>, PlaybackError> {
+        let mut player = self.rewind()?;
+        player.seek(move_index)?;
+        Ok(player.game().visible_to(viewer))
+    }
+
+    /// Adds `annotation` to the last move recorded, returning whether
+    /// there's been one.
+    pub fn annotate_last(&mut self, annotation: Annotation) -> bool {
+        match self.replay.moves.len() {
+            0 => false,
+            moves => self.replay.annotate(moves - 1, annotation),
+        }
+    }
+
+    fn is_started(&self) -> bool {
+        self.replay.initial_state.is_some()
+    }
+
+    fn record_forfeit(&mut self, forfeit: &Forfeit, event: ReplayEvent) {
+        self.replay.events.push(RecordedEvent {
+            after_move: self.replay.moves.len(),
+            event,
+        });
+        if forfeit.outcome.is_some() {
+            self.replay.outcome = forfeit.outcome.clone();
+        }
+    }
+
+    fn record_disconnect(&mut self, player: &str) {
+        self.replay.events.push(RecordedEvent {
+            after_move: self.replay.moves.len(),
+            event: ReplayEvent::Disconnected {
+                player: player.to_string(),
+            },
+        });
+    }
+
+    fn record_dice(&mut self) {
+        if let Some(log) = self.game.dice_log() {
+            self.replay.dice = Some(log.clone());
+        }
+    }
+}
+
+impl GameTrait for GameRecorder {
+    fn player_moves(&mut self, turn_token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+        let recorded = RecordedMove {
+            player: turn_token.user.name.clone(),
+            player_move: player_move.serialized.clone(),
+            think_ms: self
+                .turn_started
+                .map(|started| started.elapsed().as_millis() as u64),
+            annotations: vec![],
+        };
+        let result = self.game.player_moves(turn_token, player_move);
+        match &result {
+            PlayerMoveResult::Ok(_) => {
+                self.replay.moves.push(recorded);
+                self.turn_started = Some(Instant::now());
+            }
+            PlayerMoveResult::Win => {
+                self.replay.outcome = Some(GameOutcome::Win {
+                    winner: recorded.player.clone(),
+                });
+                self.replay.moves.push(recorded);
+            }
+            PlayerMoveResult::Draw => {
+                self.replay.outcome = Some(GameOutcome::Draw);
+                self.replay.moves.push(recorded);
+            }
+            PlayerMoveResult::OutOfTime(outcome) => {
+                self.replay.events.push(RecordedEvent {
+                    after_move: self.replay.moves.len(),
+                    event: ReplayEvent::OutOfTime {
+                        player: recorded.player,
+                    },
+                });
+                self.replay.outcome = Some(outcome.clone());
+            }
+            PlayerMoveResult::InvalidMove(_) | PlayerMoveResult::InvalidFormat(_) => {}
+        }
+        self.record_dice();
+        result
+    }
+
+    fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        self.record_disconnect(&turn_token.user.name);
+        let turn = self.game.current_player_disconnected(turn_token);
+        if turn.is_some() {
+            self.turn_started = Some(Instant::now());
+        }
+        turn
+    }
+
+    fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        let turn = self.game.try_start_game();
+        if let Some(PlayerTurn { state, .. }) = &turn {
+            self.turn_started = Some(Instant::now());
+            if !self.is_started() {
+                self.replay.initial_state = Some(state.clone());
+                self.replay.rules = Some(self.game.rules_version());
+            }
+        }
+        self.record_dice();
+        turn
+    }
+
+    fn player_connected(&mut self, user: User) {
+        if !self.is_started() {
+            self.replay.players.push(user.clone());
+        }
+        self.game.player_connected(user);
+    }
+
+    fn player_disconnected(&mut self, user: &str) {
+        if self.is_started() {
+            self.record_disconnect(user);
+        } else {
+            self.replay.players.retain(|u| u.name != user);
+        }
+        self.game.player_disconnected(user);
+    }
+
+    fn reset(&mut self, users: Vec<User>) {
+        self.replay = Replay::new(users.clone());
+        self.turn_started = None;
+        self.game.reset(users);
+    }
+
+    fn dice_log(&self) -> Option<&DiceLog> {
+        self.game.dice_log()
+    }
+
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        self.game.turn_tracker()
+    }
+
+    fn rules_version(&self) -> RulesVersion {
+        self.game.rules_version()
+    }
+
+    fn apply_handicap(&mut self, player: &str, handicap: Handicap) -> Handicap {
+        if !self.is_started() {
+            self.replay.handicaps.insert(player.to_string(), handicap);
+        }
+        self.game.apply_handicap(player, handicap)
+    }
+
+    fn player_scores(&self) -> HashMap<String, i64> {
+        self.game.player_scores()
+    }
+
+    fn clock_mut(&mut self) -> Option<&mut GameClock> {
+        self.game.clock_mut()
+    }
+
+    fn visible_to(&self, viewer: Viewer<'_>) -> Option<FilteredState> {
+        self.game.visible_to(viewer)
+    }
+
+    fn draw_offers_allowed(&self) -> bool {
+        self.game.draw_offers_allowed()
+    }
+
+    fn draw_agreed(&mut self) {
+        self.replay.events.push(RecordedEvent {
+            after_move: self.replay.moves.len(),
+            event: ReplayEvent::AgreedDraw,
+        });
+        self.replay.outcome = Some(GameOutcome::AgreedDraw);
+        self.game.draw_agreed();
+    }
+
+    fn resign(&mut self, player: &str) -> Forfeit {
+        let forfeit = self.game.resign(player);
+        let event = ReplayEvent::Resigned {
+            player: player.to_string(),
+        };
+        self.record_forfeit(&forfeit, event);
+        forfeit
+    }
+
+    fn disqualify(&mut self, player: &str, reason: &str) -> Forfeit {
+        let forfeit = self.game.disqualify(player, reason);
+        let event = ReplayEvent::Disqualified {
+            player: player.to_string(),
+            reason: reason.to_string(),
+        };
+        self.record_forfeit(&forfeit, event);
+        forfeit
+    }
+}
+
+impl Paint for GameRecorder {
+    #[cfg(feature = "gui")]
+    fn paint(&self, ctx: &mut druid::PaintCtx) {
+        self.game.paint(ctx);
+    }
+
+    fn eq(&self, other: &dyn Paint) -> bool {
+        self.game.eq(other)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self.game.as_any()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::test_game::TakeAway;
+    use super::*;
+    use crate::test_support::user;
+
+    fn play(game: &mut dyn GameTrait, turn: PlayerTurn, m: &str) -> PlayerMoveResult {
+        game.player_moves(
+            turn.token,
+            PlayerMove {
+                serialized: m.to_string(),
+            },
+        )
+    }
+
+    #[test]
+    fn rewinds_live_games() {
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(9)));
+        recorder.reset(vec![user("p1"), user("p2")]);
+        let turn = recorder.try_start_game().unwrap();
+        let PlayerMoveResult::Ok(turn) = play(&mut recorder, turn, "2") else {
+            panic!("expected ok");
+        };
+        let PlayerMoveResult::Ok(_) = play(&mut recorder, turn, "3") else {
+            panic!("expected ok");
+        };
+        let at = |index| {
+            recorder
+                .visible_at(index, Viewer::Spectator)
+                .unwrap()
+                .unwrap()
+                .serialized
+        };
+        assert_eq!(at(1), "7\n");
+        assert_eq!(at(0), "9\n");
+        assert_eq!(at(10), "4\n");
+        assert_eq!(
+            recorder.visible_to(Viewer::Spectator),
+            Some(FilteredState {
+                serialized: "4\n".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn records_accepted_moves_and_winner() {
+        let p1 = user("p1");
+        let p2 = user("p2");
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(5)));
+        recorder.reset(vec![p1.clone(), p2.clone()]);
+
+        let turn = recorder.try_start_game().unwrap();
+        let initial_state = turn.state.clone();
+        let PlayerMoveResult::InvalidMove(Some(turn)) = play(&mut recorder, turn, "4") else {
+            panic!("expected invalid move");
+        };
+        let PlayerMoveResult::Ok(turn) = play(&mut recorder, turn, "2") else {
+            panic!("expected ok");
+        };
+        assert_eq!(play(&mut recorder, turn, "3"), PlayerMoveResult::Win);
+
+        let mut replay = recorder.into_replay();
+        for recorded in &mut replay.moves {
+            assert!(recorded.think_ms.take().is_some());
+        }
+        assert_eq!(
+            replay,
+            Replay {
+                tags: BTreeMap::new(),
+                players: vec![p1, p2],
+                initial_state: Some(initial_state),
+                moves: vec![
+                    RecordedMove {
+                        player: "p1".to_string(),
+                        player_move: "2".to_string(),
+                        think_ms: None,
+                        annotations: vec![],
+                    },
+                    RecordedMove {
+                        player: "p2".to_string(),
+                        player_move: "3".to_string(),
+                        think_ms: None,
+                        annotations: vec![],
+                    },
+                ],
+                outcome: Some(GameOutcome::Win {
+                    winner: "p2".to_string()
+                }),
+                variations: vec![],
+                dice: None,
+                rules: Some(RulesVersion::INITIAL),
+                handicaps: BTreeMap::new(),
+                events: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn variation_line() {
+        let recorded = |player: &str, m: &str| RecordedMove {
+            player: player.to_string(),
+            player_move: m.to_string(),
+            think_ms: None,
+            annotations: vec![],
+        };
+        let mut replay = Replay::new(vec![user("p1"), user("p2")]);
+        replay.moves = vec![recorded("p1", "1"), recorded("p2", "1")];
+
+        assert_eq!(replay.add_variation(3, vec![]), None);
+        assert_eq!(replay.add_variation(1, vec![recorded("p2", "3")]), Some(0));
+        assert_eq!(replay.variations_at(1).count(), 1);
+        assert_eq!(replay.variations_at(0).count(), 0);
+
+        let line = replay.variation_line(0).unwrap();
+        assert_eq!(line.moves, vec![recorded("p1", "1"), recorded("p2", "3")]);
+        assert_eq!(replay.variation_line(1), None);
+    }
+
+    #[test]
+    fn annotates_moves() {
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(9)));
+        recorder.reset(vec![user("p1"), user("p2")]);
+        let turn = recorder.try_start_game().unwrap();
+        assert!(!recorder.annotate_last(Annotation::text("too early")));
+        let PlayerMoveResult::Ok(turn) = play(&mut recorder, turn, "2") else {
+            panic!("expected ok");
+        };
+        play(&mut recorder, turn, "1");
+        let live = Annotation::text("takes one, leaving six").with_evaluation(-150);
+        assert!(recorder.annotate_last(live.clone()));
+
+        let mut replay = recorder.into_replay();
+        let later = Annotation::text("fine opening")
+            .with_author("coach")
+            .with_highlights([Pos::new(2, 0)]);
+        assert!(replay.annotate(0, later.clone()));
+        assert!(!replay.annotate(2, later.clone()));
+        assert_eq!(replay.moves[0].annotations, [later]);
+        assert_eq!(replay.moves[1].annotations, [live]);
+    }
+
+    #[test]
+    fn tracks_players_until_started() {
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(5)));
+        recorder.reset(vec![]);
+        recorder.player_connected(user("p1"));
+        recorder.player_connected(user("p2"));
+        recorder.player_connected(user("p3"));
+        recorder.player_disconnected("p2");
+        recorder.try_start_game().unwrap();
+        recorder.player_disconnected("p3");
+
+        let names: Vec<_> = recorder.replay().players.iter().map(|u| &u.name).collect();
+        assert_eq!(names, vec!["p1", "p3"]);
+        assert_eq!(
+            recorder.replay().events,
+            [RecordedEvent {
+                after_move: 0,
+                event: ReplayEvent::Disconnected {
+                    player: "p3".to_string()
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn forwards_as_any_to_wrapped_game() {
+        let recorder = GameRecorder::new(Box::new(TakeAway::new(5)));
+        assert!(recorder.as_any().downcast_ref::<TakeAway>().is_some());
+        assert!(Paint::eq(&recorder, &TakeAway::new(5)));
+    }
+}
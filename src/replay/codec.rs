@@ -0,0 +1,137 @@
+//! Byte encoding of replays for storage. With the `compression` feature
+//! replays are written gzipped, and `decode` accepts both compressed and plain
+//! JSON replays regardless of how they were written, up to
+//! `MAX_REPLAY_BYTES` of JSON.
+
+#[cfg(feature = "compression")]
+use std::io::{Read, Write};
+
+use super::{Replay, ReplayJsonError};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The most JSON `decode` reads, so that a small compressed replay can't
+/// unpack to fill the memory.
+pub const MAX_REPLAY_BYTES: u64 = 32 << 20;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodecError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] ReplayJsonError),
+    #[error("the replay isn't valid UTF-8")]
+    InvalidUtf8,
+    /// The replay is compressed but the crate was built without the
+    /// `compression` feature.
+    #[error("the replay is compressed, but compression isn't enabled")]
+    CompressionUnsupported,
+    #[error("the replay is over {MAX_REPLAY_BYTES} bytes")]
+    TooLarge,
+}
+
+#[cfg(feature = "compression")]
+pub fn encode(replay: &Replay) -> Result<Vec<u8>, CodecError> {
+    use flate2::{write::GzEncoder, Compression};
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(replay.to_json().as_bytes())?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(not(feature = "compression"))]
+pub fn encode(replay: &Replay) -> Result<Vec<u8>, CodecError> {
+    Ok(replay.to_json().into_bytes())
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Replay, CodecError> {
+    let json = if bytes.starts_with(&GZIP_MAGIC) {
+        decompress(bytes, MAX_REPLAY_BYTES)?
+    } else if bytes.len() as u64 > MAX_REPLAY_BYTES {
+        return Err(CodecError::TooLarge);
+    } else {
+        String::from_utf8(bytes.to_vec()).map_err(|_| CodecError::InvalidUtf8)?
+    };
+    Ok(Replay::from_json(&json)?)
+}
+
+/// Unpacks `bytes`, giving up past `limit` bytes.
+#[cfg(feature = "compression")]
+fn decompress(bytes: &[u8], limit: u64) -> Result<String, CodecError> {
+    let mut json = vec![];
+    flate2::read::GzDecoder::new(bytes)
+        .take(limit + 1)
+        .read_to_end(&mut json)?;
+    if json.len() as u64 > limit {
+        return Err(CodecError::TooLarge);
+    }
+    String::from_utf8(json).map_err(|_| CodecError::InvalidUtf8)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(_bytes: &[u8], _limit: u64) -> Result<String, CodecError> {
+    Err(CodecError::CompressionUnsupported)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::replay::RecordedMove;
+    use crate::test_support::user;
+
+    fn sample() -> Replay {
+        let mut replay = Replay::new(vec![user("p1"), user("p2")]);
+        for i in 0..100 {
+            replay.moves.push(RecordedMove {
+                player: format!("p{}", i % 2 + 1),
+                player_move: "{\"direction\":\"north\"}".to_string(),
+                think_ms: None,
+                annotations: vec![],
+            });
+        }
+        replay
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = sample();
+        assert_eq!(decode(&encode(&replay).unwrap()).unwrap(), replay);
+    }
+
+    #[test]
+    fn reads_plain_json() {
+        let replay = sample();
+        assert_eq!(decode(replay.to_json().as_bytes()).unwrap(), replay);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compresses() {
+        let replay = sample();
+        let encoded = encode(&replay).unwrap();
+        assert!(encoded.starts_with(&GZIP_MAGIC));
+        assert!(encoded.len() < replay.to_json().len() / 4);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn stops_unpacking_at_the_limit() {
+        let json = sample().to_json();
+        let encoded = encode(&sample()).unwrap();
+        let limit = json.len() as u64;
+        assert_eq!(decompress(&encoded, limit).unwrap(), json);
+        assert!(matches!(
+            decompress(&encoded, limit - 1),
+            Err(CodecError::TooLarge)
+        ));
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn compressed_input_needs_feature() {
+        assert!(matches!(
+            decode(&[0x1f, 0x8b, 0, 0]),
+            Err(CodecError::CompressionUnsupported)
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+//! Stable JSON form of a `Replay` for external tools:
+//!
+//! ```text
+//! {"version":1,"replay":{"tags":{},"players":[{"name":"p1","color":"#0000ffff"}],...}}
+//! ```
+//!
+//! `version` is bumped whenever a change would break existing readers.
+
+use serde::{Deserialize, Serialize};
+
+use super::Replay;
+use crate::messages::{decode, MalformedMessage};
+
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayJsonError {
+    #[error("unsupported replay format version {0}")]
+    UnsupportedVersion(u32),
+    #[error(transparent)]
+    Json(#[from] MalformedMessage),
+}
+
+#[derive(Serialize)]
+struct VersionedRef<'a> {
+    version: u32,
+    replay: &'a Replay,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct Versioned {
+    replay: Replay,
+}
+
+impl Replay {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&VersionedRef {
+            version: REPLAY_FORMAT_VERSION,
+            replay: self,
+        })
+        .unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Replay, ReplayJsonError> {
+        let Version { version } = decode(json)?;
+        if version != REPLAY_FORMAT_VERSION {
+            return Err(ReplayJsonError::UnsupportedVersion(version));
+        }
+        let Versioned { replay } = decode(json)?;
+        Ok(replay)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gametraits::PlayerGameState;
+    use crate::replay::{GameOutcome, RecordedMove};
+    use crate::test_support::user;
+
+    fn sample() -> Replay {
+        let mut replay = Replay::new(vec![user("p1")]);
+        replay.initial_state = Some(PlayerGameState {
+            serialized: "5\n".to_string(),
+        });
+        replay.moves.push(RecordedMove {
+            player: "p1".to_string(),
+            player_move: "2".to_string(),
+            think_ms: None,
+            annotations: vec![],
+        });
+        replay.outcome = Some(GameOutcome::Win {
+            winner: "p1".to_string(),
+        });
+        replay
+    }
+
+    #[test]
+    fn stable_format() {
+        assert_eq!(
+            sample().to_json(),
+            r##"{"version":1,"replay":{"tags":{},"players":[{"name":"p1","color":"#0000ffff"}],"initial_state":{"serialized":"5\n"},"moves":[{"player":"p1","player_move":"2"}],"outcome":{"win":{"winner":"p1"}}}}"##
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = sample();
+        assert_eq!(Replay::from_json(&replay.to_json()).unwrap(), replay);
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let json = sample()
+            .to_json()
+            .replace("\"version\":1", "\"version\":99");
+        assert!(matches!(
+            Replay::from_json(&json),
+            Err(ReplayJsonError::UnsupportedVersion(99))
+        ));
+        assert!(matches!(
+            Replay::from_json("{}"),
+            Err(ReplayJsonError::Json(_))
+        ));
+    }
+}
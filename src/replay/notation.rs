@@ -0,0 +1,765 @@
+//! PGN-like text form of a `Replay`, meant to be pasted into chat and diffed:
+//!
+//! ```text
+//! [Event "Friday cup"]
+//! [Player "p1" "#0000ffff"]
+//! [Player "p2" "#ff0000ff"]
+//! [Winner "p2"]
+//! [InitialState "{\"pile\":5}\n"]
+//! [Rules "1.0.0"]
+//! [Dice "seed 7" "d6 3" "d6 5"]
+//! [Handicap "p2" "material 1" "score 0" "time-ms 30000"]
+//!
+//! 1. p1 {"take":2}
+//!   [ThinkMs "1520"]
+//!   [Note "leaves a losing pile" "by coach" "eval -150" "at 3,0"]
+//! 2. p2 {"take":3}
+//!
+//! (from 1
+//! 2. p2 {"take":1}
+//! )
+//! ```
+//!
+//! Variations follow the main line, each in a `(from N` ... `)` block with
+//! moves numbered as if they continued the game after move `N`. Forfeits,
+//! agreed draws, players running out of time and players leaving are tags
+//! saying how many moves came before them, as in `[Resigned "p1" "after 4"]`. How long a
+//! move took and its annotations are tags on the lines after it, each
+//! annotation its text and then whichever of author, evaluation and
+//! highlighted squares it has.
+//!
+//! Moves are written without trailing whitespace. Moves starting with a
+//! quote or holding line breaks, tabs or other control characters are
+//! quoted and escaped like tag values. Player names are expected not to
+//! contain whitespace.
+
+use std::fmt::Write;
+
+use super::{Annotation, GameOutcome, RecordedEvent, RecordedMove, Replay, ReplayEvent, Variation};
+use crate::dice::{DiceLog, Roll};
+use crate::gametraits::{color_from_hex, color_to_hex, PlayerGameState, User};
+use crate::handicap::Handicap;
+use crate::pos::Pos;
+
+const PLAYER: &str = "Player";
+const WINNER: &str = "Winner";
+const RESULT: &str = "Result";
+const INITIAL_STATE: &str = "InitialState";
+const RULES: &str = "Rules";
+const HANDICAP: &str = "Handicap";
+const RESIGNED: &str = "Resigned";
+const DISQUALIFIED: &str = "Disqualified";
+const AGREED_DRAW: &str = "AgreedDraw";
+const OUT_OF_TIME: &str = "OutOfTime";
+const DISCONNECTED: &str = "Disconnected";
+const DICE: &str = "Dice";
+const THINK_MS: &str = "ThinkMs";
+const NOTE: &str = "Note";
+const RESERVED_TAGS: [&str; 14] = [
+    PLAYER,
+    WINNER,
+    RESULT,
+    INITIAL_STATE,
+    RULES,
+    HANDICAP,
+    RESIGNED,
+    DISQUALIFIED,
+    AGREED_DRAW,
+    OUT_OF_TIME,
+    DISCONNECTED,
+    DICE,
+    THINK_MS,
+    NOTE,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("line {line}: {reason}")]
+pub struct NotationError {
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+impl Replay {
+    pub fn to_notation(&self) -> String {
+        let mut out = String::new();
+        for (key, value) in &self.tags {
+            if !RESERVED_TAGS.contains(&key.as_str()) {
+                writeln!(out, "[{key} \"{}\"]", escape(value)).unwrap();
+            }
+        }
+        for user in &self.players {
+            writeln!(
+                out,
+                "[{PLAYER} \"{}\" \"{}\"]",
+                escape(&user.name),
+                color_to_hex(user.color)
+            )
+            .unwrap();
+        }
+        match &self.outcome {
+            Some(GameOutcome::Win { winner }) => {
+                writeln!(out, "[{WINNER} \"{}\"]", escape(winner)).unwrap()
+            }
+            Some(GameOutcome::Draw) => writeln!(out, "[{RESULT} \"draw\"]").unwrap(),
+            Some(GameOutcome::AgreedDraw) => writeln!(out, "[{RESULT} \"agreed-draw\"]").unwrap(),
+            None => {}
+        }
+        if let Some(state) = &self.initial_state {
+            writeln!(out, "[{INITIAL_STATE} \"{}\"]", escape(&state.serialized)).unwrap();
+        }
+        if let Some(rules) = self.rules {
+            writeln!(out, "[{RULES} \"{rules}\"]").unwrap();
+        }
+        if let Some(dice) = &self.dice {
+            write!(out, "[{DICE} \"seed {}\"", dice.seed).unwrap();
+            for roll in &dice.rolls {
+                write!(out, " \"d{} {}\"", roll.sides, roll.value).unwrap();
+            }
+            out.push_str("]\n");
+        }
+        for (player, handicap) in &self.handicaps {
+            writeln!(
+                out,
+                "[{HANDICAP} \"{}\" \"material {}\" \"score {}\" \"time-ms {}\"]",
+                escape(player),
+                handicap.extra_material,
+                handicap.score_offset,
+                handicap.time_bonus_ms
+            )
+            .unwrap();
+        }
+        for RecordedEvent { after_move, event } in &self.events {
+            match event {
+                ReplayEvent::Resigned { player } => writeln!(
+                    out,
+                    "[{RESIGNED} \"{}\" \"after {after_move}\"]",
+                    escape(player)
+                ),
+                ReplayEvent::Disqualified { player, reason } => writeln!(
+                    out,
+                    "[{DISQUALIFIED} \"{}\" \"after {after_move}\" \"{}\"]",
+                    escape(player),
+                    escape(reason)
+                ),
+                ReplayEvent::OutOfTime { player } => writeln!(
+                    out,
+                    "[{OUT_OF_TIME} \"{}\" \"after {after_move}\"]",
+                    escape(player)
+                ),
+                ReplayEvent::Disconnected { player } => writeln!(
+                    out,
+                    "[{DISCONNECTED} \"{}\" \"after {after_move}\"]",
+                    escape(player)
+                ),
+                ReplayEvent::AgreedDraw => {
+                    writeln!(out, "[{AGREED_DRAW} \"after {after_move}\"]")
+                }
+            }
+            .unwrap();
+        }
+
+        out.push('\n');
+        for (i, m) in self.moves.iter().enumerate() {
+            write_move(&mut out, i + 1, m);
+        }
+        for variation in &self.variations {
+            writeln!(out, "\n(from {}", variation.from_move).unwrap();
+            for (i, m) in variation.moves.iter().enumerate() {
+                write_move(&mut out, variation.from_move + i + 1, m);
+            }
+            out.push_str(")\n");
+        }
+        out
+    }
+
+    pub fn from_notation(text: &str) -> Result<Replay, NotationError> {
+        let mut replay = Replay::new(vec![]);
+        let mut variation: Option<Variation> = None;
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let error = |reason| NotationError {
+                line: line_no,
+                reason,
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(tag) = line.strip_prefix('[') {
+                let tag = tag.strip_suffix(']').ok_or(error("unterminated tag"))?;
+                let (key, values) = parse_tag(tag).ok_or(error("malformed tag"))?;
+                match (key, values.as_slice()) {
+                    (PLAYER, [name, color]) => replay.players.push(User::new(
+                        name.clone(),
+                        color_from_hex(color).ok_or(error("invalid player color"))?,
+                    )),
+                    (WINNER, [winner]) => {
+                        replay.outcome = Some(GameOutcome::Win {
+                            winner: winner.clone(),
+                        })
+                    }
+                    (RESULT, [result]) if result == "draw" => {
+                        replay.outcome = Some(GameOutcome::Draw)
+                    }
+                    (RESULT, [result]) if result == "agreed-draw" => {
+                        replay.outcome = Some(GameOutcome::AgreedDraw)
+                    }
+                    (INITIAL_STATE, [state]) => {
+                        replay.initial_state = Some(PlayerGameState {
+                            serialized: state.clone(),
+                        })
+                    }
+                    (RULES, [rules]) => {
+                        replay.rules =
+                            Some(rules.parse().map_err(|_| error("invalid rules version"))?)
+                    }
+                    (DICE, [seed, rolls @ ..]) => {
+                        replay.dice = Some(parse_dice(seed, rolls).ok_or(error("invalid dice"))?)
+                    }
+                    (HANDICAP, [player, material, score, time]) => {
+                        let handicap = parse_handicap(material, score, time)
+                            .ok_or(error("invalid handicap"))?;
+                        replay.handicaps.insert(player.clone(), handicap);
+                    }
+                    (RESIGNED, [player, after]) => replay.events.push(RecordedEvent {
+                        after_move: parse_after(after).ok_or(error("invalid event"))?,
+                        event: ReplayEvent::Resigned {
+                            player: player.clone(),
+                        },
+                    }),
+                    (DISQUALIFIED, [player, after, reason]) => replay.events.push(RecordedEvent {
+                        after_move: parse_after(after).ok_or(error("invalid event"))?,
+                        event: ReplayEvent::Disqualified {
+                            player: player.clone(),
+                            reason: reason.clone(),
+                        },
+                    }),
+                    (OUT_OF_TIME, [player, after]) => replay.events.push(RecordedEvent {
+                        after_move: parse_after(after).ok_or(error("invalid event"))?,
+                        event: ReplayEvent::OutOfTime {
+                            player: player.clone(),
+                        },
+                    }),
+                    (DISCONNECTED, [player, after]) => replay.events.push(RecordedEvent {
+                        after_move: parse_after(after).ok_or(error("invalid event"))?,
+                        event: ReplayEvent::Disconnected {
+                            player: player.clone(),
+                        },
+                    }),
+                    (THINK_MS, [think_ms]) => {
+                        let m = last_move(&mut replay, &mut variation)
+                            .ok_or(error("time without a move"))?;
+                        m.think_ms = Some(think_ms.parse().map_err(|_| error("invalid time"))?);
+                    }
+                    (NOTE, [text, details @ ..]) => {
+                        let annotation =
+                            parse_note(text, details).ok_or(error("invalid annotation"))?;
+                        last_move(&mut replay, &mut variation)
+                            .ok_or(error("annotation without a move"))?
+                            .annotations
+                            .push(annotation);
+                    }
+                    (AGREED_DRAW, [after]) => replay.events.push(RecordedEvent {
+                        after_move: parse_after(after).ok_or(error("invalid event"))?,
+                        event: ReplayEvent::AgreedDraw,
+                    }),
+                    (key, [value]) if !RESERVED_TAGS.contains(&key) => {
+                        replay.tags.insert(key.to_string(), value.clone());
+                    }
+                    _ => return Err(error("unexpected tag values")),
+                }
+                continue;
+            }
+
+            if let Some(from) = line.strip_prefix("(from ") {
+                if variation.is_some() {
+                    return Err(error("nested variation"));
+                }
+                let from_move = from.parse().map_err(|_| error("invalid variation start"))?;
+                variation = Some(Variation {
+                    from_move,
+                    moves: vec![],
+                });
+                continue;
+            }
+            if line == ")" {
+                let variation = variation.take().ok_or(error("unopened variation"))?;
+                if variation.from_move > replay.moves.len() {
+                    return Err(error("variation starts after the game"));
+                }
+                replay.variations.push(variation);
+                continue;
+            }
+
+            let (number, rest) = line.split_once(". ").ok_or(error("expected a move"))?;
+            let (moves, first) = match &mut variation {
+                Some(v) => (&mut v.moves, v.from_move + 1),
+                None if replay.variations.is_empty() => (&mut replay.moves, 1),
+                None => return Err(error("move after variations")),
+            };
+            if number.parse::<usize>() != Ok(first + moves.len()) {
+                return Err(error("move number out of order"));
+            }
+            let (player, player_move) = rest.split_once(' ').ok_or(error("missing move"))?;
+            let player_move = match parse_quoted(player_move) {
+                Some((player_move, "")) => player_move,
+                Some(_) => return Err(error("text after a quoted move")),
+                None if player_move.starts_with('"') => return Err(error("unterminated move")),
+                None => player_move.to_string(),
+            };
+            moves.push(RecordedMove {
+                player: player.to_string(),
+                player_move,
+                think_ms: None,
+                annotations: vec![],
+            });
+        }
+        if variation.is_some() {
+            return Err(NotationError {
+                line: text.lines().count(),
+                reason: "unterminated variation",
+            });
+        }
+        Ok(replay)
+    }
+}
+
+fn parse_handicap(material: &str, score: &str, time: &str) -> Option<Handicap> {
+    Some(Handicap {
+        extra_material: material.strip_prefix("material ")?.parse().ok()?,
+        score_offset: score.strip_prefix("score ")?.parse().ok()?,
+        time_bonus_ms: time.strip_prefix("time-ms ")?.parse().ok()?,
+    })
+}
+
+fn parse_dice(seed: &str, rolls: &[String]) -> Option<DiceLog> {
+    Some(DiceLog {
+        seed: seed.strip_prefix("seed ")?.parse().ok()?,
+        rolls: rolls
+            .iter()
+            .map(|roll| {
+                let (sides, value) = roll.strip_prefix('d')?.split_once(' ')?;
+                Some(Roll {
+                    sides: sides.parse().ok()?,
+                    value: value.parse().ok()?,
+                })
+            })
+            .collect::<Option<_>>()?,
+    })
+}
+
+fn parse_note(text: &str, details: &[String]) -> Option<Annotation> {
+    let mut annotation = Annotation::text(text);
+    for detail in details {
+        let (kind, value) = detail.split_once(' ')?;
+        match kind {
+            "by" => annotation.author = Some(value.to_string()),
+            "eval" => annotation.evaluation = Some(value.parse().ok()?),
+            "at" => {
+                let (x, y) = value.split_once(',')?;
+                annotation
+                    .highlights
+                    .push(Pos::new(x.parse().ok()?, y.parse().ok()?));
+            }
+            _ => return None,
+        }
+    }
+    Some(annotation)
+}
+
+fn parse_after(after: &str) -> Option<usize> {
+    after.strip_prefix("after ")?.parse().ok()
+}
+
+fn write_move(out: &mut String, number: usize, m: &RecordedMove) {
+    let player_move = m.player_move.trim_end();
+    if player_move.starts_with('"') || player_move.contains(char::is_control) {
+        writeln!(out, "{number}. {} \"{}\"", m.player, escape(player_move)).unwrap();
+    } else {
+        writeln!(out, "{number}. {} {player_move}", m.player).unwrap();
+    }
+    if let Some(think_ms) = m.think_ms {
+        writeln!(out, "  [{THINK_MS} \"{think_ms}\"]").unwrap();
+    }
+    for annotation in &m.annotations {
+        write!(out, "  [{NOTE} \"{}\"", escape(&annotation.text)).unwrap();
+        if let Some(author) = &annotation.author {
+            write!(out, " \"by {}\"", escape(author)).unwrap();
+        }
+        if let Some(evaluation) = annotation.evaluation {
+            write!(out, " \"eval {evaluation}\"").unwrap();
+        }
+        for pos in &annotation.highlights {
+            write!(out, " \"at {},{}\"", pos.x, pos.y).unwrap();
+        }
+        out.push_str("]\n");
+    }
+}
+
+/// The move last read, in the open variation if there is one.
+fn last_move<'a>(
+    replay: &'a mut Replay,
+    variation: &'a mut Option<Variation>,
+) -> Option<&'a mut RecordedMove> {
+    match variation {
+        Some(v) => v.moves.last_mut(),
+        None if replay.variations.is_empty() => replay.moves.last_mut(),
+        None => None,
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parses `Key "value" "value"...`, undoing `escape` on the values.
+fn parse_tag(tag: &str) -> Option<(&str, Vec<String>)> {
+    let (key, mut rest) = tag.split_once(' ')?;
+    let mut values = vec![];
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Some((key, values));
+        }
+        let (value, after) = parse_quoted(rest)?;
+        values.push(value);
+        rest = after;
+    }
+}
+
+/// Parses the quoted value `s` starts with, undoing `escape`, and returns
+/// it with what follows.
+fn parse_quoted(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut value = String::new();
+    let end = loop {
+        match chars.next()? {
+            (i, '"') => break i,
+            (_, '\\') => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                c => value.push(c),
+            },
+            (_, c) => value.push(c),
+        }
+    };
+    Some((value, &s[end + 2..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::gametraits::{GameTrait, PlayerMove, PlayerMoveResult};
+    use crate::replay::test_game::TakeAway;
+    use crate::replay::{verify_replay, GameRecorder};
+    use crate::rules::RulesVersion;
+    use crate::test_support::{user, users};
+
+    fn sample() -> Replay {
+        let mut replay = Replay::new(vec![
+            user("p1"),
+            User::new("p2", Color::rgba8(0x12, 0x34, 0x56, 0x78)),
+        ]);
+        replay
+            .tags
+            .insert("Event".to_string(), "Friday \"cup\"".to_string());
+        replay.initial_state = Some(PlayerGameState {
+            serialized: "{\"pile\":5}\n".to_string(),
+        });
+        replay.moves = vec![
+            RecordedMove {
+                player: "p1".to_string(),
+                player_move: "{\"take\": 2}".to_string(),
+                think_ms: None,
+                annotations: vec![],
+            },
+            RecordedMove {
+                player: "p2".to_string(),
+                player_move: "{\"take\": 3}".to_string(),
+                think_ms: None,
+                annotations: vec![],
+            },
+        ];
+        replay.outcome = Some(GameOutcome::Win {
+            winner: "p2".to_string(),
+        });
+        replay.rules = Some(RulesVersion::new(2, 1, 0));
+        replay.handicaps.insert(
+            "p2".to_string(),
+            Handicap {
+                extra_material: 1,
+                score_offset: -2,
+                time_bonus_ms: 30_000,
+            },
+        );
+        replay
+    }
+
+    #[test]
+    fn writes_notation() {
+        assert_eq!(
+            sample().to_notation(),
+            r##"[Event "Friday \"cup\""]
+[Player "p1" "#0000ffff"]
+[Player "p2" "#12345678"]
+[Winner "p2"]
+[InitialState "{\"pile\":5}\n"]
+[Rules "2.1.0"]
+[Handicap "p2" "material 1" "score -2" "time-ms 30000"]
+
+1. p1 {"take": 2}
+2. p2 {"take": 3}
+"##
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = sample();
+        assert_eq!(Replay::from_notation(&replay.to_notation()), Ok(replay));
+
+        let mut draw = Replay::new(vec![user("p1")]);
+        draw.outcome = Some(GameOutcome::Draw);
+        assert_eq!(Replay::from_notation(&draw.to_notation()), Ok(draw));
+    }
+
+    #[test]
+    fn round_trips_forfeits() {
+        let mut replay = sample();
+        replay.events = vec![
+            RecordedEvent {
+                after_move: 1,
+                event: ReplayEvent::Disqualified {
+                    player: "p3".to_string(),
+                    reason: "said \"hi\"".to_string(),
+                },
+            },
+            RecordedEvent {
+                after_move: 2,
+                event: ReplayEvent::Resigned {
+                    player: "p1".to_string(),
+                },
+            },
+            RecordedEvent {
+                after_move: 2,
+                event: ReplayEvent::OutOfTime {
+                    player: "p2".to_string(),
+                },
+            },
+            RecordedEvent {
+                after_move: 2,
+                event: ReplayEvent::Disconnected {
+                    player: "p3".to_string(),
+                },
+            },
+        ];
+        let notation = replay.to_notation();
+        assert!(notation.contains("[Resigned \"p1\" \"after 2\"]\n"));
+        assert_eq!(Replay::from_notation(&notation), Ok(replay));
+    }
+
+    #[test]
+    fn round_trips_dice() {
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(5).with_dice(7)));
+        recorder.reset(users(2));
+        let mut turn = recorder.try_start_game().unwrap();
+        for take in ["2", "2"] {
+            let player_move = PlayerMove {
+                serialized: take.to_string(),
+            };
+            let PlayerMoveResult::Ok(next) = recorder.player_moves(turn.token, player_move) else {
+                panic!("expected ok");
+            };
+            turn = next;
+        }
+        let replay = recorder.into_replay();
+        assert_eq!(replay.dice.as_ref().map(|dice| dice.rolls.len()), Some(2));
+
+        let notation = replay.to_notation();
+        assert!(notation.contains("[Dice \"seed 7\" \"d6 "));
+        let parsed = Replay::from_notation(&notation).unwrap();
+        assert_eq!(parsed, replay);
+        let game = Box::new(TakeAway::new(5).with_dice(7));
+        assert_eq!(verify_replay(game, &parsed), Ok(()));
+
+        assert_eq!(
+            Replay::from_notation("[Dice \"seed 7\" \"6\"]"),
+            Err(NotationError {
+                line: 1,
+                reason: "invalid dice"
+            })
+        );
+    }
+
+    #[test]
+    fn round_trips_move_times() {
+        let mut replay = sample();
+        replay.moves[0].think_ms = Some(1520);
+        replay.add_variation(
+            1,
+            vec![RecordedMove {
+                player: "p2".to_string(),
+                player_move: "{\"take\": 1}".to_string(),
+                think_ms: Some(0),
+                annotations: vec![],
+            }],
+        );
+        let notation = replay.to_notation();
+        assert!(notation.contains("1. p1 {\"take\": 2}\n  [ThinkMs \"1520\"]\n2. p2"));
+        assert_eq!(Replay::from_notation(&notation), Ok(replay));
+
+        assert_eq!(
+            Replay::from_notation("[ThinkMs \"5\"]\n1. p1 2\n"),
+            Err(NotationError {
+                line: 1,
+                reason: "time without a move"
+            })
+        );
+    }
+
+    #[test]
+    fn round_trips_annotations() {
+        let mut replay = sample();
+        replay.moves[0].think_ms = Some(1520);
+        replay.moves[0].annotations = vec![
+            Annotation::text("leaves a \"losing\" pile")
+                .with_author("coach")
+                .with_evaluation(-150)
+                .with_highlights([Pos::new(3, 0), Pos::new(-1, 2)]),
+            Annotation::text("").with_author("p2"),
+        ];
+        replay.moves[1].annotations = vec![Annotation::text("wins")];
+        let notation = replay.to_notation();
+        assert!(notation.contains(
+            "  [Note \"leaves a \\\"losing\\\" pile\" \"by coach\" \"eval -150\" \"at 3,0\" \"at -1,2\"]\n"
+        ));
+        assert_eq!(Replay::from_notation(&notation), Ok(replay));
+
+        assert_eq!(
+            Replay::from_notation("1. p1 2\n[Note \"hm\" \"eval high\"]\n"),
+            Err(NotationError {
+                line: 2,
+                reason: "invalid annotation"
+            })
+        );
+    }
+
+    #[test]
+    fn quotes_moves_that_would_break_lines() {
+        let mut replay = Replay::new(vec![user("p1"), user("p2")]);
+        for player_move in ["line\nbreak", "tab\tbed", "\"up\"", "c:\\ \"x\""] {
+            replay.moves.push(RecordedMove {
+                player: "p1".to_string(),
+                player_move: player_move.to_string(),
+                think_ms: None,
+                annotations: vec![],
+            });
+        }
+        let notation = replay.to_notation();
+        assert!(notation.ends_with(
+            "1. p1 \"line\\nbreak\"\n2. p1 \"tab\\tbed\"\n3. p1 \"\\\"up\\\"\"\n4. p1 c:\\ \"x\"\n"
+        ));
+        assert_eq!(Replay::from_notation(&notation), Ok(replay));
+
+        assert_eq!(
+            Replay::from_notation("1. p1 \"open\n"),
+            Err(NotationError {
+                line: 1,
+                reason: "unterminated move"
+            })
+        );
+    }
+
+    #[test]
+    fn round_trips_agreed_draws() {
+        let mut replay = sample();
+        replay.outcome = Some(GameOutcome::AgreedDraw);
+        replay.events = vec![RecordedEvent {
+            after_move: 2,
+            event: ReplayEvent::AgreedDraw,
+        }];
+        let notation = replay.to_notation();
+        assert!(notation.contains("[AgreedDraw \"after 2\"]\n"));
+        assert_eq!(Replay::from_notation(&notation), Ok(replay));
+    }
+
+    #[test]
+    fn variations() {
+        let mut replay = sample();
+        replay.add_variation(
+            1,
+            vec![RecordedMove {
+                player: "p2".to_string(),
+                player_move: "{\"take\": 1}".to_string(),
+                think_ms: None,
+                annotations: vec![],
+            }],
+        );
+        replay.add_variation(0, vec![]);
+        let text = replay.to_notation();
+        assert!(text
+            .ends_with("2. p2 {\"take\": 3}\n\n(from 1\n2. p2 {\"take\": 1}\n)\n\n(from 0\n)\n"));
+        assert_eq!(Replay::from_notation(&text), Ok(replay));
+
+        assert_eq!(
+            Replay::from_notation("1. p1 1\n(from 2\n)"),
+            Err(NotationError {
+                line: 3,
+                reason: "variation starts after the game"
+            })
+        );
+        assert_eq!(
+            Replay::from_notation("1. p1 1\n(from 0\n1. p1 2"),
+            Err(NotationError {
+                line: 3,
+                reason: "unterminated variation"
+            })
+        );
+    }
+
+    #[test]
+    fn errors_point_at_line() {
+        assert_eq!(
+            Replay::from_notation("[Player \"p1\" \"#0000ffff\"]\n\n2. p1 1"),
+            Err(NotationError {
+                line: 3,
+                reason: "move number out of order"
+            })
+        );
+        assert_eq!(
+            Replay::from_notation("[Player \"p1\" \"blue\"]"),
+            Err(NotationError {
+                line: 1,
+                reason: "invalid player color"
+            })
+        );
+        assert_eq!(
+            Replay::from_notation("[Rules \"2\"]"),
+            Err(NotationError {
+                line: 1,
+                reason: "invalid rules version"
+            })
+        );
+        assert_eq!(
+            Replay::from_notation("[Event \"unterminated]"),
+            Err(NotationError {
+                line: 1,
+                reason: "malformed tag"
+            })
+        );
+    }
+}
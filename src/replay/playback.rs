@@ -0,0 +1,326 @@
+use crate::gametraits::{
+    GameTrait, PlayerGameState, PlayerMove, PlayerMoveResult, PlayerTurn, TurnToken, User,
+};
+
+use super::Replay;
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum PlaybackError {
+    #[error("the game didn't start")]
+    NotStarted,
+    #[error("move {index} is by {player}, who isn't playing")]
+    UnknownPlayer { index: usize, player: String },
+    #[error("move {index} was rejected")]
+    Rejected { index: usize },
+}
+
+#[derive(Clone, Debug)]
+struct Position {
+    game: Box<dyn GameTrait>,
+    next: Option<(User, PlayerGameState)>,
+}
+
+/// Steps through a `Replay` by feeding the recorded moves to a fresh game.
+///
+/// Positions are computed lazily and kept, so stepping back or seeking to an
+/// already visited move is free.
+#[derive(Clone, Debug)]
+pub struct ReplayPlayer {
+    replay: Replay,
+    positions: Vec<Position>,
+    current: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(mut game: Box<dyn GameTrait>, replay: Replay) -> Result<Self, PlaybackError> {
+        game.reset(replay.players.clone());
+        for (player, handicap) in &replay.handicaps {
+            game.apply_handicap(player, *handicap);
+        }
+        let turn = game.try_start_game().ok_or(PlaybackError::NotStarted)?;
+        let mut player = Self {
+            replay,
+            positions: vec![],
+            current: 0,
+        };
+        let start = player.with_events(0, game, Some(turn));
+        player.positions.push(start);
+        Ok(player)
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Number of moves applied to reach the current position.
+    pub fn position(&self) -> usize {
+        self.current
+    }
+
+    pub fn num_moves(&self) -> usize {
+        self.replay.moves.len()
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.current == self.num_moves()
+    }
+
+    pub fn game(&self) -> &dyn GameTrait {
+        self.positions[self.current].game.as_ref()
+    }
+
+    /// The state sent to the player to move, `None` once the game is over.
+    pub fn state(&self) -> Option<&PlayerGameState> {
+        self.positions[self.current]
+            .next
+            .as_ref()
+            .map(|(_, state)| state)
+    }
+
+    /// The turn the game hands out at the current position, as it was
+    /// returned when the game was live.
+    pub fn turn(&self) -> Option<PlayerTurn> {
+        let (user, state) = self.positions[self.current].next.clone()?;
+        Some(PlayerTurn {
+            token: TurnToken { user },
+            state,
+        })
+    }
+
+    pub fn into_game(mut self) -> Box<dyn GameTrait> {
+        self.positions.swap_remove(self.current).game
+    }
+
+    pub fn step_forward(&mut self) -> Result<bool, PlaybackError> {
+        if self.is_at_end() {
+            return Ok(false);
+        }
+        if self.current + 1 == self.positions.len() {
+            let next = self.apply(self.current)?;
+            self.positions.push(next);
+        }
+        self.current += 1;
+        Ok(true)
+    }
+
+    pub fn step_back(&mut self) -> bool {
+        if self.current == 0 {
+            return false;
+        }
+        self.current -= 1;
+        true
+    }
+
+    /// Moves to the position after `index` moves, clamped to the end of the
+    /// replay.
+    pub fn seek(&mut self, index: usize) -> Result<(), PlaybackError> {
+        let index = index.min(self.num_moves());
+        if index < self.positions.len() {
+            self.current = index;
+            return Ok(());
+        }
+        self.current = self.positions.len() - 1;
+        while self.current < index {
+            self.step_forward()?;
+        }
+        Ok(())
+    }
+
+    /// Every position of the replay, from before the first move to after the
+    /// last one. Each frame is a snapshot of the game that can be painted on
+    /// its own, without a live game behind it.
+    pub fn into_frames(mut self) -> Result<Vec<Box<dyn GameTrait>>, PlaybackError> {
+        self.seek(self.num_moves())?;
+        Ok(self.positions.into_iter().map(|p| p.game).collect())
+    }
+
+    fn apply(&self, index: usize) -> Result<Position, PlaybackError> {
+        let recorded = &self.replay.moves[index];
+        let user = self
+            .replay
+            .players
+            .iter()
+            .find(|u| u.name == recorded.player)
+            .ok_or_else(|| PlaybackError::UnknownPlayer {
+                index,
+                player: recorded.player.clone(),
+            })?;
+
+        let mut game = self.positions[index].game.clone();
+        let result = game.player_moves(
+            TurnToken { user: user.clone() },
+            PlayerMove {
+                serialized: recorded.player_move.clone(),
+            },
+        );
+        let next = match result {
+            PlayerMoveResult::Ok(turn) => Some(turn),
+            PlayerMoveResult::Win | PlayerMoveResult::Draw | PlayerMoveResult::OutOfTime(_) => None,
+            PlayerMoveResult::InvalidMove(_) | PlayerMoveResult::InvalidFormat(_) => {
+                return Err(PlaybackError::Rejected { index })
+            }
+        };
+        Ok(self.with_events(index + 1, game, next))
+    }
+
+    /// The position after `index` moves, with the events recorded there
+    /// played on `game`, the ones past the last move included when it's the
+    /// final position.
+    fn with_events(
+        &self,
+        index: usize,
+        mut game: Box<dyn GameTrait>,
+        mut turn: Option<PlayerTurn>,
+    ) -> Position {
+        let last = index == self.num_moves();
+        for recorded in &self.replay.events {
+            if recorded.after_move == index || (last && recorded.after_move > index) {
+                recorded.event.apply(game.as_mut(), &mut turn);
+            }
+        }
+        Position {
+            game,
+            next: turn.map(|turn| (turn.token.user, turn.state)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::replay::test_game::TakeAway;
+    use crate::replay::{RecordedEvent, RecordedMove, ReplayEvent};
+    use crate::test_support::{user, users};
+
+    fn replay(moves: &[(&str, &str)]) -> Replay {
+        let mut replay = Replay::new(vec![user("p1"), user("p2")]);
+        replay.moves = moves
+            .iter()
+            .map(|(player, m)| RecordedMove {
+                player: player.to_string(),
+                player_move: m.to_string(),
+                think_ms: None,
+                annotations: vec![],
+            })
+            .collect();
+        replay
+    }
+
+    fn state(player: &ReplayPlayer) -> Option<&str> {
+        player.state().map(|s| s.serialized.as_str())
+    }
+
+    #[test]
+    fn step_forward_and_back() {
+        let replay = replay(&[("p1", "2"), ("p2", "1"), ("p1", "2")]);
+        let mut player = ReplayPlayer::new(Box::new(TakeAway::new(5)), replay).unwrap();
+
+        assert_eq!(state(&player), Some("5\n"));
+        assert!(!player.step_back());
+        assert_eq!(player.step_forward(), Ok(true));
+        assert_eq!(state(&player), Some("3\n"));
+        assert_eq!(player.step_forward(), Ok(true));
+        assert_eq!(state(&player), Some("2\n"));
+        assert_eq!(player.step_forward(), Ok(true));
+        assert_eq!(state(&player), None);
+        assert!(player.is_at_end());
+        assert_eq!(player.step_forward(), Ok(false));
+
+        assert!(player.step_back());
+        assert_eq!(player.position(), 2);
+        assert_eq!(state(&player), Some("2\n"));
+        assert_eq!(player.turn().unwrap().token.user.name, "p1");
+    }
+
+    #[test]
+    fn seek() {
+        let replay = replay(&[("p1", "1"), ("p2", "1"), ("p1", "1"), ("p2", "2")]);
+        let mut player = ReplayPlayer::new(Box::new(TakeAway::new(5)), replay).unwrap();
+
+        player.seek(3).unwrap();
+        assert_eq!(state(&player), Some("2\n"));
+        player.seek(1).unwrap();
+        assert_eq!(state(&player), Some("4\n"));
+        player.seek(100).unwrap();
+        assert_eq!(player.position(), 4);
+        assert_eq!(state(&player), None);
+        player.seek(0).unwrap();
+        assert_eq!(state(&player), Some("5\n"));
+    }
+
+    #[test]
+    fn frames() {
+        let replay = replay(&[("p1", "3"), ("p2", "2")]);
+        let frames = ReplayPlayer::new(Box::new(TakeAway::new(5)), replay)
+            .unwrap()
+            .into_frames()
+            .unwrap();
+
+        let piles: Vec<_> = frames
+            .iter()
+            .map(|f| f.as_any().downcast_ref::<TakeAway>().unwrap().remaining())
+            .collect();
+        assert_eq!(piles, vec![5, 2, 0]);
+    }
+
+    #[test]
+    fn forfeits_hand_on_the_turn() {
+        let mut replay = replay(&[("p1", "1"), ("p3", "1"), ("p1", "1")]);
+        replay.players = users(3);
+        replay.events = vec![
+            RecordedEvent {
+                after_move: 1,
+                event: ReplayEvent::Disqualified {
+                    player: "p2".to_string(),
+                    reason: "too slow".to_string(),
+                },
+            },
+            RecordedEvent {
+                after_move: 3,
+                event: ReplayEvent::Resigned {
+                    player: "p3".to_string(),
+                },
+            },
+        ];
+        let mut player = ReplayPlayer::new(Box::new(TakeAway::new(9)), replay).unwrap();
+
+        player.seek(1).unwrap();
+        assert_eq!(player.turn().unwrap().token.user.name, "p3");
+        player.seek(2).unwrap();
+        assert_eq!(player.turn().unwrap().token.user.name, "p1");
+        player.seek(3).unwrap();
+        assert_eq!(state(&player), None);
+    }
+
+    #[test]
+    fn rejected_move() {
+        let replay = replay(&[("p1", "1"), ("p2", "4")]);
+        let mut player = ReplayPlayer::new(Box::new(TakeAway::new(5)), replay).unwrap();
+
+        assert_eq!(player.seek(2), Err(PlaybackError::Rejected { index: 1 }));
+        assert_eq!(player.position(), 1);
+    }
+
+    #[test]
+    fn unknown_player() {
+        let replay = replay(&[("p3", "1")]);
+        let mut player = ReplayPlayer::new(Box::new(TakeAway::new(5)), replay).unwrap();
+
+        assert_eq!(
+            player.step_forward(),
+            Err(PlaybackError::UnknownPlayer {
+                index: 0,
+                player: "p3".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn not_started() {
+        let replay = Replay::new(vec![user("p1")]);
+        assert_eq!(
+            ReplayPlayer::new(Box::new(TakeAway::new(5)), replay).unwrap_err(),
+            PlaybackError::NotStarted
+        );
+    }
+}
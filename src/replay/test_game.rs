@@ -0,0 +1,155 @@
+use std::any::Any;
+
+use tracing::warn;
+
+use crate::color::Color;
+use crate::dice::{Dice, DiceLog};
+use crate::draw::{Draw, DrawCommand, Point, Size};
+use crate::games::Viewer;
+use crate::gametraits::{
+    FilteredState, GameTrait, Paint, PlayerGameState, PlayerMove, PlayerMoveResult, PlayerTurn,
+    TurnToken, User,
+};
+use crate::TurnTracker;
+
+/// Players take turns removing 1-3 tokens from a pile, whoever takes the last
+/// token wins. Moves and states are plain numbers to keep tests readable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TakeAway {
+    start: u32,
+    remaining: u32,
+    tracker: TurnTracker,
+    /// Rolled once after every move, for testing games of chance. The rolls
+    /// decide nothing.
+    dice: Option<Dice>,
+}
+
+impl TakeAway {
+    pub fn new(start: u32) -> Self {
+        Self {
+            start,
+            remaining: start,
+            tracker: TurnTracker::new(vec![]),
+            dice: None,
+        }
+    }
+
+    pub fn with_dice(mut self, seed: u64) -> Self {
+        self.dice = Some(Dice::new(seed));
+        self
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+
+    fn state(&self) -> PlayerGameState {
+        PlayerGameState {
+            serialized: format!("{}\n", self.remaining),
+        }
+    }
+
+    fn next_turn(&mut self) -> Option<PlayerTurn> {
+        let user = self.tracker.advance_player().cloned()?;
+        Some(PlayerTurn {
+            token: TurnToken { user },
+            state: self.state(),
+        })
+    }
+}
+
+impl GameTrait for TakeAway {
+    fn player_moves(&mut self, turn_token: TurnToken, player_move: PlayerMove) -> PlayerMoveResult {
+        if self.tracker.current_player().map(|u| &u.name) != Some(&turn_token.user.name) {
+            return PlayerMoveResult::InvalidMove(None);
+        }
+        let Ok(n) = player_move.serialized.trim().parse::<u32>() else {
+            return PlayerMoveResult::InvalidFormat(Some(PlayerTurn {
+                token: turn_token,
+                state: self.state(),
+            }));
+        };
+        if !(1..=3).contains(&n) || n > self.remaining {
+            return PlayerMoveResult::InvalidMove(Some(PlayerTurn {
+                token: turn_token,
+                state: self.state(),
+            }));
+        }
+        self.remaining -= n;
+        if let Some(dice) = &mut self.dice {
+            dice.roll(6);
+        }
+        if self.remaining == 0 {
+            return PlayerMoveResult::Win;
+        }
+        PlayerMoveResult::next(self.next_turn())
+    }
+
+    fn current_player_disconnected(&mut self, turn_token: TurnToken) -> Option<PlayerTurn> {
+        self.tracker.remove_player(&turn_token.user.name).ok();
+        self.next_turn()
+    }
+
+    fn try_start_game(&mut self) -> Option<PlayerTurn> {
+        if self.tracker.num_players() < 2 {
+            return None;
+        }
+        self.next_turn()
+    }
+
+    fn player_connected(&mut self, user: User) {
+        if let Err(e) = self.tracker.add_player(user) {
+            warn!(error = %e, "player couldn't join");
+        }
+    }
+
+    fn player_disconnected(&mut self, user: &str) {
+        self.tracker.remove_player(user).ok();
+    }
+
+    fn reset(&mut self, users: Vec<User>) {
+        self.tracker = TurnTracker::new(users);
+        self.remaining = self.start;
+        if let Some(dice) = &mut self.dice {
+            *dice = Dice::new(dice.seed());
+        }
+    }
+
+    fn dice_log(&self) -> Option<&DiceLog> {
+        self.dice.as_ref().map(Dice::log)
+    }
+
+    fn turn_tracker(&self) -> Option<&TurnTracker> {
+        Some(&self.tracker)
+    }
+
+    fn visible_to(&self, _viewer: Viewer<'_>) -> Option<FilteredState> {
+        Some(FilteredState {
+            serialized: self.state().serialized,
+        })
+    }
+}
+
+/// The remaining tokens as circles in a row, one slot per starting token.
+impl Draw for TakeAway {
+    fn draw(&self, size: Size) -> Vec<DrawCommand> {
+        let slot = size.width / self.start as f64;
+        let radius = slot.min(size.height) * 0.4;
+        (0..self.remaining)
+            .map(|i| {
+                let center = Point::new(slot * (i as f64 + 0.5), size.height / 2.0);
+                DrawCommand::fill_circle(center, radius, Color::BLACK)
+            })
+            .collect()
+    }
+}
+
+impl Paint for TakeAway {
+    fn eq(&self, other: &dyn Paint) -> bool {
+        other.as_any().downcast_ref::<Self>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
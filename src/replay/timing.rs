@@ -0,0 +1,249 @@
+//! Looks through how long moves took for patterns worth a closer look when
+//! someone reports cheating: replies faster than any bot could think in
+//! positions with a lot to think about, and players whose move times are
+//! so alike they may be the same bot under two names.
+//!
+//! A flag isn't proof of anything. Fast bots reply instantly everywhere,
+//! and bots thinking to a fixed budget all look alike, so flags are for
+//! organizers to follow up on.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::{frames, PlaybackError, Replay};
+use crate::games::LegalMoves;
+use crate::gametraits::GameTrait;
+
+/// The thresholds for flagging.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimingCheck {
+    /// Replies this fast or faster count as instant.
+    pub instant_ms: u64,
+    /// Positions with at least this many legal moves count as complex.
+    pub complex_from: usize,
+    /// The fewest timed moves a player needs to be compared with others.
+    pub min_moves: usize,
+    /// How close two players' move times have to be to count as the same,
+    /// as the Kolmogorov-Smirnov distance between them: the largest gap
+    /// between the shares of each player's moves made within any time.
+    pub same_within: f64,
+}
+
+impl Default for TimingCheck {
+    fn default() -> Self {
+        Self {
+            instant_ms: 50,
+            complex_from: 10,
+            min_moves: 30,
+            same_within: 0.05,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimingFlag {
+    /// Move `index` of the replay came instantly in a complex position.
+    #[serde(rename_all = "kebab-case")]
+    InstantReply {
+        player: String,
+        index: usize,
+        think_ms: u64,
+        legal_moves: usize,
+    },
+    /// The two players' move times are spread the same way.
+    SameTiming {
+        players: [String; 2],
+        /// How many timed moves each made.
+        moves: [usize; 2],
+        distance: f64,
+    },
+}
+
+impl TimingCheck {
+    /// Instant replies in `replay`, given the number of legal moves before
+    /// each move, as `legal_move_counts` works out.
+    pub fn instant_replies(&self, replay: &Replay, legal_moves: &[usize]) -> Vec<TimingFlag> {
+        replay
+            .moves
+            .iter()
+            .zip(legal_moves)
+            .enumerate()
+            .filter_map(|(index, (recorded, legal_moves))| {
+                let think_ms = recorded.think_ms?;
+                (think_ms <= self.instant_ms && *legal_moves >= self.complex_from).then(|| {
+                    TimingFlag::InstantReply {
+                        player: recorded.player.clone(),
+                        index,
+                        think_ms,
+                        legal_moves: *legal_moves,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Pairs of players in `replays` timed alike, comparing every timed move
+    /// each made in any of them.
+    pub fn same_timing(&self, replays: &[Replay]) -> Vec<TimingFlag> {
+        let mut times: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+        for recorded in replays.iter().flat_map(|replay| &replay.moves) {
+            if let Some(think_ms) = recorded.think_ms {
+                times.entry(&recorded.player).or_default().push(think_ms);
+            }
+        }
+        times.retain(|_, times| times.len() >= self.min_moves);
+        for times in times.values_mut() {
+            times.sort_unstable();
+        }
+
+        let players: Vec<_> = times.iter().collect();
+        let mut flags = vec![];
+        for (i, (a, a_times)) in players.iter().enumerate() {
+            for (b, b_times) in &players[i + 1..] {
+                let distance = distance(a_times, b_times);
+                if distance <= self.same_within {
+                    flags.push(TimingFlag::SameTiming {
+                        players: [a.to_string(), b.to_string()],
+                        moves: [a_times.len(), b_times.len()],
+                        distance,
+                    });
+                }
+            }
+        }
+        flags
+    }
+}
+
+/// The number of legal moves before each move of `replay`, by playing it on
+/// `game`.
+pub fn legal_move_counts<G>(game: G, replay: &Replay) -> Result<Vec<usize>, PlaybackError>
+where
+    G: GameTrait + LegalMoves + 'static,
+{
+    let frames = frames(Box::new(game), replay.clone())?;
+    Ok(frames
+        .iter()
+        .take(replay.moves.len())
+        .map(|frame| {
+            frame
+                .as_any()
+                .downcast_ref::<G>()
+                .map_or(0, |game| game.legal_moves().len())
+        })
+        .collect())
+}
+
+/// The Kolmogorov-Smirnov distance between two sorted, non-empty samples.
+fn distance(a: &[u64], b: &[u64]) -> f64 {
+    let (mut i, mut j) = (0, 0);
+    let mut distance = 0.0_f64;
+    while i < a.len() && j < b.len() {
+        let time = a[i].min(b[j]);
+        while i < a.len() && a[i] == time {
+            i += 1;
+        }
+        while j < b.len() && b[j] == time {
+            j += 1;
+        }
+        let gap = i as f64 / a.len() as f64 - j as f64 / b.len() as f64;
+        distance = distance.max(gap.abs());
+    }
+    distance
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::games::connect_four::ConnectFour;
+    use crate::replay::RecordedMove;
+    use crate::test_support::user;
+
+    fn timed(moves: &[(&str, u64)]) -> Replay {
+        let mut replay = Replay::new(vec![user("p1"), user("p2")]);
+        replay.moves = moves
+            .iter()
+            .map(|(player, think_ms)| RecordedMove {
+                player: player.to_string(),
+                player_move: "{\"move\":3}".to_string(),
+                think_ms: Some(*think_ms),
+                annotations: vec![],
+            })
+            .collect();
+        replay
+    }
+
+    #[test]
+    fn flags_instant_replies_to_complex_positions() {
+        let replay = timed(&[("p1", 10), ("p2", 2000), ("p1", 5), ("p2", 30)]);
+        let check = TimingCheck::default();
+        assert_eq!(
+            check.instant_replies(&replay, &[20, 20, 2, 15]),
+            [
+                TimingFlag::InstantReply {
+                    player: "p1".to_string(),
+                    index: 0,
+                    think_ms: 10,
+                    legal_moves: 20,
+                },
+                TimingFlag::InstantReply {
+                    player: "p2".to_string(),
+                    index: 3,
+                    think_ms: 30,
+                    legal_moves: 15,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_players_timed_alike() {
+        // p1 and p3 think the same way, in different games; p2 is slower.
+        let replays: Vec<_> = (0..4)
+            .map(|game| {
+                let moves: Vec<_> = (0..10)
+                    .flat_map(|i| {
+                        let think_ms = 100 + (game * 10 + i) * 37 % 400;
+                        [("p1", think_ms), ("p2", think_ms * 3), ("p3", think_ms)]
+                    })
+                    .collect();
+                timed(&moves)
+            })
+            .collect();
+        let flags = TimingCheck::default().same_timing(&replays);
+        assert_eq!(
+            flags,
+            [TimingFlag::SameTiming {
+                players: ["p1".to_string(), "p3".to_string()],
+                moves: [40, 40],
+                distance: 0.0,
+            }]
+        );
+
+        let few = TimingCheck {
+            min_moves: 41,
+            ..TimingCheck::default()
+        };
+        assert!(few.same_timing(&replays).is_empty());
+    }
+
+    #[test]
+    fn measures_distance() {
+        assert_eq!(distance(&[1, 2, 3, 4], &[1, 2, 3, 4]), 0.0);
+        assert_eq!(distance(&[1, 2], &[3, 4]), 1.0);
+        assert_eq!(distance(&[1, 2, 3, 4], &[3, 4, 5, 6]), 0.5);
+    }
+
+    #[test]
+    fn counts_legal_moves_before_each_move() {
+        let mut replay = timed(&[("p1", 10), ("p2", 10)].repeat(4));
+        for (i, recorded) in replay.moves.iter_mut().enumerate() {
+            let column = if i < 6 { 3 } else { 0 };
+            recorded.player_move = format!("{{\"move\":{column}}}");
+        }
+        let counts = legal_move_counts(ConnectFour::default(), &replay).unwrap();
+        // The sixth disc fills the middle column.
+        assert_eq!(counts, [7, 7, 7, 7, 7, 7, 6, 6]);
+    }
+}
@@ -0,0 +1,374 @@
+use crate::dice::DiceLog;
+use crate::gametraits::{GameTrait, PlayerMove, PlayerMoveResult};
+use crate::rules::RulesVersion;
+
+use super::{GameOutcome, Replay};
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Divergence {
+    /// The replay was played under rules `recorded`, which don't play
+    /// like the game's.
+    #[error("the replay is for rules {recorded}, the game plays by {game}")]
+    RulesMismatch {
+        game: RulesVersion,
+        recorded: RulesVersion,
+    },
+    #[error("the game didn't start")]
+    NotStarted,
+    #[error("the initial state differs")]
+    InitialStateMismatch,
+    #[error("move {index} is by {recorded}, but it was {expected}'s turn")]
+    WrongPlayer {
+        index: usize,
+        expected: String,
+        recorded: String,
+    },
+    #[error("move {index} was rejected")]
+    MoveRejected { index: usize },
+    #[error("the game ended before move {index}")]
+    GameEndedEarly { index: usize },
+    #[error("the game ended in {expected:?}, not {recorded:?}")]
+    OutcomeMismatch {
+        expected: Option<GameOutcome>,
+        recorded: Option<GameOutcome>,
+    },
+    /// The recorded rolls don't follow from their seed, or aren't the ones
+    /// the game rolled, from roll `index` on.
+    #[error("the dice differ from roll {index} on")]
+    DiceMismatch { index: usize },
+}
+
+/// Plays `replay` on a fresh `game` and checks that the game agrees with it:
+/// same initial state, every move made by the player whose turn it was and
+/// accepted, the same outcome at the end and, for games of chance, the same
+/// dice rolled from the recorded seed. Replays recorded under other rules
+/// aren't played at all; ones from before rules were versioned are.
+pub fn verify_replay(mut game: Box<dyn GameTrait>, replay: &Replay) -> Result<(), Divergence> {
+    if let Some(recorded) = replay.rules {
+        let rules = game.rules_version();
+        if !rules.plays_like(recorded) {
+            return Err(Divergence::RulesMismatch {
+                game: rules,
+                recorded,
+            });
+        }
+    }
+    game.reset(replay.players.clone());
+    for (player, handicap) in &replay.handicaps {
+        game.apply_handicap(player, *handicap);
+    }
+    let mut turn = game.try_start_game();
+    match (&turn, &replay.initial_state) {
+        (None, None) if replay.moves.is_empty() && replay.outcome.is_none() => return Ok(()),
+        (None, _) => return Err(Divergence::NotStarted),
+        (Some(turn), Some(initial)) if &turn.state != initial => {
+            return Err(Divergence::InitialStateMismatch)
+        }
+        _ => {}
+    }
+
+    let mut outcome = None;
+    let mut events = replay.events.iter().peekable();
+    for (index, recorded) in replay.moves.iter().enumerate() {
+        while let Some(event) = events.next_if(|e| e.after_move <= index) {
+            if let Some(ended) = event.event.apply(game.as_mut(), &mut turn) {
+                outcome = Some(ended);
+            }
+        }
+        let current = turn.take().ok_or(Divergence::GameEndedEarly { index })?;
+        if current.token.user.name != recorded.player {
+            return Err(Divergence::WrongPlayer {
+                index,
+                expected: current.token.user.name.clone(),
+                recorded: recorded.player.clone(),
+            });
+        }
+
+        let player_move = PlayerMove {
+            serialized: recorded.player_move.clone(),
+        };
+        match game.player_moves(current.token, player_move) {
+            PlayerMoveResult::Ok(next) => turn = Some(next),
+            PlayerMoveResult::Win => {
+                outcome = Some(GameOutcome::Win {
+                    winner: recorded.player.clone(),
+                })
+            }
+            PlayerMoveResult::Draw => outcome = Some(GameOutcome::Draw),
+            PlayerMoveResult::InvalidMove(_)
+            | PlayerMoveResult::InvalidFormat(_)
+            | PlayerMoveResult::OutOfTime(_) => return Err(Divergence::MoveRejected { index }),
+        }
+    }
+
+    for event in events {
+        if let Some(ended) = event.event.apply(game.as_mut(), &mut turn) {
+            outcome = Some(ended);
+        }
+    }
+
+    if outcome != replay.outcome {
+        return Err(Divergence::OutcomeMismatch {
+            expected: outcome,
+            recorded: replay.outcome.clone(),
+        });
+    }
+    if let Some(dice) = &replay.dice {
+        dice.verify()
+            .map_err(|index| Divergence::DiceMismatch { index })?;
+    }
+    if game.dice_log() != replay.dice.as_ref() {
+        let rolls = |log: Option<&DiceLog>| log.map_or(vec![], |log| log.rolls.clone());
+        let (played, recorded) = (rolls(game.dice_log()), rolls(replay.dice.as_ref()));
+        let index = played
+            .iter()
+            .zip(&recorded)
+            .take_while(|(a, b)| a == b)
+            .count();
+        return Err(Divergence::DiceMismatch { index });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::games::connect_four::ConnectFour;
+    use crate::gametraits::PlayerGameState;
+    use crate::replay::test_game::TakeAway;
+    use crate::replay::{GameRecorder, RecordedEvent, RecordedMove, ReplayEvent};
+    use crate::test_support::{user, users};
+
+    fn replay(moves: &[(&str, &str)], winner: Option<&str>) -> Replay {
+        let mut replay = Replay::new(vec![user("p1"), user("p2")]);
+        replay.initial_state = Some(PlayerGameState {
+            serialized: "5\n".to_string(),
+        });
+        replay.moves = moves
+            .iter()
+            .map(|(player, m)| RecordedMove {
+                player: player.to_string(),
+                player_move: m.to_string(),
+                think_ms: None,
+                annotations: vec![],
+            })
+            .collect();
+        replay.outcome = winner.map(|w| GameOutcome::Win {
+            winner: w.to_string(),
+        });
+        replay
+    }
+
+    fn verify(replay: &Replay) -> Result<(), Divergence> {
+        verify_replay(Box::new(TakeAway::new(5)), replay)
+    }
+
+    #[test]
+    fn accepts_faithful_replays() {
+        assert_eq!(
+            verify(&replay(&[("p1", "2"), ("p2", "3")], Some("p2"))),
+            Ok(())
+        );
+        assert_eq!(verify(&replay(&[("p1", "2")], None)), Ok(()));
+    }
+
+    #[test]
+    fn detects_tampering() {
+        assert_eq!(
+            verify(&replay(&[("p1", "2"), ("p2", "3")], Some("p1"))),
+            Err(Divergence::OutcomeMismatch {
+                expected: Some(GameOutcome::Win {
+                    winner: "p2".to_string()
+                }),
+                recorded: Some(GameOutcome::Win {
+                    winner: "p1".to_string()
+                }),
+            })
+        );
+        assert_eq!(
+            verify(&replay(&[("p1", "2"), ("p2", "4")], Some("p2"))),
+            Err(Divergence::MoveRejected { index: 1 })
+        );
+        assert_eq!(
+            verify(&replay(&[("p1", "2"), ("p1", "3")], Some("p1"))),
+            Err(Divergence::WrongPlayer {
+                index: 1,
+                expected: "p2".to_string(),
+                recorded: "p1".to_string()
+            })
+        );
+        assert_eq!(
+            verify(&replay(
+                &[("p1", "2"), ("p2", "3"), ("p1", "1")],
+                Some("p2")
+            )),
+            Err(Divergence::GameEndedEarly { index: 2 })
+        );
+
+        let mut wrong_start = replay(&[], None);
+        wrong_start.initial_state = Some(PlayerGameState {
+            serialized: "7\n".to_string(),
+        });
+        assert_eq!(verify(&wrong_start), Err(Divergence::InitialStateMismatch));
+    }
+
+    #[test]
+    fn accepts_forfeits() {
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(9)));
+        recorder.reset(users(3));
+        let turn = recorder.try_start_game().unwrap();
+        let PlayerMoveResult::Ok(turn) = recorder.player_moves(
+            turn.token,
+            PlayerMove {
+                serialized: "1".to_string(),
+            },
+        ) else {
+            panic!("expected ok");
+        };
+        assert_eq!(turn.token.user.name, "p2");
+        assert!(recorder.disqualify("p2", "too slow").outcome.is_none());
+        recorder.resign("p1");
+        let replay = recorder.into_replay();
+        assert_eq!(
+            replay.outcome,
+            Some(GameOutcome::Win {
+                winner: "p3".to_string()
+            })
+        );
+        assert_eq!(replay.events.len(), 2);
+        assert_eq!(verify_replay(Box::new(TakeAway::new(9)), &replay), Ok(()));
+
+        let mut unresigned = replay.clone();
+        unresigned.events.pop();
+        assert_eq!(
+            verify_replay(Box::new(TakeAway::new(9)), &unresigned),
+            Err(Divergence::OutcomeMismatch {
+                expected: None,
+                recorded: replay.outcome.clone(),
+            })
+        );
+    }
+
+    #[test]
+    fn accepts_agreed_draws() {
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(9)));
+        recorder.reset(users(2));
+        let turn = recorder.try_start_game().unwrap();
+        recorder.player_moves(
+            turn.token,
+            PlayerMove {
+                serialized: "2".to_string(),
+            },
+        );
+        recorder.draw_agreed();
+        let replay = recorder.into_replay();
+        assert_eq!(replay.outcome, Some(GameOutcome::AgreedDraw));
+        assert_eq!(verify_replay(Box::new(TakeAway::new(9)), &replay), Ok(()));
+
+        let mut undrawn = replay.clone();
+        undrawn.events.clear();
+        assert_eq!(
+            verify_replay(Box::new(TakeAway::new(9)), &undrawn),
+            Err(Divergence::OutcomeMismatch {
+                expected: None,
+                recorded: Some(GameOutcome::AgreedDraw),
+            })
+        );
+    }
+
+    #[test]
+    fn accepts_running_out_of_time() {
+        let game = ConnectFour::default().with_clock(Duration::from_secs(15));
+        let mut recorder = GameRecorder::new(Box::new(game));
+        recorder.reset(users(2));
+        let turn = recorder.try_start_game().unwrap();
+        recorder
+            .clock_mut()
+            .unwrap()
+            .take_time("p1", Duration::from_secs(15));
+        let column = PlayerMove {
+            serialized: "{\"move\":3}".to_string(),
+        };
+        let p2 = GameOutcome::Win {
+            winner: "p2".to_string(),
+        };
+        assert_eq!(
+            recorder.player_moves(turn.token, column),
+            PlayerMoveResult::OutOfTime(p2.clone())
+        );
+        let replay = recorder.into_replay();
+        assert_eq!(replay.outcome, Some(p2));
+        assert_eq!(
+            replay.events,
+            [RecordedEvent {
+                after_move: 0,
+                event: ReplayEvent::OutOfTime {
+                    player: "p1".to_string()
+                },
+            }]
+        );
+        assert_eq!(
+            verify_replay(Box::new(ConnectFour::default()), &replay),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn accepts_disconnects() {
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(9)));
+        recorder.reset(users(3));
+        let turn = recorder.try_start_game().unwrap();
+        let take = |n: &str| PlayerMove {
+            serialized: n.to_string(),
+        };
+        let PlayerMoveResult::Ok(turn) = recorder.player_moves(turn.token, take("1")) else {
+            panic!("expected ok");
+        };
+        let turn = recorder.current_player_disconnected(turn.token).unwrap();
+        assert_eq!(turn.token.user.name, "p3");
+        recorder.player_disconnected("p1");
+        assert!(matches!(
+            recorder.player_moves(turn.token, take("3")),
+            PlayerMoveResult::Ok(_)
+        ));
+        let replay = recorder.into_replay();
+        assert_eq!(replay.events.len(), 2);
+        assert_eq!(verify_replay(Box::new(TakeAway::new(9)), &replay), Ok(()));
+
+        let mut stayed = replay.clone();
+        stayed.events.clear();
+        assert_eq!(
+            verify_replay(Box::new(TakeAway::new(9)), &stayed),
+            Err(Divergence::WrongPlayer {
+                index: 1,
+                expected: "p2".to_string(),
+                recorded: "p3".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn refuses_other_rules() {
+        let mut replay = replay(&[("p1", "2"), ("p2", "3")], Some("p2"));
+        replay.rules = Some(RulesVersion::INITIAL);
+        assert_eq!(verify(&replay), Ok(()));
+        replay.rules = Some(RulesVersion::new(2, 0, 0));
+        assert_eq!(
+            verify(&replay),
+            Err(Divergence::RulesMismatch {
+                game: RulesVersion::INITIAL,
+                recorded: RulesVersion::new(2, 0, 0)
+            })
+        );
+    }
+
+    #[test]
+    fn unstarted_games() {
+        let mut replay = Replay::new(vec![user("p1")]);
+        assert_eq!(verify(&replay), Ok(()));
+        replay.outcome = Some(GameOutcome::Draw);
+        assert_eq!(verify(&replay), Err(Divergence::NotStarted));
+    }
+}
@@ -0,0 +1,124 @@
+//! Seeded randomness. Games take all their randomness from a `GameRng`, so
+//! that anything generated from a seed (maps, shuffles, rolls) comes out the
+//! same on every machine and version of the crate, a game can be replayed
+//! bit for bit and a flaky test fails the same way every time. The state
+//! serializes with the game, so a restored game carries on with the same
+//! numbers it would have had.
+//!
+//! `SplitMix64` is the generator the crate uses. It is not cryptographic,
+//! only fast and well mixed.
+
+use std::fmt::Debug;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+pub trait GameRng: Clone + Debug + Serialize + DeserializeOwned {
+    fn from_seed(seed: u64) -> Self;
+
+    fn next_u64(&mut self) -> u64;
+
+    /// Uniform in `0..n`, without the bias of a plain modulo. `n` must not be
+    /// zero.
+    fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "empty range");
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+
+    /// Uniform in `0..n` as an index.
+    fn index(&mut self, n: usize) -> usize {
+        self.below(n as u64) as usize
+    }
+
+    /// Uniform in `0.0..1.0`.
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// True with probability `p`.
+    fn chance(&mut self, p: f64) -> bool {
+        self.unit() < p
+    }
+
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        (!items.is_empty()).then(|| &items[self.index(items.len())])
+    }
+
+    /// Fisher-Yates shuffle.
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.index(i + 1));
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl GameRng for SplitMix64 {
+    fn from_seed(seed: u64) -> Self {
+        Self::new(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        // Reference values of SplitMix64 seeded with 0.
+        let mut rng = SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+
+        let mut a = SplitMix64::new(42);
+        let mut b = a.clone();
+        let mut items: Vec<_> = (0..10).collect();
+        a.shuffle(&mut items);
+        let mut again: Vec<_> = (0..10).collect();
+        b.shuffle(&mut again);
+        assert_eq!(items, again);
+        items.sort();
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+
+        for _ in 0..100 {
+            assert!(a.below(3) < 3);
+            assert!((0.0..1.0).contains(&a.unit()));
+        }
+        assert_eq!(a.choose::<u8>(&[]), None);
+    }
+
+    fn roll_twice<R: GameRng>(seed: u64) -> [u64; 2] {
+        let mut rng = R::from_seed(seed);
+        [rng.below(6), rng.below(6)]
+    }
+
+    #[test]
+    fn generic_over_the_generator() {
+        let mut rng = SplitMix64::new(9);
+        assert_eq!(roll_twice::<SplitMix64>(9), [rng.below(6), rng.below(6)]);
+    }
+}
@@ -0,0 +1,113 @@
+//! Versions of a game's rules, so a replay is only checked against the
+//! rules it was played under. Each game says which it plays by with
+//! `GameTrait::rules_version`, and recorders write that into the replay.
+//!
+//! The major version goes up with any change that can make an old game's
+//! moves play out differently, such as when snakes collide. Minor versions
+//! add things old games never did, and patches change nothing a replay can
+//! see.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Written as `major.minor.patch`, as in `2.1.0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RulesVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl RulesVersion {
+    /// What games are at until their rules first change.
+    pub const INITIAL: RulesVersion = RulesVersion::new(1, 0, 0);
+
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Whether games played under `recorded` play out the same under these
+    /// rules: the same major version, and no newer than these.
+    pub fn plays_like(&self, recorded: RulesVersion) -> bool {
+        self.major == recorded.major && *self >= recorded
+    }
+}
+
+impl Default for RulesVersion {
+    fn default() -> Self {
+        Self::INITIAL
+    }
+}
+
+impl fmt::Display for RulesVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{0:?} isn't a rules version, expected major.minor.patch")]
+pub struct ParseRulesVersionError(pub String);
+
+impl FromStr for RulesVersion {
+    type Err = ParseRulesVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseRulesVersionError(s.to_string());
+        let mut parts = s.split('.').map(|part| part.parse::<u32>());
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) => {
+                Ok(Self::new(major, minor, patch))
+            }
+            _ => Err(error()),
+        }
+    }
+}
+
+impl TryFrom<String> for RulesVersion {
+    type Error = ParseRulesVersionError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<RulesVersion> for String {
+    fn from(version: RulesVersion) -> Self {
+        version.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_what_it_writes() {
+        let version = RulesVersion::new(2, 10, 3);
+        assert_eq!(version.to_string(), "2.10.3");
+        assert_eq!("2.10.3".parse(), Ok(version));
+        for bad in ["2.10", "2.10.3.1", "2.x.3", ""] {
+            assert_eq!(
+                bad.parse::<RulesVersion>(),
+                Err(ParseRulesVersionError(bad.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn newer_minors_play_like_older_ones() {
+        let rules = RulesVersion::new(2, 1, 0);
+        assert!(rules.plays_like(RulesVersion::new(2, 0, 5)));
+        assert!(rules.plays_like(rules));
+        assert!(!rules.plays_like(RulesVersion::new(2, 2, 0)));
+        assert!(!rules.plays_like(RulesVersion::new(1, 9, 0)));
+    }
+}
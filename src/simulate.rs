@@ -0,0 +1,291 @@
+//! Plays whole games between bots with nothing but the rules in between: no
+//! server, no sockets, no rendering. Running thousands of games after a rule
+//! change and comparing the reports is a cheap regression test, and any move
+//! the game rejects from a bot that only plays `LegalMoves` points at a bug
+//! in one or the other.
+//!
+//! Games go through `GameTrait` exactly as they would on the server, moves
+//! serialized and all, so what's tested is what gets hosted. Wrapping bots
+//! in a `FaultyBot` adds the slow, lost and garbled answers of real
+//! networks, to test timeouts and error handling end to end.
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::bot::{Answer, Client};
+use crate::color::Color;
+use crate::games::LegalMoves;
+use crate::gametraits::{GameTrait, PlayerMoveResult, User};
+use crate::guards::{EndGuards, Stop};
+use crate::replay::GameOutcome;
+use crate::time::Instant;
+
+mod faults;
+
+pub use faults::FaultyBot;
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SimulationError {
+    /// The game didn't start with the bots connected.
+    #[error("the game didn't start")]
+    NotStarted,
+    /// The bot had no move to make but the game wanted one.
+    #[error("bot {bot} had no move")]
+    NoMove { bot: usize },
+    /// The game rejected the bot's move, and it had no retries left.
+    #[error("move {move_index} by bot {bot} was rejected")]
+    Rejected { bot: usize, move_index: usize },
+    /// The game went on past the move limit.
+    #[error("the game went past the move limit")]
+    TooLong,
+    /// A bot timed out and the game stopped without it.
+    #[error("bot {bot} timed out")]
+    Abandoned { bot: usize },
+    /// One of the simulation's `EndGuards` stopped the game.
+    #[error("stopped by a guard: {0:?}")]
+    Stopped(Stop),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameResult {
+    /// Winners are named after the bot, `bot1` for the first and so on.
+    pub outcome: Result<GameOutcome, SimulationError>,
+    /// The moves the game accepted.
+    pub moves: usize,
+    /// The bots by seat, first to move first.
+    pub seating: Vec<usize>,
+    pub duration: Duration,
+    /// Answers that were lost or came after the turn timeout.
+    pub timeouts: usize,
+    /// Moves the game rejected, retried or not.
+    pub rejected: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub games: Vec<GameResult>,
+    /// How long each bot spent choosing its moves, over all games.
+    pub think_time: Vec<Duration>,
+}
+
+impl Report {
+    pub fn wins(&self, bot: usize) -> usize {
+        let name = bot_name(bot);
+        self.games
+            .iter()
+            .filter(
+                |game| matches!(&game.outcome, Ok(GameOutcome::Win { winner }) if *winner == name),
+            )
+            .count()
+    }
+
+    pub fn draws(&self) -> usize {
+        self.games
+            .iter()
+            .filter(|game| game.outcome == Ok(GameOutcome::Draw))
+            .count()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &SimulationError)> + '_ {
+        self.games
+            .iter()
+            .enumerate()
+            .filter_map(|(i, game)| game.outcome.as_ref().err().map(|e| (i, e)))
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.games.iter().map(|game| game.duration).sum()
+    }
+
+    pub fn mean_moves(&self) -> f64 {
+        let moves: usize = self.games.iter().map(|game| game.moves).sum();
+        moves as f64 / self.games.len().max(1) as f64
+    }
+}
+
+/// Bots in the order added, each game played on a fresh copy of the game it
+/// was made with. Seats rotate from one game to the next so that no bot
+/// always gets to move first.
+///
+/// A bot that doesn't answer, or answers after the turn timeout, is
+/// disconnected like the server would, and the game goes on without it if
+/// it can.
+pub struct Simulation<G: LegalMoves> {
+    template: G,
+    bots: Vec<Box<dyn Client<G>>>,
+    max_moves: usize,
+    turn_timeout: Option<Duration>,
+    retries: usize,
+    guards: EndGuards,
+}
+
+impl<G> Simulation<G>
+where
+    G: GameTrait + LegalMoves + Clone,
+    G::Move: Serialize,
+{
+    pub fn new(template: G) -> Self {
+        Self {
+            template,
+            bots: vec![],
+            max_moves: 10_000,
+            turn_timeout: None,
+            retries: 0,
+            guards: EndGuards::new(),
+        }
+    }
+
+    pub fn bot(mut self, bot: impl Client<G> + 'static) -> Self {
+        self.bots.push(Box::new(bot));
+        self
+    }
+
+    /// Games still going after `max_moves` moves end in `TooLong`.
+    pub fn max_moves(mut self, max_moves: usize) -> Self {
+        self.max_moves = max_moves;
+        self
+    }
+
+    pub fn turn_timeout(mut self, timeout: Duration) -> Self {
+        self.turn_timeout = Some(timeout);
+        self
+    }
+
+    /// How many rejected moves in a row a bot may follow with another try
+    /// at the same turn before the game ends in `Rejected`.
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Checks every game with a copy of `guards`, to catch bots going round
+    /// in circles long before the move limit.
+    pub fn guards(mut self, guards: EndGuards) -> Self {
+        self.guards = guards;
+        self
+    }
+
+    pub fn run(&mut self, games: usize) -> Report {
+        let mut report = Report {
+            games: Vec::with_capacity(games),
+            think_time: vec![Duration::ZERO; self.bots.len()],
+        };
+        for i in 0..games {
+            let seating: Vec<usize> = (0..self.bots.len())
+                .map(|seat| (seat + i) % self.bots.len())
+                .collect();
+            let result = self.play(seating, &mut report.think_time);
+            report.games.push(result);
+        }
+        report
+    }
+
+    fn play(&mut self, seating: Vec<usize>, think_time: &mut [Duration]) -> GameResult {
+        let started = Instant::now();
+        let users: Vec<User> = seating
+            .iter()
+            .map(|bot| User::new(bot_name(*bot), Color::GRAY))
+            .collect();
+        let mut game = self.template.clone();
+        game.reset(users.clone());
+        let mut result = GameResult {
+            outcome: Err(SimulationError::NotStarted),
+            moves: 0,
+            seating,
+            duration: Duration::ZERO,
+            timeouts: 0,
+            rejected: 0,
+        };
+        let mut strikes = 0;
+        let mut turn = game.try_start_game();
+        let mut guards = self.guards.clone();
+        guards.start(&game);
+        result.outcome = loop {
+            let Some(current) = turn.take() else {
+                break Err(SimulationError::NotStarted);
+            };
+            if result.moves == self.max_moves {
+                break Err(SimulationError::TooLong);
+            }
+            let seat = users.iter().position(|u| u.name == current.token.user.name);
+            let bot = result.seating[seat.unwrap()];
+            let thinking = Instant::now();
+            let answer = self.bots[bot].answer(&game);
+            let elapsed = thinking.elapsed();
+            think_time[bot] += elapsed;
+            let on_time = self.turn_timeout.is_none_or(|timeout| elapsed <= timeout);
+            let player_move = match answer {
+                Answer::NoMove => break Err(SimulationError::NoMove { bot }),
+                Answer::Move(player_move) if on_time => player_move,
+                Answer::Move(_) | Answer::Dropped => {
+                    result.timeouts += 1;
+                    turn = game.current_player_disconnected(current.token);
+                    if turn.is_none() {
+                        break Err(SimulationError::Abandoned { bot });
+                    }
+                    continue;
+                }
+            };
+            let winner = current.token.user.name.clone();
+            match game.player_moves(current.token, player_move) {
+                PlayerMoveResult::Ok(next) => {
+                    result.moves += 1;
+                    strikes = 0;
+                    if let Some(stop) = guards.after_move(&game) {
+                        break Err(SimulationError::Stopped(stop));
+                    }
+                    turn = Some(next);
+                }
+                PlayerMoveResult::Win => {
+                    result.moves += 1;
+                    break Ok(GameOutcome::Win { winner });
+                }
+                PlayerMoveResult::Draw => {
+                    result.moves += 1;
+                    break Ok(GameOutcome::Draw);
+                }
+                PlayerMoveResult::OutOfTime(outcome) => break Ok(outcome),
+                PlayerMoveResult::InvalidMove(again) | PlayerMoveResult::InvalidFormat(again) => {
+                    result.rejected += 1;
+                    strikes += 1;
+                    match again {
+                        Some(again) if strikes <= self.retries => turn = Some(again),
+                        _ => {
+                            break Err(SimulationError::Rejected {
+                                bot,
+                                move_index: result.moves,
+                            })
+                        }
+                    }
+                }
+            }
+        };
+        result.duration = started.elapsed();
+        result
+    }
+}
+
+fn bot_name(bot: usize) -> String {
+    format!("bot{}", bot + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bot::RandomBot;
+    use crate::games::connect_four::ConnectFour;
+
+    #[test]
+    fn plays_random_bots_against_each_other() {
+        let mut simulation = Simulation::new(ConnectFour::default())
+            .bot(RandomBot::new("a", 1))
+            .bot(RandomBot::new("b", 2));
+        let report = simulation.run(50);
+        assert_eq!(report.errors().count(), 0);
+        assert_eq!(report.wins(0) + report.wins(1) + report.draws(), 50);
+        assert_eq!(report.games[0].seating, [0, 1]);
+        assert_eq!(report.games[1].seating, [1, 0]);
+        assert!(report.mean_moves() >= 7.0);
+    }
+}
@@ -0,0 +1,168 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::bot::{Answer, Client};
+use crate::gametraits::PlayerMove;
+use crate::rng::{GameRng, SplitMix64};
+
+/// Messages a broken client might send instead of a move.
+const GARBAGE: &[&str] = &["", "{", "null", "\"move\"", "{\"move\":", "[1,2,3]"];
+
+/// Wraps a bot in a bad network: answers arrive late, get lost, or arrive
+/// mangled. Every fault is drawn from a seed, so a failing simulation can
+/// be run again exactly as it was, delays aside.
+#[derive(Clone, Debug)]
+pub struct FaultyBot<C> {
+    inner: C,
+    rng: SplitMix64,
+    delay: Duration,
+    jitter: Duration,
+    drop_rate: f64,
+    malformed_rate: f64,
+}
+
+impl<C> FaultyBot<C> {
+    /// No faults until some are added.
+    pub fn new(inner: C, seed: u64) -> Self {
+        Self {
+            inner,
+            rng: SplitMix64::from_seed(seed),
+            delay: Duration::ZERO,
+            jitter: Duration::ZERO,
+            drop_rate: 0.0,
+            malformed_rate: 0.0,
+        }
+    }
+
+    /// Every answer takes at least `delay`.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// On top of the delay, up to `jitter` more, uniformly.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Loses the answer with probability `rate`.
+    pub fn dropping(mut self, rate: f64) -> Self {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Mangles the move with probability `rate`, into garbage or a cut off
+    /// copy of itself.
+    pub fn malformed(mut self, rate: f64) -> Self {
+        self.malformed_rate = rate;
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn garble(&mut self, player_move: PlayerMove) -> PlayerMove {
+        let serialized = if self.rng.chance(0.5) {
+            self.rng.choose(GARBAGE).unwrap().to_string()
+        } else {
+            let mut cut = self.rng.index(player_move.serialized.len().max(1));
+            while !player_move.serialized.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            player_move.serialized[..cut].to_string()
+        };
+        PlayerMove { serialized }
+    }
+}
+
+impl<G, C: Client<G>> Client<G> for FaultyBot<C> {
+    fn answer(&mut self, game: &G) -> Answer {
+        let answer = self.inner.answer(game);
+        let delay = self.delay + self.jitter.mul_f64(self.rng.unit());
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+        if self.rng.chance(self.drop_rate) {
+            return Answer::Dropped;
+        }
+        match answer {
+            Answer::Move(player_move) if self.rng.chance(self.malformed_rate) => {
+                Answer::Move(self.garble(player_move))
+            }
+            answer => answer,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::games::connect_four::ConnectFour;
+    use crate::simulate::{Simulation, SimulationError};
+
+    /// Always plays the leftmost column, spelled out so no JSON is needed.
+    struct Leftmost;
+
+    impl Client<ConnectFour> for Leftmost {
+        fn answer(&mut self, _: &ConnectFour) -> Answer {
+            Answer::Move(PlayerMove {
+                serialized: "{\"move\":0}".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn dropped_answers_abandon_the_game() {
+        let mut simulation = Simulation::new(ConnectFour::default())
+            .bot(FaultyBot::new(Leftmost, 1).dropping(1.0))
+            .bot(Leftmost);
+        let report = simulation.run(1);
+        let game = &report.games[0];
+        assert_eq!(game.outcome, Err(SimulationError::Abandoned { bot: 0 }));
+        assert_eq!(game.timeouts, 1);
+        assert_eq!(game.moves, 0);
+    }
+
+    #[test]
+    fn slow_answers_time_out() {
+        let mut simulation = Simulation::new(ConnectFour::default())
+            .bot(FaultyBot::new(Leftmost, 1).with_delay(Duration::from_millis(20)))
+            .bot(Leftmost)
+            .turn_timeout(Duration::from_millis(5));
+        let report = simulation.run(1);
+        assert_eq!(report.games[0].timeouts, 1);
+        assert!(report.think_time[0] >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn garbled_moves_use_up_retries() {
+        let mut simulation = Simulation::new(ConnectFour::default())
+            .bot(FaultyBot::new(Leftmost, 7).malformed(1.0))
+            .bot(Leftmost)
+            .retries(2);
+        let report = simulation.run(1);
+        let game = &report.games[0];
+        assert_eq!(
+            game.outcome,
+            Err(SimulationError::Rejected {
+                bot: 0,
+                move_index: 0
+            })
+        );
+        assert_eq!(game.rejected, 3);
+    }
+
+    #[test]
+    fn same_seed_same_faults() {
+        let answers = |seed| {
+            let mut bot = FaultyBot::new(Leftmost, seed).dropping(0.3).malformed(0.3);
+            (0..50)
+                .map(|_| format!("{:?}", bot.answer(&ConnectFour::default())))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(answers(3), answers(3));
+        assert_ne!(answers(3), answers(4));
+    }
+}
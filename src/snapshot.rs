@@ -0,0 +1,176 @@
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::GameClock;
+use crate::gametraits::{GameTrait, PlayerTurn};
+use crate::replay::{GameRecorder, PlaybackError, Replay, ReplayPlayer};
+use crate::TurnTracker;
+
+mod scheduler;
+mod store;
+
+pub use scheduler::{recover, RecoveredGame, SnapshotScheduler};
+pub use store::{DirSnapshotStore, DirStoreError, MemorySnapshotStore, SnapshotStore};
+
+/// A running game frozen to disk, so the server can be restarted without
+/// voiding it.
+///
+/// Games are not serializable themselves, so the snapshot holds the replay so
+/// far and `restore` replays it on a fresh game. The game must therefore be
+/// deterministic given its moves.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub replay: Replay,
+    /// As it was when captured. Replaying the moves rebuilds it.
+    pub turn_tracker: Option<TurnTracker>,
+    /// Clocks aren't replayed, so `restore` puts this one back.
+    pub clock: Option<GameClock>,
+    pub config: serde_json::Value,
+}
+
+impl GameSnapshot {
+    /// Mutable only because that's how games hand out their clock.
+    pub fn capture(recorder: &mut GameRecorder) -> Self {
+        Self {
+            replay: recorder.replay().clone(),
+            turn_tracker: recorder.turn_tracker().cloned(),
+            clock: recorder.clock_mut().cloned(),
+            config: serde_json::Value::Null,
+        }
+    }
+
+    pub fn with_turn_tracker(mut self, turn_tracker: TurnTracker) -> Self {
+        self.turn_tracker = Some(turn_tracker);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: GameClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn with_config(mut self, config: serde_json::Value) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn save<W: Write>(&self, writer: W) -> serde_json::Result<()> {
+        serde_json::to_writer(writer, self)
+    }
+
+    pub fn load<R: Read>(reader: R) -> serde_json::Result<Self> {
+        serde_json::from_reader(reader)
+    }
+
+    /// Replays the snapshot onto `game`, returning a recorder that continues
+    /// where the snapshot left off, and the turn that was pending, if any.
+    pub fn restore(
+        &self,
+        mut game: Box<dyn GameTrait>,
+    ) -> Result<(GameRecorder, Option<PlayerTurn>), PlaybackError> {
+        let (game, turn) = if self.replay.initial_state.is_none() {
+            game.reset(self.replay.players.clone());
+            (game, None)
+        } else {
+            let mut player = ReplayPlayer::new(game, self.replay.clone())?;
+            player.seek(player.num_moves())?;
+            let turn = player.turn();
+            (player.into_game(), turn)
+        };
+        let mut recorder = GameRecorder::resume(game, self.replay.clone());
+        if let (Some(clock), Some(restored)) = (&self.clock, recorder.clock_mut()) {
+            *restored = clock.clone();
+        }
+        Ok((recorder, turn))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::games::connect_four::ConnectFour;
+    use crate::gametraits::{Paint, PlayerMove, PlayerMoveResult};
+    use crate::replay::test_game::TakeAway;
+    use crate::test_support::user;
+
+    fn recorder_after(moves: &[&str]) -> (GameRecorder, PlayerTurn) {
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(9)));
+        recorder.reset(vec![user("p1"), user("p2")]);
+        let mut turn = recorder.try_start_game().unwrap();
+        for m in moves {
+            let result = recorder.player_moves(
+                turn.token,
+                PlayerMove {
+                    serialized: m.to_string(),
+                },
+            );
+            let PlayerMoveResult::Ok(next) = result else {
+                panic!("expected ok");
+            };
+            turn = next;
+        }
+        (recorder, turn)
+    }
+
+    #[test]
+    fn save_and_load() {
+        let (mut recorder, _) = recorder_after(&["1", "2"]);
+        let mut clock = GameClock::new(Duration::from_secs(60));
+        clock.add_player("p1");
+        let snapshot = GameSnapshot::capture(&mut recorder)
+            .with_turn_tracker(TurnTracker::new(vec![user("p1")]))
+            .with_clock(clock);
+
+        let mut saved = vec![];
+        snapshot.save(&mut saved).unwrap();
+        assert_eq!(GameSnapshot::load(saved.as_slice()).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn restore_continues_game() {
+        let (mut recorder, turn) = recorder_after(&["1", "2", "3"]);
+        let snapshot = GameSnapshot::capture(&mut recorder);
+        assert_eq!(snapshot.turn_tracker.as_ref(), recorder.turn_tracker());
+
+        let (restored, restored_turn) = snapshot.restore(Box::new(TakeAway::new(9))).unwrap();
+        assert_eq!(restored_turn, Some(turn));
+        let game = recorder.game().as_any().downcast_ref::<TakeAway>().unwrap();
+        assert!(Paint::eq(&restored, game));
+        assert_eq!(restored.replay(), recorder.replay());
+    }
+
+    #[test]
+    fn restore_before_start() {
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(9)));
+        recorder.reset(vec![user("p1")]);
+        let snapshot = GameSnapshot::capture(&mut recorder);
+
+        let (mut restored, turn) = snapshot.restore(Box::new(TakeAway::new(9))).unwrap();
+        assert_eq!(turn, None);
+        restored.player_connected(user("p2"));
+        assert!(restored.try_start_game().is_some());
+    }
+
+    #[test]
+    fn restore_keeps_the_clock() {
+        let game = ConnectFour::default().with_clock(Duration::from_secs(60));
+        let mut recorder = GameRecorder::new(Box::new(game.clone()));
+        recorder.reset(vec![user("p1"), user("p2")]);
+        recorder.try_start_game().unwrap();
+        recorder
+            .clock_mut()
+            .unwrap()
+            .take_time("p1", Duration::from_secs(20));
+        let snapshot = GameSnapshot::capture(&mut recorder);
+        assert_eq!(
+            snapshot.clock.as_ref().unwrap().remaining("p1"),
+            Some(Duration::from_secs(40))
+        );
+
+        let (mut restored, _) = snapshot.restore(Box::new(game)).unwrap();
+        assert_eq!(restored.clock_mut(), recorder.clock_mut());
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use super::{GameSnapshot, SnapshotStore};
+use crate::gametraits::{GameTrait, PlayerTurn};
+use crate::replay::{GameRecorder, PlaybackError};
+use crate::time::Instant;
+
+/// Decides when to snapshot the running games, and keeps the store in sync
+/// with them: every due `tick` saves all games passed in and removes the
+/// snapshots of games that are no longer running.
+#[derive(Clone, Debug)]
+pub struct SnapshotScheduler {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl SnapshotScheduler {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+        }
+    }
+
+    pub fn due(&self, now: Instant) -> bool {
+        self.last
+            .is_none_or(|last| now.saturating_duration_since(last) >= self.interval)
+    }
+
+    /// Snapshots the games from `running` if the interval has passed, returning
+    /// whether it did. `running` is only called when a snapshot is due.
+    pub fn tick<S, I>(
+        &mut self,
+        now: Instant,
+        store: &mut S,
+        running: impl FnOnce() -> I,
+    ) -> Result<bool, S::Error>
+    where
+        S: SnapshotStore,
+        I: IntoIterator<Item = (String, GameSnapshot)>,
+    {
+        if !self.due(now) {
+            return Ok(false);
+        }
+        let mut saved = HashSet::new();
+        for (id, snapshot) in running() {
+            store.save(&id, &snapshot)?;
+            saved.insert(id);
+        }
+        for id in store.ids()? {
+            if !saved.contains(&id) {
+                store.remove(&id)?;
+            }
+        }
+        self.last = Some(now);
+        Ok(true)
+    }
+}
+
+pub struct RecoveredGame {
+    pub id: String,
+    /// The stored snapshot, for its clock, turn tracker and config.
+    pub snapshot: GameSnapshot,
+    pub restored: Result<(GameRecorder, Option<PlayerTurn>), PlaybackError>,
+}
+
+/// Restores every game in `store`, for use on startup. `new_game` creates the
+/// fresh game each snapshot is replayed onto. A game that fails to restore
+/// doesn't stop the others from being recovered.
+pub fn recover<S: SnapshotStore>(
+    store: &S,
+    mut new_game: impl FnMut(&GameSnapshot) -> Box<dyn GameTrait>,
+) -> Result<Vec<RecoveredGame>, S::Error> {
+    let mut recovered = vec![];
+    for id in store.ids()? {
+        let Some(snapshot) = store.load(&id)? else {
+            continue;
+        };
+        let restored = snapshot.restore(new_game(&snapshot));
+        recovered.push(RecoveredGame {
+            id,
+            snapshot,
+            restored,
+        });
+    }
+    Ok(recovered)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gametraits::{PlayerMove, PlayerMoveResult};
+    use crate::replay::test_game::TakeAway;
+    use crate::snapshot::MemorySnapshotStore;
+    use crate::test_support::user;
+
+    fn started(moves: &[&str]) -> GameRecorder {
+        let mut recorder = GameRecorder::new(Box::new(TakeAway::new(9)));
+        recorder.reset(vec![user("p1"), user("p2")]);
+        let mut turn = recorder.try_start_game().unwrap();
+        for m in moves {
+            let player_move = PlayerMove {
+                serialized: m.to_string(),
+            };
+            let PlayerMoveResult::Ok(next) = recorder.player_moves(turn.token, player_move) else {
+                panic!("expected ok");
+            };
+            turn = next;
+        }
+        recorder
+    }
+
+    #[test]
+    fn snapshots_on_interval() {
+        let mut store = MemorySnapshotStore::default();
+        let mut scheduler = SnapshotScheduler::new(Duration::from_secs(10));
+        let start = Instant::now();
+        let mut games = [("a", started(&["1"])), ("b", started(&[]))];
+        let running = |games: &mut [(&str, GameRecorder)]| {
+            games
+                .iter_mut()
+                .map(|(id, g)| (id.to_string(), GameSnapshot::capture(g)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            scheduler.tick(start, &mut store, || running(&mut games)),
+            Ok(true)
+        );
+        assert_eq!(store.ids(), Ok(vec!["a".to_string(), "b".to_string()]));
+
+        let later = start + Duration::from_secs(5);
+        assert!(!scheduler.due(later));
+        assert_eq!(
+            scheduler.tick(later, &mut store, || -> Vec<_> { panic!("not due") }),
+            Ok(false)
+        );
+
+        let later = start + Duration::from_secs(10);
+        assert_eq!(
+            scheduler.tick(later, &mut store, || running(&mut games[1..])),
+            Ok(true)
+        );
+        assert_eq!(store.ids(), Ok(vec!["b".to_string()]));
+    }
+
+    #[test]
+    fn recovers_games() {
+        let mut recorder = started(&["1", "2"]);
+        let mut store = MemorySnapshotStore::default();
+        store
+            .save("a", &GameSnapshot::capture(&mut recorder))
+            .unwrap();
+        let mut broken = GameSnapshot::capture(&mut started(&[]));
+        broken.replay.moves = recorder.replay().moves.clone();
+        broken.replay.moves[0].player_move = "7".to_string();
+        store.save("b", &broken).unwrap();
+
+        let recovered = recover(&store, |_| Box::new(TakeAway::new(9))).unwrap();
+        assert_eq!(recovered.len(), 2);
+        let (restored, turn) = recovered[0].restored.as_ref().unwrap();
+        assert_eq!(restored.replay(), recorder.replay());
+        assert_eq!(turn.as_ref().unwrap().token.user.name, "p1");
+        assert!(matches!(
+            recovered[1].restored,
+            Err(PlaybackError::Rejected { index: 0 })
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+
+use super::GameSnapshot;
+
+/// Where the snapshots of running games are kept, keyed by game id.
+pub trait SnapshotStore {
+    type Error;
+
+    /// Stores `snapshot`, replacing any earlier one for the same game.
+    fn save(&mut self, id: &str, snapshot: &GameSnapshot) -> Result<(), Self::Error>;
+
+    fn remove(&mut self, id: &str) -> Result<(), Self::Error>;
+
+    fn ids(&self) -> Result<Vec<String>, Self::Error>;
+
+    fn load(&self, id: &str) -> Result<Option<GameSnapshot>, Self::Error>;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemorySnapshotStore {
+    snapshots: BTreeMap<String, GameSnapshot>,
+}
+
+impl SnapshotStore for MemorySnapshotStore {
+    type Error = Infallible;
+
+    fn save(&mut self, id: &str, snapshot: &GameSnapshot) -> Result<(), Self::Error> {
+        self.snapshots.insert(id.to_string(), snapshot.clone());
+        Ok(())
+    }
+
+    fn remove(&mut self, id: &str) -> Result<(), Self::Error> {
+        self.snapshots.remove(id);
+        Ok(())
+    }
+
+    fn ids(&self) -> Result<Vec<String>, Self::Error> {
+        Ok(self.snapshots.keys().cloned().collect())
+    }
+
+    fn load(&self, id: &str) -> Result<Option<GameSnapshot>, Self::Error> {
+        Ok(self.snapshots.get(id).cloned())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DirStoreError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Ids become file names, so they can't contain path separators or start
+    /// with a dot.
+    #[error("invalid snapshot id {0:?}")]
+    InvalidId(String),
+}
+
+/// One `<id>.json` file per game in a directory. Snapshots are written to a
+/// temporary file and renamed into place, so a crash mid-write leaves the
+/// previous snapshot intact.
+#[derive(Clone, Debug)]
+pub struct DirSnapshotStore {
+    dir: PathBuf,
+}
+
+const EXTENSION: &str = ".json";
+
+impl DirSnapshotStore {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, DirStoreError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, DirStoreError> {
+        if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+            return Err(DirStoreError::InvalidId(id.to_string()));
+        }
+        Ok(self.dir.join(format!("{id}{EXTENSION}")))
+    }
+}
+
+impl SnapshotStore for DirSnapshotStore {
+    type Error = DirStoreError;
+
+    fn save(&mut self, id: &str, snapshot: &GameSnapshot) -> Result<(), Self::Error> {
+        let path = self.path(id)?;
+        let tmp = self.dir.join(format!(".{id}{EXTENSION}.tmp"));
+        let file = File::create(&tmp)?;
+        snapshot.save(&file)?;
+        file.sync_all()?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    fn remove(&mut self, id: &str) -> Result<(), Self::Error> {
+        match fs::remove_file(self.path(id)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn ids(&self) -> Result<Vec<String>, Self::Error> {
+        let mut ids = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if let Some(id) = name.strip_suffix(EXTENSION) {
+                if !id.starts_with('.') {
+                    ids.push(id.to_string());
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn load(&self, id: &str) -> Result<Option<GameSnapshot>, Self::Error> {
+        match File::open(self.path(id)?) {
+            Ok(file) => Ok(Some(GameSnapshot::load(io::BufReader::new(file))?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::replay::Replay;
+    use crate::test_support::user;
+
+    fn snapshot(players: &[&str]) -> GameSnapshot {
+        let replay = Replay::new(players.iter().map(|p| user(p)).collect());
+        GameSnapshot {
+            replay,
+            turn_tracker: None,
+            clock: None,
+            config: serde_json::Value::Null,
+        }
+    }
+
+    fn check_store<S: SnapshotStore>(store: &mut S)
+    where
+        S::Error: std::fmt::Debug,
+    {
+        store.save("b", &snapshot(&["p1"])).unwrap();
+        store.save("a", &snapshot(&["p2"])).unwrap();
+        store.save("b", &snapshot(&["p3"])).unwrap();
+        assert_eq!(store.ids().unwrap(), vec!["a", "b"]);
+        assert_eq!(store.load("b").unwrap(), Some(snapshot(&["p3"])));
+
+        store.remove("b").unwrap();
+        store.remove("missing").unwrap();
+        assert_eq!(store.ids().unwrap(), vec!["a"]);
+        assert_eq!(store.load("b").unwrap(), None);
+    }
+
+    #[test]
+    fn memory_store() {
+        check_store(&mut MemorySnapshotStore::default());
+    }
+
+    #[test]
+    fn dir_store() {
+        let dir = std::env::temp_dir().join(format!("snapshots-{}", std::process::id()));
+        let mut store = DirSnapshotStore::open(&dir).unwrap();
+        check_store(&mut store);
+        assert!(matches!(
+            store.save("../a", &snapshot(&[])),
+            Err(DirStoreError::InvalidId(_))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,467 @@
+//! People watching games without playing them. Each game has its own
+//! spectators, and every update the game broadcasts is queued for each of
+//! them, to be taken by whatever writes to their connection. Spectators
+//! never move, so none of this touches the turn order or the game itself.
+//!
+//! A spectator reading too slowly fills their queue. With `DropOldest` the
+//! oldest updates make room, and since the deltas after a dropped one can't
+//! be applied, the spectator gets nothing more until the next full state.
+//! With `Disconnect` they're dropped from the game instead.
+//!
+//! A spectator can rewind to an earlier move, see `rewind`, and gets no
+//! live updates until they catch up.
+//!
+//! Updates can also be held back for a while before anyone sees them, so a
+//! bot's author watching live can't learn what their bot isn't told.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::host::GameId;
+use crate::metrics::{MessageRate, Metric, Metrics};
+use crate::time::Instant;
+
+/// What's sent to spectators: the whole state, or what changed since the
+/// last update, as a `GridDelta`.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Update<S, D> {
+    Full(S),
+    Delta(D),
+}
+
+/// What to do with a spectator whose queue is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backpressure {
+    DropOldest,
+    Disconnect,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SpectatorId(u64);
+
+#[derive(Debug)]
+struct Feed<S, D> {
+    queue: VecDeque<Update<S, D>>,
+    /// Whether deltas can be applied, which they can't until a full state
+    /// arrives, on joining or after updates were dropped.
+    in_sync: bool,
+    /// Looking at an earlier move, and not sent live updates.
+    rewound: bool,
+    closed: bool,
+}
+
+type Feeds<S, D> = Vec<(SpectatorId, Arc<Mutex<Feed<S, D>>>)>;
+
+/// Updates waiting out the delay, each with when it's due.
+type Held<S, D> = VecDeque<(Instant, Update<S, D>)>;
+
+/// One spectator's end of a game's broadcasts. Dropping it leaves the game.
+#[derive(Debug)]
+pub struct Subscription<S, D> {
+    id: SpectatorId,
+    game: GameId,
+    feed: Arc<Mutex<Feed<S, D>>>,
+}
+
+impl<S, D> Subscription<S, D> {
+    pub fn id(&self) -> SpectatorId {
+        self.id
+    }
+
+    pub fn game(&self) -> GameId {
+        self.game
+    }
+
+    /// Every update queued since the last take, oldest first.
+    pub fn take(&self) -> Vec<Update<S, D>> {
+        self.feed.lock().unwrap().queue.drain(..).collect()
+    }
+
+    /// Whether the registry let go of this spectator, for falling behind
+    /// or because the game was removed. Nothing more will be queued.
+    pub fn is_closed(&self) -> bool {
+        self.feed.lock().unwrap().closed
+    }
+}
+
+/// The spectators of every game, kept by the server apart from the players.
+#[derive(Debug)]
+pub struct SpectatorRegistry<S, D> {
+    /// Updates queued per spectator before backpressure kicks in.
+    capacity: usize,
+    backpressure: Backpressure,
+    delay: Duration,
+    games: HashMap<GameId, Feeds<S, D>>,
+    held: HashMap<GameId, Held<S, D>>,
+    next_id: u64,
+    /// Updates queued for spectators, one per spectator, for `Metrics`.
+    queued: MessageRate,
+}
+
+impl<S: Clone, D: Clone> SpectatorRegistry<S, D> {
+    /// Queues at most `capacity` updates per spectator, at least one.
+    pub fn new(capacity: usize, backpressure: Backpressure) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            backpressure,
+            delay: Duration::ZERO,
+            games: HashMap::new(),
+            held: HashMap::new(),
+            next_id: 0,
+            queued: MessageRate::new(),
+        }
+    }
+
+    /// Holds every update back for `delay` before spectators get it, as 30
+    /// seconds for tournament games. Held updates go out on `release`.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Starts watching `game`. Nothing is queued until the game's next full
+    /// state, see `needs_full`.
+    pub fn subscribe(&mut self, game: GameId) -> Subscription<S, D> {
+        let id = SpectatorId(self.next_id);
+        self.next_id += 1;
+        let feed = Arc::new(Mutex::new(Feed {
+            queue: VecDeque::new(),
+            in_sync: false,
+            rewound: false,
+            closed: false,
+        }));
+        self.games.entry(game).or_default().push((id, feed.clone()));
+        Subscription { id, game, feed }
+    }
+
+    pub fn unsubscribe(&mut self, id: SpectatorId) {
+        for spectators in self.games.values_mut() {
+            spectators.retain(|(spectator, feed)| {
+                let keep = *spectator != id;
+                if !keep {
+                    feed.lock().unwrap().closed = true;
+                }
+                keep
+            });
+        }
+        self.games.retain(|_, spectators| !spectators.is_empty());
+    }
+
+    /// Shows spectator `id` `state` from an earlier move, such as from
+    /// `GameRecorder::visible_at`, in place of whatever live updates they
+    /// hadn't taken yet. Live updates stop until `catch_up`. Returns whether
+    /// there's such a spectator.
+    pub fn rewind(&mut self, id: SpectatorId, state: S) -> bool {
+        let Some(feed) = self.feed(id) else {
+            return false;
+        };
+        let mut feed = feed.lock().unwrap();
+        feed.rewound = true;
+        feed.queue.clear();
+        feed.queue.push_back(Update::Full(state));
+        true
+    }
+
+    /// Brings a rewound spectator back to the live game, from its next full
+    /// state.
+    pub fn catch_up(&mut self, id: SpectatorId) -> bool {
+        let Some(feed) = self.feed(id) else {
+            return false;
+        };
+        let mut feed = feed.lock().unwrap();
+        feed.rewound = false;
+        feed.in_sync = false;
+        true
+    }
+
+    fn feed(&self, id: SpectatorId) -> Option<&Arc<Mutex<Feed<S, D>>>> {
+        self.games
+            .values()
+            .flatten()
+            .find(|(spectator, _)| *spectator == id)
+            .map(|(_, feed)| feed)
+    }
+
+    /// Lets go of everyone watching `game`, as when it's over.
+    pub fn remove_game(&mut self, game: GameId) {
+        self.held.remove(&game);
+        for (_, feed) in self.games.remove(&game).into_iter().flatten() {
+            feed.lock().unwrap().closed = true;
+        }
+    }
+
+    /// Spectators still subscribed to `game`.
+    pub fn spectators(&self, game: GameId) -> usize {
+        self.games.get(&game).map_or(0, Vec::len)
+    }
+
+    /// Whether any of `game`'s spectators is waiting on a full state, so
+    /// the next broadcast should be one rather than a delta. With a delay
+    /// it stays so until a full state is released to them.
+    pub fn needs_full(&self, game: GameId) -> bool {
+        self.games.get(&game).is_some_and(|spectators| {
+            spectators.iter().any(|(_, feed)| {
+                let feed = feed.lock().unwrap();
+                !feed.in_sync && !feed.rewound
+            })
+        })
+    }
+
+    /// Queues `update` for everyone watching `game`, and returns who was
+    /// disconnected for falling behind. Spectators whose subscription was
+    /// dropped are let go of on the way. With a delay the update is held
+    /// instead, see `broadcast_at`.
+    pub fn broadcast(&mut self, game: GameId, update: Update<S, D>) -> Vec<SpectatorId> {
+        self.broadcast_at(game, update, Instant::now())
+    }
+
+    /// As `broadcast`, for an update made at `now`. With a delay, the update
+    /// waits for a `release` at least the delay later.
+    pub fn broadcast_at(
+        &mut self,
+        game: GameId,
+        update: Update<S, D>,
+        now: Instant,
+    ) -> Vec<SpectatorId> {
+        if self.delay.is_zero() {
+            return self.deliver(game, update, now);
+        }
+        if self.games.contains_key(&game) {
+            let held = self.held.entry(game).or_default();
+            held.push_back((now + self.delay, update));
+        }
+        vec![]
+    }
+
+    /// Queues the held updates due by `now`, and returns who was
+    /// disconnected for falling behind. Call it as often as updates should
+    /// trickle out, such as every tick.
+    pub fn release(&mut self, now: Instant) -> Vec<SpectatorId> {
+        let mut due = vec![];
+        self.held.retain(|game, held| {
+            while held.front().is_some_and(|(at, _)| *at <= now) {
+                let (_, update) = held.pop_front().unwrap();
+                due.push((*game, update));
+            }
+            !held.is_empty()
+        });
+        let mut disconnected = vec![];
+        for (game, update) in due {
+            disconnected.extend(self.deliver(game, update, now));
+        }
+        disconnected
+    }
+
+    fn deliver(&mut self, game: GameId, update: Update<S, D>, now: Instant) -> Vec<SpectatorId> {
+        let Some(spectators) = self.games.get_mut(&game) else {
+            return vec![];
+        };
+        let mut disconnected = vec![];
+        let mut queued = 0;
+        spectators.retain(|(id, feed)| {
+            if Arc::strong_count(feed) == 1 {
+                return false;
+            }
+            let mut feed = feed.lock().unwrap();
+            if feed.rewound {
+                return true;
+            }
+            match &update {
+                Update::Full(_) => feed.in_sync = true,
+                Update::Delta(_) if !feed.in_sync => return true,
+                Update::Delta(_) => {}
+            }
+            if feed.queue.len() >= self.capacity {
+                match self.backpressure {
+                    Backpressure::DropOldest => {
+                        // Deltas after the dropped update go with it.
+                        feed.queue.pop_front();
+                        while matches!(feed.queue.front(), Some(Update::Delta(_))) {
+                            feed.queue.pop_front();
+                        }
+                        if feed.queue.is_empty() && matches!(update, Update::Delta(_)) {
+                            feed.in_sync = false;
+                            return true;
+                        }
+                    }
+                    Backpressure::Disconnect => {
+                        feed.closed = true;
+                        disconnected.push(*id);
+                        return false;
+                    }
+                }
+            }
+            feed.queue.push_back(update.clone());
+            queued += 1;
+            true
+        });
+        self.queued.record_many(queued, now);
+        if spectators.is_empty() {
+            self.games.remove(&game);
+        }
+        disconnected
+    }
+}
+
+/// The spectators watching, in total and per game, and how many updates
+/// were queued for them in the last second.
+impl<S, D> Metrics for SpectatorRegistry<S, D> {
+    fn metrics(&self, now: Instant) -> Vec<Metric> {
+        let mut games: Vec<_> = self
+            .games
+            .iter()
+            .map(|(game, spectators)| (*game, spectators.len() as u64))
+            .collect();
+        games.sort();
+        let mut metrics = vec![
+            Metric::total("spectators", games.iter().map(|(_, n)| n).sum()),
+            Metric::total("spectator_messages_per_second", self.queued.per_second(now)),
+        ];
+        metrics.extend(
+            games
+                .into_iter()
+                .map(|(game, n)| Metric::per_game("spectators", game, n)),
+        );
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::host::GameHost;
+
+    type Registry = SpectatorRegistry<&'static str, u32>;
+
+    fn game_ids(n: usize) -> Vec<GameId> {
+        let host = GameHost::new(1);
+        (0..n)
+            .map(|_| host.add(Box::new(crate::replay::test_game::TakeAway::new(1))))
+            .collect()
+    }
+
+    #[test]
+    fn fans_out_per_game() {
+        let ids = game_ids(2);
+        let mut registry = Registry::new(8, Backpressure::Disconnect);
+        let a = registry.subscribe(ids[0]);
+        let b = registry.subscribe(ids[0]);
+        let other = registry.subscribe(ids[1]);
+        assert!(registry.needs_full(ids[0]));
+        registry.broadcast(ids[0], Update::Delta(1));
+        registry.broadcast(ids[0], Update::Full("board"));
+        registry.broadcast(ids[0], Update::Delta(2));
+        assert!(!registry.needs_full(ids[0]));
+        for spectator in [&a, &b] {
+            assert_eq!(spectator.take(), [Update::Full("board"), Update::Delta(2)]);
+        }
+        assert!(other.take().is_empty());
+
+        drop(b);
+        registry.broadcast(ids[0], Update::Delta(3));
+        assert_eq!(registry.spectators(ids[0]), 1);
+        registry.remove_game(ids[0]);
+        assert!(a.is_closed());
+        assert!(!other.is_closed());
+    }
+
+    #[test]
+    fn slow_spectators_resync_after_drops() {
+        let ids = game_ids(1);
+        let mut registry = Registry::new(2, Backpressure::DropOldest);
+        let slow = registry.subscribe(ids[0]);
+        registry.broadcast(ids[0], Update::Full("a"));
+        registry.broadcast(ids[0], Update::Full("b"));
+        registry.broadcast(ids[0], Update::Full("c"));
+        assert_eq!(slow.take(), [Update::Full("b"), Update::Full("c")]);
+
+        registry.broadcast(ids[0], Update::Delta(1));
+        registry.broadcast(ids[0], Update::Delta(2));
+        registry.broadcast(ids[0], Update::Delta(3));
+        assert!(registry.needs_full(ids[0]));
+        assert!(slow.take().is_empty());
+        registry.broadcast(ids[0], Update::Delta(4));
+        registry.broadcast(ids[0], Update::Full("d"));
+        assert_eq!(slow.take(), [Update::Full("d")]);
+    }
+
+    #[test]
+    fn slow_spectators_can_be_disconnected() {
+        let ids = game_ids(1);
+        let mut registry = Registry::new(1, Backpressure::Disconnect);
+        let slow = registry.subscribe(ids[0]);
+        assert!(registry.broadcast(ids[0], Update::Full("a")).is_empty());
+        assert_eq!(registry.broadcast(ids[0], Update::Delta(1)), [slow.id()]);
+        assert!(slow.is_closed());
+        assert_eq!(registry.spectators(ids[0]), 0);
+        assert_eq!(slow.take(), [Update::Full("a")]);
+    }
+
+    #[test]
+    fn holds_updates_back_for_the_delay() {
+        let ids = game_ids(1);
+        let delay = Duration::from_secs(30);
+        let mut registry = Registry::new(8, Backpressure::Disconnect).with_delay(delay);
+        let watcher = registry.subscribe(ids[0]);
+        let start = Instant::now();
+        registry.broadcast_at(ids[0], Update::Full("a"), start);
+        registry.broadcast_at(ids[0], Update::Delta(1), start + Duration::from_secs(1));
+        assert!(watcher.take().is_empty());
+
+        registry.release(start + Duration::from_secs(10));
+        assert!(watcher.take().is_empty());
+        registry.release(start + delay);
+        assert_eq!(watcher.take(), [Update::Full("a")]);
+        registry.release(start + delay + Duration::from_secs(1));
+        assert_eq!(watcher.take(), [Update::Delta(1)]);
+    }
+
+    #[test]
+    fn counts_spectators_and_updates() {
+        let ids = game_ids(2);
+        let mut registry = Registry::new(8, Backpressure::Disconnect);
+        let _watchers = [
+            registry.subscribe(ids[0]),
+            registry.subscribe(ids[0]),
+            registry.subscribe(ids[1]),
+        ];
+        let start = Instant::now();
+        registry.broadcast_at(ids[0], Update::Full("a"), start);
+        let metrics = registry.metrics(start + Duration::from_secs(1));
+        assert_eq!(
+            metrics,
+            [
+                Metric::total("spectators", 3),
+                Metric::total("spectator_messages_per_second", 2),
+                Metric::per_game("spectators", ids[0], 2),
+                Metric::per_game("spectators", ids[1], 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn rewound_spectators_catch_up() {
+        let ids = game_ids(1);
+        let mut registry = Registry::new(8, Backpressure::Disconnect);
+        let live = registry.subscribe(ids[0]);
+        let commentator = registry.subscribe(ids[0]);
+        registry.broadcast(ids[0], Update::Full("move 3"));
+        registry.broadcast(ids[0], Update::Delta(4));
+
+        assert!(registry.rewind(commentator.id(), "move 1"));
+        registry.broadcast(ids[0], Update::Delta(5));
+        assert_eq!(commentator.take(), [Update::Full("move 1")]);
+        assert!(!registry.needs_full(ids[0]));
+
+        assert!(registry.catch_up(commentator.id()));
+        assert!(registry.needs_full(ids[0]));
+        registry.broadcast(ids[0], Update::Delta(6));
+        registry.broadcast(ids[0], Update::Full("move 6"));
+        assert_eq!(commentator.take(), [Update::Full("move 6")]);
+        assert_eq!(live.take().len(), 5);
+    }
+}
@@ -0,0 +1,382 @@
+//! Headless render testing. `RecordingPainter` keeps the draw commands a game
+//! emits, `render_text` turns them into stable text, and `assert_golden`
+//! compares that text to a file checked in next to the tests. Run the tests
+//! with `UPDATE_GOLDEN=1` to write the files after an intended change.
+//!
+//! Golden replays work the same way for rules: `assert_replay_golden` plays
+//! a replay checked in under `tests/golden/replays` and fails when the game
+//! no longer plays it the same.
+//!
+//! Also fixtures for the users and turn trackers most tests start from.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::color::Color;
+use crate::draw::{Affine, Draw, DrawCommand, Painter, Point, Rect, Size, Stroke};
+use crate::gametraits::{GameTrait, PlayerMove, PlayerMoveResult};
+use crate::replay::{verify_replay, GameRecorder, Replay};
+
+mod fixtures;
+
+pub use fixtures::{tracker, user, users, TrackerBuilder};
+
+#[derive(Clone, Debug, Default)]
+pub struct RecordingPainter {
+    pub commands: Vec<DrawCommand>,
+}
+
+impl RecordingPainter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn to_text(&self) -> String {
+        render_text(&self.commands)
+    }
+}
+
+impl Painter for RecordingPainter {
+    fn draw(&mut self, command: &DrawCommand) {
+        self.commands.push(command.clone());
+    }
+}
+
+/// One line per command, numbers rounded to three decimals so golden files
+/// don't change with floating point noise.
+pub fn render_text(commands: &[DrawCommand]) -> String {
+    let mut out = String::new();
+    let mut depth = 0;
+    for command in commands {
+        if let DrawCommand::Restore = command {
+            depth = usize::saturating_sub(depth, 1);
+        }
+        out.push_str(&"  ".repeat(depth));
+        match command {
+            DrawCommand::Rect { rect, fill, stroke } => {
+                write!(
+                    out,
+                    "rect {}{}",
+                    rect_text(*rect),
+                    paint_text(*fill, *stroke)
+                )
+            }
+            DrawCommand::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => write!(
+                out,
+                "circle {} r={}{}",
+                point_text(*center),
+                num(*radius),
+                paint_text(*fill, *stroke)
+            ),
+            DrawCommand::Line { from, to, stroke } => write!(
+                out,
+                "line {} {}{}",
+                point_text(*from),
+                point_text(*to),
+                paint_text(None, Some(*stroke))
+            ),
+            DrawCommand::Polygon {
+                points,
+                fill,
+                stroke,
+            } => {
+                let points: Vec<_> = points.iter().map(|p| point_text(*p)).collect();
+                write!(
+                    out,
+                    "polygon {}{}",
+                    points.join(" "),
+                    paint_text(*fill, *stroke)
+                )
+            }
+            DrawCommand::Text {
+                text,
+                origin,
+                size,
+                color,
+            } => write!(
+                out,
+                "text {} size={} color={} {text:?}",
+                point_text(*origin),
+                num(*size),
+                color_text(*color)
+            ),
+            DrawCommand::Image { rect, image } => write!(
+                out,
+                "image {} {}x{} fnv={:016x}",
+                rect_text(*rect),
+                image.width,
+                image.height,
+                fnv1a(&image.pixels)
+            ),
+            DrawCommand::Save => {
+                depth += 1;
+                write!(out, "save")
+            }
+            DrawCommand::Restore => write!(out, "restore"),
+            DrawCommand::Transform(Affine(coefficients)) => {
+                let coefficients: Vec<_> = coefficients.iter().map(|c| num(*c)).collect();
+                write!(out, "transform {}", coefficients.join(" "))
+            }
+        }
+        .unwrap();
+        out.push('\n');
+    }
+    out
+}
+
+/// Records a frame of `drawable` at `size` and compares it to the golden file
+/// at `path`.
+#[track_caller]
+pub fn assert_render_golden(drawable: &impl Draw, size: Size, path: impl AsRef<Path>) {
+    let mut painter = RecordingPainter::new();
+    painter.draw_all(&drawable.draw(size));
+    assert_golden(path, &painter.to_text());
+}
+
+/// Panics with a line diff if `actual` differs from the contents of `path`,
+/// unless `UPDATE_GOLDEN` is set, in which case the file is (re)written.
+#[track_caller]
+pub fn assert_golden(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        std::fs::write(path, actual).unwrap();
+        return;
+    }
+    let expected = match std::fs::read_to_string(path) {
+        Ok(expected) => expected,
+        Err(e) => panic!(
+            "Can't read golden file {}: {e}, run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        ),
+    };
+    if expected != actual {
+        panic!(
+            "Rendering differs from golden file {} (- expected, + actual):\n{}",
+            path.display(),
+            diff_lines(&expected, actual)
+        );
+    }
+}
+
+/// Plays the golden replay at `path`, in notation, on `game` and panics if
+/// the current rules disagree with it anywhere, see `verify_replay`.
+///
+/// With `UPDATE_GOLDEN` set the moves are played again instead and the file
+/// rewritten with the initial state and outcome the rules give now, stopping
+/// at the first move they reject. That is also how a new golden replay gets
+/// filled in after writing down just its players and moves.
+#[track_caller]
+pub fn assert_replay_golden(game: Box<dyn GameTrait>, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => panic!("Can't read golden replay {}: {e}", path.display()),
+    };
+    let replay = match Replay::from_notation(&text) {
+        Ok(replay) => replay,
+        Err(e) => panic!(
+            "Golden replay {} line {}: {}",
+            path.display(),
+            e.line,
+            e.reason
+        ),
+    };
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(path, rerecord(game, &replay).to_notation()).unwrap();
+        return;
+    }
+    if let Err(divergence) = verify_replay(game, &replay) {
+        panic!(
+            "Golden replay {} no longer plays the same: {divergence:?}, run with UPDATE_GOLDEN=1 \
+             to accept the change",
+            path.display()
+        );
+    }
+}
+
+/// `replay` played on `game` for as long as the game accepts its moves,
+/// keeping its tags, move times and the variations that still branch off.
+fn rerecord(game: Box<dyn GameTrait>, replay: &Replay) -> Replay {
+    let mut recorder = GameRecorder::new(game);
+    recorder.reset(replay.players.clone());
+    let mut turn = recorder.try_start_game();
+    for recorded in &replay.moves {
+        let Some(current) = turn.take().filter(|t| t.token.user.name == recorded.player) else {
+            break;
+        };
+        let player_move = PlayerMove {
+            serialized: recorded.player_move.clone(),
+        };
+        match recorder.player_moves(current.token, player_move) {
+            PlayerMoveResult::Ok(next) => turn = Some(next),
+            _ => break,
+        }
+    }
+    let mut rerecorded = recorder.into_replay();
+    for (moved, recorded) in rerecorded.moves.iter_mut().zip(&replay.moves) {
+        moved.think_ms = recorded.think_ms;
+    }
+    rerecorded.tags = replay.tags.clone();
+    rerecorded.variations = replay
+        .variations
+        .iter()
+        .filter(|v| v.from_move <= rerecorded.moves.len())
+        .cloned()
+        .collect();
+    rerecorded
+}
+
+/// A line diff of `expected` against `actual`, unchanged lines prefixed with
+/// two spaces and changed ones with `- ` and `+ `.
+pub fn diff_lines(expected: &str, actual: &str) -> String {
+    let (a, b): (Vec<_>, Vec<_>) = (expected.lines().collect(), actual.lines().collect());
+    // Longest common subsequence lengths of the suffixes.
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            writeln!(out, "  {}", a[i]).unwrap();
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            writeln!(out, "+ {}", b[j]).unwrap();
+            j += 1;
+        } else {
+            writeln!(out, "- {}", a[i]).unwrap();
+            i += 1;
+        }
+    }
+    out
+}
+
+fn num(n: f64) -> String {
+    let s = format!("{:.3}", n);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    match s {
+        "-0" => "0".to_string(),
+        s => s.to_string(),
+    }
+}
+
+fn point_text(p: Point) -> String {
+    format!("{},{}", num(p.x), num(p.y))
+}
+
+fn rect_text(r: Rect) -> String {
+    format!(
+        "{} {}",
+        point_text(Point::new(r.x0, r.y0)),
+        point_text(Point::new(r.x1, r.y1))
+    )
+}
+
+fn color_text(color: Color) -> String {
+    let (r, g, b, a) = color.as_rgba8();
+    format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+}
+
+fn paint_text(fill: Option<Color>, stroke: Option<Stroke>) -> String {
+    let mut out = String::new();
+    if let Some(color) = fill {
+        write!(out, " fill={}", color_text(color)).unwrap();
+    }
+    if let Some(Stroke { color, width }) = stroke {
+        write!(out, " stroke={}/{}", color_text(color), num(width)).unwrap();
+    }
+    out
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::replay::test_game::TakeAway;
+
+    #[test]
+    fn renders_commands_as_text() {
+        let mut painter = RecordingPainter::new();
+        painter.draw_all(&[
+            DrawCommand::Save,
+            DrawCommand::Transform(Affine::translate(0.1 + 0.2, -0.0)),
+            DrawCommand::stroke_rect(Rect::new(0.0, 0.0, 2.0, 1.0), Color::RED, 0.5),
+            DrawCommand::Restore,
+            DrawCommand::text("a", Point::new(1.0, 2.0), 12.0, Color::BLACK),
+        ]);
+        assert_eq!(
+            painter.to_text(),
+            "save
+  transform 1 0 0 1 0.3 0
+  rect 0,0 2,1 stroke=#ff0000ff/0.5
+restore
+text 1,2 size=12 color=#000000ff \"a\"
+"
+        );
+    }
+
+    #[test]
+    fn diffs_lines() {
+        assert_eq!(diff_lines("a\nb\nc\n", "a\nc\nd\n"), "  a\n- b\n  c\n+ d\n");
+    }
+
+    #[test]
+    fn golden_game_frame() {
+        assert_render_golden(
+            &TakeAway::new(3),
+            Size::new(90.0, 30.0),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/take_away.txt"),
+        );
+    }
+
+    #[test]
+    fn golden_replay() {
+        assert_replay_golden(
+            Box::new(TakeAway::new(5)),
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/golden/replays/take_away.txt"
+            ),
+        );
+    }
+
+    #[test]
+    fn rerecords_under_changed_rules() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/golden/replays/take_away.txt"
+        );
+        let replay = Replay::from_notation(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert!(verify_replay(Box::new(TakeAway::new(6)), &replay).is_err());
+
+        let rerecorded = rerecord(Box::new(TakeAway::new(6)), &replay);
+        assert_eq!(rerecorded.moves, replay.moves);
+        assert_eq!(rerecorded.outcome, None);
+        assert_eq!(
+            verify_replay(Box::new(TakeAway::new(6)), &rerecorded),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use crate::color::Color;
+use crate::gametraits::User;
+use crate::TurnTracker;
+
+/// A user called `name`, always in the same color so renders stay stable.
+pub fn user(name: &str) -> User {
+    User::new(name, Color::BLUE)
+}
+
+/// `n` users called p1, p2 and so on.
+pub fn users(n: usize) -> Vec<User> {
+    (1..=n).map(|i| user(&format!("p{i}"))).collect()
+}
+
+/// A tracker with `n` users from `users`, nobody's turn yet.
+pub fn tracker(n: usize) -> TurnTracker {
+    TrackerBuilder::new().fill_to(n).build()
+}
+
+/// Builds a `TurnTracker` part way through a game:
+/// `TrackerBuilder::new().fill_to(3).advanced(2).build()` has handed out
+/// turns to p1 and p2, so p2 is the current player and p3 is next.
+#[derive(Clone, Debug, Default)]
+pub struct TrackerBuilder {
+    players: Vec<User>,
+    advanced: usize,
+}
+
+impl TrackerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn player(mut self, name: &str) -> Self {
+        self.players.push(user(name));
+        self
+    }
+
+    /// Fills the tracker to `n` players with users named for their seat,
+    /// so after two players have been added `fill_to(4)` adds p3 and p4.
+    pub fn fill_to(mut self, n: usize) -> Self {
+        let start = self.players.len() + 1;
+        self.players
+            .extend((start..=n).map(|i| user(&format!("p{i}"))));
+        self
+    }
+
+    /// Hands out `turns` turns before returning the tracker.
+    pub fn advanced(mut self, turns: usize) -> Self {
+        self.advanced = turns;
+        self
+    }
+
+    pub fn build(self) -> TurnTracker {
+        let mut tracker = TurnTracker::new(self.players);
+        for _ in 0..self.advanced {
+            tracker.advance_player();
+        }
+        tracker
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builds_trackers_part_way() {
+        assert_eq!(users(2), [user("p1"), user("p2")]);
+        let mut tracker = TrackerBuilder::new()
+            .player("host")
+            .fill_to(3)
+            .advanced(2)
+            .build();
+        let names: Vec<_> = tracker.players().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, ["host", "p2", "p3"]);
+        assert_eq!(tracker.current_player(), Some(&user("p2")));
+        assert_eq!(tracker.advance_player(), Some(&user("p3")));
+    }
+}
@@ -0,0 +1,10 @@
+//! `Instant` as the crate uses it. Natively it's the standard library's;
+//! in browsers, on `wasm32-unknown-unknown`, where
+//! `std::time::Instant::now` panics, it's `web_time`'s, which reads the
+//! page's clock. Code taking an `Instant` from the crate should name it
+//! through here to build for both.
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use std::time::Instant;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use web_time::Instant;
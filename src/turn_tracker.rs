@@ -1,197 +1,563 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
 use crate::gametraits::User;
-use itertools::enumerate;
-use itertools::Itertools;
-use log::debug;
+use crate::names::{validate_username, NameError};
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use tracing::debug;
+
+mod shared;
+
+pub use shared::{SharedTurns, TurnSnapshot};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Seats kept inline, enough for most games without touching the heap.
+const INLINE_SEATS: usize = 4;
+
+/// Players sit in seats linked into a ring in the order they joined, so that
+/// one can leave without shifting everyone after them: removal unlinks the
+/// seat and frees it for the next player to join, all in constant time.
+/// `PlayerId`s index the seats directly.
+///
+/// Up to `INLINE_SEATS` seats live in the tracker itself and names are
+/// found by scanning them. Past that the seats move to the heap and a map
+/// of names to seats is kept.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "StoredTracker", into = "StoredTracker")]
 pub struct TurnTracker {
-    players: Vec<User>,
-    next_player_index: usize,
+    seats: SmallVec<[Seat; INLINE_SEATS]>,
+    /// Seats of players who left, to be taken by those who join.
+    free: SmallVec<[usize; INLINE_SEATS]>,
+    /// Empty until the seats spill onto the heap.
+    by_name: HashMap<String, usize>,
+    num_players: usize,
+    /// The seat of the player who joined first, if anyone is playing.
+    first: Option<usize>,
+    /// The seat of the player to move next, if anyone is playing.
+    next: Option<usize>,
     single_player_mode_started: bool,
+    /// The seat of the player `advance_player` last returned.
+    current: Option<usize>,
 }
 
-impl TurnTracker {
-    fn player_string(&self) -> String {
-        let mut players: String = String::new();
-        for (i, User { name, .. }) in enumerate(&self.players) {
-            if i == self.next_player_index {
-                players += format!(", *{name}").as_str();
-            } else {
-                players += format!(", {name}").as_str();
+#[derive(Clone, Debug)]
+struct Seat {
+    user: Option<User>,
+    /// Bumped every time the seat is vacated, so ids of players who left
+    /// don't find whoever sits there next.
+    generation: u32,
+    prev: usize,
+    next: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TrackerError {
+    #[error(transparent)]
+    InvalidName(#[from] NameError),
+    #[error("{0} is already playing")]
+    AlreadyPlaying(String),
+    #[error("{0} isn't playing")]
+    NotPlaying(String),
+}
+
+/// Names a player for as long as they're playing. Looking a player up by
+/// id is an index rather than a hash of their name, for games that query
+/// the tracker in a hot loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PlayerId {
+    seat: usize,
+    generation: u32,
+}
+
+/// The players in turn order, first to join first.
+#[derive(Clone, Debug)]
+pub struct Players<'a> {
+    seats: &'a [Seat],
+    at: Option<usize>,
+    left: usize,
+}
+
+impl<'a> Iterator for Players<'a> {
+    type Item = &'a User;
+
+    fn next(&mut self) -> Option<&'a User> {
+        if self.left == 0 {
+            return None;
+        }
+        let seat = &self.seats[self.at?];
+        self.left -= 1;
+        self.at = Some(seat.next);
+        seat.user.as_ref()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+impl ExactSizeIterator for Players<'_> {}
+
+/// The players in turn order with the next to move starred, `p1, *p2, p3`.
+/// Nothing is formatted until it's displayed, so it costs nothing in a log
+/// line that's filtered out.
+#[derive(Clone, Copy, Debug)]
+pub struct Rotation<'a> {
+    tracker: &'a TurnTracker,
+}
+
+impl fmt::Display for Rotation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let next = self.tracker.next_user();
+        for (i, user) in self.tracker.players().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
             }
+            if Some(user) == next {
+                f.write_str("*")?;
+            }
+            f.write_str(&user.name)?;
         }
-        players
+        Ok(())
+    }
+}
+
+impl TurnTracker {
+    /// The rotation for display, e.g. in a lobby or a log line.
+    pub fn rotation(&self) -> Rotation<'_> {
+        Rotation { tracker: self }
     }
 
     pub fn new(players: Vec<User>) -> Self {
-        debug!("Creating turn tracker, with users {players:?}");
-        Self {
-            players,
-            next_player_index: 0,
+        debug!(?players, "creating turn tracker");
+        let mut tracker = Self {
+            seats: SmallVec::new(),
+            free: SmallVec::new(),
+            by_name: HashMap::new(),
+            num_players: 0,
+            first: None,
+            next: None,
             single_player_mode_started: false,
+            current: None,
+        };
+        for user in players {
+            tracker.seat(user);
         }
+        tracker
     }
 
     pub fn is_playing(&self, username: &str) -> bool {
-        self.players.iter().any(|p| p.name == username)
-    }
-
-    pub fn remove_player(&mut self, username: &str) {
-        let (i, _) = self
-            .players
-            .iter()
-            .find_position(|u| u.name == username)
-            .unwrap();
-
-        if i <= self.next_player_index {
-            // Remove player earlier in the list
-            if i < self.next_player_index {
-                self.next_player_index -= 1;
-            } else if self.next_player_index == self.players.len() - 1 {
-                self.next_player_index = 0;
+        self.seat_of(username).is_some()
+    }
+
+    pub fn player_id(&self, username: &str) -> Option<PlayerId> {
+        let seat = self.seat_of(username)?;
+        Some(PlayerId {
+            seat,
+            generation: self.seats[seat].generation,
+        })
+    }
+
+    /// The player with `id`, unless they have left.
+    pub fn get(&self, id: PlayerId) -> Option<&User> {
+        let seat = self.seats.get(id.seat)?;
+        seat.user
+            .as_ref()
+            .filter(|_| seat.generation == id.generation)
+    }
+
+    /// The id of the player whose turn it is.
+    pub fn current_id(&self) -> Option<PlayerId> {
+        let seat = self.current?;
+        Some(PlayerId {
+            seat,
+            generation: self.seats[seat].generation,
+        })
+    }
+
+    pub fn remove_player(&mut self, username: &str) -> Result<User, TrackerError> {
+        let id = self
+            .player_id(username)
+            .ok_or_else(|| TrackerError::NotPlaying(username.to_string()))?;
+        Ok(self.remove(id).expect("the id was just looked up"))
+    }
+
+    /// Removes the player with `id` and returns them, or nothing if they
+    /// had already left.
+    pub fn remove(&mut self, id: PlayerId) -> Option<User> {
+        self.get(id)?;
+        let seat = id.seat;
+        let Seat { prev, next, .. } = self.seats[seat];
+        let user = self.seats[seat].user.take().unwrap();
+        self.by_name.remove(&user.name);
+        self.num_players -= 1;
+        self.seats[seat].generation = self.seats[seat].generation.wrapping_add(1);
+        self.free.push(seat);
+        if self.num_players == 0 {
+            self.first = None;
+            self.next = None;
+        } else {
+            self.seats[prev].next = next;
+            self.seats[next].prev = prev;
+            if self.first == Some(seat) {
+                self.first = Some(next);
+            }
+            if self.next == Some(seat) {
+                // The player after them moves instead, wrapping round to
+                // the first if they were last.
+                self.next = Some(next);
             }
         }
-        self.players = self
-            .players
-            .iter()
-            .filter(|u| u.name != username)
-            .map(Clone::clone)
-            .collect();
-        let p_str = self.player_string();
-        debug!("Removing player {username}, left: {p_str}");
+        if self.current == Some(seat) {
+            self.current = None;
+        }
+        debug!(player = %user.name, rotation = %self.rotation(), "player removed");
+        self.debug_assert_invariants();
+        Some(user)
     }
 
-    pub fn add_player(&mut self, user: User) {
-        if self.players.iter().any(|p| p.name == user.name) {
-            panic!("Player with identical name added twice");
+    pub fn add_player(&mut self, user: User) -> Result<(), TrackerError> {
+        validate_username(&user.name)?;
+        if self.is_playing(&user.name) {
+            return Err(TrackerError::AlreadyPlaying(user.name.clone()));
         }
-        let p_name = user.name.clone();
-        self.players.push(user);
-        if self.players.len() == 2 && self.single_player_mode_started {
-            self.next_player_index = 1;
+        let seat = self.seat(user.clone());
+        if self.num_players == 2 && self.single_player_mode_started {
+            self.next = Some(seat);
         }
-        let p_str = self.player_string();
-        debug!("Adding player {p_name}, new: {p_str}");
+        debug!(player = %user.name, rotation = %self.rotation(), "player added");
+        self.debug_assert_invariants();
+        Ok(())
     }
 
-    pub fn advance_player(&mut self) -> Option<User> {
-        if self.players.is_empty() {
-            return None;
+    /// Seats `user` last in turn order, and first to move if they're alone.
+    fn seat(&mut self, user: User) -> usize {
+        let seat = self.free.pop().unwrap_or(self.seats.len());
+        // Alone, the new player sits between themselves; otherwise between
+        // the last to join and the first.
+        let (prev, next) = match self.first {
+            Some(first) => (self.seats[first].prev, first),
+            None => (seat, seat),
+        };
+        if seat == self.seats.len() {
+            self.seats.push(Seat {
+                user: Some(user),
+                generation: 0,
+                prev,
+                next,
+            });
+        } else {
+            self.seats[seat].user = Some(user);
+            self.seats[seat].prev = prev;
+            self.seats[seat].next = next;
         }
-        self.single_player_mode_started = self.players.len() == 1;
+        self.seats[prev].next = seat;
+        self.seats[next].prev = seat;
+        if self.first.is_none() {
+            self.first = Some(seat);
+            self.next = Some(seat);
+        }
+        self.num_players += 1;
+        if self.seats.spilled() {
+            if self.by_name.is_empty() {
+                // Just spilled, or everyone else left: index whoever's here.
+                for (seat, user) in self.seats.iter().enumerate() {
+                    if let Some(user) = &user.user {
+                        self.by_name.insert(user.name.clone(), seat);
+                    }
+                }
+            } else {
+                let name = self.seats[seat].user.as_ref().unwrap().name.clone();
+                self.by_name.insert(name, seat);
+            }
+        }
+        seat
+    }
 
-        let current_index = self.next_player_index;
-        self.next_player_index = (self.next_player_index + 1) % self.players.len();
-        let p_str = self.player_string();
-        debug!("Advancing player, new: {p_str}");
-        self.players.get(current_index).map(Clone::clone)
+    fn seat_of(&self, name: &str) -> Option<usize> {
+        if self.seats.spilled() {
+            self.by_name.get(name).copied()
+        } else {
+            self.seats
+                .iter()
+                .position(|seat| seat.user.as_ref().is_some_and(|u| u.name == name))
+        }
+    }
+
+    /// Gives the turn to the next player and returns them. Nothing is
+    /// allocated, so callers that only look at the player pay no clone.
+    pub fn advance_player(&mut self) -> Option<&User> {
+        let seat = self.next?;
+        #[cfg(feature = "metrics")]
+        crate::metrics::prometheus::TURNS.inc();
+        self.single_player_mode_started = self.num_players == 1;
+
+        self.next = Some(self.seats[seat].next);
+        self.current = Some(seat);
+        debug!(rotation = %self.rotation(), "turn advanced");
+        self.debug_assert_invariants();
+        self.user(seat)
+    }
+
+    /// The player whose turn it is, i.e. the one `advance_player` returned
+    /// last, unless they have since left.
+    pub fn current_player(&self) -> Option<&User> {
+        self.user(self.current?)
+    }
+
+    pub fn players(&self) -> Players<'_> {
+        Players {
+            seats: &self.seats,
+            at: self.first,
+            left: self.num_players,
+        }
     }
 
     pub fn num_players(&self) -> usize {
-        self.players.len()
+        self.num_players
+    }
+
+    /// Panics if the bookkeeping has gone wrong: the seats not linked into
+    /// one ring of everyone playing, two players with the same name, a
+    /// current player who isn't playing, or single player mode set while
+    /// someone other than the first player holds the turn. Every mutation
+    /// checks this in debug builds.
+    #[track_caller]
+    pub fn assert_invariants(&self) {
+        let mut seen = HashSet::new();
+        let mut at = self.first;
+        while let Some(seat) = at {
+            let user = self.user(seat);
+            let user = user.unwrap_or_else(|| panic!("empty seat {seat} in turn order"));
+            assert_eq!(
+                self.seat_of(&user.name),
+                Some(seat),
+                "{} is playing twice",
+                user.name
+            );
+            assert_eq!(self.seats[self.seats[seat].next].prev, seat, "broken ring");
+            seen.insert(seat);
+            at = Some(self.seats[seat].next).filter(|next| Some(*next) != self.first);
+            assert!(seen.len() <= self.num_players, "ring longer than players");
+        }
+        assert_eq!(seen.len(), self.num_players, "players outside the ring");
+        if self.seats.spilled() {
+            assert_eq!(self.by_name.len(), self.num_players, "names not indexed");
+        }
+        match self.next {
+            Some(next) => assert!(seen.contains(&next), "next seat {next} is empty"),
+            None => assert!(seen.is_empty(), "no next player with players"),
+        }
+        assert_eq!(
+            self.seats.len(),
+            seen.len() + self.free.len(),
+            "seats lost track of"
+        );
+        if let Some(current) = self.current {
+            assert!(seen.contains(&current), "current seat {current} is empty");
+            assert!(
+                !self.single_player_mode_started || self.first == Some(current),
+                "single player mode with seat {current} to play"
+            );
+        }
+    }
+
+    #[track_caller]
+    fn debug_assert_invariants(&self) {
+        if cfg!(debug_assertions) {
+            self.assert_invariants();
+        }
     }
 
     pub fn is_first_player(&self, name: &str) -> bool {
-        if let Some(first_player) = self.players.first() {
+        if let Some(first_player) = self.first.and_then(|seat| self.user(seat)) {
             name == first_player.name
         } else {
             false
         }
     }
+
+    fn user(&self, seat: usize) -> Option<&User> {
+        self.seats[seat].user.as_ref()
+    }
+
+    fn next_user(&self) -> Option<&User> {
+        self.user(self.next?)
+    }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+/// Equal when the same players are in the same order with the same turn
+/// to come, however their seats are laid out.
+impl PartialEq for TurnTracker {
+    fn eq(&self, other: &Self) -> bool {
+        self.players().eq(other.players())
+            && self.next_user() == other.next_user()
+            && self.single_player_mode_started == other.single_player_mode_started
+            && self.current_player() == other.current_player()
+    }
+}
+
+impl Eq for TurnTracker {}
 
-    fn make_player(name: &str) -> User {
-        User {
-            name: name.to_string(),
-            color: druid::piet::Color::BLUE,
+/// The tracker as it's stored, the players in a list with the index of the
+/// next to move.
+#[derive(Serialize, Deserialize)]
+struct StoredTracker {
+    #[serde(with = "crate::gametraits::stored_users")]
+    players: Vec<User>,
+    next_player_index: usize,
+    single_player_mode_started: bool,
+    #[serde(default)]
+    current_player: Option<String>,
+}
+
+impl From<TurnTracker> for StoredTracker {
+    fn from(tracker: TurnTracker) -> Self {
+        let next = tracker.next_user();
+        let next_player_index = tracker
+            .players()
+            .position(|user| Some(user) == next)
+            .unwrap_or(0);
+        Self {
+            players: tracker.players().cloned().collect(),
+            next_player_index,
+            single_player_mode_started: tracker.single_player_mode_started,
+            current_player: tracker.current_player().map(|u| u.name.clone()),
         }
     }
+}
+
+impl TryFrom<StoredTracker> for TurnTracker {
+    type Error = String;
+
+    fn try_from(stored: StoredTracker) -> Result<Self, String> {
+        let len = stored.players.len();
+        if stored.next_player_index >= len.max(1) {
+            return Err(format!(
+                "next index {} out of bounds for {len} players",
+                stored.next_player_index
+            ));
+        }
+        let mut names = HashSet::new();
+        if !stored.players.iter().all(|user| names.insert(&user.name)) {
+            return Err("a player is stored twice".to_string());
+        }
+        let mut tracker = TurnTracker::new(stored.players);
+        if let Some(current) = &stored.current_player {
+            let Some(seat) = tracker.seat_of(current) else {
+                return Err(format!("current player {current} isn't playing"));
+            };
+            tracker.current = Some(seat);
+        }
+        for _ in 0..stored.next_player_index {
+            tracker.next = tracker.next.map(|seat| tracker.seats[seat].next);
+        }
+        tracker.single_player_mode_started = stored.single_player_mode_started;
+        Ok(tracker)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{tracker, user, users, TrackerBuilder};
 
     #[test]
     fn construct_and_loop() {
-        let p1 = make_player("p1");
-        let p2 = make_player("p2");
-        let p3 = make_player("p3");
+        let p1 = user("p1");
+        let p2 = user("p2");
+        let p3 = user("p3");
         let mut t = TurnTracker::new(vec![p1.clone(), p2.clone(), p3.clone()]);
 
         for _ in 1..50 {
-            assert_eq!(t.advance_player(), Some(p1.clone()));
-            assert_eq!(t.advance_player(), Some(p2.clone()));
-            assert_eq!(t.advance_player(), Some(p3.clone()));
+            assert_eq!(t.advance_player(), Some(&p1));
+            assert_eq!(t.advance_player(), Some(&p2));
+            assert_eq!(t.advance_player(), Some(&p3));
         }
     }
 
     #[test]
     fn add_one() {
-        let p1 = make_player("p1");
-        let p2 = make_player("p2");
+        let p1 = user("p1");
+        let p2 = user("p2");
         let mut t = TurnTracker::new(vec![p1.clone(), p2.clone()]);
 
-        assert_eq!(t.advance_player(), Some(p1.clone()));
-        let p3 = make_player("p3");
-        t.add_player(p3.clone());
+        assert_eq!(t.advance_player(), Some(&p1));
+        let p3 = user("p3");
+        t.add_player(p3.clone()).unwrap();
         for _ in 1..10 {
-            assert_eq!(t.advance_player(), Some(p2.clone()));
-            assert_eq!(t.advance_player(), Some(p3.clone()));
-            assert_eq!(t.advance_player(), Some(p1.clone()));
+            assert_eq!(t.advance_player(), Some(&p2));
+            assert_eq!(t.advance_player(), Some(&p3));
+            assert_eq!(t.advance_player(), Some(&p1));
         }
     }
 
     #[test]
     fn add_two() {
-        let p1 = make_player("p1");
+        let p1 = user("p1");
         let mut t = TurnTracker::new(vec![p1.clone()]);
 
-        assert_eq!(t.advance_player(), Some(p1.clone()));
-        let p2 = make_player("p2");
-        let p3 = make_player("p3");
-        t.add_player(p2.clone());
-        t.add_player(p3.clone());
+        assert_eq!(t.advance_player(), Some(&p1));
+        let p2 = user("p2");
+        let p3 = user("p3");
+        t.add_player(p2.clone()).unwrap();
+        t.add_player(p3.clone()).unwrap();
         for _ in 1..10 {
-            assert_eq!(t.advance_player(), Some(p2.clone()));
-            assert_eq!(t.advance_player(), Some(p3.clone()));
-            assert_eq!(t.advance_player(), Some(p1.clone()));
+            assert_eq!(t.advance_player(), Some(&p2));
+            assert_eq!(t.advance_player(), Some(&p3));
+            assert_eq!(t.advance_player(), Some(&p1));
         }
     }
     #[test]
     fn remove_last() {
-        let p1 = make_player("p1");
-        let p2 = make_player("p2");
+        let p1 = user("p1");
+        let p2 = user("p2");
         let mut t = TurnTracker::new(vec![p1.clone(), p2.clone()]);
 
-        assert_eq!(t.advance_player(), Some(p1.clone()));
-        t.remove_player("p1");
+        assert_eq!(t.advance_player(), Some(&p1));
+        t.remove_player("p1").unwrap();
         for _ in 1..10 {
-            assert_eq!(t.advance_player(), Some(p2.clone()));
+            assert_eq!(t.advance_player(), Some(&p2));
         }
     }
 
     #[test]
     fn remove_next() {
-        let p1 = make_player("p1");
-        let p2 = make_player("p2");
+        let p1 = user("p1");
+        let p2 = user("p2");
         let mut t = TurnTracker::new(vec![p1.clone(), p2.clone()]);
 
-        assert_eq!(t.advance_player(), Some(p1.clone()));
-        t.remove_player("p2");
+        assert_eq!(t.advance_player(), Some(&p1));
+        t.remove_player("p2").unwrap();
         for _ in 1..10 {
-            assert_eq!(t.advance_player(), Some(p1.clone()));
+            assert_eq!(t.advance_player(), Some(&p1));
         }
     }
 
+    #[test]
+    fn current_player() {
+        let p1 = user("p1");
+        let p2 = user("p2");
+        let mut t = TurnTracker::new(vec![p1.clone(), p2.clone()]);
+
+        assert_eq!(t.current_player(), None);
+        t.advance_player();
+        assert_eq!(t.current_player(), Some(&p1));
+        t.advance_player();
+        assert_eq!(t.current_player(), Some(&p2));
+        t.remove_player("p2").unwrap();
+        assert_eq!(t.current_player(), None);
+        assert!(t.players().eq([&p1]));
+    }
+
     #[test]
     fn remove_all() {
-        let p1 = make_player("p1");
-        let p2 = make_player("p2");
+        let p1 = user("p1");
+        let p2 = user("p2");
         let mut t = TurnTracker::new(vec![p1.clone(), p2.clone()]);
 
-        assert_eq!(t.advance_player(), Some(p1.clone()));
-        t.remove_player("p2");
-        t.remove_player("p1");
+        assert_eq!(t.advance_player(), Some(&p1));
+        t.remove_player("p2").unwrap();
+        t.remove_player("p1").unwrap();
         for _ in 1..10 {
             assert_eq!(t.advance_player(), None);
         }
@@ -199,47 +565,189 @@ mod test {
 
     #[test]
     fn start_empty() {
-        let p1 = make_player("p1");
-        let p2 = make_player("p2");
+        let p1 = user("p1");
+        let p2 = user("p2");
         let mut t = TurnTracker::new(vec![]);
 
         assert_eq!(t.advance_player(), None);
-        t.add_player(p1.clone());
-        t.add_player(p2.clone());
+        t.add_player(p1.clone()).unwrap();
+        t.add_player(p2.clone()).unwrap();
         for _ in 1..10 {
-            assert_eq!(t.advance_player(), Some(p1.clone()));
-            assert_eq!(t.advance_player(), Some(p2.clone()));
+            assert_eq!(t.advance_player(), Some(&p1));
+            assert_eq!(t.advance_player(), Some(&p2));
         }
     }
 
     #[test]
     fn add_one_advance_add_one_then_rinse_repeat() {
-        let p1 = make_player("p1");
-        let p2 = make_player("p2");
+        let p1 = user("p1");
+        let p2 = user("p2");
         let mut t = TurnTracker::new(vec![]);
 
         assert_eq!(t.advance_player(), None);
-        t.add_player(p1.clone());
-        assert_eq!(t.advance_player(), Some(p1.clone()));
-        t.add_player(p2.clone());
-        assert_eq!(t.advance_player(), Some(p2.clone()));
+        t.add_player(p1.clone()).unwrap();
+        assert_eq!(t.advance_player(), Some(&p1));
+        t.add_player(p2.clone()).unwrap();
+        assert_eq!(t.advance_player(), Some(&p2));
 
-        t.remove_player("p1");
-        t.remove_player("p2");
+        t.remove_player("p1").unwrap();
+        t.remove_player("p2").unwrap();
 
         assert_eq!(t.advance_player(), None);
-        t.add_player(p1.clone());
-        assert_eq!(t.advance_player(), Some(p1.clone()));
-        t.add_player(p2.clone());
-        assert_eq!(t.advance_player(), Some(p2.clone()));
+        t.add_player(p1.clone()).unwrap();
+        assert_eq!(t.advance_player(), Some(&p1));
+        t.add_player(p2.clone()).unwrap();
+        assert_eq!(t.advance_player(), Some(&p2));
+    }
+
+    #[test]
+    fn rejects_duplicates_and_strangers() {
+        let mut t = tracker(2);
+        assert_eq!(
+            t.add_player(user("p1")),
+            Err(TrackerError::AlreadyPlaying("p1".to_string()))
+        );
+        assert_eq!(
+            t.add_player(user("server")),
+            Err(TrackerError::InvalidName(NameError::Reserved(
+                "server".to_string()
+            )))
+        );
+        assert_eq!(
+            t.remove_player("p3"),
+            Err(TrackerError::NotPlaying("p3".to_string()))
+        );
+        assert_eq!(t.remove_player("p2"), Ok(user("p2")));
+        assert_eq!(t.num_players(), 1);
+    }
+
+    /// Random adds, removes and advances, with the invariants checked after
+    /// each by the tracker itself.
+    #[test]
+    fn random_mutations_keep_invariants() {
+        use crate::rng::{GameRng, SplitMix64};
+
+        for seed in 0..10 {
+            let mut rng = SplitMix64::new(seed);
+            let mut t = TurnTracker::new(vec![]);
+            let mut next_name = 0;
+            for _ in 0..1000 {
+                match rng.below(10) {
+                    0..=2 => {
+                        next_name += 1;
+                        t.add_player(user(&format!("p{next_name}"))).unwrap();
+                    }
+                    3..=4 if t.num_players() > 0 => {
+                        let seat = rng.index(t.num_players());
+                        let name = t.players().nth(seat).unwrap().name.clone();
+                        t.remove_player(&name).unwrap();
+                        assert!(!t.is_playing(&name));
+                    }
+                    _ => {
+                        let turn = t.advance_player().cloned();
+                        assert_eq!(turn.is_some(), t.num_players() > 0);
+                        assert_eq!(turn.as_ref(), t.current_player());
+                    }
+                }
+                t.assert_invariants();
+            }
+        }
+    }
+
+    #[test]
+    fn removes_in_constant_time_keeping_order() {
+        let mut t = TrackerBuilder::new().fill_to(5).advanced(2).build();
+        t.remove_player("p3").unwrap();
+        t.remove_player("p1").unwrap();
+        t.add_player(user("p6")).unwrap();
+        let names: Vec<_> = t.players().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, ["p2", "p4", "p5", "p6"]);
+        assert_eq!(t.advance_player(), Some(&user("p4")));
+        // The seats left by p3 and p1 are taken, not added to.
+        assert_eq!(t.seats.len(), 5);
+    }
+
+    #[test]
+    fn ids_go_stale_when_players_leave() {
+        let mut t = tracker(3);
+        let p2 = t.player_id("p2").unwrap();
+        assert_eq!(t.get(p2), Some(&user("p2")));
+        assert_eq!(t.remove(p2), Some(user("p2")));
+        assert!(!t.is_playing("p2"));
+        // p4 takes the seat p2 left, but not their id.
+        t.add_player(user("p4")).unwrap();
+        assert_eq!(t.get(p2), None);
+        assert_eq!(t.remove(p2), None);
+        assert_eq!(t.num_players(), 3);
+        assert_eq!(t.advance_player(), Some(&user("p1")));
+        assert_eq!(t.get(t.current_id().unwrap()), Some(&user("p1")));
+    }
+
+    #[test]
+    fn shares_users_with_the_caller() {
+        let p1 = user("p1");
+        let mut t = TurnTracker::new(vec![p1.clone()]);
+        assert!(t.advance_player().unwrap().ptr_eq(&p1));
+        assert!(!t.players().next().unwrap().ptr_eq(&user("p1")));
+    }
+
+    #[test]
+    fn displays_the_rotation() {
+        let mut t = tracker(3);
+        assert_eq!(t.rotation().to_string(), "*p1, p2, p3");
+        t.advance_player();
+        t.remove_player("p3").unwrap();
+        assert_eq!(t.rotation().to_string(), "p1, *p2");
+        assert_eq!(TurnTracker::new(vec![]).rotation().to_string(), "");
+    }
+
+    #[test]
+    fn spills_onto_the_heap_past_a_few_players() {
+        let mut t = tracker(INLINE_SEATS);
+        assert!(!t.seats.spilled());
+        assert!(t.by_name.is_empty());
+        t.add_player(user("extra")).unwrap();
+        assert!(t.seats.spilled());
+        assert_eq!(t.by_name.len(), INLINE_SEATS + 1);
+        t.remove_player("p2").unwrap();
+        assert!(!t.is_playing("p2"));
+        assert!(t.is_playing("extra"));
+    }
+
+    #[test]
+    fn stores_as_a_list() {
+        let mut t = TrackerBuilder::new().fill_to(3).advanced(2).build();
+        t.remove_player("p1").unwrap();
+        let stored = StoredTracker::from(t.clone());
+        assert_eq!(stored.players, [user("p2"), user("p3")]);
+        assert_eq!(stored.next_player_index, 1);
+        assert_eq!(TurnTracker::try_from(stored), Ok(t));
+    }
+
+    #[test]
+    fn rejects_stored_trackers_out_of_bounds() {
+        let stored = StoredTracker {
+            players: users(2),
+            next_player_index: 2,
+            single_player_mode_started: false,
+            current_player: None,
+        };
+        assert!(TurnTracker::try_from(stored).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "playing twice")]
+    fn duplicate_names_break_invariants() {
+        let t = TurnTracker::new(vec![user("p1"), user("p1")]);
+        t.assert_invariants();
     }
 
     #[test]
     fn single_player() {
-        let p1 = make_player("p1");
+        let p1 = user("p1");
         let mut t = TurnTracker::new(vec![p1.clone()]);
         for _ in 1..10 {
-            assert_eq!(t.advance_player(), Some(p1.clone()));
+            assert_eq!(t.advance_player(), Some(&p1));
         }
     }
 }
@@ -0,0 +1,100 @@
+use std::sync::{Arc, RwLock};
+
+use crate::gametraits::User;
+
+use super::TurnTracker;
+
+/// The rotation as it was when published: everyone playing in turn order,
+/// whose turn it is and who's up next.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TurnSnapshot {
+    pub players: Vec<User>,
+    pub current: Option<User>,
+    pub next: Option<User>,
+    /// Counts publishes, for readers to tell whether anything changed.
+    pub version: u64,
+}
+
+/// The turn order for spectators and broadcasts to read while the game
+/// loop owns the tracker. The game loop publishes a fresh snapshot after
+/// changing the tracker, and readers get whichever was published last
+/// without ever waiting on the game: the lock only guards swapping one
+/// `Arc` for another. Clones share the same snapshot.
+#[derive(Clone, Debug, Default)]
+pub struct SharedTurns {
+    latest: Arc<RwLock<Arc<TurnSnapshot>>>,
+}
+
+impl SharedTurns {
+    pub fn new(tracker: &TurnTracker) -> Self {
+        let shared = Self::default();
+        shared.publish(tracker);
+        shared
+    }
+
+    /// Replaces the snapshot with the tracker as it is now. The snapshot is
+    /// built before the swap, so readers are held up only by the swap.
+    pub fn publish(&self, tracker: &TurnTracker) {
+        let mut snapshot = tracker.snapshot();
+        let mut latest = self.latest.write().unwrap();
+        snapshot.version = latest.version + 1;
+        *latest = Arc::new(snapshot);
+    }
+
+    pub fn load(&self) -> Arc<TurnSnapshot> {
+        self.latest.read().unwrap().clone()
+    }
+}
+
+impl TurnTracker {
+    /// The rotation copied out, for `SharedTurns` or anything else that
+    /// can't borrow the tracker. Users are shared, not copied.
+    pub fn snapshot(&self) -> TurnSnapshot {
+        TurnSnapshot {
+            players: self.players().cloned().collect(),
+            current: self.current_player().cloned(),
+            next: self.next_user().cloned(),
+            version: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::*;
+    use crate::test_support::{tracker, user};
+
+    #[test]
+    fn readers_see_whole_snapshots() {
+        let mut t = tracker(3);
+        let shared = SharedTurns::new(&t);
+        assert_eq!(shared.load().version, 1);
+        assert_eq!(shared.load().next, Some(user("p1")));
+
+        let reader = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let mut last = 0;
+                while last < 301 {
+                    let snapshot = shared.load();
+                    assert!(snapshot.version >= last);
+                    if let Some(current) = &snapshot.current {
+                        assert!(snapshot.players.contains(current));
+                    }
+                    last = snapshot.version;
+                }
+            })
+        };
+        for i in 0..300 {
+            if i % 3 == 0 {
+                t.add_player(user(&format!("q{i}"))).unwrap();
+            }
+            t.advance_player();
+            shared.publish(&t);
+        }
+        reader.join().unwrap();
+        assert_eq!(shared.load().current.as_ref(), t.current_player());
+    }
+}
@@ -0,0 +1,181 @@
+use crate::gametraits::{GameTrait, PlayerMove, PlayerMoveResult, TurnToken};
+
+/// Keeps every state a move was applied to, so moves can be undone and
+/// redone. Applying a new move after undoing discards the redo history.
+///
+/// Storing the whole state (a game together with its `TurnTracker`, say)
+/// means undo rolls everything back in one step instead of each part having
+/// to know how to reverse a move.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UndoStack<S> {
+    done: Vec<S>,
+    current: S,
+    undone: Vec<S>,
+}
+
+impl<S: Clone> UndoStack<S> {
+    pub fn new(initial: S) -> Self {
+        Self {
+            done: vec![],
+            current: initial,
+            undone: vec![],
+        }
+    }
+
+    pub fn current(&self) -> &S {
+        &self.current
+    }
+
+    pub fn into_current(self) -> S {
+        self.current
+    }
+
+    /// Applies `f` to the current state as one undoable step.
+    pub fn apply<R>(&mut self, f: impl FnOnce(&mut S) -> R) -> R {
+        let mut next = self.current.clone();
+        let result = f(&mut next);
+        self.push(next);
+        result
+    }
+
+    pub fn push(&mut self, next: S) {
+        self.done.push(std::mem::replace(&mut self.current, next));
+        self.undone.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(previous) = self.done.pop() else {
+            return false;
+        };
+        self.undone
+            .push(std::mem::replace(&mut self.current, previous));
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(next) = self.undone.pop() else {
+            return false;
+        };
+        self.done.push(std::mem::replace(&mut self.current, next));
+        true
+    }
+}
+
+impl UndoStack<Box<dyn GameTrait>> {
+    /// Plays a move, only creating an undo step if the game accepted it or
+    /// ended.
+    pub fn player_moves(
+        &mut self,
+        turn_token: TurnToken,
+        player_move: PlayerMove,
+    ) -> PlayerMoveResult {
+        let mut game = self.current.clone();
+        let result = game.player_moves(turn_token, player_move);
+        match result {
+            PlayerMoveResult::Ok(_)
+            | PlayerMoveResult::Win
+            | PlayerMoveResult::Draw
+            | PlayerMoveResult::OutOfTime(_) => self.push(game),
+            PlayerMoveResult::InvalidMove(_) | PlayerMoveResult::InvalidFormat(_) => {}
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gametraits::User;
+    use crate::replay::test_game::TakeAway;
+    use crate::test_support::user;
+    use crate::TurnTracker;
+
+    #[test]
+    fn undo_redo() {
+        let mut stack = UndoStack::new(0);
+        stack.apply(|n| *n += 1);
+        stack.apply(|n| *n += 1);
+        assert_eq!(*stack.current(), 2);
+
+        assert!(stack.undo());
+        assert!(stack.undo());
+        assert!(!stack.undo());
+        assert_eq!(*stack.current(), 0);
+
+        assert!(stack.redo());
+        assert_eq!(*stack.current(), 1);
+        stack.apply(|n| *n += 10);
+        assert!(!stack.can_redo());
+        assert_eq!(*stack.current(), 11);
+    }
+
+    #[test]
+    fn turn_tracker_rolls_back() {
+        let p1 = user("p1");
+        let p2 = user("p2");
+        let mut stack = UndoStack::new(TurnTracker::new(vec![p1.clone(), p2.clone()]));
+
+        assert_eq!(
+            stack.apply(|t| t.advance_player().cloned()),
+            Some(p1.clone())
+        );
+        assert_eq!(
+            stack.apply(|t| t.advance_player().cloned()),
+            Some(p2.clone())
+        );
+        stack.undo();
+        assert_eq!(stack.apply(|t| t.advance_player().cloned()), Some(p2));
+        stack.undo();
+        stack.undo();
+        assert_eq!(stack.apply(|t| t.advance_player().cloned()), Some(p1));
+    }
+
+    #[test]
+    fn game_moves() {
+        let p1 = user("p1");
+        let p2 = user("p2");
+        let mut game: Box<dyn GameTrait> = Box::new(TakeAway::new(5));
+        game.reset(vec![p1.clone(), p2.clone()]);
+        game.try_start_game().unwrap();
+        let mut stack = UndoStack::new(game);
+
+        let play = |stack: &mut UndoStack<Box<dyn GameTrait>>, user: &User, m: &str| {
+            stack.player_moves(
+                TurnToken { user: user.clone() },
+                PlayerMove {
+                    serialized: m.to_string(),
+                },
+            )
+        };
+        let remaining = |stack: &UndoStack<Box<dyn GameTrait>>| {
+            let game = stack.current().as_any().downcast_ref::<TakeAway>();
+            game.unwrap().remaining()
+        };
+
+        assert!(matches!(
+            play(&mut stack, &p1, "2"),
+            PlayerMoveResult::Ok(_)
+        ));
+        assert!(matches!(
+            play(&mut stack, &p2, "9"),
+            PlayerMoveResult::InvalidMove(_)
+        ));
+        assert_eq!(remaining(&stack), 3);
+        assert!(stack.undo());
+        assert!(!stack.can_undo());
+        assert_eq!(remaining(&stack), 5);
+        assert!(matches!(
+            play(&mut stack, &p1, "1"),
+            PlayerMoveResult::Ok(_)
+        ));
+        assert_eq!(remaining(&stack), 4);
+    }
+}